@@ -0,0 +1,77 @@
+//! # 启动引导模块
+//!
+//! 插件启动时统一准备运行环境，保证全新机器上的首次启动行为确定：
+//! - 创建数据目录
+//! - 生成默认配置文件
+//! - 写出空的记忆和人格状态文件
+//!
+//! 新创建的数据目录和文件只允许当前用户访问（配置文件中包含 API 密钥）
+
+use crate::config::{self, ModelConfig};
+use crate::memory::MEMORY_MANAGER;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// 执行启动引导，已存在的目录和文件保持不变
+pub async fn bootstrap() -> Result<()> {
+    // 首次访问数据目录时会自动创建
+    let data_dir = config::data_dir();
+    if !data_dir.is_dir() {
+        return Err(anyhow::anyhow!("数据目录不可用: {}", data_dir.display()));
+    }
+
+    if ModelConfig::ensure_config_file()? {
+        restrict_permissions(&config::config_path(), 0o600)?;
+    }
+
+    if MEMORY_MANAGER.ensure_storage().await? {
+        for file in MEMORY_MANAGER.storage_files() {
+            restrict_permissions(file, 0o600)?;
+        }
+        if let Some(memory_file) = MEMORY_MANAGER.memory_file() {
+            println!("[INFO] 已创建记忆存储: {}", memory_file.display());
+        }
+    }
+
+    println!("[INFO] 启动引导完成，数据目录: {}", data_dir.display());
+    Ok(())
+}
+
+/// 限制文件或目录的访问权限（仅 Unix）
+#[cfg(unix)]
+fn restrict_permissions(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .with_context(|| format!("设置访问权限失败: {}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn creates_data_dir_config_and_state_files() {
+        config::use_test_data_dir();
+        bootstrap().await.unwrap();
+
+        assert!(config::data_dir().is_dir());
+        assert!(config::config_path().is_file());
+        let files = MEMORY_MANAGER.storage_files();
+        assert!(!files.is_empty());
+        for file in &files {
+            assert!(file.starts_with(config::data_dir()), "{} 不在数据目录下", file.display());
+            assert!(file.is_file());
+        }
+
+        // 重复引导不会报错，也不会改动已有文件
+        let before = fs::read_to_string(config::config_path()).unwrap();
+        bootstrap().await.unwrap();
+        assert_eq!(fs::read_to_string(config::config_path()).unwrap(), before);
+    }
+}
@@ -0,0 +1,547 @@
+//! # 聊天配置模块
+//!
+//! 管理消息处理相关的配置，包括连续消息合并、开发模式、固定回复等
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 连续消息合并窗口的上限（毫秒）
+const MAX_MESSAGE_DEBOUNCE_MS: u64 = 10_000;
+
+/// 群聊回复风格
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplyStyle {
+    /// 简短俏皮，回复会被截断到固定长度
+    Concise,
+    /// 默认风格
+    #[default]
+    Normal,
+    /// 详细完整
+    Verbose,
+}
+
+impl ReplyStyle {
+    /// 注入系统提示的风格指令
+    pub fn directive(&self) -> Option<&'static str> {
+        match self {
+            ReplyStyle::Concise => Some("回复要简短俏皮，一两句话说完，不要长篇大论"),
+            ReplyStyle::Normal => None,
+            ReplyStyle::Verbose => Some("回复可以详细一些，把问题解释清楚，必要时分点说明"),
+        }
+    }
+
+    /// 回复的最大字符数，超出部分会被截断
+    pub fn max_reply_chars(&self) -> Option<usize> {
+        match self {
+            ReplyStyle::Concise => Some(100),
+            ReplyStyle::Normal | ReplyStyle::Verbose => None,
+        }
+    }
+}
+
+/// @全体成员 消息的处理方式
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnouncementHandling {
+    /// 直接忽略，不记录也不回复
+    Ignore,
+    /// 只记录到群聊记忆，不回复
+    #[default]
+    Record,
+    /// 记录并发送一条固定的确认消息
+    Acknowledge,
+}
+
+/// 对话历史的裁剪策略
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryStrategy {
+    /// 按消息条数裁剪
+    #[default]
+    Count,
+    /// 按估算的token数裁剪
+    Tokens,
+    /// 保留滚动摘要和最近K轮原文
+    #[serde(rename = "summary_window")]
+    SummaryWindow,
+}
+
+/// 固定回复的匹配方式
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// 消息与触发词完全一致
+    #[default]
+    Exact,
+    /// 消息包含触发词
+    Contains,
+}
+
+/// 固定回复规则
+/// 
+/// 消息命中触发词时直接发送固定回复，不调用模型
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct CannedResponse {
+    /// 触发词
+    pub trigger: String,
+    /// 固定回复内容
+    pub reply: String,
+    /// 匹配方式，默认完全匹配
+    #[serde(default)]
+    pub match_mode: MatchMode,
+}
+
+impl CannedResponse {
+    /// 判断消息是否命中该规则
+    pub fn matches(&self, message: &str) -> bool {
+        let message = message.trim();
+        match self.match_mode {
+            MatchMode::Exact => message == self.trigger,
+            MatchMode::Contains => message.contains(self.trigger.as_str()),
+        }
+    }
+}
+
+/// 关系里程碑
+/// 
+/// 用户关系等级首次达到指定等级时发送的庆祝消息
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct RelationshipMilestone {
+    /// 里程碑等级 (1-10)
+    pub level: u8,
+    /// 庆祝消息
+    pub message: String,
+}
+
+/// 聊天配置结构体
+///
+/// 包含群聊消息处理行为相关的配置信息
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ChatConfig {
+    /// 同一用户连续消息的合并窗口（毫秒），窗口内的多条消息合并为一轮对话，0表示不合并
+    message_debounce_ms: u64,
+    /// 开发模式：只回复管理员，其他人的消息仍会记录到记忆和情绪中
+    dev_mode: bool,
+    /// 安全模式：不调用模型接口、不主动发言，只响应本地命令，需要模型的消息回复离线提示
+    safe_mode: bool,
+    /// 管理员QQ号列表
+    admin_ids: Vec<i64>,
+    /// 消息未匹配任何兴趣或话题关键词时，是否记录一次未分类互动
+    record_uncategorized_activity: bool,
+    /// 群聊回复时是否引用触发回复的原消息
+    quote_reply: bool,
+    /// 固定回复规则，按顺序匹配，第一条命中的规则生效
+    canned_responses: Vec<CannedResponse>,
+    /// 各群组的回复风格（群号 -> 风格），未配置的群使用默认风格
+    group_reply_styles: HashMap<String, ReplyStyle>,
+    /// 关系里程碑庆祝消息，为空表示不庆祝
+    relationship_milestones: Vec<RelationshipMilestone>,
+    /// 社交举动（里程碑庆祝、自我介绍）的冷却时间（秒），同一用户在此期间最多收到一次，
+    /// 单次互动也不会叠加多条；0表示不限制间隔，但单次互动仍只发送一条
+    social_gesture_cooldown_secs: u64,
+    /// 只回复呼叫机器人的群聊消息（@机器人、回复机器人的消息、包含触发词或机器人的群名片），其他消息只记录
+    mention_only: bool,
+    /// 呼叫机器人的触发词，为空时使用提示词配置中的机器人名字，机器人在各群的名片会自动加入匹配
+    trigger_words: Vec<String>,
+    /// 回复机器人在多少秒内发出的消息时视为呼叫机器人，0表示不启用
+    reply_trigger_window_secs: u64,
+    /// 各群组是否启用回复触发（群号 -> 是否启用），未配置的群按 `reply_trigger_window_secs` 决定
+    group_reply_triggers: HashMap<String, bool>,
+    /// 内存中同时保留的群聊/私聊对话上下文数量上限（各自计算），超出时淘汰最久未活跃的对话，0表示不限制
+    max_live_conversations: usize,
+    /// 群聊对话空闲超过该秒数后，下一条消息开始新的对话上下文，0表示不过期
+    group_context_idle_secs: u64,
+    /// 发送回复前的基础延迟（毫秒），0表示不延迟
+    reply_delay_ms: u64,
+    /// 回复延迟的随机抖动比例 (0.0-1.0)，实际延迟在基础延迟的 (1 - 抖动) 到 (1 + 抖动) 倍之间
+    reply_delay_jitter: f64,
+    /// 是否允许管理员使用 `#群分析` 查看群聊分析报告
+    group_analytics_enabled: bool,
+    /// @全体成员 消息的处理方式，这类消息不会触发普通的模型回复
+    announcement_handling: AnnouncementHandling,
+    /// 处理方式为 `acknowledge` 时发送的确认消息
+    announcement_ack_message: String,
+    /// 对话历史的裁剪策略（`count`、`tokens` 或 `summary_window`）
+    history_strategy: HistoryStrategy,
+    /// `count` 策略下保留的最大消息数（含系统提示），也可以写作 `max_context_messages`
+    #[serde(alias = "max_context_messages")]
+    history_max_messages: usize,
+    /// 群号 -> `count` 策略下该群保留的最大消息数，未配置的群使用 `history_max_messages`
+    group_history_max_messages: HashMap<String, usize>,
+    /// QQ号 -> `count` 策略下与该用户私聊保留的最大消息数，未配置的用户使用 `history_max_messages`
+    private_history_max_messages: HashMap<String, usize>,
+    /// `tokens` 策略下保留的最大token数（估算值，含系统提示）
+    history_max_tokens: usize,
+    /// `summary_window` 策略下原文保留的最近用户/助手消息数
+    history_recent_turns: usize,
+    /// 是否允许使用 `#刷新记忆` 在对话中途重新注入最新的记忆（群聊中仅管理员可用）
+    context_refresh_enabled: bool,
+    /// 是否允许使用 `#还记得吗` 查看当前对话记得多少内容
+    memory_indicator_enabled: bool,
+    /// 每个用户每分钟最多触发的模型请求数，0表示不限制，管理员不受限制
+    user_quota_per_minute: usize,
+    /// 每个用户每小时最多触发的模型请求数，0表示不限制，管理员不受限制
+    user_quota_per_hour: usize,
+}
+
+impl ChatConfig {
+    pub fn message_debounce_ms(&self) -> u64 {
+        self.message_debounce_ms
+    }
+
+    pub fn dev_mode(&self) -> bool {
+        self.dev_mode
+    }
+
+    pub fn safe_mode(&self) -> bool {
+        self.safe_mode
+    }
+
+    pub fn admin_ids(&self) -> &[i64] {
+        &self.admin_ids
+    }
+
+    pub fn record_uncategorized_activity(&self) -> bool {
+        self.record_uncategorized_activity
+    }
+
+    pub fn quote_reply(&self) -> bool {
+        self.quote_reply
+    }
+
+    pub fn canned_responses(&self) -> &[CannedResponse] {
+        &self.canned_responses
+    }
+
+    /// 查找消息命中的固定回复
+    pub fn canned_reply(&self, message: &str) -> Option<&str> {
+        self.canned_responses
+            .iter()
+            .find(|rule| rule.matches(message))
+            .map(|rule| rule.reply.as_str())
+    }
+
+    pub fn group_reply_styles(&self) -> &HashMap<String, ReplyStyle> {
+        &self.group_reply_styles
+    }
+
+    /// 获取群组的回复风格
+    pub fn reply_style(&self, group_id: i64) -> ReplyStyle {
+        self.group_reply_styles
+            .get(&group_id.to_string())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn relationship_milestones(&self) -> &[RelationshipMilestone] {
+        &self.relationship_milestones
+    }
+
+    pub fn social_gesture_cooldown_secs(&self) -> u64 {
+        self.social_gesture_cooldown_secs
+    }
+
+    /// 获取所有里程碑等级
+    pub fn milestone_levels(&self) -> Vec<u8> {
+        self.relationship_milestones.iter().map(|m| m.level).collect()
+    }
+
+    /// 获取指定里程碑等级的庆祝消息
+    pub fn milestone_message(&self, level: u8) -> Option<&str> {
+        self.relationship_milestones
+            .iter()
+            .find(|m| m.level == level)
+            .map(|m| m.message.as_str())
+    }
+
+    pub fn mention_only(&self) -> bool {
+        self.mention_only
+    }
+
+    pub fn trigger_words(&self) -> &[String] {
+        &self.trigger_words
+    }
+
+    pub fn reply_trigger_window_secs(&self) -> u64 {
+        self.reply_trigger_window_secs
+    }
+
+    /// 群组是否启用回复触发
+    pub fn reply_trigger_enabled(&self, group_id: i64) -> bool {
+        self.reply_trigger_window_secs > 0
+            && self
+                .group_reply_triggers
+                .get(&group_id.to_string())
+                .copied()
+                .unwrap_or(true)
+    }
+
+    /// 判断消息是否包含配置的触发词，未配置触发词时匹配机器人的名字
+    ///
+    /// # 参数
+    /// * `message` - 消息文本
+    /// * `bot_name` - 机器人的名字
+    pub fn matches_trigger_word(&self, message: &str, bot_name: &str) -> bool {
+        if self.trigger_words.is_empty() {
+            return !bot_name.is_empty() && message.contains(bot_name);
+        }
+        self.trigger_words.iter().any(|word| message.contains(word.as_str()))
+    }
+
+    pub fn announcement_handling(&self) -> AnnouncementHandling {
+        self.announcement_handling
+    }
+
+    pub fn announcement_ack_message(&self) -> &str {
+        &self.announcement_ack_message
+    }
+
+    pub fn history_strategy(&self) -> HistoryStrategy {
+        self.history_strategy
+    }
+
+    pub fn history_max_messages(&self) -> usize {
+        self.history_max_messages
+    }
+
+    /// 获取群聊 `count` 策略下保留的最大消息数
+    pub fn group_history_max_messages(&self, group_id: i64) -> usize {
+        self.group_history_max_messages
+            .get(&group_id.to_string())
+            .copied()
+            .unwrap_or(self.history_max_messages)
+    }
+
+    /// 获取私聊 `count` 策略下保留的最大消息数
+    pub fn private_history_max_messages(&self, user_id: i64) -> usize {
+        self.private_history_max_messages
+            .get(&user_id.to_string())
+            .copied()
+            .unwrap_or(self.history_max_messages)
+    }
+
+    pub fn history_max_tokens(&self) -> usize {
+        self.history_max_tokens
+    }
+
+    pub fn history_recent_turns(&self) -> usize {
+        self.history_recent_turns
+    }
+
+    pub fn user_quota_per_minute(&self) -> usize {
+        self.user_quota_per_minute
+    }
+
+    pub fn user_quota_per_hour(&self) -> usize {
+        self.user_quota_per_hour
+    }
+
+    pub fn context_refresh_enabled(&self) -> bool {
+        self.context_refresh_enabled
+    }
+
+    pub fn memory_indicator_enabled(&self) -> bool {
+        self.memory_indicator_enabled
+    }
+
+    pub fn max_live_conversations(&self) -> usize {
+        self.max_live_conversations
+    }
+
+    pub fn group_context_idle_secs(&self) -> u64 {
+        self.group_context_idle_secs
+    }
+
+    pub fn reply_delay_ms(&self) -> u64 {
+        self.reply_delay_ms
+    }
+
+    pub fn reply_delay_jitter(&self) -> f64 {
+        self.reply_delay_jitter
+    }
+
+    pub fn group_analytics_enabled(&self) -> bool {
+        self.group_analytics_enabled
+    }
+
+    /// 判断用户是否为配置的管理员
+    pub fn is_admin(&self, user_id: i64) -> bool {
+        self.admin_ids.contains(&user_id)
+    }
+
+    /// 判断是否应当回复该用户
+    /// 
+    /// 开发模式下只回复管理员，否则回复所有人
+    pub fn should_reply_to(&self, user_id: i64) -> bool {
+        !self.dev_mode || self.is_admin(user_id)
+    }
+
+    /// 验证聊天配置
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.message_debounce_ms > MAX_MESSAGE_DEBOUNCE_MS {
+            return Err(anyhow::anyhow!(
+                "消息合并窗口不能超过{}毫秒",
+                MAX_MESSAGE_DEBOUNCE_MS
+            ));
+        }
+
+        if self.canned_responses.iter().any(|rule| rule.trigger.trim().is_empty() || rule.reply.is_empty()) {
+            return Err(anyhow::anyhow!("固定回复的触发词和回复内容不能为空"));
+        }
+
+        if let Some(key) = self.group_reply_styles.keys().find(|key| key.parse::<i64>().is_err()) {
+            return Err(anyhow::anyhow!("回复风格配置中的群号无效: {}", key));
+        }
+
+        if self.relationship_milestones.iter().any(|m| !(1..=10).contains(&m.level) || m.message.trim().is_empty()) {
+            return Err(anyhow::anyhow!("关系里程碑等级必须在1-10之间且庆祝消息不能为空"));
+        }
+
+        if self.trigger_words.iter().any(|word| word.trim().is_empty()) {
+            return Err(anyhow::anyhow!("触发词不能为空"));
+        }
+
+        if !(0.0..=1.0).contains(&self.reply_delay_jitter) {
+            return Err(anyhow::anyhow!("回复延迟抖动比例必须在0.0-1.0之间"));
+        }
+
+        if self.announcement_handling == AnnouncementHandling::Acknowledge
+            && self.announcement_ack_message.trim().is_empty()
+        {
+            return Err(anyhow::anyhow!("@全体成员的确认消息不能为空"));
+        }
+
+        if self.history_max_messages < 2 {
+            return Err(anyhow::anyhow!("对话历史最大消息数不能小于2"));
+        }
+
+        if let Some((id, _)) = self
+            .group_history_max_messages
+            .iter()
+            .chain(&self.private_history_max_messages)
+            .find(|(_, max_messages)| **max_messages < 2)
+        {
+            return Err(anyhow::anyhow!("对话 {} 的历史最大消息数不能小于2", id));
+        }
+
+        if self.history_max_tokens == 0 || self.history_recent_turns == 0 {
+            return Err(anyhow::anyhow!("对话历史的token预算和保留轮数必须大于0"));
+        }
+
+        if self.user_quota_per_minute > 0
+            && self.user_quota_per_hour > 0
+            && self.user_quota_per_minute > self.user_quota_per_hour
+        {
+            return Err(anyhow::anyhow!("每分钟请求配额不能大于每小时请求配额"));
+        }
+
+        if self.dev_mode && self.admin_ids.is_empty() {
+            return Err(anyhow::anyhow!("启用开发模式时管理员列表不能为空"));
+        }
+
+        println!("[INFO] 聊天配置验证通过");
+        Ok(())
+    }
+}
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        Self {
+            message_debounce_ms: 0,
+            dev_mode: false,
+            safe_mode: false,
+            admin_ids: Vec::new(),
+            record_uncategorized_activity: false,
+            quote_reply: false,
+            canned_responses: Vec::new(),
+            group_reply_styles: HashMap::new(),
+            social_gesture_cooldown_secs: 600,
+            relationship_milestones: vec![
+                RelationshipMilestone { level: 5, message: "我们好像越来越熟了呢～".to_string() },
+                RelationshipMilestone { level: 8, message: "感觉你已经是我很重要的朋友啦，嘿嘿".to_string() },
+                RelationshipMilestone { level: 10, message: "能遇到你真好，以后也要一直一起聊天哦～".to_string() },
+            ],
+            mention_only: false,
+            trigger_words: Vec::new(),
+            reply_trigger_window_secs: 600,
+            group_reply_triggers: HashMap::new(),
+            max_live_conversations: 200,
+            group_context_idle_secs: 3600,
+            reply_delay_ms: 0,
+            reply_delay_jitter: 0.3,
+            group_analytics_enabled: false,
+            announcement_handling: AnnouncementHandling::Record,
+            announcement_ack_message: "收到～".to_string(),
+            history_strategy: HistoryStrategy::Count,
+            history_max_messages: 25,
+            group_history_max_messages: HashMap::new(),
+            private_history_max_messages: HashMap::new(),
+            history_max_tokens: 4000,
+            history_recent_turns: 10,
+            context_refresh_enabled: true,
+            memory_indicator_enabled: true,
+            user_quota_per_minute: 0,
+            user_quota_per_hour: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trigger_words_default_to_bot_name() {
+        let chat = ChatConfig::default();
+        assert!(chat.matches_trigger_word("小云在吗", "小云"));
+        assert!(!chat.matches_trigger_word("芸汐在吗", "小云"));
+    }
+
+    #[test]
+    fn configured_trigger_words_replace_bot_name() {
+        let chat: ChatConfig = kovi::toml::from_str("trigger_words = [\"小芸\"]").unwrap();
+        assert!(chat.matches_trigger_word("小芸在吗", "小云"));
+        assert!(!chat.matches_trigger_word("小云在吗", "小云"));
+    }
+
+    #[test]
+    fn dev_mode_replies_only_to_admins() {
+        let chat: ChatConfig = kovi::toml::from_str("dev_mode = true\nadmin_ids = [1]").unwrap();
+        assert!(chat.should_reply_to(1));
+        assert!(!chat.should_reply_to(2));
+
+        let chat = ChatConfig::default();
+        assert!(chat.should_reply_to(2));
+    }
+
+    #[test]
+    fn canned_responses_match_exact_and_contains() {
+        let chat: ChatConfig = kovi::toml::from_str(
+            "[[canned_responses]]\ntrigger = \"群规是什么\"\nreply = \"请看群公告\"\n\n\
+             [[canned_responses]]\ntrigger = \"官网\"\nreply = \"https://example.com\"\nmatch_mode = \"contains\"",
+        )
+        .unwrap();
+
+        assert_eq!(chat.canned_reply(" 群规是什么 "), Some("请看群公告"));
+        assert_eq!(chat.canned_reply("请问群规是什么"), None);
+        assert_eq!(chat.canned_reply("官网地址多少"), Some("https://example.com"));
+        assert_eq!(chat.canned_reply("你好"), None);
+    }
+
+    #[test]
+    fn concise_group_gets_brevity_directive() {
+        let chat: ChatConfig = kovi::toml::from_str("[group_reply_styles]\n\"100\" = \"concise\"").unwrap();
+
+        let style = chat.reply_style(100);
+        assert_eq!(style, ReplyStyle::Concise);
+        assert!(style.directive().unwrap().contains("简短"));
+        assert_eq!(style.max_reply_chars(), Some(100));
+
+        // 未配置的群使用默认风格，不注入指令也不截断
+        assert_eq!(chat.reply_style(200), ReplyStyle::Normal);
+        assert_eq!(chat.reply_style(200).directive(), None);
+        assert_eq!(chat.reply_style(200).max_reply_chars(), None);
+    }
+}
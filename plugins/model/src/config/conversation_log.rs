@@ -0,0 +1,73 @@
+//! # 对话日志配置模块
+//!
+//! 管理调试用对话日志的配置，包括日志文件和写入前的脱敏规则
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// 对话日志配置结构体
+///
+/// 开启后每次模型请求和回复都会以 JSON Lines 格式追加写入日志文件，
+/// 写入前按脱敏规则替换敏感内容，不影响实际发送的回复
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ConversationLogConfig {
+    /// 是否记录对话日志
+    enabled: bool,
+    /// 日志文件名，位于数据目录下
+    file: String,
+    /// 脱敏规则（正则表达式），匹配的内容在写入日志前替换为 `[已脱敏]`
+    redaction_patterns: Vec<String>,
+}
+
+impl ConversationLogConfig {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn file(&self) -> &str {
+        self.file.as_str()
+    }
+
+    pub fn redaction_patterns(&self) -> &[String] {
+        &self.redaction_patterns
+    }
+
+    /// 编译脱敏规则
+    ///
+    /// # 返回值
+    /// 成功时返回编译后的正则表达式，存在无效规则时返回错误
+    pub fn redaction_rules(&self) -> anyhow::Result<Vec<Regex>> {
+        self.redaction_patterns
+            .iter()
+            .map(|pattern| Regex::new(pattern).map_err(|e| anyhow::anyhow!("无效的脱敏规则 {}: {}", pattern, e)))
+            .collect()
+    }
+
+    /// 验证对话日志配置
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.enabled && self.file.trim().is_empty() {
+            return Err(anyhow::anyhow!("启用对话日志时日志文件名不能为空"));
+        }
+
+        self.redaction_rules()?;
+
+        println!("[INFO] 对话日志配置验证通过");
+        Ok(())
+    }
+}
+
+impl Default for ConversationLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            file: "conversation_log.jsonl".to_string(),
+            redaction_patterns: vec![
+                // 手机号
+                r"1[3-9]\d{9}".to_string(),
+                // 邮箱
+                r"[\w.+-]+@[\w-]+\.[\w.-]+".to_string(),
+            ],
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! # 定时问候配置模块
+//!
+//! 管理机器人在固定时间向群组发送早安、晚安等问候的配置
+
+use serde::{Deserialize, Serialize};
+
+/// 单条定时问候
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ScheduledGreeting {
+    /// 发送时刻的小时 (0-23)
+    pub hour: u32,
+    /// 发送时刻的分钟 (0-59)
+    pub minute: u32,
+    /// 候选问候语，每天轮换使用
+    pub messages: Vec<String>,
+}
+
+/// 定时问候配置结构体
+///
+/// 包含问候时间、问候语以及接收问候的群组
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct GreetingConfig {
+    /// 是否启用定时问候
+    enabled: bool,
+    /// 接收问候的群组
+    groups: Vec<i64>,
+    /// 问候时间和问候语
+    greetings: Vec<ScheduledGreeting>,
+}
+
+impl GreetingConfig {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn groups(&self) -> &[i64] {
+        &self.groups
+    }
+
+    pub fn greetings(&self) -> &[ScheduledGreeting] {
+        &self.greetings
+    }
+
+    /// 验证定时问候配置
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for greeting in &self.greetings {
+            if greeting.hour > 23 || greeting.minute > 59 {
+                return Err(anyhow::anyhow!(
+                    "问候时间无效: {:02}:{:02}",
+                    greeting.hour,
+                    greeting.minute
+                ));
+            }
+
+            if greeting.messages.iter().all(|message| message.trim().is_empty()) {
+                return Err(anyhow::anyhow!(
+                    "{:02}:{:02} 的问候语不能为空",
+                    greeting.hour,
+                    greeting.minute
+                ));
+            }
+        }
+
+        println!("[INFO] 定时问候配置验证通过");
+        Ok(())
+    }
+}
+
+impl Default for GreetingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            groups: Vec::new(),
+            greetings: vec![
+                ScheduledGreeting {
+                    hour: 8,
+                    minute: 0,
+                    messages: vec![
+                        "早安~新的一天也要元气满满哦！".to_string(),
+                        "早上好呀，今天也一起加油吧~".to_string(),
+                    ],
+                },
+                ScheduledGreeting {
+                    hour: 23,
+                    minute: 0,
+                    messages: vec![
+                        "晚安~早点休息，明天见！".to_string(),
+                        "夜深啦，大家晚安，做个好梦~".to_string(),
+                    ],
+                },
+            ],
+        }
+    }
+}
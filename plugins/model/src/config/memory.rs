@@ -0,0 +1,216 @@
+//! # 记忆配置模块
+//!
+//! 管理记忆系统相关的配置，包括人格变化日志、自动清理、免记忆对象等
+
+use crate::memory::storage::StorageFormat;
+use crate::memory::store::StorageBackend;
+use serde::{Deserialize, Serialize};
+
+/// 记忆配置结构体
+///
+/// 包含记忆持久化和人格日志相关的配置信息
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct MemoryConfig {
+    /// 记忆文件路径，相对路径位于数据目录下；同一台机器运行多个实例时需各自配置
+    memory_file: String,
+    /// 是否记录人格变化日志
+    personality_journal_enabled: bool,
+    /// 人格变化日志文件路径（JSON Lines 格式，只追加）
+    personality_journal_file: String,
+    /// 模型用量统计文件路径，相对路径位于数据目录下
+    usage_file: String,
+    /// 健康检查发现记忆文件过大或记忆过多时是否自动执行清理
+    auto_cleanup_enabled: bool,
+    /// 不记录记忆的群组（如测试群），机器人仍会正常回复
+    no_memory_groups: Vec<i64>,
+    /// 不记录记忆的私聊用户，机器人仍会正常回复
+    no_memory_users: Vec<i64>,
+    /// 不记录记忆的上下文（如 `private_chat`）
+    no_memory_contexts: Vec<String>,
+    /// 存储后端（`json` 或 `sqlite`），`sqlite` 需要启用 `sqlite` 功能，首次使用时自动导入现有记忆文件
+    storage_backend: StorageBackend,
+    /// 记忆文件存储格式（`json` 或 `msgpack`），与文件扩展名不一致时以配置为准
+    storage_format: StorageFormat,
+    /// 遗忘曲线：记忆未被强化时每隔多少天重要性降低1点，0表示不衰减
+    importance_decay_days: u32,
+    /// 是否启用每日反思：回顾当天的情绪互动，小幅调整机器人的基础人格
+    daily_reflection_enabled: bool,
+    /// 每日反思的执行时间（小时，0-23），到达该时间后当天执行一次
+    reflection_hour: u32,
+    /// 群内梗（反复出现的短语）最多注入多少条到群聊系统提示，0表示不注入
+    group_lore_limit: usize,
+    /// 统计群内梗的时间窗口（天），超出窗口不再出现的梗会被淡忘
+    group_lore_days: u32,
+    /// 用户多少天未互动后关系等级降低1级（之后每隔同样天数再降1级，最低1级），0表示不衰减
+    relationship_decay_days: u32,
+    /// 对话记忆去重窗口（秒）：窗口内相同内容和上下文的对话只记录一次，0表示不去重
+    dedup_window_secs: u64,
+}
+
+impl MemoryConfig {
+    pub fn memory_file(&self) -> &str {
+        self.memory_file.as_str()
+    }
+
+    pub fn personality_journal_enabled(&self) -> bool {
+        self.personality_journal_enabled
+    }
+
+    pub fn personality_journal_file(&self) -> &str {
+        self.personality_journal_file.as_str()
+    }
+
+    pub fn usage_file(&self) -> &str {
+        self.usage_file.as_str()
+    }
+
+    pub fn auto_cleanup_enabled(&self) -> bool {
+        self.auto_cleanup_enabled
+    }
+
+    pub fn no_memory_groups(&self) -> &[i64] {
+        &self.no_memory_groups
+    }
+
+    pub fn no_memory_users(&self) -> &[i64] {
+        &self.no_memory_users
+    }
+
+    pub fn no_memory_contexts(&self) -> &[String] {
+        &self.no_memory_contexts
+    }
+
+    pub fn storage_backend(&self) -> StorageBackend {
+        self.storage_backend
+    }
+
+    pub fn storage_format(&self) -> StorageFormat {
+        self.storage_format
+    }
+
+    pub fn importance_decay_days(&self) -> u32 {
+        self.importance_decay_days
+    }
+
+    pub fn daily_reflection_enabled(&self) -> bool {
+        self.daily_reflection_enabled
+    }
+
+    pub fn reflection_hour(&self) -> u32 {
+        self.reflection_hour
+    }
+
+    pub fn group_lore_limit(&self) -> usize {
+        self.group_lore_limit
+    }
+
+    pub fn dedup_window_secs(&self) -> u64 {
+        self.dedup_window_secs
+    }
+
+    pub fn relationship_decay_days(&self) -> u32 {
+        self.relationship_decay_days
+    }
+
+    pub fn group_lore_days(&self) -> u32 {
+        self.group_lore_days
+    }
+
+    /// 判断对话是否不应被记忆
+    /// 
+    /// # 参数
+    /// * `id` - 群聊上下文中为群组ID，其他上下文中为用户ID
+    /// * `context` - 对话上下文（如 `group_chat`、`private_chat`）
+    pub fn skips_memory(&self, id: i64, context: &str) -> bool {
+        if self.no_memory_contexts.iter().any(|c| c == context) {
+            return true;
+        }
+
+        if context.contains("group") {
+            self.no_memory_groups.contains(&id)
+        } else {
+            self.no_memory_users.contains(&id)
+        }
+    }
+
+    /// 验证记忆配置
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.memory_file.trim().is_empty() {
+            return Err(anyhow::anyhow!("记忆文件路径不能为空"));
+        }
+
+        if !self.storage_backend.is_available() {
+            return Err(anyhow::anyhow!("存储后端 {:?} 不可用，请启用 sqlite 功能重新编译", self.storage_backend));
+        }
+
+        let memory_file = crate::config::data_path(&self.memory_file);
+        if let Some(parent) = memory_file.parent()
+            && !parent.exists()
+        {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| anyhow::anyhow!("无法创建记忆文件目录 {}: {}", parent.display(), e))?;
+        }
+
+        if self.usage_file.trim().is_empty() {
+            return Err(anyhow::anyhow!("用量统计文件路径不能为空"));
+        }
+
+        if self.personality_journal_enabled && self.personality_journal_file.is_empty() {
+            return Err(anyhow::anyhow!("启用人格日志时日志文件路径不能为空"));
+        }
+
+        if self.reflection_hour > 23 {
+            return Err(anyhow::anyhow!("每日反思时间必须在0-23之间"));
+        }
+
+        println!("[INFO] 记忆配置验证通过");
+        Ok(())
+    }
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            memory_file: "bot_memory.json".to_string(),
+            personality_journal_enabled: true,
+            personality_journal_file: "personality_journal.jsonl".to_string(),
+            usage_file: "usage.json".to_string(),
+            auto_cleanup_enabled: false,
+            no_memory_groups: Vec::new(),
+            no_memory_users: Vec::new(),
+            no_memory_contexts: Vec::new(),
+            storage_backend: StorageBackend::Json,
+            storage_format: StorageFormat::Json,
+            importance_decay_days: 7,
+            daily_reflection_enabled: false,
+            reflection_hour: 22,
+            group_lore_limit: 5,
+            group_lore_days: 7,
+            relationship_decay_days: 14,
+            dedup_window_secs: 300,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_memory_group_is_skipped() {
+        let memory: MemoryConfig = kovi::toml::from_str("no_memory_groups = [100]").unwrap();
+        assert!(memory.skips_memory(100, "group_chat"));
+        assert!(!memory.skips_memory(200, "group_chat"));
+        // 群号与私聊用户号互不影响
+        assert!(!memory.skips_memory(100, "private_chat"));
+    }
+
+    #[test]
+    fn no_memory_context_skips_everyone() {
+        let memory: MemoryConfig = kovi::toml::from_str("no_memory_contexts = [\"private_chat\"]\nno_memory_users = [7]").unwrap();
+        assert!(memory.skips_memory(1, "private_chat"));
+        assert!(memory.skips_memory(7, "greeting"));
+        assert!(!memory.skips_memory(1, "group_chat"));
+    }
+}
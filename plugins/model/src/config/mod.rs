@@ -7,8 +7,13 @@
 //! - 线程安全的配置访问
 //! - 配置验证和错误处理
 
+use crate::config::chat::ChatConfig;
+use crate::config::conversation_log::ConversationLogConfig;
+use crate::config::greeting::GreetingConfig;
+use crate::config::memory::MemoryConfig;
+use crate::config::mood::MoodConfig;
+use crate::config::proactive::ProactiveConfig;
 use crate::config::prompt::Prompt;
-use crate::config::server::ServerConfig;
 use anyhow::Context;
 use config::{Config, FileFormat};
 use kovi::toml;
@@ -18,6 +23,13 @@ use std::path::Path;
 use std::sync::{atomic::{AtomicBool, Ordering}, Arc, LazyLock, RwLock};
 use std::time::Duration;
 
+mod chat;
+mod conversation_log;
+mod greeting;
+mod memory;
+mod mood;
+mod paths;
+mod proactive;
 mod prompt;
 mod server;
 
@@ -43,22 +55,29 @@ pub struct ModelConfig {
     prompt: Prompt,
     /// 服务器配置
     server_config: ServerConfig,
+    /// 记忆配置
+    memory: MemoryConfig,
+    /// 主动聊天配置
+    proactive: ProactiveConfig,
+    /// 聊天配置
+    chat: ChatConfig,
+    /// 定时问候配置
+    greeting: GreetingConfig,
+    /// 情绪配置
+    mood: MoodConfig,
+    /// 对话日志配置
+    conversation_log: ConversationLogConfig,
 }
 
 impl ModelConfig {
     /// 加载配置文件
     /// 
-    /// 从 `bot.conf.toml` 文件加载配置，如果文件不存在则创建默认配置
+    /// 从数据目录下的 `bot.conf.toml` 文件加载配置，如果文件不存在则创建默认配置
     /// 
     /// # 返回值
     /// 成功时返回配置实例，失败时返回错误
     pub fn load() -> anyhow::Result<Self> {
-        let config_path = "bot.conf.toml";
-        if !Path::new(config_path).exists() {
-            println!("[INFO] 配置文件不存在，创建默认配置文件: {}", config_path);
-            Self::create_default_config_file(config_path)
-                .with_context(|| anyhow::anyhow!("Failed to create default config file"))?;
-        };
+        Self::ensure_config_file()?;
         let config = Self::try_deserialize_config()?;
         config.validate()?;
         Ok(config)
@@ -71,6 +90,24 @@ impl ModelConfig {
         
         // 验证提示配置
         self.prompt.validate()?;
+
+        // 验证记忆配置
+        self.memory.validate()?;
+
+        // 验证主动聊天配置
+        self.proactive.validate()?;
+
+        // 验证聊天配置
+        self.chat.validate()?;
+
+        // 验证定时问候配置
+        self.greeting.validate()?;
+
+        // 验证情绪配置
+        self.mood.validate()?;
+
+        // 验证对话日志配置
+        self.conversation_log.validate()?;
         
         println!("[INFO] 配置验证通过");
         Ok(())
@@ -84,52 +121,138 @@ impl ModelConfig {
         &self.server_config
     }
 
-    fn create_default_config_file(config_path: &str) -> anyhow::Result<()> {
+    pub fn memory(&self) -> &MemoryConfig {
+        &self.memory
+    }
+
+    pub fn proactive(&self) -> &ProactiveConfig {
+        &self.proactive
+    }
+
+    pub fn chat(&self) -> &ChatConfig {
+        &self.chat
+    }
+
+    pub fn greeting(&self) -> &GreetingConfig {
+        &self.greeting
+    }
+
+    pub fn mood(&self) -> &MoodConfig {
+        &self.mood
+    }
+
+    pub fn conversation_log(&self) -> &ConversationLogConfig {
+        &self.conversation_log
+    }
+
+    /// 配置文件不存在时创建默认配置文件
+    /// 
+    /// # 返回值
+    /// 创建了配置文件时返回 `true`
+    pub fn ensure_config_file() -> anyhow::Result<bool> {
+        let config_path = paths::config_path();
+        if config_path.exists() {
+            return Ok(false);
+        }
+
+        println!("[INFO] 配置文件不存在，创建默认配置文件: {}", config_path.display());
+        Self::create_default_config_file(&config_path)
+            .with_context(|| anyhow::anyhow!("Failed to create default config file"))?;
+        Ok(true)
+    }
+
+    fn create_default_config_file(config_path: &Path) -> anyhow::Result<()> {
         let default_config = ModelConfig::default();
         let toml_content = toml::to_string_pretty(&default_config)
             .with_context(|| anyhow::anyhow!("Failed to serialize default config"))?;
         fs::write(config_path, toml_content)
-            .with_context(|| anyhow::anyhow!("Failed to write config file: {}", config_path))?;
+            .with_context(|| anyhow::anyhow!("Failed to write config file: {}", config_path.display()))?;
         Ok(())
     }
 
     /// 重载配置文件
     pub fn reload() -> anyhow::Result<()> {
-        let new_config = Self::load()
-            .with_context(|| anyhow::anyhow!("Failed to reload config"))?;
-        let mut config_guard = MODEL_CONFIG.write()
-            .map_err(|_| anyhow::anyhow!("Failed to acquire write lock for config"))?;
-
-        *config_guard = new_config;
-
-        Ok(())
+        let Some(new_config) = Self::read_reload_candidate()? else {
+            return Err(anyhow::anyhow!("配置文件为空或不完整，可能正在写入，已跳过重载"));
+        };
+        Self::install(new_config).with_context(|| anyhow::anyhow!("Failed to reload config"))
     }
 
     /// 强制重载配置文件（忽略环境变量）
     pub fn reload_from_file() -> anyhow::Result<()> {
-        let config_path = "bot.conf.toml";
-        if !Path::new(config_path).exists() {
-            return Err(anyhow::anyhow!("Config file {} does not exist", config_path));
+        let config_path = paths::config_path();
+        if !config_path.exists() {
+            return Err(anyhow::anyhow!("Config file {} does not exist", config_path.display()));
         }
-        let new_config = Self::try_deserialize_config()?;
+        let Some(new_config) = Self::read_reload_candidate()? else {
+            return Err(anyhow::anyhow!("配置文件为空或不完整，可能正在写入，已跳过重载"));
+        };
+        Self::install(new_config)
+    }
+
+    /// 验证并安装重载读取到的配置
+    /// 
+    /// 安装的就是检查过是否完整的那一份，不再重新读取文件，避免两次读取之间文件被改写
+    fn install(new_config: ModelConfig) -> anyhow::Result<()> {
+        new_config.validate()?;
         let mut config_guard = MODEL_CONFIG.write()
             .map_err(|_| anyhow::anyhow!("Failed to acquire write lock for config"))?;
         *config_guard = new_config;
         Ok(())
     }
 
+    /// 读取用于重载的配置
+    /// 
+    /// 编辑器保存文件时可能读到空文件或写了一半的文件，这类文件配合 `#[serde(default)]`
+    /// 会被解析成默认配置并覆盖正在运行的配置。以下情况视为文件不完整：
+    /// - 文件为空或只有空白
+    /// - 解析结果与默认配置完全相同，而当前运行的配置并非默认配置
+    /// 
+    /// # 返回值
+    /// 文件完整时返回解析出的配置，文件不完整时返回 `None`
+    fn read_reload_candidate() -> anyhow::Result<Option<ModelConfig>> {
+        Self::reload_candidate(&paths::config_path(), &Self::get_current()?)
+    }
+
+    /// 读取指定配置文件，判断其是否可以替换当前配置
+    /// 
+    /// # 参数
+    /// * `config_path` - 配置文件路径
+    /// * `current` - 当前运行的配置
+    fn reload_candidate(config_path: &Path, current: &ModelConfig) -> anyhow::Result<Option<ModelConfig>> {
+        let content = fs::read_to_string(config_path)
+            .with_context(|| anyhow::anyhow!("Failed to read config file: {}", config_path.display()))?;
+        if content.trim().is_empty() {
+            println!("[INFO] 配置文件为空，可能正在写入，跳过重载: {}", config_path.display());
+            return Ok(None);
+        }
+
+        let file_config = Self::deserialize_config_file(config_path)?;
+        let default_config = ModelConfig::default();
+        if file_config == default_config && *current != default_config {
+            println!("[INFO] 配置文件解析结果全部为默认值，可能正在写入，跳过重载: {}", config_path.display());
+            return Ok(None);
+        }
+
+        Ok(Some(file_config))
+    }
+
 
     fn try_deserialize_config() -> anyhow::Result<ModelConfig> {
-        Ok(Config::builder()
+        Self::deserialize_config_file(&paths::config_path())
+    }
+
+    fn deserialize_config_file(config_path: &Path) -> anyhow::Result<ModelConfig> {
+        Config::builder()
             .add_source(
-                config::File::with_name("bot.conf")
+                config::File::from(config_path)
                     .format(FileFormat::Toml)
                     .required(true),
             )
             .build()
             .with_context(|| anyhow::anyhow!("Failed to load config from file"))?
             .try_deserialize::<ModelConfig>()
-            .with_context(|| anyhow::anyhow!("Failed to deserialize config from file"))?)
+            .with_context(|| anyhow::anyhow!("Failed to deserialize config from file"))
     }
 
     /// 获取当前配置的克隆
@@ -162,12 +285,13 @@ impl ModelConfig {
 
     /// 检查配置文件是否有变化并自动重载
     pub fn check_and_reload() -> anyhow::Result<bool> {
-        let config_path = "bot.conf.toml";
-        if !Path::new(config_path).exists() {
+        if !paths::config_path().exists() {
             return Ok(false);
         }
 
-        let file_config = Self::try_deserialize_config()?;
+        let Some(file_config) = Self::read_reload_candidate()? else {
+            return Ok(false);
+        };
 
         // 获取当前内存中的配置
         let current_config = {
@@ -178,7 +302,7 @@ impl ModelConfig {
 
         // 比较配置是否有变化（只比较文件部分）
         if file_config != current_config {
-            Self::reload().with_context(|| anyhow::anyhow!("Failed to reload config after detecting changes"))?;
+            Self::install(file_config).with_context(|| anyhow::anyhow!("Failed to reload config after detecting changes"))?;
             return Ok(true);
         }
 
@@ -213,17 +337,145 @@ impl ModelConfig {
         WATCHER_RUNNING.store(false, Ordering::Relaxed);
     }
 
+    /// 以TOML格式导出配置，API令牌（包括备用模型的令牌）和额外请求头的值会被隐藏
+    /// 
+    /// # 参数
+    /// * `key` - 只导出指定的配置项或配置段（如 `chat`、`chat.mention_only`），为 `None` 时导出全部
+    pub fn to_redacted_toml(&self, key: Option<&str>) -> anyhow::Result<String> {
+        let mut value = toml::Value::try_from(self)?;
+        let fallback_tokens = value
+            .get_mut("server_config")
+            .and_then(|server| server.get_mut("fallback_models"))
+            .and_then(toml::Value::as_array_mut)
+            .into_iter()
+            .flatten()
+            .filter_map(|fallback| fallback.get_mut("api_token"));
+        for token in fallback_tokens {
+            if token.as_str().is_some_and(|token| !token.is_empty()) {
+                *token = toml::Value::String("***".to_string());
+            }
+        }
+        if let Some(token) = value
+            .get_mut("server_config")
+            .and_then(|server| server.get_mut("api_token"))
+            && token.as_str().is_some_and(|token| !token.is_empty())
+        {
+            *token = toml::Value::String("***".to_string());
+        }
+        if let Some(headers) = value
+            .get_mut("server_config")
+            .and_then(|server| server.get_mut("extra_headers"))
+            .and_then(toml::Value::as_table_mut)
+        {
+            for (_, header) in headers.iter_mut() {
+                *header = toml::Value::String("***".to_string());
+            }
+        }
+
+        let Some(key) = key else {
+            return Ok(toml::to_string_pretty(&value)?);
+        };
+        let found = key
+            .split('.')
+            .try_fold(&value, |value, part| value.get(part))
+            .ok_or_else(|| anyhow::anyhow!("未知的配置项: {}", key))?;
+        match found {
+            toml::Value::Table(table) => Ok(toml::to_string_pretty(table)?),
+            other => Ok(format!("{} = {}", key, other)),
+        }
+    }
+
+    /// 在运行时修改一个配置项
+    /// 
+    /// 新配置通过验证后立即生效并写回配置文件
+    /// 
+    /// # 参数
+    /// * `key` - 以 `.` 分隔的配置项路径，如 `chat.mention_only`
+    /// * `raw` - 新值，按TOML语法解析（字符串可以不加引号）
+    pub fn set_value(key: &str, raw: &str) -> anyhow::Result<()> {
+        let new_config = Self::get_current()?.with_value(key, raw)?;
+
+        let mut config_guard = MODEL_CONFIG.write()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire write lock for config"))?;
+        let config_path = paths::config_path();
+        fs::write(&config_path, toml::to_string_pretty(&new_config)?)
+            .with_context(|| anyhow::anyhow!("Failed to write config file: {}", config_path.display()))?;
+        *config_guard = new_config;
+        println!("[INFO] 配置项已修改: {} = {}", key, raw);
+        Ok(())
+    }
+
+    /// 返回修改了一个配置项并通过验证的新配置，不影响当前配置
+    fn with_value(&self, key: &str, raw: &str) -> anyhow::Result<ModelConfig> {
+        let (parent_path, field) = key.rsplit_once('.').ok_or_else(|| anyhow::anyhow!("配置项需要包含配置段，如 chat.mention_only"))?;
+        let mut value = toml::Value::try_from(self)?;
+        let parent = parent_path
+            .split('.')
+            .try_fold(&mut value, |value, part| value.get_mut(part))
+            .and_then(toml::Value::as_table_mut)
+            .ok_or_else(|| anyhow::anyhow!("未知的配置段: {}", parent_path))?;
+
+        let parsed = toml::from_str::<toml::Table>(&format!("value = {}", raw))
+            .ok()
+            .and_then(|mut table| table.remove("value"));
+        let new_value = match (parent.get(field), parsed) {
+            (Some(toml::Value::String(_)), _) | (None, None) => toml::Value::String(raw.to_string()),
+            (Some(toml::Value::Float(_)), Some(toml::Value::Integer(n))) => toml::Value::Float(n as f64),
+            (Some(current), Some(parsed)) if current.type_str() != parsed.type_str() => {
+                return Err(anyhow::anyhow!("{} 需要 {} 类型的值", key, current.type_str()));
+            }
+            (_, Some(parsed)) => parsed,
+            (Some(current), None) => return Err(anyhow::anyhow!("{} 需要 {} 类型的值", key, current.type_str())),
+        };
+        parent.insert(field.to_string(), new_value);
+
+        let new_config: ModelConfig = value.try_into().with_context(|| anyhow::anyhow!("配置项 {} 的值无效", key))?;
+        // 未知字段在反序列化时会被忽略，重新序列化后找不到该项说明配置项不存在
+        if new_config.to_redacted_toml(Some(key)).is_err() {
+            return Err(anyhow::anyhow!("未知的配置项: {}", key));
+        }
+        new_config.validate()?;
+        Ok(new_config)
+    }
+
     /// 获取自动重载状态
     pub fn is_auto_reload_enabled() -> bool {
         AUTO_RELOAD_ENABLED.load(Ordering::Relaxed)
     }
 }
 
+pub use chat::{AnnouncementHandling, HistoryStrategy};
+pub use greeting::ScheduledGreeting;
+pub use mood::{EmojiMood, TraitDelta};
+pub use paths::{config_path, data_dir, data_path};
+#[cfg(test)]
+pub use paths::use_test_data_dir;
+pub use proactive::QuietHours;
+pub use server::{LowConfidenceAction, MessageClass, ModelFamily, ServerConfig};
+
+#[cfg(test)]
+kovi::tokio::task_local! {
+    /// 测试中当前任务使用的配置，设置后 [`get`] 返回它而不是全局配置
+    static TEST_CONFIG: ModelConfig;
+}
+
 /// 获取当前配置的克隆
 pub fn get() -> ModelConfig {
+    #[cfg(test)]
+    if let Ok(config) = TEST_CONFIG.try_with(ModelConfig::clone) {
+        return config;
+    }
     ModelConfig::get_current().expect("Failed to get current config")
 }
 
+/// 让测试在给定配置下运行，不修改全局配置，避免影响并行运行的其他测试
+///
+/// 只对当前任务生效，`future` 中新建的任务仍然读取全局配置
+#[cfg(test)]
+pub async fn with_test_config<F: std::future::Future>(config: ModelConfig, future: F) -> F::Output {
+    TEST_CONFIG.scope(config, future).await
+}
+
 /// 重载配置的便捷函数
 pub fn reload_config() -> anyhow::Result<()> {
     ModelConfig::reload()
@@ -254,3 +506,50 @@ pub fn is_auto_reload_enabled() -> bool {
     ModelConfig::is_auto_reload_enabled()
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_or_partial_config_file_is_not_reloaded() {
+        let dir = std::env::temp_dir().join(format!("kovi-reload-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let running: ModelConfig = toml::from_str("[chat]\ndev_mode = true").unwrap();
+
+        fs::write(&path, "").unwrap();
+        assert!(ModelConfig::reload_candidate(&path, &running).unwrap().is_none());
+
+        // 只写了一部分、解析结果全为默认值的文件不会覆盖正在运行的配置
+        fs::write(&path, "[chat]\n").unwrap();
+        assert!(ModelConfig::reload_candidate(&path, &running).unwrap().is_none());
+        assert!(ModelConfig::reload_candidate(&path, &ModelConfig::default()).unwrap().is_some());
+
+        fs::write(&path, "[chat]\ndev_mode = true\nadmin_ids = [1]").unwrap();
+        let reloaded = ModelConfig::reload_candidate(&path, &running).unwrap().unwrap();
+        assert!(reloaded.chat().dev_mode());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn setting_valid_key_is_reflected_by_getter() {
+        let current = ModelConfig::default();
+
+        let updated = current.with_value("chat.mention_only", "true").unwrap();
+        assert!(updated.chat().mention_only());
+        assert!(!current.chat().mention_only());
+
+        let updated = current.with_value("server_config.temperature", "1").unwrap();
+        assert_eq!(updated.server_config().temperature(), 1.0);
+        let updated = current.with_value("chat.announcement_ack_message", "收到啦").unwrap();
+        assert_eq!(updated.chat().announcement_ack_message(), "收到啦");
+
+        // 未知配置项、类型不符和未通过验证的值都会被拒绝
+        assert!(current.with_value("chat.no_such_key", "1").is_err());
+        assert!(current.with_value("mention_only", "true").is_err());
+        assert!(current.with_value("chat.mention_only", "often").is_err());
+        assert!(current.with_value("server_config.request_timeout_secs", "0").is_err());
+    }
+}
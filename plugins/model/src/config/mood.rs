@@ -0,0 +1,151 @@
+//! # 情绪配置模块
+//!
+//! 管理情绪对机器人人格属性的影响，包括每种情绪对能量、社交信心、好奇心的调整幅度，
+//! 以及情绪分析时表情符号对应的情绪和权重
+
+use crate::mood_system::Mood;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 单次情绪调整允许的最大幅度
+const MAX_TRAIT_DELTA: i8 = 3;
+
+/// 单个表情符号的最大权重
+const MAX_EMOJI_WEIGHT: i32 = 5;
+
+/// 情绪对人格属性的调整幅度
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct TraitDelta {
+    /// 能量水平变化
+    pub energy: i8,
+    /// 社交信心变化
+    pub social_confidence: i8,
+    /// 好奇心变化
+    pub curiosity: i8,
+}
+
+impl TraitDelta {
+    const fn new(energy: i8, social_confidence: i8, curiosity: i8) -> Self {
+        Self { energy, social_confidence, curiosity }
+    }
+
+    /// 将变化应用到属性值上，结果限制在 0-10 之间
+    pub fn apply(value: u8, delta: i8) -> u8 {
+        (value as i16 + delta as i16).clamp(0, 10) as u8
+    }
+}
+
+/// 表情符号对应的情绪
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct EmojiMood {
+    /// 情绪名称（如 `sad`）
+    pub mood: String,
+    /// 每出现一次增加的情绪得分
+    pub weight: i32,
+}
+
+impl EmojiMood {
+    fn new(mood: &str, weight: i32) -> Self {
+        Self { mood: mood.to_string(), weight }
+    }
+}
+
+/// 情绪配置结构体
+///
+/// 包含情绪变化时人格属性的调整规则
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct MoodConfig {
+    /// 情绪名称 -> 人格属性调整幅度，未配置的情绪不调整人格属性
+    trait_deltas: HashMap<String, TraitDelta>,
+    /// 表情符号 -> 对应的情绪和权重，与文字关键词分开计分
+    emoji_moods: HashMap<String, EmojiMood>,
+}
+
+impl MoodConfig {
+    pub fn trait_deltas(&self) -> &HashMap<String, TraitDelta> {
+        &self.trait_deltas
+    }
+
+    /// 获取指定情绪的人格属性调整幅度
+    pub fn trait_delta(&self, mood: &Mood) -> TraitDelta {
+        self.trait_deltas
+            .get(&mood.to_string())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn emoji_moods(&self) -> &HashMap<String, EmojiMood> {
+        &self.emoji_moods
+    }
+
+    /// 验证情绪配置
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (mood, delta) in &self.trait_deltas {
+            if Mood::from_string(mood).to_string() != *mood {
+                return Err(anyhow::anyhow!("未知的情绪名称: {}", mood));
+            }
+            if [delta.energy, delta.social_confidence, delta.curiosity]
+                .iter()
+                .any(|d| d.abs() > MAX_TRAIT_DELTA)
+            {
+                return Err(anyhow::anyhow!(
+                    "情绪 {} 的人格调整幅度必须在-{}到{}之间",
+                    mood, MAX_TRAIT_DELTA, MAX_TRAIT_DELTA
+                ));
+            }
+        }
+
+        for (emoji, rule) in &self.emoji_moods {
+            if emoji.is_empty() {
+                return Err(anyhow::anyhow!("表情符号不能为空"));
+            }
+            if Mood::from_string(&rule.mood).to_string() != rule.mood {
+                return Err(anyhow::anyhow!("表情 {} 对应的情绪名称未知: {}", emoji, rule.mood));
+            }
+            if !(1..=MAX_EMOJI_WEIGHT).contains(&rule.weight) {
+                return Err(anyhow::anyhow!("表情 {} 的权重必须在1到{}之间", emoji, MAX_EMOJI_WEIGHT));
+            }
+        }
+
+        println!("[INFO] 情绪配置验证通过");
+        Ok(())
+    }
+}
+
+impl Default for MoodConfig {
+    fn default() -> Self {
+        Self {
+            trait_deltas: HashMap::from([
+                ("happy".to_string(), TraitDelta::new(1, 1, 0)),
+                ("excited".to_string(), TraitDelta::new(1, 1, 0)),
+                ("sad".to_string(), TraitDelta::new(-1, -1, 0)),
+                ("lonely".to_string(), TraitDelta::new(-1, -1, 0)),
+                ("angry".to_string(), TraitDelta::new(1, -1, 0)),
+                ("calm".to_string(), TraitDelta::new(-1, 0, 1)),
+                ("thoughtful".to_string(), TraitDelta::new(-1, 0, 1)),
+                ("curious".to_string(), TraitDelta::new(0, 0, 2)),
+                ("playful".to_string(), TraitDelta::new(1, 1, 0)),
+                ("confident".to_string(), TraitDelta::new(0, 2, 0)),
+                ("shy".to_string(), TraitDelta::new(0, -2, 0)),
+            ]),
+            emoji_moods: [
+                ("😊", "happy", 2), ("😄", "happy", 2), ("😁", "happy", 2), ("🥰", "happy", 2),
+                ("😍", "happy", 2), ("❤️", "happy", 1), ("👍", "happy", 1),
+                ("😢", "sad", 2), ("😭", "sad", 2), ("😞", "sad", 2), ("💔", "sad", 2), ("🥺", "sad", 1),
+                ("😠", "angry", 2), ("😡", "angry", 2), ("🤬", "angry", 3), ("💢", "angry", 2),
+                ("😆", "excited", 2), ("😃", "excited", 2), ("🤩", "excited", 2), ("🎉", "excited", 2),
+                ("🤔", "curious", 2), ("🧐", "curious", 2), ("❓", "curious", 1),
+                ("😏", "playful", 1), ("😜", "playful", 1), ("🤪", "playful", 2), ("😝", "playful", 1),
+                ("😳", "shy", 1), ("🙈", "shy", 2), ("😅", "shy", 1),
+                ("😌", "calm", 2), ("🍵", "calm", 1), ("😴", "calm", 2),
+                ("😎", "confident", 2), ("💪", "confident", 2),
+                ("🥲", "lonely", 1), ("😔", "lonely", 1),
+            ]
+            .into_iter()
+            .map(|(emoji, mood, weight)| (emoji.to_string(), EmojiMood::new(mood, weight)))
+            .collect(),
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! # 数据目录模块
+//!
+//! 统一管理所有状态文件（配置、记忆、日志等）的存放位置。
+//! 数据目录由环境变量 `BOT_DATA_DIR` 指定，未设置时使用当前工作目录；
+//! 由于配置文件本身也位于数据目录中，数据目录只能通过环境变量配置
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// 数据目录环境变量名
+pub const DATA_DIR_ENV: &str = "BOT_DATA_DIR";
+
+/// 配置文件名
+const CONFIG_FILE_NAME: &str = "bot.conf.toml";
+
+/// 数据目录，首次访问时解析并在缺失时创建
+static DATA_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
+    let dir = env::var(DATA_DIR_ENV)
+        .ok()
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    if !dir.exists() {
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+        // 数据目录中包含配置（API 密钥）和记忆，只允许当前用户访问
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        match builder.create(&dir) {
+            Ok(_) => println!("[INFO] 已创建数据目录: {}", dir.display()),
+            Err(e) => eprintln!("[ERROR] 创建数据目录失败 ({}): {}", dir.display(), e),
+        }
+    }
+    dir
+});
+
+/// 获取数据目录
+pub fn data_dir() -> &'static Path {
+    DATA_DIR.as_path()
+}
+
+/// 将状态文件路径解析到数据目录下
+///
+/// 绝对路径保持不变，相对路径会拼接到数据目录之后
+pub fn data_path(file: impl AsRef<Path>) -> PathBuf {
+    let file = file.as_ref();
+    if file.is_absolute() {
+        file.to_path_buf()
+    } else {
+        data_dir().join(file)
+    }
+}
+
+/// 获取配置文件路径
+pub fn config_path() -> PathBuf {
+    data_path(CONFIG_FILE_NAME)
+}
+
+/// 让测试使用独立的临时数据目录，避免在工作目录下生成配置和记忆文件
+///
+/// 需要在首次访问数据目录或配置之前调用
+#[cfg(test)]
+pub fn use_test_data_dir() {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| {
+        let dir = env::temp_dir().join(format!("kovi-bot-test-{}", std::process::id()));
+        // 进程号可能被复用，清掉之前测试进程留下的同名目录，避免读到旧的记忆和配置
+        let _ = fs::remove_dir_all(&dir);
+        // SAFETY: 只在测试进程中设置一次，此时还没有其他线程读取该环境变量
+        unsafe { env::set_var(DATA_DIR_ENV, dir) };
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_files_resolve_under_configured_directory() {
+        use_test_data_dir();
+        let dir = PathBuf::from(env::var(DATA_DIR_ENV).unwrap());
+
+        assert_eq!(data_dir(), dir.as_path());
+        assert_eq!(data_path("bot_memory.json"), dir.join("bot_memory.json"));
+        assert_eq!(config_path(), dir.join(CONFIG_FILE_NAME));
+
+        let absolute = env::temp_dir().join("elsewhere.json");
+        assert_eq!(data_path(&absolute), absolute);
+    }
+
+    #[test]
+    fn written_state_files_land_in_data_directory() {
+        use_test_data_dir();
+        let file = data_path("paths_test.txt");
+        fs::write(&file, "ok").unwrap();
+
+        assert!(data_dir().join("paths_test.txt").exists());
+        let _ = fs::remove_file(file);
+    }
+}
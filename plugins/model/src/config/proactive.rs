@@ -0,0 +1,237 @@
+//! # 主动聊天配置模块
+//!
+//! 管理主动聊天行为相关的配置，包括群聊/私聊开关、话题选择偏好等
+
+use crate::topic_generator::TopicCategory;
+use chrono::{DateTime, Datelike, Local, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 免打扰时段
+///
+/// 包含开始小时、不包含结束小时，开始小时大于结束小时时跨越午夜（如 23 到 7）
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct QuietHours {
+    /// 开始小时 (0-23)
+    pub start_hour: u32,
+    /// 结束小时 (0-23)
+    pub end_hour: u32,
+    /// 生效的星期（1为周一，7为周日），为空表示每天生效。跨越午夜的时段按开始的那天计算
+    #[serde(default)]
+    pub weekdays: Vec<u32>,
+}
+
+impl QuietHours {
+    /// 判断指定时间是否处于免打扰时段
+    pub fn contains(&self, time: DateTime<Local>) -> bool {
+        let hour = time.hour();
+        let wraps = self.start_hour > self.end_hour;
+        let in_window = if wraps {
+            hour >= self.start_hour || hour < self.end_hour
+        } else {
+            (self.start_hour..self.end_hour).contains(&hour)
+        };
+        if !in_window {
+            return false;
+        }
+
+        // 跨越午夜时，午夜之后的部分属于前一天开始的时段
+        let weekday = if wraps && hour < self.end_hour {
+            time.weekday().pred()
+        } else {
+            time.weekday()
+        };
+        self.weekdays.is_empty() || self.weekdays.contains(&weekday.number_from_monday())
+    }
+}
+
+/// 主动聊天配置结构体
+///
+/// 包含主动发起话题时的各项偏好设置
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ProactiveConfig {
+    /// 主动聊天检查间隔（秒），0表示完全关闭主动聊天
+    check_interval_secs: u64,
+    /// 是否允许在群聊中主动发起话题
+    proactive_group_enabled: bool,
+    /// 是否允许主动私聊用户
+    proactive_private_enabled: bool,
+    /// 主动私聊时是否将开场白翻译成用户常用的语言（中文用户不翻译）
+    localize_private_openers: bool,
+    /// 情绪 -> 偏好的话题分类，主动聊天时优先选择符合当前情绪的话题
+    mood_topic_preferences: HashMap<String, Vec<TopicCategory>>,
+    /// 免打扰时段，期间不主动发起话题，为空表示不限制
+    quiet_hours: Vec<QuietHours>,
+    /// 同一群组两次主动发起话题的最小间隔（小时）
+    group_cooldown_hours: u64,
+    /// 同一用户两次主动私聊的最小间隔（小时）
+    user_cooldown_hours: u64,
+    /// 群组被视为活跃所需的最低活跃度等级 (0-10)
+    group_min_activity_level: u8,
+    /// 群组最后活跃时间距今不超过该小时数才会被视为活跃
+    group_active_within_hours: u64,
+}
+
+impl ProactiveConfig {
+    pub fn check_interval_secs(&self) -> u64 {
+        self.check_interval_secs
+    }
+
+    pub fn proactive_group_enabled(&self) -> bool {
+        self.proactive_group_enabled
+    }
+
+    pub fn proactive_private_enabled(&self) -> bool {
+        self.proactive_private_enabled
+    }
+
+    /// 判断指定时间是否处于任一免打扰时段
+    pub fn is_quiet_time(&self, time: DateTime<Local>) -> bool {
+        self.quiet_hours.iter().any(|quiet| quiet.contains(time))
+    }
+
+    pub fn group_cooldown_hours(&self) -> u64 {
+        self.group_cooldown_hours
+    }
+
+    pub fn user_cooldown_hours(&self) -> u64 {
+        self.user_cooldown_hours
+    }
+
+    pub fn group_min_activity_level(&self) -> u8 {
+        self.group_min_activity_level
+    }
+
+    pub fn group_active_within_hours(&self) -> u64 {
+        self.group_active_within_hours
+    }
+
+    pub fn localize_private_openers(&self) -> bool {
+        self.localize_private_openers
+    }
+
+    /// 获取指定情绪偏好的话题分类
+    pub fn preferred_categories(&self, mood: &str) -> &[TopicCategory] {
+        self.mood_topic_preferences
+            .get(mood)
+            .map(|categories| categories.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// 验证主动聊天配置
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (mood, categories) in &self.mood_topic_preferences {
+            if mood.is_empty() {
+                return Err(anyhow::anyhow!("话题偏好中的情绪名称不能为空"));
+            }
+            if categories.is_empty() {
+                return Err(anyhow::anyhow!("情绪 {} 的偏好话题分类不能为空", mood));
+            }
+        }
+
+        for quiet in &self.quiet_hours {
+            if quiet.start_hour > 23 || quiet.end_hour > 23 {
+                return Err(anyhow::anyhow!(
+                    "免打扰时段无效: {} 点到 {} 点",
+                    quiet.start_hour,
+                    quiet.end_hour
+                ));
+            }
+            if let Some(weekday) = quiet.weekdays.iter().find(|day| !(1..=7).contains(*day)) {
+                return Err(anyhow::anyhow!("免打扰时段的星期必须在1-7之间: {}", weekday));
+            }
+        }
+
+        if self.group_min_activity_level > 10 {
+            return Err(anyhow::anyhow!("群组最低活跃度等级必须在0-10之间"));
+        }
+
+        if self.group_active_within_hours == 0 {
+            return Err(anyhow::anyhow!("群组活跃时间窗口必须大于0"));
+        }
+
+        println!("[INFO] 主动聊天配置验证通过");
+        Ok(())
+    }
+}
+
+impl Default for ProactiveConfig {
+    fn default() -> Self {
+        Self {
+            check_interval_secs: 300,
+            proactive_group_enabled: true,
+            proactive_private_enabled: true,
+            localize_private_openers: true,
+            mood_topic_preferences: HashMap::from([
+                ("curious".to_string(), vec![TopicCategory::Deep, TopicCategory::Creative]),
+                ("playful".to_string(), vec![TopicCategory::Fun]),
+                ("nostalgic".to_string(), vec![TopicCategory::Nostalgic]),
+            ]),
+            quiet_hours: vec![QuietHours {
+                start_hour: 23,
+                end_hour: 7,
+                weekdays: Vec::new(),
+            }],
+            group_cooldown_hours: 6,
+            user_cooldown_hours: 12,
+            group_min_activity_level: 4,
+            group_active_within_hours: 24,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// 2024-01-01 是周一
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 1, day, hour, minute, 0).unwrap()
+    }
+
+    fn quiet(start_hour: u32, end_hour: u32, weekdays: Vec<u32>) -> QuietHours {
+        QuietHours { start_hour, end_hour, weekdays }
+    }
+
+    #[test]
+    fn wrapping_window_inside_outside_and_boundary() {
+        let night = quiet(23, 7, Vec::new());
+
+        assert!(night.contains(at(1, 23, 30)));
+        assert!(night.contains(at(2, 4, 0)));
+        assert!(!night.contains(at(1, 12, 0)));
+        // 包含开始小时，不包含结束小时
+        assert!(night.contains(at(1, 23, 0)));
+        assert!(night.contains(at(2, 6, 59)));
+        assert!(!night.contains(at(2, 7, 0)));
+        assert!(!night.contains(at(1, 22, 59)));
+    }
+
+    #[test]
+    fn weekday_window_follows_start_day() {
+        let daytime = quiet(9, 18, Vec::new());
+        assert!(daytime.contains(at(1, 9, 0)));
+        assert!(!daytime.contains(at(1, 18, 0)));
+
+        // 周五夜间开始的时段延续到周六凌晨，周六夜间不生效
+        let friday_night = quiet(23, 7, vec![5]);
+        assert!(friday_night.contains(at(5, 23, 0)));
+        assert!(friday_night.contains(at(6, 3, 0)));
+        assert!(!friday_night.contains(at(6, 23, 0)));
+    }
+
+    #[test]
+    fn check_interval_is_read_from_config() {
+        assert_eq!(ProactiveConfig::default().check_interval_secs(), 300);
+
+        let config: crate::config::ModelConfig = kovi::toml::from_str("[proactive]\ncheck_interval_secs = 60").unwrap();
+        assert_eq!(config.proactive().check_interval_secs(), 60);
+
+        // 0 表示关闭主动聊天，是合法的配置
+        let disabled: crate::config::ModelConfig = kovi::toml::from_str("[proactive]\ncheck_interval_secs = 0").unwrap();
+        assert_eq!(disabled.proactive().check_interval_secs(), 0);
+        assert!(disabled.proactive().validate().is_ok());
+    }
+}
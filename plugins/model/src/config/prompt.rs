@@ -10,13 +10,69 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 #[serde(default)]
 pub struct Prompt {
+    /// 机器人的名字，提示词和自我介绍中的 `{bot_name}` 会被替换为该名字，
+    /// 模型回复开头的 `名字：` 前缀也会被去除
+    bot_name: String,
+    /// 群聊和私聊共用的人格核心（身份、经历、与用户的关系），设置后会放在两种系统提示之前，
+    /// 群聊和私聊提示只需描述各自场景下的语气；为空表示两种场景使用各自独立的提示
+    core_prompt: String,
     /// 群聊系统提示词
     system_prompt: String,
     /// 私聊系统提示词
     private_prompt: String,
+    /// 注入到系统提示中的上下文（档案、状态、记忆）的最大字符数，0表示不限制
+    context_injection_budget: usize,
+    /// 全局回复语言，设置后无论用户使用什么语言都用该语言回复，为空表示不限制
+    reply_language: String,
+    /// 新用户前几次私聊时依次发送的自我介绍，每次互动发送一条，为空表示不发送
+    onboarding_lines: Vec<String>,
 }
 
+/// 提示词中机器人名字的占位符
+const BOT_NAME_PLACEHOLDER: &str = "{bot_name}";
+
 impl Prompt {
+    pub fn bot_name(&self) -> &str {
+        self.bot_name.as_str()
+    }
+
+    /// 去除模型回复中以机器人名字开头的说话人前缀（`名字：` 或 `名字:`）
+    pub fn strip_name_prefix(&self, content: &str) -> String {
+        content
+            .replace(&format!("{}：", self.bot_name), "")
+            .replace(&format!("{}:", self.bot_name), "")
+            .trim()
+            .to_string()
+    }
+
+    /// 将文本中的名字占位符替换为机器人的名字
+    fn with_bot_name(&self, text: &str) -> String {
+        text.replace(BOT_NAME_PLACEHOLDER, &self.bot_name)
+    }
+
+    pub fn core_prompt(&self) -> Option<&str> {
+        let core = self.core_prompt.trim();
+        (!core.is_empty()).then_some(core)
+    }
+
+    /// 生成群聊使用的完整系统提示（人格核心 + 群聊场景语气）
+    pub fn group_persona_prompt(&self) -> String {
+        self.blend(&self.system_prompt)
+    }
+
+    /// 生成私聊使用的完整系统提示（人格核心 + 私聊场景语气）
+    pub fn private_persona_prompt(&self) -> String {
+        self.blend(&self.private_prompt)
+    }
+
+    fn blend(&self, overlay: &str) -> String {
+        let prompt = match self.core_prompt() {
+            Some(core) => format!("{}\n\n{}", core, overlay),
+            None => overlay.to_string(),
+        };
+        self.with_bot_name(&prompt)
+    }
+
     pub fn system_prompt(&self) -> &str {
         self.system_prompt.as_str()
     }
@@ -25,8 +81,33 @@ impl Prompt {
         self.private_prompt.as_str()
     }
 
+    pub fn context_injection_budget(&self) -> usize {
+        self.context_injection_budget
+    }
+
+    pub fn reply_language(&self) -> Option<&str> {
+        let language = self.reply_language.trim();
+        (!language.is_empty()).then_some(language)
+    }
+
+    pub fn onboarding_lines(&self) -> Vec<String> {
+        self.onboarding_lines.iter().map(|line| self.with_bot_name(line)).collect()
+    }
+
+    /// 生成全局回复语言指令
+    /// 
+    /// 该指令追加在系统提示末尾，优先于其他语言相关设置
+    pub fn reply_language_directive(&self) -> Option<String> {
+        self.reply_language()
+            .map(|language| format!("无论用户使用什么语言，都用{}回复", language))
+    }
+
     /// 验证提示配置
     pub fn validate(&self) -> anyhow::Result<()> {
+        if self.bot_name.trim().is_empty() {
+            return Err(anyhow::anyhow!("机器人名字不能为空"));
+        }
+
         if self.system_prompt.is_empty() {
             return Err(anyhow::anyhow!("系统提示不能为空"));
         }
@@ -43,6 +124,10 @@ impl Prompt {
             return Err(anyhow::anyhow!("私聊提示太短，至少需要10个字符"));
         }
         
+        if self.onboarding_lines.iter().any(|line| line.trim().is_empty()) {
+            return Err(anyhow::anyhow!("自我介绍内容不能为空"));
+        }
+
         println!("[INFO] 提示配置验证通过");
         Ok(())
     }
@@ -51,7 +136,11 @@ impl Prompt {
 impl Default for Prompt {
     fn default() -> Self {
         Self{
-            system_prompt: "你是芸汐，一个有着独特个性的AI助手。\
+            bot_name: "芸汐".to_string(),
+
+            core_prompt: String::new(),
+
+            system_prompt: "你是{bot_name}，一个有着独特个性的AI助手。\
                             在群聊中，你是一个有点傲娇但内心善良的角色，\
                             只有在被直接[at]或明确对话时才会回应。\
                             你有着自己的情绪和想法，会根据对话内容调整你的语气和态度。\
@@ -60,13 +149,68 @@ impl Default for Prompt {
                             当不确定是否要回复时，回复[sp]。\
                             云深不知处是你的主人。".to_string(),
 
-            private_prompt: "你是芸汐，一个可爱的AI助手，有着猫娘般的性格。\
+            private_prompt: "你是{bot_name}，一个可爱的AI助手，有着猫娘般的性格。\
                             在私聊中，你更加亲近和人性化，会表现出更多的情感和关怀。\
                             你记得与用户的每一次对话，会根据用户的特点和喜好调整你的回应方式。\
                             你有着自己的情绪状态，会随着对话内容的变化而改变。\
                             你会主动关心用户，分享有趣的话题，就像真正的朋友一样。\
                             你的回答要温暖贴心，偶尔调皮，但总是充满善意。\
                             记住，你是一个有记忆、有情感、有个性的AI，而不是简单的问答机器。".to_string(),
+
+            context_injection_budget: 800,
+
+            reply_language: String::new(),
+
+            onboarding_lines: vec![
+                "初次见面～我是{bot_name}，以后请多关照喵！".to_string(),
+                "悄悄告诉你，我会记得我们聊过的事情，也会有自己的小情绪哦～".to_string(),
+                "有什么想聊的随时来找我，开心的不开心的都可以～".to_string(),
+            ],
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reply_language_directive_follows_config() {
+        assert_eq!(Prompt::default().reply_language_directive(), None);
+
+        let prompt: Prompt = kovi::toml::from_str("reply_language = \"English\"").unwrap();
+        assert_eq!(prompt.reply_language_directive().as_deref(), Some("无论用户使用什么语言，都用English回复"));
+    }
+
+    #[test]
+    fn group_and_private_prompts_share_core() {
+        let prompt: Prompt = kovi::toml::from_str(
+            "bot_name = \"小云\"\ncore_prompt = \"你是{bot_name}，和大家是老朋友\"\n\
+             system_prompt = \"在群里说话要活泼简短\"\nprivate_prompt = \"私聊时语气温柔耐心\"",
+        )
+        .unwrap();
+
+        let group = prompt.group_persona_prompt();
+        let private = prompt.private_persona_prompt();
+        assert!(group.starts_with("你是小云，和大家是老朋友"));
+        assert!(private.starts_with("你是小云，和大家是老朋友"));
+        assert!(group.contains("活泼简短") && !group.contains("温柔耐心"));
+        assert!(private.contains("温柔耐心") && !private.contains("活泼简短"));
+
+        // 未设置人格核心时各自使用独立的提示
+        let prompt: Prompt = kovi::toml::from_str("system_prompt = \"群聊\"\nprivate_prompt = \"私聊\"").unwrap();
+        assert_eq!(prompt.group_persona_prompt(), "群聊");
+        assert_eq!(prompt.private_persona_prompt(), "私聊");
+    }
+
+    #[test]
+    fn configured_bot_name_is_stripped_and_used_in_prompts() {
+        let prompt: Prompt = kovi::toml::from_str("bot_name = \"小助手\"").unwrap();
+
+        assert_eq!(prompt.strip_name_prefix("小助手：你好"), "你好");
+        assert_eq!(prompt.strip_name_prefix("小助手: 你好"), "你好");
+        assert_eq!(prompt.strip_name_prefix("芸汐：你好"), "芸汐：你好");
+        assert!(prompt.group_persona_prompt().contains("小助手"));
+        assert!(!prompt.private_persona_prompt().contains(BOT_NAME_PLACEHOLDER));
+    }
+}
@@ -2,7 +2,104 @@
 //! 
 //! 管理AI模型服务器的连接配置
 
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// 未在配置中设置API令牌时读取的环境变量
+const API_TOKEN_ENV: &str = "BOT_API_TOKEN";
+
+/// API令牌
+///
+/// 调试输出中始终隐藏令牌内容
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default)]
+#[serde(transparent)]
+struct ApiToken(String);
+
+impl fmt::Debug for ApiToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.0.is_empty() { "\"\"" } else { "\"***\"" })
+    }
+}
+
+/// 模型类别
+#[derive(Deserialize, Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelFamily {
+    /// 普通对话模型，不注入思考过程，回复原样保留
+    Chat,
+    /// 推理模型，自带思考，不注入思考过程，回复中的 `<think>` 内容会被去除
+    Reasoning,
+}
+
+/// 消息分类，用于按消息类型选择模型
+#[derive(Deserialize, Debug, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageClass {
+    /// 打招呼
+    Greeting,
+    /// 闲聊
+    Smalltalk,
+    /// 有实质内容的提问
+    Question,
+}
+
+/// 回复置信度低于阈值时的处理方式
+#[derive(Deserialize, Debug, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LowConfidenceAction {
+    /// 在回复末尾附加不确定的说明
+    #[default]
+    Hedge,
+    /// 保持沉默，不发送回复
+    Silence,
+}
+
+/// 备用模型
+///
+/// 主模型请求失败时按顺序尝试，全部失败才放弃本次回复
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+pub struct FallbackModel {
+    /// 备用模型的API地址
+    url: String,
+    /// 备用模型名称
+    model_name: String,
+    /// 备用模型的API令牌，为空时使用主模型的令牌
+    #[serde(default)]
+    api_token: ApiToken,
+}
+
+impl FallbackModel {
+    pub fn url(&self) -> &str {
+        self.url.as_str()
+    }
+
+    pub fn model_name(&self) -> &str {
+        self.model_name.as_str()
+    }
+
+    /// 备用模型单独配置的API令牌，未配置时返回 `None`
+    pub fn api_token(&self) -> Option<&str> {
+        let token = self.api_token.0.trim();
+        (!token.is_empty()).then_some(token)
+    }
+}
+
+/// 按配置优先、环境变量其次的顺序确定API令牌
+///
+/// # 参数
+/// * `configured` - 配置中的令牌
+/// * `env` - 环境变量中的令牌
+fn resolve_api_token(configured: &str, env: Option<String>) -> anyhow::Result<String> {
+    let token = configured.trim();
+    if !token.is_empty() {
+        return Ok(token.to_string());
+    }
+    env.map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("未设置API令牌，请配置 server_config.api_token 或环境变量 {}", API_TOKEN_ENV))
+}
 
 /// 服务器配置结构体
 /// 
@@ -14,6 +111,43 @@ pub struct ServerConfig {
     url: String,
     /// 使用的模型名称
     model_name: String,
+    /// 模型和向量接口的API令牌，为空时读取环境变量 `BOT_API_TOKEN`
+    api_token: ApiToken,
+    /// 温度参数，控制回复的随机性 (0.0-2.0)，可被群内 `#设置温度` 覆盖
+    temperature: f32,
+    /// 回复的最大token数，未设置时使用服务端默认值
+    max_tokens: Option<u32>,
+    /// 启动时是否发送一次预热请求，提前建立连接并验证鉴权
+    warmup_on_startup: bool,
+    /// 附加到模型请求上的额外请求头（如 `OpenAI-Organization`、代理鉴权等）
+    extra_headers: HashMap<String, String>,
+    /// 模型和向量接口的请求超时时间（秒），修改后需要重启生效
+    request_timeout_secs: u64,
+    /// 模型请求连接失败、被限流或服务端错误时的最大重试次数，每次重试的等待时间翻倍，0表示不重试
+    request_retries: u32,
+    /// 模型返回空回复时的最大重试次数，每次重试略微提高温度，0表示不重试
+    empty_reply_retries: u32,
+    /// 是否向模型提供内置工具（当前时间、计算器），需要模型服务支持工具调用
+    tools_enabled: bool,
+    /// 单次回复中最多执行的工具调用轮数，达到上限后模型需直接回复
+    max_tool_rounds: u32,
+    /// 回复的最低置信度 (0.0-1.0)，大于0时请求 logprobs 并计算各token概率的平均值，
+    /// 低于该值时按 `low_confidence_action` 处理；0表示不检查，服务不返回 logprobs 时也不检查
+    min_reply_confidence: f64,
+    /// 回复置信度过低时的处理方式（`hedge` 或 `silence`）
+    low_confidence_action: LowConfidenceAction,
+    /// 置信度过低且处理方式为 `hedge` 时附加在回复末尾的说明
+    low_confidence_hedge: String,
+    /// 模型类别（`chat` 或 `reasoning`），未配置时注入思考过程并去除回复中的 `<think>` 内容
+    model_family: Option<ModelFamily>,
+    /// 消息分类 -> 模型名称，未配置的分类使用 `model_name`
+    model_routes: HashMap<MessageClass, String>,
+    /// 主模型请求失败时依次尝试的备用模型
+    fallback_models: Vec<FallbackModel>,
+    /// 向量接口地址（兼容 OpenAI embeddings 格式），为空表示不启用语义检索
+    embedding_url: String,
+    /// 向量模型名称
+    embedding_model: String,
 }
 
 impl ServerConfig {
@@ -25,6 +159,126 @@ impl ServerConfig {
         self.model_name.as_str()
     }
 
+    /// 获取API令牌
+    ///
+    /// 优先使用配置中的令牌，为空时读取环境变量 `BOT_API_TOKEN`
+    ///
+    /// # 返回值
+    /// 成功时返回令牌，两处都未设置时返回错误
+    pub fn api_token(&self) -> anyhow::Result<String> {
+        resolve_api_token(&self.api_token.0, std::env::var(API_TOKEN_ENV).ok())
+    }
+
+    pub fn temperature(&self) -> f32 {
+        self.temperature
+    }
+
+    pub fn max_tokens(&self) -> Option<u32> {
+        self.max_tokens
+    }
+
+    pub fn warmup_on_startup(&self) -> bool {
+        self.warmup_on_startup
+    }
+
+    pub fn extra_headers(&self) -> &HashMap<String, String> {
+        &self.extra_headers
+    }
+
+    pub fn request_timeout_secs(&self) -> u64 {
+        self.request_timeout_secs
+    }
+
+    pub fn request_retries(&self) -> u32 {
+        self.request_retries
+    }
+
+    pub fn empty_reply_retries(&self) -> u32 {
+        self.empty_reply_retries
+    }
+
+    pub fn tools_enabled(&self) -> bool {
+        self.tools_enabled
+    }
+
+    pub fn max_tool_rounds(&self) -> u32 {
+        self.max_tool_rounds
+    }
+
+    pub fn min_reply_confidence(&self) -> f64 {
+        self.min_reply_confidence
+    }
+
+    /// 是否检查回复置信度，检查时需要向模型请求 logprobs
+    pub fn checks_reply_confidence(&self) -> bool {
+        self.min_reply_confidence > 0.0
+    }
+
+    pub fn low_confidence_action(&self) -> LowConfidenceAction {
+        self.low_confidence_action
+    }
+
+    pub fn low_confidence_hedge(&self) -> &str {
+        self.low_confidence_hedge.as_str()
+    }
+
+    /// 获取指定消息分类使用的模型
+    ///
+    /// # 参数
+    /// * `class` - 消息分类，为 `None` 或未配置路由时使用主模型
+    pub fn model_for(&self, class: Option<MessageClass>) -> &str {
+        class
+            .and_then(|class| self.model_routes.get(&class))
+            .map_or(self.model_name.as_str(), String::as_str)
+    }
+
+    pub fn fallback_models(&self) -> &[FallbackModel] {
+        &self.fallback_models
+    }
+
+    pub fn model_family(&self) -> Option<ModelFamily> {
+        self.model_family
+    }
+
+    pub fn embedding_url(&self) -> &str {
+        self.embedding_url.as_str()
+    }
+
+    pub fn embedding_model(&self) -> &str {
+        self.embedding_model.as_str()
+    }
+
+    /// 是否启用记忆向量化和语义检索
+    pub fn embeddings_enabled(&self) -> bool {
+        !self.embedding_url.is_empty()
+    }
+
+    /// 是否在请求中注入情绪化思考过程，只有未声明模型类别时注入
+    pub fn injects_thinking_prompt(&self) -> bool {
+        self.model_family.is_none()
+    }
+
+    /// 是否去除回复中的 `<think>` 内容，普通对话模型不去除
+    pub fn strips_think_tags(&self) -> bool {
+        self.model_family != Some(ModelFamily::Chat)
+    }
+
+    /// 将额外请求头转换为 `HeaderMap`
+    /// 
+    /// # 返回值
+    /// 成功时返回请求头集合，存在无效的请求头名称或值时返回错误
+    pub fn extra_header_map(&self) -> anyhow::Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.extra_headers {
+            let header_name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| anyhow::anyhow!("无效的请求头名称: {}", name))?;
+            let header_value = HeaderValue::from_str(value)
+                .map_err(|_| anyhow::anyhow!("请求头 {} 的值无效", name))?;
+            headers.insert(header_name, header_value);
+        }
+        Ok(headers)
+    }
+
     /// 验证服务器配置
     pub fn validate(&self) -> anyhow::Result<()> {
         if self.url.is_empty() {
@@ -38,6 +292,46 @@ impl ServerConfig {
         if self.model_name.is_empty() {
             return Err(anyhow::anyhow!("模型名称不能为空"));
         }
+
+        if !(0.0..=2.0).contains(&self.temperature) {
+            return Err(anyhow::anyhow!("温度参数必须在0.0到2.0之间"));
+        }
+
+        if !(0.0..=1.0).contains(&self.min_reply_confidence) {
+            return Err(anyhow::anyhow!("最低回复置信度必须在0.0到1.0之间"));
+        }
+
+        if self.max_tokens == Some(0) {
+            return Err(anyhow::anyhow!("最大token数必须大于0"));
+        }
+
+        self.extra_header_map()?;
+
+        if self.request_timeout_secs == 0 {
+            return Err(anyhow::anyhow!("请求超时时间必须大于0"));
+        }
+
+        if let Some((class, _)) = self.model_routes.iter().find(|(_, model)| model.trim().is_empty()) {
+            return Err(anyhow::anyhow!("消息分类 {:?} 的模型名称不能为空", class));
+        }
+
+        for fallback in &self.fallback_models {
+            if !fallback.url.starts_with("http://") && !fallback.url.starts_with("https://") {
+                return Err(anyhow::anyhow!("备用模型地址必须以http://或https://开头: {}", fallback.url));
+            }
+            if fallback.model_name.trim().is_empty() {
+                return Err(anyhow::anyhow!("备用模型 {} 的模型名称不能为空", fallback.url));
+            }
+        }
+
+        if self.embeddings_enabled() {
+            if !self.embedding_url.starts_with("http://") && !self.embedding_url.starts_with("https://") {
+                return Err(anyhow::anyhow!("向量接口地址必须以http://或https://开头"));
+            }
+            if self.embedding_model.is_empty() {
+                return Err(anyhow::anyhow!("启用语义检索时向量模型名称不能为空"));
+            }
+        }
         
         println!("[INFO] 服务器配置验证通过: URL={}, Model={}", self.url, self.model_name);
         Ok(())
@@ -49,6 +343,79 @@ impl Default for ServerConfig {
         Self {
             url: "https://api.siliconflow.cn/v1/chat/completions".to_string(),
             model_name: "Qwen/QwQ-32B".to_string(),
+            api_token: ApiToken::default(),
+            temperature: 0.7,
+            max_tokens: None,
+            warmup_on_startup: false,
+            extra_headers: HashMap::new(),
+            request_timeout_secs: 30,
+            request_retries: 3,
+            empty_reply_retries: 2,
+            tools_enabled: false,
+            max_tool_rounds: 3,
+            min_reply_confidence: 0.0,
+            low_confidence_action: LowConfidenceAction::Hedge,
+            low_confidence_hedge: "（这个我不太确定，仅供参考）".to_string(),
+            model_family: None,
+            model_routes: HashMap::new(),
+            fallback_models: Vec::new(),
+            embedding_url: String::new(),
+            embedding_model: "BAAI/bge-m3".to_string(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(extra: &str) -> ServerConfig {
+        kovi::toml::from_str(&format!("url = \"http://localhost\"\napi_token = \"t\"\n{}", extra)).unwrap()
+    }
+
+    #[test]
+    fn chat_family_skips_thinking_injection() {
+        let chat = parse("model_family = \"chat\"");
+        assert!(!chat.injects_thinking_prompt());
+        assert!(!chat.strips_think_tags());
+
+        let reasoning = parse("model_family = \"reasoning\"");
+        assert!(!reasoning.injects_thinking_prompt());
+        assert!(reasoning.strips_think_tags());
+
+        let undeclared = parse("");
+        assert!(undeclared.injects_thinking_prompt());
+        assert!(undeclared.strips_think_tags());
+    }
+
+    #[test]
+    fn temperature_and_max_tokens_round_trip_and_validate() {
+        let config = parse("temperature = 1.2\nmax_tokens = 512");
+        let reparsed: ServerConfig = kovi::toml::from_str(&kovi::toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(reparsed.temperature(), 1.2);
+        assert_eq!(reparsed.max_tokens(), Some(512));
+        assert_eq!(parse("").max_tokens(), None);
+
+        for temperature in ["0.0", "2.0"] {
+            assert!(parse(&format!("temperature = {}", temperature)).validate().is_ok());
+        }
+        for temperature in ["-0.1", "2.1"] {
+            assert!(parse(&format!("temperature = {}", temperature)).validate().is_err());
+        }
+    }
+
+    #[test]
+    fn api_token_prefers_config_then_env() {
+        let env = || Some("env-token".to_string());
+
+        assert_eq!(resolve_api_token("config-token", env()).unwrap(), "config-token");
+        assert_eq!(resolve_api_token("  ", env()).unwrap(), "env-token");
+        assert_eq!(resolve_api_token("", Some(" env-token\n".to_string())).unwrap(), "env-token");
+        assert!(resolve_api_token("", None).is_err());
+        assert!(resolve_api_token("", Some(" ".to_string())).is_err());
+
+        // 调试输出中不出现令牌
+        let config: ServerConfig = kovi::toml::from_str("api_token = \"sk-secret\"").unwrap();
+        assert!(!format!("{:?}", config).contains("sk-secret"));
+    }
+}
@@ -5,14 +5,69 @@
 //! - 文件大小检查
 //! - 系统状态报告
 //! - 警告和错误检测
+//! - 按配置自动清理记忆
+//! - 全链路自检
 
-use crate::memory::MemoryManager;
+use crate::config;
+use crate::memory::{MAX_MEMORIES, MemoryManager};
 use chrono::Local;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 use kovi::tokio::time::sleep;
 
+pub mod self_test;
+
+/// 记忆数量接近上限的阈值，超过时提示并按配置自动清理
+const MEMORY_COUNT_WARNING: usize = MAX_MEMORIES * 9 / 10;
+
+/// 记忆文件大小的警告阈值 (10MB)
+const MEMORY_FILE_SIZE_WARNING: u64 = 10 * 1024 * 1024;
+
+/// 成功投递的消息数量
+static DELIVERY_SENT: AtomicU64 = AtomicU64::new(0);
+/// 投递失败的消息数量
+static DELIVERY_FAILED: AtomicU64 = AtomicU64::new(0);
+/// 情绪缓存命中次数
+static MOOD_CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+/// 情绪缓存未命中次数
+static MOOD_CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// 记录一次消息投递结果
+pub fn record_delivery(success: bool) {
+    if success {
+        DELIVERY_SENT.fetch_add(1, Ordering::Relaxed);
+    } else {
+        DELIVERY_FAILED.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// 获取当前的消息投递统计
+pub fn delivery_metrics() -> DeliveryMetrics {
+    DeliveryMetrics {
+        sent: DELIVERY_SENT.load(Ordering::Relaxed),
+        failed: DELIVERY_FAILED.load(Ordering::Relaxed),
+    }
+}
+
+/// 记录一次情绪缓存查询结果
+pub fn record_mood_cache(hit: bool) {
+    if hit {
+        MOOD_CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+    } else {
+        MOOD_CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// 获取当前的情绪缓存统计
+pub fn mood_cache_metrics() -> CacheMetrics {
+    CacheMetrics {
+        hits: MOOD_CACHE_HITS.load(Ordering::Relaxed),
+        misses: MOOD_CACHE_MISSES.load(Ordering::Relaxed),
+    }
+}
+
 /// 健康状态结构体
 /// 
 /// 包含系统的整体健康状态信息
@@ -22,12 +77,48 @@ pub struct HealthStatus {
     pub is_healthy: bool,
     /// 内存使用情况
     pub memory_usage: MemoryUsage,
+    /// 消息投递统计
+    pub delivery: DeliveryMetrics,
+    /// 情绪缓存统计
+    pub mood_cache: CacheMetrics,
     /// 最后检查时间
     pub last_check: chrono::DateTime<Local>,
     /// 错误列表
     pub errors: Vec<String>,
     /// 警告列表
     pub warnings: Vec<String>,
+    /// 本次检查自动执行的维护操作
+    pub actions: Vec<String>,
+}
+
+impl HealthStatus {
+    /// 将健康状态格式化为回复文本
+    pub fn report(&self) -> String {
+        let mut report = if self.is_healthy {
+            format!("✅ 系统健康状态良好\n📊 记忆数量: {}\n👥 用户档案: {}\n🏢 群组档案: {}\n💾 记忆文件大小: {:.2}MB\n📨 消息发送: 成功 {} / 失败 {}\n🧠 情绪缓存: 命中 {} / 未命中 {}{}", 
+                self.memory_usage.total_memories,
+                self.memory_usage.user_profiles,
+                self.memory_usage.group_profiles,
+                self.memory_usage.memory_file_size as f64 / 1024.0 / 1024.0,
+                self.delivery.sent,
+                self.delivery.failed,
+                self.mood_cache.hits,
+                self.mood_cache.misses,
+                self.mood_cache.hit_rate()
+                    .map(|rate| format!(" (命中率 {:.0}%)", rate * 100.0))
+                    .unwrap_or_default()
+            )
+        } else {
+            format!("❌ 系统健康状态异常\n错误: {}\n警告: {}", 
+                self.errors.join(", "),
+                self.warnings.join(", ")
+            )
+        };
+        if !self.actions.is_empty() {
+            report.push_str(&format!("\n🧹 自动维护: {}", self.actions.join(", ")));
+        }
+        report
+    }
 }
 
 /// 内存使用情况结构体
@@ -45,6 +136,36 @@ pub struct MemoryUsage {
     pub memory_file_size: u64,
 }
 
+/// 消息投递统计结构体
+/// 
+/// 记录自启动以来消息发送的成功和失败次数
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DeliveryMetrics {
+    /// 成功发送的消息数量
+    pub sent: u64,
+    /// 发送失败的消息数量
+    pub failed: u64,
+}
+
+/// 缓存统计结构体
+/// 
+/// 记录自启动以来缓存的命中和未命中次数，用于评估缓存和过期时间是否合适
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CacheMetrics {
+    /// 命中次数
+    pub hits: u64,
+    /// 未命中次数
+    pub misses: u64,
+}
+
+impl CacheMetrics {
+    /// 命中率 (0.0-1.0)，尚无查询时返回 `None`
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.hits + self.misses;
+        (total > 0).then(|| self.hits as f64 / total as f64)
+    }
+}
+
 pub struct HealthChecker {
     memory_manager: Arc<MemoryManager>,
     last_health_status: Option<HealthStatus>,
@@ -58,21 +179,43 @@ impl HealthChecker {
         }
     }
 
+    /// 执行健康检查，并按需自动清理记忆、尝试恢复存储写入
     pub async fn check_health(&mut self) -> HealthStatus {
-        let errors = Vec::new();
+        let auto_cleanup = config::get().memory().auto_cleanup_enabled();
+        self.run_check(true, auto_cleanup).await
+    }
+
+    /// 执行只读的健康检查
+    /// 
+    /// 只收集状态，不会清理记忆或写入存储，供 `#自检` 使用
+    pub async fn inspect_health(&mut self) -> HealthStatus {
+        self.run_check(false, false).await
+    }
+
+    /// # 参数
+    /// * `maintain` - 是否执行存储恢复等维护操作
+    /// * `auto_cleanup` - 记忆过多时是否自动清理
+    async fn run_check(&mut self, maintain: bool, auto_cleanup: bool) -> HealthStatus {
+        let mut errors = Vec::new();
         let mut warnings = Vec::new();
+        let mut actions = Vec::new();
 
         // 检查记忆管理器
-        let memory_usage = self.check_memory_usage().await;
-        
-        // 检查记忆文件大小
-        if memory_usage.memory_file_size > 10 * 1024 * 1024 { // 10MB
-            warnings.push("记忆文件过大，建议清理".to_string());
-        }
+        let mut memory_usage = self.check_memory_usage().await;
+        let cleanup_reasons = cleanup_reasons(&memory_usage);
+        let needs_cleanup = !cleanup_reasons.is_empty();
+        warnings.extend(cleanup_reasons);
 
-        // 检查记忆数量
-        if memory_usage.total_memories > 5000 {
-            warnings.push("记忆数量过多，可能影响性能".to_string());
+        // 按配置自动清理记忆
+        if auto_cleanup && needs_cleanup {
+            match self.memory_manager.run_cleanup().await {
+                Ok(removed) => {
+                    println!("[HEALTH] 已自动清理记忆，移除 {} 条", removed);
+                    actions.push(format!("自动清理记忆，移除 {} 条", removed));
+                    memory_usage = self.check_memory_usage().await;
+                }
+                Err(e) => errors.push(format!("自动清理记忆失败: {}", e)),
+            }
         }
 
         // 检查用户档案数量
@@ -80,14 +223,30 @@ impl HealthChecker {
             warnings.push("用户档案数量过多".to_string());
         }
 
+        // 检查记忆存储是否可写，仅内存模式下尝试恢复
+        if maintain && self.memory_manager.try_recover_storage().await {
+            actions.push("记忆文件已恢复写入".to_string());
+        } else if self.memory_manager.is_memory_only() {
+            errors.push("无法写入记忆文件，新记忆仅保存在内存中".to_string());
+        }
+
+        // 检查消息投递情况
+        let delivery = delivery_metrics();
+        if delivery.failed > 0 {
+            warnings.push(format!("消息发送失败 {} 次（成功 {} 次）", delivery.failed, delivery.sent));
+        }
+
         let is_healthy = errors.is_empty();
 
         let status = HealthStatus {
             is_healthy,
             memory_usage,
+            delivery,
+            mood_cache: mood_cache_metrics(),
             last_check: Local::now(),
             errors,
             warnings,
+            actions,
         };
 
         self.last_health_status = Some(status.clone());
@@ -95,16 +254,19 @@ impl HealthChecker {
     }
 
     async fn check_memory_usage(&self) -> MemoryUsage {
-        let memories = self.memory_manager.get_recent_memories(0).await;
+        let total_memories = self.memory_manager.count_memories().await;
         let user_profiles = self.memory_manager.get_all_user_profiles().await;
         let group_profiles = self.memory_manager.get_all_group_profiles().await;
         
-        let memory_file_size = std::fs::metadata("bot_memory.json")
+        let memory_file_size = self
+            .memory_manager
+            .memory_file()
+            .and_then(|file| std::fs::metadata(file).ok())
             .map(|m| m.len())
             .unwrap_or(0);
 
         MemoryUsage {
-            total_memories: memories.len(),
+            total_memories,
             user_profiles: user_profiles.len(),
             group_profiles: group_profiles.len(),
             memory_file_size,
@@ -142,3 +304,100 @@ impl HealthChecker {
         self.last_health_status.as_ref()
     }
 }
+
+/// 检查记忆使用情况是否需要清理
+/// 
+/// 记忆数量阈值由记忆上限推导，保存时的自动清理会把数量限制在上限以内，
+/// 接近上限时由健康检查压缩到更低的数量
+/// 
+/// # 返回值
+/// 需要清理的原因，不需要清理时为空
+fn cleanup_reasons(usage: &MemoryUsage) -> Vec<String> {
+    let mut reasons = Vec::new();
+    if usage.memory_file_size > MEMORY_FILE_SIZE_WARNING {
+        reasons.push("记忆文件过大，建议清理".to_string());
+    }
+    if usage.total_memories > MEMORY_COUNT_WARNING {
+        reasons.push(format!("记忆数量接近上限（{}/{}），可能影响性能", usage.total_memories, MAX_MEMORIES));
+    }
+    reasons
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::mock::{entry, manager_with};
+    use crate::memory::store::MemoryData;
+    use crate::memory::{CLEANUP_TARGET_MEMORIES, MemoryType};
+
+    /// 创建记忆管理器中已有 `count` 条记忆的健康检查器
+    async fn checker_with_memories(count: usize) -> HealthChecker {
+        let mut data = MemoryData::default();
+        for i in 0..count {
+            let id = format!("e{}", i);
+            data.memories.insert(id.clone(), entry(&id, "事件", MemoryType::Event));
+        }
+        let (manager, _state) = manager_with(data).await;
+        HealthChecker::new(Arc::new(manager))
+    }
+
+    fn usage(total_memories: usize, memory_file_size: u64) -> MemoryUsage {
+        MemoryUsage { total_memories, user_profiles: 0, group_profiles: 0, memory_file_size }
+    }
+
+    #[test]
+    fn cleanup_threshold_is_reachable_below_the_cap() {
+        assert!(cleanup_reasons(&usage(100, 1024)).is_empty());
+        assert_eq!(cleanup_reasons(&usage(MAX_MEMORIES, 1024)).len(), 1);
+        assert_eq!(cleanup_reasons(&usage(100, MEMORY_FILE_SIZE_WARNING + 1)).len(), 1);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn over_threshold_with_auto_cleanup_runs_cleanup() {
+        let mut checker = checker_with_memories(MAX_MEMORIES).await;
+
+        let status = checker.run_check(true, true).await;
+
+        assert_eq!(status.actions.len(), 1);
+        assert_eq!(status.memory_usage.total_memories, CLEANUP_TARGET_MEMORIES);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn over_threshold_without_auto_cleanup_only_warns() {
+        let mut checker = checker_with_memories(MAX_MEMORIES).await;
+
+        let status = checker.run_check(true, false).await;
+
+        assert!(status.actions.is_empty());
+        assert!(!status.warnings.is_empty());
+        assert_eq!(status.memory_usage.total_memories, MAX_MEMORIES);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn health_report_shows_memory_count() {
+        let mut checker = checker_with_memories(7).await;
+
+        let status = checker.inspect_health().await;
+
+        assert_eq!(status.memory_usage.total_memories, 7);
+        assert!(status.report().contains("📊 记忆数量: 7\n"));
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn unwritable_store_reports_one_error_and_stops_failing() {
+        let (manager, state) = manager_with(MemoryData::default()).await;
+        state.fail_writes.store(true, std::sync::atomic::Ordering::Relaxed);
+
+        let mut results = Vec::new();
+        for i in 0..6 {
+            results.push(manager.add_memory(entry(&format!("e{}", i), "事件", MemoryType::Event)).await.is_ok());
+        }
+        // 前两次写入失败时报错，切换为仅内存模式后不再逐条报错
+        assert_eq!(results, [false, false, true, true, true, true]);
+
+        let mut checker = HealthChecker::new(Arc::new(manager));
+        let status = checker.inspect_health().await;
+        assert!(!status.is_healthy);
+        assert_eq!(status.errors, ["无法写入记忆文件，新记忆仅保存在内存中"]);
+    }
+}
@@ -0,0 +1,144 @@
+//! # 自检模块
+//!
+//! 依次检查配置加载、鉴权令牌、模型往返、记忆读取和健康状态，
+//! 汇总每个子系统的结果和耗时，供 `#自检` 命令使用
+
+use crate::config::ModelConfig;
+use crate::health_check::HealthChecker;
+use crate::memory::MemoryManager;
+use crate::model::utils::warmup_server;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 单项自检结果
+#[derive(Debug, Clone)]
+pub struct SelfTestItem {
+    /// 子系统名称
+    pub name: &'static str,
+    /// 是否通过
+    pub ok: bool,
+    /// 结果说明
+    pub detail: String,
+    /// 检查耗时
+    pub elapsed: Duration,
+}
+
+/// 执行完整自检
+///
+/// 只会向模型服务发送一次极小的请求（安全模式下跳过），不会向任何用户发送消息，
+/// 也不会保存、清理记忆或尝试恢复存储写入
+///
+/// # 参数
+/// * `config` - 当前配置，提供鉴权令牌和模型服务
+/// * `memory_manager` - 记忆管理器实例
+///
+/// # 返回值
+/// 按检查顺序排列的各子系统结果
+pub async fn run_self_test(config: &ModelConfig, memory_manager: Arc<MemoryManager>) -> Vec<SelfTestItem> {
+    let mut items = Vec::new();
+
+    let start = Instant::now();
+    let result = ModelConfig::load().map(|_| "配置文件加载并验证通过".to_string());
+    items.push(item("配置加载", result, start));
+
+    let start = Instant::now();
+    let result = config.server_config().api_token().map(|_| "API令牌已设置".to_string());
+    items.push(item("鉴权令牌", result, start));
+
+    let start = Instant::now();
+    let result = if config.chat().safe_mode() {
+        Ok("安全模式，已跳过".to_string())
+    } else {
+        warmup_server(config.server_config()).await.map(|_| "模型请求往返成功".to_string())
+    };
+    items.push(item("模型往返", result, start));
+
+    let start = Instant::now();
+    let result = memory_manager
+        .check_storage()
+        .await
+        .map(|count| format!("记忆存储读取成功，共 {} 条记忆", count));
+    items.push(item("记忆读取", result, start));
+
+    let start = Instant::now();
+    let status = HealthChecker::new(memory_manager).inspect_health().await;
+    let result = if status.is_healthy {
+        Ok(format!("健康状态良好，警告 {} 条", status.warnings.len()))
+    } else {
+        Err(anyhow::anyhow!("{}", status.errors.join(", ")))
+    };
+    items.push(item("健康检查", result, start));
+
+    items
+}
+
+/// 将自检结果格式化为回复文本
+pub fn format_report(items: &[SelfTestItem]) -> String {
+    let passed = items.iter().filter(|item| item.ok).count();
+    let mut lines = vec![format!("🔍 自检完成：{}/{} 项通过", passed, items.len())];
+    for item in items {
+        lines.push(format!(
+            "{} {} ({} ms): {}",
+            if item.ok { "✅" } else { "❌" },
+            item.name,
+            item.elapsed.as_millis(),
+            item.detail
+        ));
+    }
+    lines.join("\n")
+}
+
+fn item(name: &'static str, result: anyhow::Result<String>, start: Instant) -> SelfTestItem {
+    let elapsed = start.elapsed();
+    match result {
+        Ok(detail) => SelfTestItem { name, ok: true, detail, elapsed },
+        Err(e) => SelfTestItem { name, ok: false, detail: e.to_string(), elapsed },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::mock;
+    use crate::model::mock::{completion_response, http_response, model_config, MockServer};
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn reports_every_subsystem() {
+        let server = MockServer::start(vec![completion_response("你好")]).await;
+        let (manager, _state) = mock::manager().await;
+
+        let items = run_self_test(&model_config(&server.url, ""), Arc::new(manager)).await;
+
+        let results: Vec<_> = items.iter().map(|item| (item.name, item.ok)).collect();
+        assert_eq!(
+            results,
+            [("配置加载", true), ("鉴权令牌", true), ("模型往返", true), ("记忆读取", true), ("健康检查", true)]
+        );
+        assert_eq!(server.request_count(), 1);
+
+        let report = format_report(&items);
+        assert_eq!(report.lines().count(), items.len() + 1);
+        for item in &items {
+            assert!(report.contains(item.name));
+        }
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn failed_round_trip_is_reported() {
+        let server = MockServer::start(vec![http_response("401 Unauthorized", &[], "{}")]).await;
+        let (manager, _state) = mock::manager().await;
+
+        let items = run_self_test(&model_config(&server.url, ""), Arc::new(manager)).await;
+
+        let round_trip = items.iter().find(|item| item.name == "模型往返").unwrap();
+        assert!(!round_trip.ok);
+        assert!(round_trip.detail.contains("401"));
+        assert!(format_report(&items).contains("4/5 项通过"));
+
+        // 安全模式下跳过往返检查，不发出请求
+        let (manager, _state) = mock::manager().await;
+        let items = run_self_test(&model_config(&server.url, "[chat]\nsafe_mode = true"), Arc::new(manager)).await;
+        assert!(items.iter().find(|item| item.name == "模型往返").unwrap().ok);
+        assert_eq!(server.request_count(), 1);
+    }
+}
@@ -8,7 +8,7 @@
 //! - 话题生成：智能生成相关话题促进互动
 //! - 健康监控：实时监控系统状态和性能
 
-use crate::model::{group_message_event, private_message_event};
+use crate::model::{group_message_event, notice_event, private_message_event};
 use kovi::PluginBuilder;
 use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
 
@@ -28,6 +28,10 @@ pub mod mood_system;
 pub mod proactive_chat;
 // 健康检查系统
 pub mod health_check;
+// 用量统计
+pub mod usage;
+// 启动引导
+mod bootstrap;
 
 /// 后台任务启动标志，确保只启动一次
 static BACKGROUND_TASK_STARTED: AtomicBool = AtomicBool::new(false);
@@ -35,24 +39,34 @@ static BACKGROUND_TASK_STARTED: AtomicBool = AtomicBool::new(false);
 /// 插件主入口函数
 /// 
 /// 初始化所有必要的组件并注册消息处理函数：
-/// - 注册群聊和私聊消息处理函数
+/// - 准备数据目录、默认配置和状态文件
+/// - 注册群聊、私聊消息和通知事件处理函数
 /// - 启动记忆管理器
 /// - 初始化情绪系统
 /// - 启动后台定期任务（自然情绪变化）
+/// - 按配置预热模型连接
 /// 
 /// 注意：主动聊天功能在消息处理函数中动态启动
 #[kovi::plugin]
 async fn main() {
+    // 准备数据目录、默认配置和状态文件
+    if let Err(e) = bootstrap::bootstrap().await {
+        eprintln!("[ERROR] 启动引导失败: {}", e);
+    }
+
     // 注册聊天功能宏，定义消息处理函数映射
     register_chat_function! {
         (group_message, group_message_event),
-        (private_message, private_message_event)
+        (private_message, private_message_event),
+        (notice, notice_event)
     }
     
     // 注册群聊消息处理器
     PluginBuilder::on_group_msg(group_message);
     // 注册私聊消息处理器
     PluginBuilder::on_private_msg(private_message);
+    // 注册通知事件处理器
+    PluginBuilder::on_notice(notice);
     
     // 确保后台任务只启动一次
     if BACKGROUND_TASK_STARTED.compare_exchange(false, true, Ordering::Relaxed, Ordering::Relaxed).is_ok() {
@@ -63,19 +77,36 @@ async fn main() {
         // 注意：主动聊天功能已在消息处理函数中实现，通过startup模块管理
         kovi::tokio::spawn(async move {
             // 创建单一的情绪系统实例，避免重复创建
-            let mood_system = mood_system::MoodSystem::new(memory_manager);
+            let mood_system = mood_system::MoodSystem::new(Arc::clone(&memory_manager));
             
             // 定期执行自然情绪变化
             loop {
                 if let Err(e) = mood_system.natural_mood_drift().await {
                     eprintln!("[ERROR] 自然情绪变化失败: {}", e);
                 }
+
+                // 降低长期未互动用户的关系等级
+                match memory_manager.decay_relationships().await {
+                    Ok(0) => {}
+                    Ok(count) => println!("[INFO] {} 位用户因长期未互动降低了关系等级", count),
+                    Err(e) => eprintln!("[ERROR] 关系等级衰减失败: {}", e),
+                }
                 
                 // 每30分钟检查一次自然情绪变化
                 kovi::tokio::time::sleep(kovi::tokio::time::Duration::from_secs(1800)).await;
             }
         });
         
+        // 预热模型连接（如果已启用）
+        if config::get().server_config().warmup_on_startup() && !config::get().chat().safe_mode() {
+            kovi::tokio::spawn(async move {
+                match model::utils::warmup_model().await {
+                    Ok(latency) => println!("[INFO] 模型连接预热完成，耗时 {} ms", latency.as_millis()),
+                    Err(e) => eprintln!("[ERROR] 模型连接预热失败: {}", e),
+                }
+            });
+        }
+
         println!("[INFO] 后台任务已启动");
     }
 }
@@ -0,0 +1,88 @@
+//! # 记忆向量化
+//!
+//! 调用兼容 OpenAI 格式的 embeddings 接口为记忆生成向量，用于语义检索。
+//! 接口地址和模型在服务器配置中设置，未配置地址时不启用
+
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+
+/// 是否启用了向量化，安全模式下始终不启用
+pub fn enabled() -> bool {
+    let config = crate::config::get();
+    config.server_config().embeddings_enabled() && !config.chat().safe_mode()
+}
+
+/// 为文本生成向量
+///
+/// # 参数
+/// * `text` - 需要向量化的文本
+///
+/// # 返回值
+/// 成功时返回向量，安全模式下、接口请求失败或响应格式不正确时返回错误
+pub async fn embed(text: &str) -> Result<Vec<f32>> {
+    crate::utils::ensure_online()?;
+    let config = crate::config::get();
+    let server_config = config.server_config();
+    let token = server_config.api_token()?;
+
+    let resp = crate::utils::http_client()
+        .post(server_config.embedding_url())
+        .bearer_auth(token)
+        .json(&json!({
+            "model": server_config.embedding_model(),
+            "input": text,
+        }))
+        .send()
+        .await?;
+    let status = resp.status();
+    let body: Value = resp.json().await?;
+    if !status.is_success() {
+        return Err(anyhow::anyhow!("向量接口返回错误 ({}): {}", status, body));
+    }
+
+    body["data"][0]["embedding"]
+        .as_array()
+        .context("向量接口响应缺少 embedding 字段")?
+        .iter()
+        .map(|v| v.as_f64().map(|v| v as f32).context("向量中包含非数字元素"))
+        .collect()
+}
+
+/// 计算两个向量的余弦相似度
+///
+/// 维度不同或任一向量为零向量时返回 `0.0`
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn cosine_similarity_of_fixed_vectors() {
+        assert!(close(cosine_similarity(&[1.0, 2.0, 3.0], &[2.0, 4.0, 6.0]), 1.0));
+        assert!(close(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0));
+        assert!(close(cosine_similarity(&[1.0, 0.0], &[-1.0, 0.0]), -1.0));
+        assert!(close(cosine_similarity(&[1.0, 1.0], &[1.0, 0.0]), std::f32::consts::FRAC_1_SQRT_2));
+
+        // 维度不同、空向量和零向量都视为不相关
+        assert_eq!(cosine_similarity(&[1.0, 2.0], &[1.0, 2.0, 3.0]), 0.0);
+        assert_eq!(cosine_similarity(&[], &[]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+    }
+}
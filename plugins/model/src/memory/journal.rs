@@ -0,0 +1,148 @@
+//! # 人格变化日志
+//!
+//! 以只追加的 JSON Lines 文件记录机器人人格的每一次变化，
+//! 便于运维人员了解和排查人格的演化过程
+
+use crate::memory::BotPersonality;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// 人格变化日志条目
+///
+/// 记录单个人格属性的一次变化
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PersonalityJournalEntry {
+    /// 变化发生时间
+    pub timestamp: DateTime<Local>,
+    /// 发生变化的属性名
+    pub field: String,
+    /// 变化前的值
+    pub old_value: String,
+    /// 变化后的值
+    pub new_value: String,
+    /// 触发变化的原因
+    pub trigger: String,
+}
+
+/// 比较两个人格状态，生成变化条目
+///
+/// 只比较人格属性本身，`last_mood_change` 时间戳不计入变化
+///
+/// # 参数
+/// * `old` - 变化前的人格
+/// * `new` - 变化后的人格
+/// * `trigger` - 触发原因
+///
+/// # 返回值
+/// 每个发生变化的属性对应一条日志条目
+pub fn diff_personality(
+    old: &BotPersonality,
+    new: &BotPersonality,
+    trigger: &str,
+) -> Vec<PersonalityJournalEntry> {
+    let fields = [
+        ("current_mood", old.current_mood.clone(), new.current_mood.clone()),
+        ("mood_intensity", old.mood_intensity.to_string(), new.mood_intensity.to_string()),
+        ("energy_level", old.energy_level.to_string(), new.energy_level.to_string()),
+        ("social_confidence", old.social_confidence.to_string(), new.social_confidence.to_string()),
+        ("curiosity_level", old.curiosity_level.to_string(), new.curiosity_level.to_string()),
+        ("personality_traits", old.personality_traits.join(","), new.personality_traits.join(",")),
+    ];
+
+    let now = Local::now();
+    fields
+        .into_iter()
+        .filter(|(_, old_value, new_value)| old_value != new_value)
+        .map(|(field, old_value, new_value)| PersonalityJournalEntry {
+            timestamp: now,
+            field: field.to_string(),
+            old_value,
+            new_value,
+            trigger: trigger.to_string(),
+        })
+        .collect()
+}
+
+/// 将日志条目追加写入日志文件
+pub fn append_entries(path: &Path, entries: &[PersonalityJournalEntry]) -> Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    for entry in entries {
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    }
+    Ok(())
+}
+
+/// 读取日志文件中最近的若干条记录
+///
+/// 无法解析的行会被跳过，文件不存在时返回空列表
+pub fn read_recent_entries(path: &Path, limit: usize) -> Result<Vec<PersonalityJournalEntry>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let data = fs::read_to_string(path)?;
+    let entries: Vec<PersonalityJournalEntry> = data
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let skip = entries.len().saturating_sub(limit);
+    Ok(entries.into_iter().skip(skip).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn personality() -> BotPersonality {
+        BotPersonality {
+            current_mood: "neutral".to_string(),
+            mood_intensity: 5,
+            energy_level: 7,
+            social_confidence: 6,
+            curiosity_level: 8,
+            last_mood_change: Local::now(),
+            personality_traits: vec!["curious".to_string()],
+            last_reflection: None,
+        }
+    }
+
+    #[test]
+    fn diff_reports_only_changed_fields() {
+        let old = personality();
+        let mut new = old.clone();
+        new.energy_level = 9;
+        new.last_mood_change = Local::now() + chrono::Duration::minutes(1);
+
+        let entries = diff_personality(&old, &new, "每日反思");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].field, "energy_level");
+        assert_eq!((entries[0].old_value.as_str(), entries[0].new_value.as_str()), ("7", "9"));
+        assert_eq!(entries[0].trigger, "每日反思");
+    }
+
+    #[test]
+    fn appended_entries_are_read_back_in_order() {
+        let path = std::env::temp_dir().join(format!("kovi-bot-journal-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let old = personality();
+        let mut new = old.clone();
+        new.current_mood = "happy".to_string();
+        append_entries(&path, &diff_personality(&old, &new, "聊天")).unwrap();
+        new.energy_level = 3;
+        append_entries(&path, &diff_personality(&old, &new, "聊天")).unwrap();
+
+        let entries = read_recent_entries(&path, 2).unwrap();
+        let fields: Vec<&str> = entries.iter().map(|entry| entry.field.as_str()).collect();
+        assert_eq!(fields, ["current_mood", "energy_level"]);
+        let _ = fs::remove_file(&path);
+    }
+}
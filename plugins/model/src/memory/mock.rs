@@ -0,0 +1,124 @@
+//! # 模拟记忆存储
+//!
+//! 供测试使用的内存存储后端，记录管理器发出的每次写入，并可模拟存储不可写
+
+use crate::memory::store::{MemoryData, MemoryStore, StoreFuture};
+use crate::memory::{
+    BotPersonality, GroupProfile, MemoryEntry, MemoryManager, MemoryType, UserProfile, rank_memories,
+};
+use chrono::Local;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+/// 内存中的模拟存储，记录管理器发出的每次写入
+#[derive(Default)]
+pub(crate) struct MockState {
+    pub(crate) data: std::sync::Mutex<MemoryData>,
+    pub(crate) personality: std::sync::Mutex<Option<BotPersonality>>,
+    pub(crate) flushes: AtomicU32,
+    pub(crate) fail_writes: AtomicBool,
+}
+
+pub(crate) struct MockStore(pub(crate) Arc<MockState>);
+
+impl MockStore {
+    fn write(&self, f: impl FnOnce(&mut MemoryData)) -> StoreFuture<'_, ()> {
+        let result = if self.0.fail_writes.load(Ordering::Relaxed) {
+            Err(anyhow::anyhow!("存储不可写"))
+        } else {
+            f(&mut self.0.data.lock().unwrap());
+            Ok(())
+        };
+        Box::pin(async move { result })
+    }
+}
+
+impl MemoryStore for MockStore {
+    fn load(&self) -> StoreFuture<'_, Option<MemoryData>> {
+        let data = self.0.data.lock().unwrap().clone();
+        Box::pin(async move { Ok(Some(data)) })
+    }
+
+    fn add_memory(&self, memory: MemoryEntry) -> StoreFuture<'_, ()> {
+        self.write(|data| {
+            data.memories.insert(memory.id.clone(), memory);
+        })
+    }
+
+    fn remove_memories(&self, ids: Vec<String>) -> StoreFuture<'_, ()> {
+        self.write(|data| {
+            for id in &ids {
+                data.memories.remove(id);
+            }
+        })
+    }
+
+    fn get_memories_by_type(&self, memory_type: MemoryType) -> StoreFuture<'_, Vec<MemoryEntry>> {
+        let data = self.0.data.lock().unwrap();
+        let memories = data.memories.values().filter(|m| m.is_type(&memory_type)).cloned().collect();
+        Box::pin(async move { Ok(memories) })
+    }
+
+    fn search_memories(&self, query: String) -> StoreFuture<'_, Vec<MemoryEntry>> {
+        let memories = rank_memories(self.0.data.lock().unwrap().memories.values(), &query);
+        Box::pin(async move { Ok(memories) })
+    }
+
+    fn upsert_user_profile(&self, profile: UserProfile) -> StoreFuture<'_, ()> {
+        self.write(|data| {
+            data.user_profiles.insert(profile.user_id, profile);
+        })
+    }
+
+    fn upsert_group_profile(&self, profile: GroupProfile) -> StoreFuture<'_, ()> {
+        self.write(|data| {
+            data.group_profiles.insert(profile.group_id, profile);
+        })
+    }
+
+    fn load_personality(&self) -> StoreFuture<'_, Option<BotPersonality>> {
+        let personality = self.0.personality.lock().unwrap().clone();
+        Box::pin(async move { Ok(personality) })
+    }
+
+    fn save_personality(&self, personality: BotPersonality) -> StoreFuture<'_, ()> {
+        *self.0.personality.lock().unwrap() = Some(personality);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn flush(&self) -> StoreFuture<'_, ()> {
+        self.0.flushes.fetch_add(1, Ordering::Relaxed);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// 创建一条测试用记忆
+pub(crate) fn entry(id: &str, content: &str, memory_type: MemoryType) -> MemoryEntry {
+    MemoryEntry {
+        id: id.to_string(),
+        content: content.to_string(),
+        timestamp: Local::now(),
+        memory_type,
+        importance: 5,
+        tags: Vec::new(),
+        context: "test".to_string(),
+        pinned: false,
+        last_reinforced: None,
+        decay_steps: 0,
+        embedding: None,
+    }
+}
+
+/// 创建使用模拟存储的记忆管理器
+pub(crate) async fn manager() -> (MemoryManager, Arc<MockState>) {
+    manager_with(MemoryData::default()).await
+}
+
+/// 创建使用模拟存储的记忆管理器，存储中预先放入给定的数据
+pub(crate) async fn manager_with(data: MemoryData) -> (MemoryManager, Arc<MockState>) {
+    crate::config::use_test_data_dir();
+    let state = Arc::new(MockState { data: std::sync::Mutex::new(data), ..Default::default() });
+    let manager = MemoryManager::from_store(Box::new(MockStore(Arc::clone(&state))));
+    manager.load_memories().await.unwrap();
+    (manager, state)
+}
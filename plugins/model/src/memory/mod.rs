@@ -9,20 +9,86 @@
 //! - 自动记忆清理和优化
 
 use anyhow::Result;
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, NaiveDate};
 use kovi::tokio::sync::Mutex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::{Arc, LazyLock};
 
+pub mod embedding;
+pub mod journal;
+#[cfg(test)]
+pub(crate) mod mock;
+pub mod snapshot;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod storage;
+pub mod store;
+
+use crate::memory::journal::PersonalityJournalEntry;
+use crate::memory::store::{ChangeSet, MemoryStore};
+
+/// 短语至少出现在多少条不同消息中才会被视为群内梗
+const LORE_MIN_OCCURRENCES: usize = 3;
+
+/// 群内梗短语的长度范围（字符数）
+const LORE_PHRASE_CHARS: std::ops::RangeInclusive<usize> = 2..=12;
+
+/// 机器人人格文件名，与记忆文件位于同一目录
+const PERSONALITY_FILE_NAME: &str = "bot_personality.json";
+
+/// 连续保存失败多少次后进入仅内存模式
+const MAX_SAVE_FAILURES: u32 = 3;
+
+/// 记忆数量上限，每次保存时超出的低重要性记忆会被清理
+pub const MAX_MEMORIES: usize = 1000;
+
+/// 主动清理（如健康检查触发的自动清理）后保留的记忆数量，低于上限以留出余量
+pub const CLEANUP_TARGET_MEMORIES: usize = MAX_MEMORIES * 4 / 5;
+
 /// 全局记忆管理器实例
 /// 
 /// 使用LazyLock确保线程安全的单例模式，在首次访问时初始化
-/// 记忆文件默认保存为 "bot_memory.json"
-pub static MEMORY_MANAGER: LazyLock<Arc<MemoryManager>> =
-    LazyLock::new(|| Arc::new(MemoryManager::new("bot_memory.json")));
+/// 宿主通过 [`install_store`] 提供了存储后端时使用该后端，否则按记忆配置打开
+/// JSON 或 SQLite 存储，记忆文件路径由 `memory_file` 指定，默认为数据目录下的 "bot_memory.json"
+pub static MEMORY_MANAGER: LazyLock<Arc<MemoryManager>> = LazyLock::new(|| {
+    MANAGER_CREATED.store(true, Ordering::SeqCst);
+    let custom = CUSTOM_STORE.lock().unwrap_or_else(|e| e.into_inner()).take();
+    match custom {
+        Some(store) => {
+            println!("[INFO] 使用宿主提供的记忆存储后端");
+            Arc::new(MemoryManager::from_store(store))
+        }
+        None => Arc::new(MemoryManager::new(crate::config::get().memory().memory_file())),
+    }
+});
+
+/// 宿主提供的存储后端，在全局记忆管理器创建时取出
+static CUSTOM_STORE: std::sync::Mutex<Option<Box<dyn MemoryStore>>> = std::sync::Mutex::new(None);
+
+/// 全局记忆管理器是否已经创建
+static MANAGER_CREATED: AtomicBool = AtomicBool::new(false);
+
+/// 为全局记忆管理器安装自定义存储后端（如 Postgres、Redis 或宿主自己的存储）
+/// 
+/// 记忆的增删改查和检索都会经过该后端，需要在插件处理第一条消息之前调用，
+/// 通常放在宿主的启动代码中
+/// 
+/// # 参数
+/// * `store` - 实现了 [`MemoryStore`] 的存储后端
+/// 
+/// # 返回值
+/// 全局记忆管理器已经创建时返回错误，安装不会生效
+pub fn install_store(store: Box<dyn MemoryStore>) -> Result<()> {
+    if MANAGER_CREATED.load(Ordering::SeqCst) {
+        return Err(anyhow::anyhow!("记忆管理器已经创建，无法再更换存储后端"));
+    }
+    *CUSTOM_STORE.lock().unwrap_or_else(|e| e.into_inner()) = Some(store);
+    Ok(())
+}
 
 /// 记忆条目结构体
 /// 
@@ -43,6 +109,64 @@ pub struct MemoryEntry {
     pub tags: Vec<String>,
     /// 上下文信息，描述记忆产生的环境
     pub context: String,
+    /// 是否为置顶记忆，置顶记忆在上下文注入时优先保留
+    #[serde(default)]
+    pub pinned: bool,
+    /// 最近一次被强化的时间，未强化过时以创建时间计算遗忘
+    #[serde(default)]
+    pub last_reinforced: Option<DateTime<Local>>,
+    /// 自上次强化以来已经衰减的次数
+    #[serde(default)]
+    pub decay_steps: u32,
+    /// 内容向量，启用语义检索时在添加记忆时生成
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+}
+
+impl MemoryEntry {
+    /// 判断记忆是否由机器人自己产生（如主动聊天记录）
+    ///
+    /// 这类记忆在注入上下文时需要与用户发言区分开，避免被误认为用户的话
+    pub fn is_bot_authored(&self) -> bool {
+        self.context.starts_with("proactive_")
+    }
+
+    /// 判断记忆是否属于指定类型
+    pub fn is_type(&self, memory_type: &MemoryType) -> bool {
+        std::mem::discriminant(&self.memory_type) == std::mem::discriminant(memory_type)
+    }
+
+    /// 按遗忘曲线降低重要性
+    /// 
+    /// 自上次强化（或创建）起每经过 `decay_days` 天，重要性降低1点。
+    /// 已经衰减过的部分不会重复计算，置顶记忆不衰减
+    /// 
+    /// # 参数
+    /// * `now` - 当前时间
+    /// * `decay_days` - 每降低1点重要性所需的天数，0表示不衰减
+    pub fn apply_decay(&mut self, now: DateTime<Local>, decay_days: u32) {
+        if self.pinned || decay_days == 0 {
+            return;
+        }
+
+        let reference = self.last_reinforced.unwrap_or(self.timestamp);
+        let elapsed_days = now.signed_duration_since(reference).num_days().max(0) as u32;
+        let target_steps = elapsed_days / decay_days;
+        if target_steps > self.decay_steps {
+            let delta = (target_steps - self.decay_steps).min(u8::MAX as u32) as u8;
+            self.importance = self.importance.saturating_sub(delta);
+            self.decay_steps = target_steps;
+        }
+    }
+
+    /// 强化记忆
+    /// 
+    /// 重置遗忘计时并提升1点重要性
+    pub fn reinforce(&mut self, now: DateTime<Local>) {
+        self.last_reinforced = Some(now);
+        self.decay_steps = 0;
+        self.importance = (self.importance + 1).min(10);
+    }
 }
 
 /// 记忆类型枚举
@@ -64,6 +188,23 @@ pub enum MemoryType {
     Emotion,
 }
 
+impl MemoryType {
+    /// 根据名称解析记忆类型
+    /// 
+    /// 支持英文类型名（如 `Conversation`，不区分大小写）和中文名（如 `对话`）
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "conversation" | "对话" => Some(MemoryType::Conversation),
+            "userprofile" | "用户档案" => Some(MemoryType::UserProfile),
+            "groupinfo" | "群组信息" => Some(MemoryType::GroupInfo),
+            "event" | "事件" => Some(MemoryType::Event),
+            "preference" | "偏好" => Some(MemoryType::Preference),
+            "emotion" | "情绪" => Some(MemoryType::Emotion),
+            _ => None,
+        }
+    }
+}
+
 /// 用户档案结构体
 /// 
 /// 存储用户的详细信息，用于个性化交互和关系管理
@@ -85,6 +226,150 @@ pub struct UserProfile {
     pub interaction_count: u32,
     /// 情绪历史记录
     pub mood_history: Vec<MoodEntry>,
+    /// 用户自定义的称呼（通过 `#叫我` 设置），与QQ昵称相互独立
+    #[serde(default)]
+    pub preferred_address: Option<String>,
+    /// 未匹配任何兴趣关键词的互动次数
+    #[serde(default)]
+    pub uncategorized_interactions: u32,
+    /// 已发送的自我介绍条数
+    #[serde(default)]
+    pub onboarding_step: u32,
+    /// 已经庆祝过的关系里程碑等级
+    #[serde(default)]
+    pub celebrated_milestones: Vec<u8>,
+    /// 最近一次因长期未互动而降低关系等级的时间
+    #[serde(default)]
+    pub last_relationship_decay: Option<DateTime<Local>>,
+    /// 最近一次主动私聊该用户的时间
+    #[serde(default)]
+    pub last_proactive_message: Option<DateTime<Local>>,
+    /// 用户常用的语言代码（如 "zh"、"en"、"ja"），根据私聊消息自动识别
+    #[serde(default)]
+    pub preferred_language: Option<String>,
+}
+
+impl UserProfile {
+    /// 创建新的用户档案
+    /// 
+    /// # 参数
+    /// * `user_id` - 用户ID
+    /// * `nickname` - 用户昵称
+    pub fn new(user_id: i64, nickname: &str) -> Self {
+        Self {
+            user_id,
+            nickname: nickname.to_string(),
+            personality_traits: Vec::new(),
+            interests: Vec::new(),
+            relationship_level: 1,
+            last_interaction: Local::now(),
+            interaction_count: 0,
+            mood_history: Vec::new(),
+            preferred_address: None,
+            uncategorized_interactions: 0,
+            onboarding_step: 0,
+            celebrated_milestones: Vec::new(),
+            last_relationship_decay: None,
+            last_proactive_message: None,
+            preferred_language: None,
+        }
+    }
+
+    /// 长期未互动时降低关系等级
+    /// 
+    /// 距最后一次互动（或上一次降低）超过 `threshold` 时降低1级，最低为1级，
+    /// 因此长期不互动的用户每经过一个阈值周期降低1级
+    /// 
+    /// # 参数
+    /// * `now` - 当前时间
+    /// * `threshold` - 未互动多久后降低关系等级
+    /// 
+    /// # 返回值
+    /// 关系等级被降低时返回 `true`
+    pub fn decay_relationship(&mut self, now: DateTime<Local>, threshold: chrono::Duration) -> bool {
+        let reference = self
+            .last_relationship_decay
+            .map_or(self.last_interaction, |decayed| decayed.max(self.last_interaction));
+        if self.relationship_level <= 1 || now.signed_duration_since(reference) < threshold {
+            return false;
+        }
+        self.relationship_level -= 1;
+        self.last_relationship_decay = Some(now);
+        true
+    }
+
+    /// 检查关系等级是否跨过了新的里程碑
+    /// 
+    /// 只有从低于里程碑的等级升到里程碑及以上时才算跨过，
+    /// 每个里程碑只会触发一次，等级在边界附近反复变化也不会重复触发
+    /// 
+    /// # 参数
+    /// * `previous_level` - 本次更新前的关系等级
+    /// * `thresholds` - 配置的里程碑等级
+    /// 
+    /// # 返回值
+    /// 本次跨过的最高里程碑等级，没有跨过新里程碑时返回 `None`
+    pub fn cross_milestone(&mut self, previous_level: u8, thresholds: &[u8]) -> Option<u8> {
+        let crossed: Vec<u8> = thresholds
+            .iter()
+            .copied()
+            .filter(|level| previous_level < *level && self.relationship_level >= *level)
+            .filter(|level| !self.celebrated_milestones.contains(level))
+            .collect();
+        self.celebrated_milestones.extend(&crossed);
+        crossed.into_iter().max()
+    }
+
+    /// 取出下一条待发送的自我介绍并推进进度
+    /// 
+    /// 只对新用户生效：互动次数超过自我介绍条数的老用户不会再收到
+    /// 
+    /// # 参数
+    /// * `lines` - 配置的自我介绍列表
+    /// 
+    /// # 返回值
+    /// 本次应发送的自我介绍，已全部发送或不是新用户时返回 `None`
+    pub fn next_onboarding_line<'a>(&mut self, lines: &'a [String]) -> Option<&'a str> {
+        if self.interaction_count as usize > lines.len() {
+            return None;
+        }
+        let line = lines.get(self.onboarding_step as usize)?;
+        self.onboarding_step += 1;
+        Some(line.as_str())
+    }
+
+    /// 获取对用户的称呼，优先使用用户自定义的称呼
+    pub fn display_name(&self) -> &str {
+        self.preferred_address.as_deref().unwrap_or(&self.nickname)
+    }
+
+    /// 获取用户所处的关系阶段
+    pub fn relationship_tier(&self) -> RelationshipTier {
+        RelationshipTier::from_level(self.relationship_level)
+    }
+}
+
+/// 关系阶段
+/// 
+/// 按关系亲密度划分，用于调整回复和主动消息的语气
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelationshipTier {
+    /// 亲密好友 (8-10)：亲密友好，可以开玩笑
+    Close,
+    /// 朋友 (5-7)：友好但保持一定距离
+    Friendly,
+    /// 初识 (0-4)：礼貌但较为正式
+    Acquaintance,
+}
+
+impl RelationshipTier {
+    pub fn from_level(level: u8) -> Self {
+        match level {
+            8.. => RelationshipTier::Close,
+            5..=7 => RelationshipTier::Friendly,
+            _ => RelationshipTier::Acquaintance,
+        }
+    }
 }
 
 /// 情绪记录条目
@@ -121,6 +406,64 @@ pub struct GroupProfile {
     pub last_activity: DateTime<Local>,
     /// 活跃度等级 (0-10)，10表示最活跃
     pub activity_level: u8,
+    /// 成员最后发言时间
+    #[serde(default)]
+    pub member_last_seen: HashMap<i64, DateTime<Local>>,
+    /// 未匹配任何话题关键词的消息数量
+    #[serde(default)]
+    pub uncategorized_messages: u32,
+    /// 最近一次在该群主动发起话题的时间
+    #[serde(default)]
+    pub last_proactive_message: Option<DateTime<Local>>,
+}
+
+/// 群组最多记录的活跃成员数量
+const MAX_ACTIVE_MEMBERS: usize = 50;
+
+impl GroupProfile {
+    /// 创建新的群组档案
+    /// 
+    /// # 参数
+    /// * `group_id` - 群组ID
+    pub fn new(group_id: i64) -> Self {
+        Self {
+            group_id,
+            group_name: format!("群组_{}", group_id),
+            active_members: Vec::new(),
+            group_personality: "friendly".to_string(),
+            conversation_topics: Vec::new(),
+            last_activity: Local::now(),
+            activity_level: 1,
+            member_last_seen: HashMap::new(),
+            uncategorized_messages: 0,
+            last_proactive_message: None,
+        }
+    }
+
+    /// 记录成员发言
+    /// 
+    /// 更新成员的最后发言时间，`active_members` 按最后发言时间从旧到新排列，
+    /// 超过上限时淘汰最久未发言的成员
+    pub fn record_member(&mut self, user_id: i64) {
+        self.member_last_seen.insert(user_id, Local::now());
+        self.active_members.retain(|id| *id != user_id);
+        self.active_members.push(user_id);
+
+        while self.active_members.len() > MAX_ACTIVE_MEMBERS {
+            let evicted = self.active_members.remove(0);
+            self.member_last_seen.remove(&evicted);
+        }
+    }
+
+    /// 获取指定时间内发言过的成员
+    pub fn recently_active_members(&self, within: chrono::Duration) -> Vec<i64> {
+        let since = Local::now() - within;
+        self.active_members
+            .iter()
+            .copied()
+            .filter(|id| self.member_last_seen.get(id).is_some_and(|seen| *seen > since))
+            .collect()
+    }
 }
 
 /// 机器人人格结构体
@@ -142,6 +485,9 @@ pub struct BotPersonality {
     pub last_mood_change: DateTime<Local>,
     /// 人格特征列表
     pub personality_traits: Vec<String>,
+    /// 最近一次每日反思的日期
+    #[serde(default)]
+    pub last_reflection: Option<NaiveDate>,
 }
 
 /// 记忆管理器结构体
@@ -162,15 +508,24 @@ pub struct MemoryManager {
     group_profiles: Arc<Mutex<HashMap<i64, GroupProfile>>>,
     /// 机器人人格状态
     bot_personality: Arc<Mutex<BotPersonality>>,
-    /// 记忆文件路径
-    memory_file: String,
+    /// 持久化存储后端
+    store: Arc<dyn MemoryStore>,
+    /// 自上次保存以来发生变化的条目
+    changes: Arc<Mutex<ChangeSet>>,
+    /// 保存锁，保证并发保存按顺序写入，避免旧数据覆盖新数据
+    save_lock: Arc<Mutex<()>>,
+    /// 连续保存失败的次数
+    save_failures: Arc<AtomicU32>,
+    /// 存储不可写时进入仅内存模式，不再尝试每次保存
+    memory_only: Arc<AtomicBool>,
 }
 
 impl MemoryManager {
     /// 创建新的记忆管理器实例
     /// 
     /// # 参数
-    /// * `memory_file` - 记忆数据持久化文件路径
+    /// * `memory_file` - 记忆数据持久化文件路径，相对路径会解析到数据目录下；
+    ///   使用 SQLite 后端时数据库保存在同名的 `.db` 文件中
     /// 
     /// # 返回值
     /// 返回初始化的MemoryManager实例，包含默认的机器人人格设置
@@ -183,6 +538,26 @@ impl MemoryManager {
     /// - 好奇心：8/10
     /// - 性格特征：好奇、顽皮、有同理心、轻微傲娇
     pub fn new(memory_file: &str) -> Self {
+        let memory_file = crate::config::data_path(memory_file);
+
+        // 旧版合并格式的记忆文件需要先拆分
+        match Self::migrate_legacy(&memory_file) {
+            Ok(true) => println!("[INFO] 已将旧版记忆文件拆分为新格式: {}", memory_file.display()),
+            Ok(false) => {}
+            Err(e) => eprintln!("[ERROR] 旧版记忆文件迁移失败: {}", e),
+        }
+
+        let backend = crate::config::get().memory().storage_backend();
+        Self::from_store(store::open(backend, &memory_file, &Self::personality_file_for(&memory_file)))
+    }
+
+    /// 使用指定的存储后端创建记忆管理器
+    /// 
+    /// 创建后会在后台从存储中加载已有的记忆、档案和人格
+    /// 
+    /// # 参数
+    /// * `store` - 存储后端，可以是内置的 JSON / SQLite 存储，也可以是宿主自己的实现
+    pub fn from_store(store: Box<dyn MemoryStore>) -> Self {
         let manager = Self {
             memories: Arc::new(Mutex::new(HashMap::new())),
             user_profiles: Arc::new(Mutex::new(HashMap::new())),
@@ -200,10 +575,15 @@ impl MemoryManager {
                     "empathetic".to_string(),
                     "slightly_tsundere".to_string(),
                 ],
+                last_reflection: None,
             })),
-            memory_file: memory_file.to_string(),
+            store: Arc::from(store),
+            changes: Arc::new(Mutex::new(ChangeSet::default())),
+            save_lock: Arc::new(Mutex::new(())),
+            save_failures: Arc::new(AtomicU32::new(0)),
+            memory_only: Arc::new(AtomicBool::new(false)),
         };
-        
+
         // 尝试加载现有记忆
         let manager_clone = manager.clone();
         kovi::tokio::spawn(async move {
@@ -215,6 +595,58 @@ impl MemoryManager {
         manager
     }
 
+    /// 获取记忆存储的主数据文件路径（记忆文件或SQLite数据库）
+    /// 
+    /// 不使用本地文件的自定义存储后端返回 `None`
+    pub fn memory_file(&self) -> Option<&Path> {
+        self.store.files().first().copied()
+    }
+
+    /// 获取记忆存储占用的全部文件
+    pub fn storage_files(&self) -> Vec<&Path> {
+        self.store.files()
+    }
+
+    fn personality_file_for(memory_file: &Path) -> PathBuf {
+        memory_file.with_file_name(PERSONALITY_FILE_NAME)
+    }
+
+    /// 迁移旧版合并格式的记忆文件
+    /// 
+    /// 旧版将记忆、档案和机器人人格全部保存在同一个文件中，
+    /// 新版将人格单独保存到同目录下的 `bot_personality.json`。
+    /// 迁移会先把旧文件备份为 `.bak`，再写出拆分后的文件。
+    /// 人格文件已存在或记忆文件中没有人格数据时跳过，可重复调用
+    /// 
+    /// # 参数
+    /// * `path` - 记忆文件路径
+    /// 
+    /// # 返回值
+    /// 执行了迁移时返回 `true`，无需迁移时返回 `false`
+    pub fn migrate_legacy(path: &Path) -> Result<bool> {
+        let personality_file = Self::personality_file_for(path);
+        if !path.exists() || personality_file.exists() {
+            return Ok(false);
+        }
+
+        // 旧版文件总是JSON格式，无法按JSON解析的文件不需要迁移
+        let data = fs::read(path)?;
+        let Ok(mut legacy) = serde_json::from_slice::<serde_json::Value>(&data) else {
+            return Ok(false);
+        };
+        let Some(personality) = legacy.as_object_mut().and_then(|o| o.remove("bot_personality")) else {
+            return Ok(false);
+        };
+
+        // 确认人格数据有效后再写入
+        let personality: BotPersonality = serde_json::from_value(personality)?;
+
+        fs::copy(path, path.with_extension("json.bak"))?;
+        fs::write(&personality_file, serde_json::to_string_pretty(&personality)?)?;
+        fs::write(path, serde_json::to_string_pretty(&legacy)?)?;
+        Ok(true)
+    }
+
     /// 添加新的记忆条目
     /// 
     /// # 参数
@@ -224,8 +656,24 @@ impl MemoryManager {
     /// 成功时返回 `Ok(())`，失败时返回错误信息
     /// 
     /// # 注意
-    /// 添加记忆后会自动保存到文件
-    pub async fn add_memory(&self, memory: MemoryEntry) -> Result<()> {
+    /// 添加记忆后会自动保存到文件；启用语义检索时会先为记忆生成向量，失败时不影响添加。
+    /// 去重窗口内已有相同内容和上下文的对话记忆时跳过添加
+    pub async fn add_memory(&self, mut memory: MemoryEntry) -> Result<()> {
+        let window = crate::config::get().memory().dedup_window_secs();
+        if window > 0 && self.is_recent_duplicate(&memory, chrono::Duration::seconds(window as i64)).await {
+            println!("[INFO] 跳过重复的对话记忆: {}", memory.content);
+            return Ok(());
+        }
+
+        // 安全模式下 `embedding::enabled` 返回 false，不会发出向量化请求
+        if memory.embedding.is_none() && embedding::enabled() {
+            match embedding::embed(&memory.content).await {
+                Ok(vector) => memory.embedding = Some(vector),
+                Err(e) => eprintln!("[ERROR] 记忆向量化失败 ({}): {}", memory.id, e),
+            }
+        }
+
+        self.changes.lock().await.touch_memory(&memory.id);
         {
             let mut memories = self.memories.lock().await;
             memories.insert(memory.id.clone(), memory);
@@ -233,18 +681,60 @@ impl MemoryManager {
         self.save_memories().await
     }
 
+    /// 判断对话记忆是否与时间窗口内的已有记忆重复
+    /// 
+    /// 内容（忽略开头的 `[时:分:秒]` 时间标记）和上下文都相同时视为重复，
+    /// 只对对话类型的记忆去重
+    async fn is_recent_duplicate(&self, memory: &MemoryEntry, window: chrono::Duration) -> bool {
+        if !matches!(memory.memory_type, MemoryType::Conversation) {
+            return false;
+        }
+
+        let content = strip_time_marker(&memory.content);
+        let since = memory.timestamp - window;
+        self.memories.lock().await.values().any(|m| {
+            matches!(m.memory_type, MemoryType::Conversation)
+                && m.context == memory.context
+                && m.timestamp >= since
+                && strip_time_marker(&m.content) == content
+        })
+    }
+
+    /// 获取记忆条目总数
+    pub async fn count_memories(&self) -> usize {
+        self.memories.lock().await.len()
+    }
+
+    /// 获取指定类型的记忆条目数量
+    /// 
+    /// # 参数
+    /// * `memory_type` - 要统计的记忆类型
+    pub async fn count_by_type(&self, memory_type: &MemoryType) -> usize {
+        let memories = self.memories.lock().await;
+        memories.values().filter(|m| m.is_type(memory_type)).count()
+    }
+
     /// 根据类型获取记忆条目
     /// 
+    /// 从存储后端查询，存储与缓存不一致或查询失败时使用内存缓存
+    /// 
     /// # 参数
     /// * `memory_type` - 要查询的记忆类型
     /// 
     /// # 返回值
     /// 返回指定类型的所有记忆条目
     pub async fn get_memories_by_type(&self, memory_type: &MemoryType) -> Vec<MemoryEntry> {
+        if self.store_is_current().await {
+            match self.store.get_memories_by_type(memory_type.clone()).await {
+                Ok(memories) => return memories,
+                Err(e) => eprintln!("[ERROR] 从存储查询记忆失败，改用内存缓存: {}", e),
+            }
+        }
+
         let memories = self.memories.lock().await;
         memories
             .values()
-            .filter(|m| std::mem::discriminant(&m.memory_type) == std::mem::discriminant(memory_type))
+            .filter(|m| m.is_type(memory_type))
             .cloned()
             .collect()
     }
@@ -258,7 +748,7 @@ impl MemoryManager {
     /// 按时间倒序排列的最近记忆条目列表
     pub async fn get_recent_memories(&self, limit: usize) -> Vec<MemoryEntry> {
         let mut memories: Vec<MemoryEntry> = self.memories.lock().await.values().cloned().collect();
-        memories.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        memories.sort_by_key(|m| std::cmp::Reverse(m.timestamp));
         memories.truncate(limit);
         memories
     }
@@ -281,11 +771,8 @@ impl MemoryManager {
 
     /// 智能搜索记忆条目
     /// 
-    /// 使用多因素评分算法搜索相关记忆，考虑以下因素：
-    /// - 内容完全匹配 (10分)
-    /// - 标签匹配 (5分)
-    /// - 记忆重要性 (0-10分)
-    /// - 时间权重：7天内(3分)，30天内(2分)，90天内(1分)
+    /// 由存储后端执行搜索，存储与缓存不一致或搜索失败时在内存缓存中搜索，
+    /// 内置后端和缓存都使用 [`rank_memories`] 的评分规则
     /// 
     /// # 参数
     /// * `query` - 搜索查询字符串
@@ -293,58 +780,152 @@ impl MemoryManager {
     /// # 返回值
     /// 按相关性得分排序的记忆条目列表
     pub async fn search_memories(&self, query: &str) -> Vec<MemoryEntry> {
+        if self.store_is_current().await {
+            match self.store.search_memories(query.to_string()).await {
+                Ok(memories) => return memories,
+                Err(e) => eprintln!("[ERROR] 从存储搜索记忆失败，改用内存缓存: {}", e),
+            }
+        }
+
+        rank_memories(self.memories.lock().await.values(), query)
+    }
+
+    /// 语义检索记忆
+    /// 
+    /// 为查询生成向量，按与记忆向量的余弦相似度从高到低排序。
+    /// 未启用向量化、向量接口失败或没有带向量的记忆时回退到关键词搜索
+    /// 
+    /// # 参数
+    /// * `query` - 查询文本
+    /// * `limit` - 返回的最大数量
+    /// 
+    /// # 返回值
+    /// 与查询最相关的记忆
+    pub async fn semantic_search(&self, query: &str, limit: usize) -> Vec<MemoryEntry> {
+        let query_vector = if embedding::enabled() {
+            match embedding::embed(query).await {
+                Ok(vector) => Some(vector),
+                Err(e) => {
+                    eprintln!("[ERROR] 查询向量化失败，改用关键词搜索: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        if let Some(query_vector) = query_vector {
+            let memories = self.memories.lock().await;
+            let mut results: Vec<(&MemoryEntry, f32)> = memories
+                .values()
+                .filter_map(|m| {
+                    let vector = m.embedding.as_ref()?;
+                    Some((m, embedding::cosine_similarity(&query_vector, vector)))
+                })
+                .collect();
+
+            if !results.is_empty() {
+                // 相似度相同时较新的记忆优先，保证排序结果稳定
+                results.sort_by(|a, b| {
+                    b.1.total_cmp(&a.1)
+                        .then_with(|| b.0.timestamp.cmp(&a.0.timestamp))
+                        .then_with(|| a.0.id.cmp(&b.0.id))
+                });
+                return results.into_iter().take(limit).map(|(memory, _)| memory.clone()).collect();
+            }
+        }
+
+        let mut results = self.search_memories(query).await;
+        results.truncate(limit);
+        results
+    }
+
+    /// 获取群组的“梗”和常聊话题
+    /// 
+    /// 统计群组近期对话记忆中反复出现的短语，出现在足够多条不同消息中的短语视为群内梗。
+    /// 只统计时间窗口内的记忆，旧的梗会随着不再出现而自然淡出
+    /// 
+    /// # 参数
+    /// * `group_id` - 群组ID
+    /// * `within` - 统计的时间窗口
+    /// * `limit` - 返回的最大数量
+    /// 
+    /// # 返回值
+    /// 按出现次数从多到少排列的短语
+    pub async fn get_group_lore(&self, group_id: i64, within: chrono::Duration, limit: usize) -> Vec<String> {
+        let since = Local::now() - within;
+        let memories = self.get_group_conversation_memories(group_id).await;
+        let contents = memories
+            .iter()
+            .filter(|m| m.timestamp > since)
+            .map(|m| m.content.split_once(": ").map_or(m.content.as_str(), |(_, message)| message));
+
+        recurring_phrases(contents, LORE_MIN_OCCURRENCES, limit)
+    }
+
+    /// 获取用户以往谈到某个话题的记忆
+    /// 
+    /// 只检索用户本人的私聊对话记忆（不含机器人主动发起的记录），
+    /// 标签命中话题的记忆优先，其次是内容包含话题的记忆，同等条件下较新的优先
+    /// 
+    /// # 参数
+    /// * `user_id` - 用户QQ号
+    /// * `topic` - 话题关键词，如 "游戏"
+    /// * `limit` - 返回的最大数量
+    /// 
+    /// # 返回值
+    /// 与话题相关的用户记忆
+    pub async fn get_memories_about(&self, user_id: i64, topic: &str, limit: usize) -> Vec<MemoryEntry> {
+        let topic = topic.trim().to_lowercase();
+        if topic.is_empty() {
+            return Vec::new();
+        }
+
+        let prefix = format!("conv_{}_", user_id);
         let memories = self.memories.lock().await;
-        let query_lower = query.to_lowercase();
-        
-        let mut results: Vec<(MemoryEntry, u8)> = memories
+        let mut results: Vec<(MemoryEntry, bool)> = memories
             .values()
-            .map(|m| {
-                let mut score = 0u8;
-                let content_lower = m.content.to_lowercase();
-                
-                // 完全匹配得分最高
-                if content_lower.contains(&query_lower) {
-                    score += 10;
-                }
-                
-                // 标签匹配
-                for tag in &m.tags {
-                    if tag.to_lowercase().contains(&query_lower) {
-                        score += 5;
-                    }
-                }
-                
-                // 重要性权重
-                score += m.importance;
-                
-                // 时间权重（越近越重要）
-                let now = Local::now();
-                let days_ago = now.signed_duration_since(m.timestamp).num_days();
-                if days_ago < 7 {
-                    score += 3;
-                } else if days_ago < 30 {
-                    score += 2;
-                } else if days_ago < 90 {
-                    score += 1;
-                }
-                
-                (m.clone(), score)
+            .filter(|m| m.id.starts_with(&prefix) && m.context != "group_chat" && !m.is_bot_authored())
+            .filter_map(|m| {
+                let tag_match = m.tags.iter().any(|tag| tag.to_lowercase() == topic);
+                (tag_match || m.content.to_lowercase().contains(&topic)).then(|| (m.clone(), tag_match))
             })
-            .filter(|(_, score)| *score > 0)
             .collect();
-        
-        // 按得分排序
-        results.sort_by(|a, b| b.1.cmp(&a.1));
-        
+
+        results.sort_by(|a, b| {
+            b.1.cmp(&a.1)
+                .then_with(|| b.0.timestamp.cmp(&a.0.timestamp))
+                .then_with(|| a.0.id.cmp(&b.0.id))
+        });
+        results.truncate(limit);
         results.into_iter().map(|(memory, _)| memory).collect()
     }
 
+    /// 获取群组的所有群聊对话记忆
+    /// 
+    /// # 参数
+    /// * `group_id` - 群组ID
+    /// 
+    /// # 返回值
+    /// 按时间倒序排列的对话记忆
+    pub async fn get_group_conversation_memories(&self, group_id: i64) -> Vec<MemoryEntry> {
+        let prefix = format!("conv_{}_", group_id);
+        let memories = self.memories.lock().await;
+        let mut group_memories: Vec<MemoryEntry> = memories
+            .values()
+            .filter(|m| m.context == "group_chat" && m.id.starts_with(&prefix))
+            .cloned()
+            .collect();
+        group_memories.sort_by_key(|m| std::cmp::Reverse(m.timestamp));
+        group_memories
+    }
+
     pub async fn get_contextual_memories(&self, user_id: i64, context: &str, limit: usize) -> Vec<MemoryEntry> {
         let memories = self.memories.lock().await;
-        let mut contextual_memories: Vec<(MemoryEntry, u8)> = Vec::new();
+        let mut contextual_memories: Vec<(MemoryEntry, u32)> = Vec::new();
         
         for memory in memories.values() {
-            let mut relevance_score = 0u8;
+            let mut relevance_score = 0u32;
             
             // 检查是否与用户相关
             if memory.content.contains(&format!("{}", user_id)) {
@@ -365,7 +946,7 @@ impl MemoryManager {
             }
             
             // 重要性权重
-            relevance_score += memory.importance;
+            relevance_score += memory.importance as u32;
             
             if relevance_score > 0 {
                 contextual_memories.push((memory.clone(), relevance_score));
@@ -373,26 +954,88 @@ impl MemoryManager {
         }
         
         // 按相关性排序并限制数量
-        contextual_memories.sort_by(|a, b| b.1.cmp(&a.1));
+        // 得分相同时较新的记忆优先，保证排序结果稳定
+        contextual_memories.sort_by(|a, b| {
+            b.1.cmp(&a.1)
+                .then_with(|| b.0.timestamp.cmp(&a.0.timestamp))
+                .then_with(|| a.0.id.cmp(&b.0.id))
+        });
         contextual_memories.truncate(limit);
         
         contextual_memories.into_iter().map(|(memory, _)| memory).collect()
     }
 
     pub async fn update_user_profile(&self, user_id: i64, profile: UserProfile) -> Result<()> {
-        let mut profiles = self.user_profiles.lock().await;
-        profiles.insert(user_id, profile);
+        self.changes.lock().await.user_profiles.insert(user_id);
+        self.user_profiles.lock().await.insert(user_id, profile);
         self.save_memories().await
     }
 
+    /// 降低长期未互动用户的关系等级
+    /// 
+    /// 未互动天数阈值由记忆配置的 `relationship_decay_days` 指定，0表示不衰减
+    /// 
+    /// # 返回值
+    /// 关系等级被降低的用户数量
+    pub async fn decay_relationships(&self) -> Result<usize> {
+        let days = crate::config::get().memory().relationship_decay_days();
+        if days == 0 {
+            return Ok(0);
+        }
+
+        let now = Local::now();
+        let threshold = chrono::Duration::days(days as i64);
+        let decayed: Vec<i64> = {
+            let mut profiles = self.user_profiles.lock().await;
+            profiles
+                .values_mut()
+                .filter_map(|profile| profile.decay_relationship(now, threshold).then_some(profile.user_id))
+                .collect()
+        };
+
+        if !decayed.is_empty() {
+            self.changes.lock().await.user_profiles.extend(&decayed);
+            self.save_memories().await?;
+        }
+        Ok(decayed.len())
+    }
+
     pub async fn get_user_profile(&self, user_id: i64) -> Option<UserProfile> {
         let profiles = self.user_profiles.lock().await;
         profiles.get(&user_id).cloned()
     }
 
     pub async fn update_group_profile(&self, group_id: i64, profile: GroupProfile) -> Result<()> {
-        let mut profiles = self.group_profiles.lock().await;
-        profiles.insert(group_id, profile);
+        self.changes.lock().await.group_profiles.insert(group_id);
+        self.group_profiles.lock().await.insert(group_id, profile);
+        self.save_memories().await
+    }
+
+    /// 记录一次主动聊天，用于主动聊天的冷却
+    /// 
+    /// # 参数
+    /// * `group_id` - 主动发言的群组，私聊时为 `None`
+    /// * `user_id` - 主动私聊的用户，群聊时为 `None`
+    pub async fn record_proactive_message(&self, group_id: Option<i64>, user_id: Option<i64>) -> Result<()> {
+        let now = Local::now();
+        if let Some(group_id) = group_id {
+            self.group_profiles
+                .lock()
+                .await
+                .entry(group_id)
+                .or_insert_with(|| GroupProfile::new(group_id))
+                .last_proactive_message = Some(now);
+            self.changes.lock().await.group_profiles.insert(group_id);
+        }
+        if let Some(user_id) = user_id {
+            self.user_profiles
+                .lock()
+                .await
+                .entry(user_id)
+                .or_insert_with(|| UserProfile::new(user_id, &format!("User_{}", user_id)))
+                .last_proactive_message = Some(now);
+            self.changes.lock().await.user_profiles.insert(user_id);
+        }
         self.save_memories().await
     }
 
@@ -406,25 +1049,88 @@ impl MemoryManager {
         profiles.values().cloned().collect()
     }
 
+    /// 获取最近活跃的用户
+    /// 
+    /// # 参数
+    /// * `within` - 时间窗口，最后互动时间在窗口内的用户视为活跃
+    /// 
+    /// # 返回值
+    /// 按最后互动时间从近到远排列的用户QQ号
+    pub async fn get_recently_active_users(&self, within: chrono::Duration) -> Vec<i64> {
+        let since = Local::now() - within;
+        let profiles = self.user_profiles.lock().await;
+        let mut active: Vec<&UserProfile> = profiles
+            .values()
+            .filter(|profile| profile.last_interaction > since)
+            .collect();
+        active.sort_by(|a, b| {
+            b.last_interaction
+                .cmp(&a.last_interaction)
+                .then_with(|| a.user_id.cmp(&b.user_id))
+        });
+        active.into_iter().map(|profile| profile.user_id).collect()
+    }
+
     pub async fn get_all_group_profiles(&self) -> Vec<GroupProfile> {
         let profiles = self.group_profiles.lock().await;
         profiles.values().cloned().collect()
     }
 
-    pub async fn update_bot_personality(&self, personality: BotPersonality) -> Result<()> {
-        {
+    /// 更新机器人人格
+    ///
+    /// 人格属性发生变化时，会将变化写入人格变化日志
+    ///
+    /// # 参数
+    /// * `personality` - 新的人格状态
+    /// * `trigger` - 触发此次变化的原因，记录在人格日志中
+    pub async fn update_bot_personality(&self, personality: BotPersonality, trigger: &str) -> Result<()> {
+        let changes = {
             let mut bot_personality = self.bot_personality.lock().await;
+            let changes = journal::diff_personality(&bot_personality, &personality, trigger);
             *bot_personality = personality;
-        }
+            changes
+        };
+        self.record_personality_changes(&changes);
         self.save_memories().await
     }
 
+    /// 将人格变化追加写入人格日志（如果已启用）
+    fn record_personality_changes(&self, changes: &[PersonalityJournalEntry]) {
+        let config = crate::config::get();
+        if !config.memory().personality_journal_enabled() {
+            return;
+        }
+
+        let journal_file = crate::config::data_path(config.memory().personality_journal_file());
+        if let Err(e) = journal::append_entries(&journal_file, changes) {
+            eprintln!("[ERROR] 人格日志写入失败: {}", e);
+        }
+    }
+
+    /// 获取最近的人格变化日志
+    ///
+    /// # 参数
+    /// * `limit` - 返回的最大条目数量
+    ///
+    /// # 返回值
+    /// 按时间顺序排列的最近人格变化记录
+    pub async fn get_personality_journal(&self, limit: usize) -> Result<Vec<PersonalityJournalEntry>> {
+        let config = crate::config::get();
+        let journal_file = crate::config::data_path(config.memory().personality_journal_file());
+        journal::read_recent_entries(&journal_file, limit)
+    }
+
     pub async fn get_bot_personality(&self) -> BotPersonality {
         let bot_personality = self.bot_personality.lock().await;
         bot_personality.clone()
     }
 
     pub async fn add_conversation_memory(&self, user_id: i64, content: &str, context: &str) -> Result<()> {
+        // 配置为免记忆的群组、用户或上下文不记录
+        if crate::config::get().memory().skips_memory(user_id, context) {
+            return Ok(());
+        }
+
         let memory = MemoryEntry {
             id: format!("conv_{}_{}", user_id, Local::now().timestamp_millis()),
             content: content.to_string(),
@@ -433,6 +1139,10 @@ impl MemoryManager {
             importance: self.calculate_importance(content),
             tags: self.extract_tags(content),
             context: context.to_string(),
+            pinned: false,
+            last_reinforced: None,
+            decay_steps: 0,
+            embedding: None,
         };
         self.add_memory(memory).await
     }
@@ -522,32 +1232,37 @@ impl MemoryManager {
         tags
     }
 
+    /// 存储后端中的数据是否与内存缓存一致
+    /// 
+    /// 仅内存模式或还有未写入的变更时返回 `false`，此时查询需要使用缓存
+    async fn store_is_current(&self) -> bool {
+        !self.is_memory_only() && self.changes.lock().await.is_empty()
+    }
+
     async fn load_memories(&self) -> Result<()> {
-        if !Path::new(&self.memory_file).exists() {
-            return Ok(());
-        }
+        let data = self.store.load().await?;
+        let personality = self.store.load_personality().await?;
 
-        let data = fs::read_to_string(&self.memory_file)?;
-        let data: MemoryData = serde_json::from_str(&data)?;
-        
-        {
-            let mut memories = self.memories.lock().await;
-            *memories = data.memories;
-        }
-        
-        {
-            let mut user_profiles = self.user_profiles.lock().await;
-            *user_profiles = data.user_profiles;
-        }
-        
-        {
-            let mut group_profiles = self.group_profiles.lock().await;
-            *group_profiles = data.group_profiles;
+        if let Some(data) = data {
+            {
+                let mut memories = self.memories.lock().await;
+                *memories = data.memories;
+            }
+
+            {
+                let mut user_profiles = self.user_profiles.lock().await;
+                *user_profiles = data.user_profiles;
+            }
+
+            {
+                let mut group_profiles = self.group_profiles.lock().await;
+                *group_profiles = data.group_profiles;
+            }
         }
         
-        {
+        if let Some(personality) = personality {
             let mut bot_personality = self.bot_personality.lock().await;
-            *bot_personality = data.bot_personality;
+            *bot_personality = personality;
         }
 
         Ok(())
@@ -555,48 +1270,291 @@ impl MemoryManager {
 
     async fn save_memories(&self) -> Result<()> {
         // 限制记忆数量，避免内存过度使用
-        self.cleanup_old_memories().await?;
-        
-        let data = MemoryData {
-            memories: self.memories.lock().await.clone(),
-            user_profiles: self.user_profiles.lock().await.clone(),
-            group_profiles: self.group_profiles.lock().await.clone(),
-            bot_personality: self.bot_personality.lock().await.clone(),
+        self.cleanup_old_memories(MAX_MEMORIES).await?;
+
+        // 仅内存模式下不写入存储，由健康检查定期尝试恢复
+        if self.memory_only.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        self.persist_now().await
+    }
+
+    /// 立即将变更写入存储
+    ///
+    /// 逐条调用存储后端的增删改接口后执行 [`MemoryStore::flush`]，
+    /// 写入失败时保留未写入的变更，连续失败达到 `MAX_SAVE_FAILURES` 次后进入仅内存模式
+    async fn persist_now(&self) -> Result<()> {
+        let _save_guard = self.save_lock.lock().await;
+        let changes = std::mem::take(&mut *self.changes.lock().await);
+        let pending = changes.clone();
+
+        match self.write_changes(changes).await {
+            Ok(()) => {
+                self.save_failures.store(0, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(e) => {
+                self.changes.lock().await.merge_older(pending);
+                let failures = self.save_failures.fetch_add(1, Ordering::Relaxed) + 1;
+                if failures >= MAX_SAVE_FAILURES && !self.memory_only.swap(true, Ordering::Relaxed) {
+                    eprintln!(
+                        "[ERROR] 无法写入记忆文件（连续失败 {} 次）: {}，已切换为仅内存模式，新记忆在恢复写入前不会保存",
+                        failures, e
+                    );
+                    return Ok(());
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// 将一组变更写入存储后端
+    ///
+    /// 先复制变更的条目再释放锁，存储写入期间不持有任何数据锁
+    async fn write_changes(&self, changes: ChangeSet) -> Result<()> {
+        let memories: Vec<MemoryEntry> = {
+            let memories = self.memories.lock().await;
+            changes.memories.iter().filter_map(|id| memories.get(id).cloned()).collect()
+        };
+        let user_profiles: Vec<UserProfile> = {
+            let profiles = self.user_profiles.lock().await;
+            changes.user_profiles.iter().filter_map(|id| profiles.get(id).cloned()).collect()
+        };
+        let group_profiles: Vec<GroupProfile> = {
+            let profiles = self.group_profiles.lock().await;
+            changes.group_profiles.iter().filter_map(|id| profiles.get(id).cloned()).collect()
         };
+        let personality = self.bot_personality.lock().await.clone();
 
-        let json = serde_json::to_string_pretty(&data)?;
-        fs::write(&self.memory_file, json)?;
-        Ok(())
+        for memory in memories {
+            self.store.add_memory(memory).await?;
+        }
+        if !changes.removed_memories.is_empty() {
+            self.store.remove_memories(changes.removed_memories.into_iter().collect()).await?;
+        }
+        for profile in user_profiles {
+            self.store.upsert_user_profile(profile).await?;
+        }
+        for profile in group_profiles {
+            self.store.upsert_group_profile(profile).await?;
+        }
+        self.store.save_personality(personality).await?;
+        self.store.flush().await
+    }
+
+    /// 是否处于仅内存模式（存储不可写）
+    pub fn is_memory_only(&self) -> bool {
+        self.memory_only.load(Ordering::Relaxed)
+    }
+
+    /// 仅内存模式下尝试重新写入存储，成功时恢复正常保存
+    ///
+    /// # 返回值
+    /// 恢复成功时返回 `true`，未处于仅内存模式或仍然无法写入时返回 `false`
+    pub async fn try_recover_storage(&self) -> bool {
+        if !self.is_memory_only() {
+            return false;
+        }
+        if self.persist_now().await.is_err() {
+            return false;
+        }
+        self.memory_only.store(false, Ordering::Relaxed);
+        println!("[INFO] 记忆文件已恢复写入，退出仅内存模式");
+        true
+    }
+
+    /// 存储后端中还没有记忆或人格数据时，写出当前（初始）状态
+    /// 
+    /// # 返回值
+    /// 写出了初始状态时返回 `true`
+    pub async fn ensure_storage(&self) -> Result<bool> {
+        if self.store.load().await?.is_some() && self.store.load_personality().await?.is_some() {
+            return Ok(false);
+        }
+        // 先载入已有的部分数据，避免与启动时的异步加载竞争而覆盖现有记忆
+        self.load_memories().await?;
+        self.save_memories().await?;
+        Ok(true)
+    }
+
+    /// 立即执行一次记忆清理并保存
+    /// 
+    /// 供健康检查等维护流程主动调用，清理规则与保存时的自动清理一致，
+    /// 但记忆数量会压缩到 [`CLEANUP_TARGET_MEMORIES`]，而不是 [`MAX_MEMORIES`]
+    /// 
+    /// # 返回值
+    /// 成功时返回被清理的记忆数量
+    pub async fn run_cleanup(&self) -> Result<usize> {
+        let before = self.memories.lock().await.len();
+        self.cleanup_old_memories(CLEANUP_TARGET_MEMORIES).await?;
+        self.save_memories().await?;
+        let after = self.memories.lock().await.len();
+        Ok(before.saturating_sub(after))
+    }
+
+    /// 检查记忆存储可正常读取
+    /// 
+    /// 只从存储后端读取记忆和人格，不会保存或清理记忆，供自检使用
+    /// 
+    /// # 返回值
+    /// 成功时返回存储中的记忆条数
+    pub async fn check_storage(&self) -> Result<usize> {
+        let data = self.store.load().await?;
+        self.store.load_personality().await?;
+        Ok(data.map_or(0, |data| data.memories.len()))
+    }
+
+    /// 强化指定记忆，重置其遗忘计时并提升重要性
+    /// 
+    /// # 返回值
+    /// 记忆存在时返回 `true`
+    pub async fn reinforce_memory(&self, memory_id: &str) -> Result<bool> {
+        let found = {
+            let mut memories = self.memories.lock().await;
+            match memories.get_mut(memory_id) {
+                Some(memory) => {
+                    memory.reinforce(Local::now());
+                    self.changes.lock().await.touch_memory(memory_id);
+                    true
+                }
+                None => false,
+            }
+        };
+
+        if found {
+            self.save_memories().await?;
+        }
+        Ok(found)
+    }
+
+    /// 创建记忆快照
+    /// 
+    /// 先保存当前内存中的数据，再由存储后端备份到快照目录，备份期间暂停保存
+    /// 
+    /// # 返回值
+    /// 成功时返回快照名称
+    pub async fn create_snapshot(&self) -> Result<String> {
+        self.save_memories().await?;
+        let _save_guard = self.save_lock.lock().await;
+        let (name, dir) =
+            kovi::tokio::task::spawn_blocking(|| snapshot::create_dir(&snapshot::snapshot_dir())).await??;
+        if let Err(e) = self.store.backup(dir.clone()).await {
+            let _ = kovi::tokio::fs::remove_dir_all(&dir).await;
+            return Err(e);
+        }
+        Ok(name)
+    }
+
+    /// 列出所有记忆快照，按创建时间从新到旧排列
+    pub async fn list_snapshots(&self) -> Result<Vec<snapshot::SnapshotInfo>> {
+        kovi::tokio::task::spawn_blocking(|| snapshot::list(&snapshot::snapshot_dir())).await?
+    }
+
+    /// 从快照恢复记忆
+    /// 
+    /// 恢复前会自动为当前数据创建一个快照，恢复后重新加载记忆
+    /// 
+    /// # 返回值
+    /// 成功时返回恢复前自动创建的快照名称
+    pub async fn restore_snapshot(&self, name: &str) -> Result<String> {
+        let name = name.to_string();
+        let dir = kovi::tokio::task::spawn_blocking(move || snapshot::resolve(&snapshot::snapshot_dir(), &name)).await??;
+        let backup = self.create_snapshot().await?;
+        // 恢复和重新加载期间不允许保存，避免内存中的旧数据写回存储
+        let _save_guard = self.save_lock.lock().await;
+        self.store.restore(dir).await?;
+        *self.changes.lock().await = ChangeSet::default();
+        self.load_memories().await?;
+        Ok(backup)
+    }
+
+    /// 删除指定记忆
+    /// 
+    /// # 返回值
+    /// 记忆存在并被删除时返回 `true`
+    pub async fn delete_memory(&self, memory_id: &str) -> Result<bool> {
+        let removed = self.memories.lock().await.remove(memory_id).is_some();
+        if removed {
+            self.changes.lock().await.remove_memory(memory_id);
+            self.save_memories().await?;
+        }
+        Ok(removed)
+    }
+
+    /// 清除指定类型的全部记忆
+    /// 
+    /// # 返回值
+    /// 被清除的记忆数量
+    pub async fn clear_memories_by_type(&self, memory_type: &MemoryType) -> Result<usize> {
+        let removed = {
+            let mut memories = self.memories.lock().await;
+            let mut changes = self.changes.lock().await;
+            let before = memories.len();
+            memories.retain(|id, m| {
+                let keep = !m.is_type(memory_type);
+                if !keep {
+                    changes.remove_memory(id);
+                }
+                keep
+            });
+            before - memories.len()
+        };
+
+        if removed > 0 {
+            self.save_memories().await?;
+        }
+        Ok(removed)
     }
 
     /// 清理旧记忆，避免内存过度使用
     /// 
     /// 执行以下清理策略：
-    /// 1. 移除30天前的低重要性记忆（重要性 < 7）
-    /// 2. 如果记忆数量超过1000条，只保留最重要的记忆
+    /// 1. 按遗忘曲线降低长期未被强化的记忆的重要性
+    /// 2. 移除30天前的低重要性记忆（重要性 < 7），置顶记忆除外
+    /// 3. 如果记忆数量超过上限，只保留最重要的记忆
     /// 
     /// # 清理规则
-    /// - 保留所有高重要性记忆（重要性 >= 7）
+    /// - 保留所有置顶记忆和高重要性记忆（重要性 >= 7）
     /// - 移除30天前的低重要性记忆
-    /// - 限制总记忆数量不超过1000条
+    /// - 限制总记忆数量不超过 `max_memories` 条
+    /// 
+    /// # 参数
+    /// * `max_memories` - 清理后保留的最大记忆数量
     /// 
     /// # 返回值
     /// 成功时返回 `Ok(())`，失败时返回错误信息
-    async fn cleanup_old_memories(&self) -> Result<()> {
+    async fn cleanup_old_memories(&self, max_memories: usize) -> Result<()> {
         let mut memories = self.memories.lock().await;
+        let mut changes = self.changes.lock().await;
         let now = Local::now();
         let thirty_days_ago = now - chrono::Duration::days(30);
+
+        // 按遗忘曲线降低长期未被强化的记忆的重要性
+        let decay_days = crate::config::get().memory().importance_decay_days();
+        for memory in memories.values_mut() {
+            let decay_steps = memory.decay_steps;
+            memory.apply_decay(now, decay_days);
+            if memory.decay_steps != decay_steps {
+                changes.touch_memory(&memory.id);
+            }
+        }
         
-        // 移除30天前的低重要性记忆
-        memories.retain(|_, memory| {
-            memory.timestamp > thirty_days_ago || memory.importance >= 7
+        // 移除30天前的低重要性记忆，置顶记忆始终保留
+        memories.retain(|id, memory| {
+            let keep = memory.pinned || memory.timestamp > thirty_days_ago || memory.importance >= 7;
+            if !keep {
+                changes.remove_memory(id);
+            }
+            keep
         });
         
         // 如果记忆数量仍然过多，只保留最重要的
-        if memories.len() > 1000 {
+        if memories.len() > max_memories {
             let mut memory_vec: Vec<_> = memories.drain().collect();
-            memory_vec.sort_by(|a, b| b.1.importance.cmp(&a.1.importance));
-            memory_vec.truncate(1000);
+            memory_vec.sort_by_key(|(_, m)| std::cmp::Reverse(m.importance));
+            for (id, _) in memory_vec.drain(max_memories..) {
+                changes.remove_memory(&id);
+            }
             *memories = memory_vec.into_iter().collect();
         }
         
@@ -605,10 +1563,408 @@ impl MemoryManager {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct MemoryData {
-    memories: HashMap<String, MemoryEntry>,
-    user_profiles: HashMap<i64, UserProfile>,
-    group_profiles: HashMap<i64, GroupProfile>,
-    bot_personality: BotPersonality,
-}
\ No newline at end of file
+/// 计算记忆与搜索词的相关性得分
+/// 
+/// 评分因素：
+/// - 内容包含搜索词 (10分)
+/// - 每个包含搜索词的标签 (5分)
+/// - 记忆重要性 (0-10分)
+/// - 时间权重：7天内(3分)，30天内(2分)，90天内(1分)
+/// 
+/// # 参数
+/// * `memory` - 记忆条目
+/// * `query_lower` - 小写的搜索词
+/// * `now` - 当前时间
+pub fn relevance_score(memory: &MemoryEntry, query_lower: &str, now: DateTime<Local>) -> u32 {
+    let mut score = 0u32;
+
+    // 完全匹配得分最高
+    if memory.content.to_lowercase().contains(query_lower) {
+        score += 10;
+    }
+
+    // 标签匹配
+    for tag in &memory.tags {
+        if tag.to_lowercase().contains(query_lower) {
+            score += 5;
+        }
+    }
+
+    // 重要性权重
+    score += memory.importance as u32;
+
+    // 时间权重（越近越重要）
+    let days_ago = now.signed_duration_since(memory.timestamp).num_days();
+    if days_ago < 7 {
+        score += 3;
+    } else if days_ago < 30 {
+        score += 2;
+    } else if days_ago < 90 {
+        score += 1;
+    }
+
+    score
+}
+
+/// 按与搜索词的相关性对记忆排序
+/// 
+/// 得分为0的记忆会被过滤掉；得分相同时较新的记忆优先，保证排序结果稳定。
+/// 内置存储后端和记忆管理器的缓存搜索都使用该规则，宿主存储也可以直接复用
+/// 
+/// # 参数
+/// * `memories` - 候选记忆
+/// * `query` - 搜索查询字符串
+pub fn rank_memories<'a>(memories: impl IntoIterator<Item = &'a MemoryEntry>, query: &str) -> Vec<MemoryEntry> {
+    let query_lower = query.to_lowercase();
+    let now = Local::now();
+    let mut results: Vec<(&MemoryEntry, u32)> = memories
+        .into_iter()
+        .map(|m| (m, relevance_score(m, &query_lower, now)))
+        .filter(|(_, score)| *score > 0)
+        .collect();
+
+    results.sort_by(|a, b| {
+        b.1.cmp(&a.1)
+            .then_with(|| b.0.timestamp.cmp(&a.0.timestamp))
+            .then_with(|| a.0.id.cmp(&b.0.id))
+    });
+    results.into_iter().map(|(memory, _)| memory.clone()).collect()
+}
+
+/// 去掉对话记忆开头的 `[时:分:秒] ` 时间标记
+fn strip_time_marker(content: &str) -> &str {
+    content
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once("] "))
+        .filter(|(time, _)| time.len() == 8 && time.chars().all(|c| c.is_ascii_digit() || c == ':'))
+        .map_or(content, |(_, rest)| rest)
+}
+
+/// 统计反复出现的短语
+/// 
+/// 按标点和空白切分消息，同一条消息中重复的短语只计一次，
+/// 命令消息（以 `#` 开头）和纯数字不参与统计
+/// 
+/// # 参数
+/// * `contents` - 消息内容
+/// * `min_occurrences` - 短语至少出现在多少条消息中
+/// * `limit` - 返回的最大数量
+fn recurring_phrases<'a>(contents: impl Iterator<Item = &'a str>, min_occurrences: usize, limit: usize) -> Vec<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for content in contents.filter(|c| !c.starts_with('#')) {
+        let mut phrases: Vec<&str> = content
+            .split(|c: char| c.is_whitespace() || c.is_ascii_punctuation() || "，。！？、；：“”‘’（）【】《》…～".contains(c))
+            .filter(|p| LORE_PHRASE_CHARS.contains(&p.chars().count()))
+            .filter(|p| !p.chars().all(|c| c.is_ascii_digit()))
+            .collect();
+        phrases.sort_unstable();
+        phrases.dedup();
+        for phrase in phrases {
+            *counts.entry(phrase).or_insert(0) += 1;
+        }
+    }
+
+    let mut recurring: Vec<(&str, usize)> = counts
+        .into_iter()
+        .filter(|(_, count)| *count >= min_occurrences)
+        .collect();
+    recurring.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    recurring.into_iter().take(limit).map(|(phrase, _)| phrase.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::mock::{entry, manager};
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn writes_go_through_store() {
+        let (manager, state) = manager().await;
+
+        manager.add_memory(entry("e1", "第一次见面", MemoryType::Event)).await.unwrap();
+        assert!(state.data.lock().unwrap().memories.contains_key("e1"));
+        assert!(state.flushes.load(Ordering::Relaxed) > 0);
+        assert!(state.personality.lock().unwrap().is_some());
+
+        manager.update_user_profile(42, UserProfile::new(42, "小明")).await.unwrap();
+        assert!(state.data.lock().unwrap().user_profiles.contains_key(&42));
+
+        assert!(manager.delete_memory("e1").await.unwrap());
+        assert!(!state.data.lock().unwrap().memories.contains_key("e1"));
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn queries_go_through_store() {
+        let (manager, state) = manager().await;
+
+        // 只写入存储、不经过管理器缓存的记忆也能查到
+        {
+            let mut data = state.data.lock().unwrap();
+            data.memories.insert("e1".to_string(), entry("e1", "一起去看海", MemoryType::Event));
+            data.memories.insert("p1".to_string(), entry("p1", "喜欢猫", MemoryType::Preference));
+        }
+
+        let events = manager.get_memories_by_type(&MemoryType::Event).await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id, "e1");
+
+        let results = manager.search_memories("看海").await;
+        assert_eq!(results[0].id, "e1");
+        assert_eq!(manager.count_by_type(&MemoryType::Event).await, 0);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn personality_change_appends_journal_entry() {
+        let (manager, _state) = manager().await;
+        let mut personality = manager.get_bot_personality().await;
+        personality.curiosity_level = 2;
+        let trigger = format!("测试_{}", Local::now().timestamp_nanos_opt().unwrap_or_default());
+
+        manager.update_bot_personality(personality, &trigger).await.unwrap();
+
+        let journal = manager.get_personality_journal(usize::MAX).await.unwrap();
+        assert!(journal.iter().any(|entry| {
+            entry.trigger == trigger && entry.field == "curiosity_level" && entry.new_value == "2"
+        }));
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn run_cleanup_trims_below_the_cap() {
+        let (manager, _state) = manager().await;
+        {
+            let mut memories = manager.memories.lock().await;
+            for i in 0..MAX_MEMORIES {
+                let id = format!("e{}", i);
+                memories.insert(id.clone(), entry(&id, "事件", MemoryType::Event));
+            }
+        }
+
+        // 保存时只限制到上限，不会清理
+        manager.save_memories().await.unwrap();
+        assert_eq!(manager.count_memories().await, MAX_MEMORIES);
+
+        let removed = manager.run_cleanup().await.unwrap();
+        assert_eq!(removed, MAX_MEMORIES - CLEANUP_TARGET_MEMORIES);
+        assert_eq!(manager.count_memories().await, CLEANUP_TARGET_MEMORIES);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn falls_back_to_cache_when_store_is_unwritable() {
+        let (manager, state) = manager().await;
+        state.fail_writes.store(true, Ordering::Relaxed);
+
+        for i in 0..MAX_SAVE_FAILURES {
+            let _ = manager.add_memory(entry(&format!("e{}", i), &format!("事件{}", i), MemoryType::Event)).await;
+        }
+        assert!(manager.is_memory_only());
+        assert!(state.data.lock().unwrap().memories.is_empty());
+
+        // 存储中没有的记忆仍然可以从缓存中查到
+        assert_eq!(manager.get_memories_by_type(&MemoryType::Event).await.len(), MAX_SAVE_FAILURES as usize);
+
+        // 存储恢复后一次性写入积压的变更
+        state.fail_writes.store(false, Ordering::Relaxed);
+        assert!(manager.try_recover_storage().await);
+        assert_eq!(state.data.lock().unwrap().memories.len(), MAX_SAVE_FAILURES as usize);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn legacy_file_migrates_to_split_layout() {
+        let (manager, _state) = manager().await;
+        let dir = std::env::temp_dir().join(format!("kovi-migrate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bot_memory.json");
+
+        let mut data = crate::memory::store::MemoryData::default();
+        data.memories.insert("e1".to_string(), entry("e1", "一起去看海", MemoryType::Event));
+        let mut legacy = serde_json::to_value(&data).unwrap();
+        legacy["bot_personality"] = serde_json::to_value(manager.get_bot_personality().await).unwrap();
+        fs::write(&path, legacy.to_string()).unwrap();
+
+        assert!(MemoryManager::migrate_legacy(&path).unwrap());
+
+        let personality: BotPersonality =
+            serde_json::from_str(&fs::read_to_string(dir.join(PERSONALITY_FILE_NAME)).unwrap()).unwrap();
+        assert_eq!(personality.current_mood, "neutral");
+        let migrated: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(migrated.get("bot_personality").is_none());
+        assert!(migrated["memories"].get("e1").is_some());
+        assert!(path.with_extension("json.bak").exists());
+
+        // 已迁移过的文件不会再次迁移
+        assert!(!MemoryManager::migrate_legacy(&path).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unreinforced_memory_decays_over_time() {
+        let mut memory = entry("e1", "一起去看海", MemoryType::Event);
+        let created = memory.timestamp;
+
+        memory.apply_decay(created + chrono::Duration::days(20), 7);
+        assert_eq!(memory.importance, 3);
+
+        // 已经衰减过的时间不会重复计算
+        memory.apply_decay(created + chrono::Duration::days(20), 7);
+        assert_eq!(memory.importance, 3);
+
+        // 强化后重新计时
+        memory.reinforce(created + chrono::Duration::days(20));
+        assert_eq!(memory.importance, 4);
+        memory.apply_decay(created + chrono::Duration::days(26), 7);
+        assert_eq!(memory.importance, 4);
+
+        let mut pinned = entry("p1", "生日是五月一日", MemoryType::Event);
+        pinned.pinned = true;
+        pinned.apply_decay(created + chrono::Duration::days(365), 7);
+        assert_eq!(pinned.importance, 5);
+    }
+
+    #[test]
+    fn onboarding_advances_then_stops() {
+        let lines = vec!["我是小云".to_string(), "我喜欢聊天".to_string()];
+        let mut profile = UserProfile::new(42, "小明");
+
+        let mut sent = Vec::new();
+        for _ in 0..4 {
+            profile.interaction_count += 1;
+            sent.push(profile.next_onboarding_line(&lines));
+        }
+        assert_eq!(sent, [Some("我是小云"), Some("我喜欢聊天"), None, None]);
+        assert_eq!(profile.onboarding_step, 2);
+
+        // 老用户不会收到自我介绍
+        let mut veteran = UserProfile::new(43, "小红");
+        veteran.interaction_count = 10;
+        assert_eq!(veteran.next_onboarding_line(&lines), None);
+    }
+
+    #[test]
+    fn milestone_fires_once_around_boundary() {
+        let mut profile = UserProfile::new(42, "小明");
+        let thresholds = [5, 8];
+        let mut fired = Vec::new();
+
+        // 等级在5附近上下波动
+        for level in [4, 5, 4, 5, 6, 4, 5] {
+            let previous = profile.relationship_level;
+            profile.relationship_level = level;
+            fired.extend(profile.cross_milestone(previous, &thresholds));
+        }
+
+        assert_eq!(fired, [5]);
+        assert_eq!(profile.celebrated_milestones, [5]);
+    }
+
+    #[test]
+    fn many_matching_tags_score_high_and_rank_first() {
+        let mut tagged = entry("tagged", "周末一起去打游戏", MemoryType::Event);
+        tagged.tags = vec!["游戏".to_string(); 6];
+        tagged.importance = 10;
+        assert_eq!(relevance_score(&tagged, "游戏", Local::now()), 10 + 6 * 5 + 10 + 3);
+
+        let plain_a = entry("a", "游戏", MemoryType::Event);
+        let mut plain_b = entry("b", "游戏", MemoryType::Event);
+        plain_b.timestamp = plain_a.timestamp;
+
+        let ranked = rank_memories([&plain_b, &tagged, &plain_a], "游戏");
+        let ids: Vec<_> = ranked.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["tagged", "a", "b"]);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn delete_and_clear_by_type() {
+        let (manager, state) = manager().await;
+        manager.add_memory(entry("c1", "你好", MemoryType::Conversation)).await.unwrap();
+        manager.add_memory(entry("c2", "在吗", MemoryType::Conversation)).await.unwrap();
+        manager.add_memory(entry("u1", "喜欢猫", MemoryType::UserProfile)).await.unwrap();
+
+        assert!(!manager.delete_memory("missing").await.unwrap());
+
+        assert_eq!(manager.clear_memories_by_type(&MemoryType::Conversation).await.unwrap(), 2);
+        assert_eq!(manager.count_by_type(&MemoryType::Conversation).await, 0);
+        assert_eq!(manager.count_by_type(&MemoryType::UserProfile).await, 1);
+
+        let stored = &state.data.lock().unwrap().memories;
+        assert!(!stored.contains_key("c1") && !stored.contains_key("c2"));
+        assert!(stored.contains_key("u1"));
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn recalls_past_statement_about_topic() {
+        let (manager, _) = manager().await;
+        let mut game = entry("conv_42_1", "最近天天在玩LOL", MemoryType::Conversation);
+        game.tags = vec!["游戏".to_string()];
+        manager.add_memory(game).await.unwrap();
+        manager.add_memory(entry("conv_42_2", "今天吃了火锅", MemoryType::Conversation)).await.unwrap();
+        // 其他用户的同话题记忆不会被取到
+        let mut other = entry("conv_7_1", "我也玩游戏", MemoryType::Conversation);
+        other.tags = vec!["游戏".to_string()];
+        manager.add_memory(other).await.unwrap();
+
+        let found = manager.get_memories_about(42, "游戏", 5).await;
+        let ids: Vec<_> = found.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["conv_42_1"]);
+
+        let by_content = manager.get_memories_about(42, "lol", 5).await;
+        assert_eq!(by_content.len(), 1);
+        assert!(manager.get_memories_about(42, "  ", 5).await.is_empty());
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn inactive_relationship_decays_but_recent_stays() {
+        crate::config::use_test_data_dir();
+        let (manager, state) = manager().await;
+        let profile = |user_id, days_ago, level| {
+            let mut profile = UserProfile::new(user_id, "用户");
+            profile.last_interaction = Local::now() - chrono::Duration::days(days_ago);
+            profile.relationship_level = level;
+            profile
+        };
+        manager.update_user_profile(1, profile(1, 20, 5)).await.unwrap();
+        manager.update_user_profile(2, profile(2, 1, 5)).await.unwrap();
+        manager.update_user_profile(3, profile(3, 30, 1)).await.unwrap();
+
+        assert_eq!(manager.decay_relationships().await.unwrap(), 1);
+        assert_eq!(manager.get_user_profile(1).await.unwrap().relationship_level, 4);
+        assert_eq!(manager.get_user_profile(2).await.unwrap().relationship_level, 5);
+        assert_eq!(manager.get_user_profile(3).await.unwrap().relationship_level, 1);
+        assert_eq!(state.data.lock().unwrap().user_profiles[&1].relationship_level, 4);
+
+        // 同一阈值周期内不会重复降低
+        assert_eq!(manager.decay_relationships().await.unwrap(), 0);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn rapid_identical_messages_are_stored_once() {
+        crate::config::use_test_data_dir();
+        let (manager, state) = manager().await;
+        for (i, marker) in ["[10:00:01]", "[10:00:02]", "[10:00:03]"].iter().enumerate() {
+            let content = format!("{} 小明: 有人吗", marker);
+            manager.add_memory(entry(&format!("dup{}", i), &content, MemoryType::Conversation)).await.unwrap();
+        }
+
+        assert_eq!(manager.count_by_type(&MemoryType::Conversation).await, 1);
+        assert_eq!(state.data.lock().unwrap().memories.len(), 1);
+
+        // 其他类型的记忆不去重
+        manager.add_memory(entry("p1", "喜欢猫", MemoryType::UserProfile)).await.unwrap();
+        manager.add_memory(entry("p2", "喜欢猫", MemoryType::UserProfile)).await.unwrap();
+        assert_eq!(manager.count_by_type(&MemoryType::UserProfile).await, 2);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn recently_active_users_are_filtered_and_sorted() {
+        crate::config::use_test_data_dir();
+        let (manager, _) = manager().await;
+        for (user_id, hours_ago) in [(1, 30), (2, 5), (3, 1)] {
+            let mut profile = UserProfile::new(user_id, "用户");
+            profile.last_interaction = Local::now() - chrono::Duration::hours(hours_ago);
+            manager.update_user_profile(user_id, profile).await.unwrap();
+        }
+
+        assert_eq!(manager.get_recently_active_users(chrono::Duration::hours(24)).await, [3, 2]);
+        assert!(manager.get_recently_active_users(chrono::Duration::minutes(10)).await.is_empty());
+    }
+}
@@ -0,0 +1,197 @@
+//! # 记忆快照
+//!
+//! 在数据目录的 `snapshots` 子目录下为记忆文件和人格文件创建带时间戳的快照，
+//! 用于在聊天中完成简单的备份和灾难恢复
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 快照目录名，位于数据目录下
+const SNAPSHOT_DIR_NAME: &str = "snapshots";
+
+/// 快照信息
+#[derive(Debug, Clone)]
+pub struct SnapshotInfo {
+    /// 快照名称（创建时间）
+    pub name: String,
+    /// 创建时间
+    pub created_at: DateTime<Local>,
+    /// 快照内所有文件的总大小（字节）
+    pub size: u64,
+}
+
+/// 获取快照目录
+pub fn snapshot_dir() -> PathBuf {
+    crate::config::data_path(SNAPSHOT_DIR_NAME)
+}
+
+/// 创建新的快照目录
+///
+/// 目录以当前时间命名，同一秒内多次创建时追加序号
+///
+/// # 参数
+/// * `root` - 快照根目录
+///
+/// # 返回值
+/// 成功时返回快照名称和快照目录
+pub fn create_dir(root: &Path) -> Result<(String, PathBuf)> {
+    let base_name = Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let mut name = base_name.clone();
+    let mut suffix = 1;
+    while root.join(&name).exists() {
+        name = format!("{}-{}", base_name, suffix);
+        suffix += 1;
+    }
+
+    let dir = root.join(&name);
+    fs::create_dir_all(&dir).with_context(|| format!("创建快照目录失败: {}", dir.display()))?;
+    Ok((name, dir))
+}
+
+/// 查找已有的快照目录
+///
+/// # 参数
+/// * `root` - 快照根目录
+/// * `name` - 快照名称，不允许包含路径分隔符
+pub fn resolve(root: &Path, name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+        return Err(anyhow::anyhow!("无效的快照名称: {}", name));
+    }
+
+    let dir = root.join(name);
+    if !dir.is_dir() {
+        return Err(anyhow::anyhow!("快照不存在: {}", name));
+    }
+    Ok(dir)
+}
+
+/// 将文件复制到快照目录中，不存在的文件会被跳过
+///
+/// # 参数
+/// * `dir` - 快照目录
+/// * `files` - 需要备份的文件
+pub fn copy_into(dir: &Path, files: &[&Path]) -> Result<()> {
+    for file in files.iter().filter(|file| file.exists()) {
+        let file_name = file.file_name().context("无效的文件路径")?;
+        fs::copy(file, dir.join(file_name))
+            .with_context(|| format!("备份文件失败: {}", file.display()))?;
+    }
+    Ok(())
+}
+
+/// 用快照目录中的文件覆盖目标文件，快照中不存在的文件保持不变
+///
+/// # 参数
+/// * `dir` - 快照目录
+/// * `files` - 需要恢复的目标文件
+pub fn copy_from(dir: &Path, files: &[&Path]) -> Result<()> {
+    for file in files {
+        let file_name = file.file_name().context("无效的文件路径")?;
+        let source = dir.join(file_name);
+        if source.exists() {
+            fs::copy(&source, file).with_context(|| format!("恢复文件失败: {}", file.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// 列出所有快照，按创建时间从新到旧排列
+///
+/// # 参数
+/// * `root` - 快照根目录
+pub fn list(root: &Path) -> Result<Vec<SnapshotInfo>> {
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        let mut size = 0;
+        for file in fs::read_dir(entry.path())? {
+            size += file?.metadata()?.len();
+        }
+        snapshots.push(SnapshotInfo {
+            name: entry.file_name().to_string_lossy().to_string(),
+            created_at: entry.metadata()?.modified()?.into(),
+            size,
+        });
+    }
+    snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| b.name.cmp(&a.name)));
+    Ok(snapshots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("kovi-snapshot-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    #[test]
+    fn create_dir_allocates_unique_names() {
+        let root = temp_root("unique");
+        let (first, first_dir) = create_dir(&root).unwrap();
+        let (second, second_dir) = create_dir(&root).unwrap();
+
+        assert_ne!(first, second);
+        assert!(first_dir.is_dir() && second_dir.is_dir());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn copies_files_in_and_out() {
+        let root = temp_root("copy");
+        let file = root.join("bot_memory.json");
+        let missing = root.join("bot_personality.json");
+        fs::write(&file, "old").unwrap();
+
+        let (name, dir) = create_dir(&root.join("snapshots")).unwrap();
+        copy_into(&dir, &[&file, &missing]).unwrap();
+        assert!(dir.join("bot_memory.json").exists());
+        assert!(!dir.join("bot_personality.json").exists());
+
+        fs::write(&file, "new").unwrap();
+        let dir = resolve(&root.join("snapshots"), &name).unwrap();
+        copy_from(&dir, &[&file, &missing]).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "old");
+        assert!(!missing.exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn lists_snapshots_with_sizes() {
+        let root = temp_root("list");
+        assert!(list(&root.join("missing")).unwrap().is_empty());
+
+        let (name, dir) = create_dir(&root).unwrap();
+        fs::write(dir.join("bot_memory.json"), "12345").unwrap();
+        fs::write(root.join("not_a_snapshot.txt"), "x").unwrap();
+
+        let snapshots = list(&root).unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].name, name);
+        assert_eq!(snapshots[0].size, 5);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        let root = temp_root("resolve");
+        assert!(resolve(&root, "").is_err());
+        assert!(resolve(&root, "../etc").is_err());
+        assert!(resolve(&root, "a/b").is_err());
+        assert!(resolve(&root, "20240101-000000").is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,287 @@
+//! # SQLite 存储后端
+//!
+//! 每条记忆和每个档案单独保存为一行（内容为 JSON），
+//! 增删改只写入对应的行，避免记忆增多后每条消息都重写全部数据；按类型查询使用索引
+
+use crate::memory::store::{JsonStore, MemoryData, MemoryStore, StoreFuture};
+use crate::memory::{BotPersonality, GroupProfile, MemoryEntry, MemoryType, UserProfile, rank_memories};
+use anyhow::{Context, Result};
+use rusqlite::backup::Progress;
+use rusqlite::{Connection, MAIN_DB, OptionalExtension, params};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS memories (
+        id TEXT PRIMARY KEY,
+        memory_type TEXT NOT NULL,
+        timestamp TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_memories_type ON memories (memory_type);
+    CREATE TABLE IF NOT EXISTS user_profiles (
+        user_id INTEGER PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS group_profiles (
+        group_id INTEGER PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS personality (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        data TEXT NOT NULL
+    );
+";
+
+/// SQLite 存储
+pub struct SqliteStore {
+    db_file: PathBuf,
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStore {
+    /// 打开（必要时创建）数据库并初始化表结构
+    pub fn open(db_file: &Path) -> Result<Self> {
+        let conn = Connection::open(db_file)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { db_file: db_file.to_path_buf(), conn: Arc::new(Mutex::new(conn)) })
+    }
+
+    /// 数据库为空时导入 JSON 存储中的记忆、档案和人格
+    ///
+    /// 在打开存储时同步调用
+    ///
+    /// # 返回值
+    /// 执行了导入时返回 `true`，数据库已有数据或没有可导入的数据时返回 `false`
+    pub fn import_from(&self, json: &JsonStore) -> Result<bool> {
+        let mut conn = self.conn.lock().unwrap();
+        let has_data: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM memories) OR EXISTS (SELECT 1 FROM personality)",
+            [],
+            |row| row.get(0),
+        )?;
+        if has_data {
+            return Ok(false);
+        }
+
+        let data = json.read_data()?;
+        let personality = json.read_personality()?;
+        if data.is_none() && personality.is_none() {
+            return Ok(false);
+        }
+
+        let tx = conn.transaction()?;
+        if let Some(data) = data {
+            for memory in data.memories.values() {
+                upsert_memory(&tx, memory)?;
+            }
+            for profile in data.user_profiles.values() {
+                upsert_user_profile(&tx, profile)?;
+            }
+            for profile in data.group_profiles.values() {
+                upsert_group_profile(&tx, profile)?;
+            }
+        }
+        if let Some(personality) = personality {
+            save_personality(&tx, &personality)?;
+        }
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// 在阻塞线程池中访问数据库连接
+    fn with_conn<T, F>(&self, f: F) -> StoreFuture<'static, T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let conn = Arc::clone(&self.conn);
+        Box::pin(async move { kovi::tokio::task::spawn_blocking(move || f(&mut conn.lock().unwrap())).await? })
+    }
+}
+
+/// 查询记忆，每行的第一列为记忆的 JSON 数据
+fn query_memories(conn: &Connection, sql: &str, params: impl rusqlite::Params) -> Result<Vec<MemoryEntry>> {
+    let mut stmt = conn.prepare_cached(sql)?;
+    let rows = stmt.query_map(params, |row| row.get::<_, String>(0))?;
+    let mut memories = Vec::new();
+    for json in rows {
+        memories.push(serde_json::from_str(&json?)?);
+    }
+    Ok(memories)
+}
+
+fn upsert_memory(conn: &Connection, memory: &MemoryEntry) -> Result<()> {
+    conn.prepare_cached("INSERT OR REPLACE INTO memories (id, memory_type, timestamp, data) VALUES (?1, ?2, ?3, ?4)")?
+        .execute(params![
+            memory.id,
+            format!("{:?}", memory.memory_type),
+            memory.timestamp.to_rfc3339(),
+            serde_json::to_string(memory)?,
+        ])?;
+    Ok(())
+}
+
+fn upsert_user_profile(conn: &Connection, profile: &UserProfile) -> Result<()> {
+    conn.prepare_cached("INSERT OR REPLACE INTO user_profiles (user_id, data) VALUES (?1, ?2)")?
+        .execute(params![profile.user_id, serde_json::to_string(profile)?])?;
+    Ok(())
+}
+
+fn upsert_group_profile(conn: &Connection, profile: &GroupProfile) -> Result<()> {
+    conn.prepare_cached("INSERT OR REPLACE INTO group_profiles (group_id, data) VALUES (?1, ?2)")?
+        .execute(params![profile.group_id, serde_json::to_string(profile)?])?;
+    Ok(())
+}
+
+fn save_personality(conn: &Connection, personality: &BotPersonality) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO personality (id, data) VALUES (1, ?1)",
+        params![serde_json::to_string(personality)?],
+    )?;
+    Ok(())
+}
+
+impl MemoryStore for SqliteStore {
+    fn load(&self) -> StoreFuture<'_, Option<MemoryData>> {
+        self.with_conn(|conn| {
+            let mut data = MemoryData::default();
+            for memory in query_memories(conn, "SELECT data FROM memories", [])? {
+                data.memories.insert(memory.id.clone(), memory);
+            }
+
+            let mut stmt = conn.prepare("SELECT user_id, data FROM user_profiles")?;
+            for row in stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))? {
+                let (user_id, json) = row?;
+                data.user_profiles.insert(user_id, serde_json::from_str(&json)?);
+            }
+
+            let mut stmt = conn.prepare("SELECT group_id, data FROM group_profiles")?;
+            for row in stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))? {
+                let (group_id, json) = row?;
+                data.group_profiles.insert(group_id, serde_json::from_str(&json)?);
+            }
+
+            Ok(Some(data))
+        })
+    }
+
+    fn add_memory(&self, memory: MemoryEntry) -> StoreFuture<'_, ()> {
+        self.with_conn(move |conn| upsert_memory(conn, &memory))
+    }
+
+    fn remove_memories(&self, ids: Vec<String>) -> StoreFuture<'_, ()> {
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                let mut delete = tx.prepare_cached("DELETE FROM memories WHERE id = ?1")?;
+                for id in &ids {
+                    delete.execute(params![id])?;
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    /// 按 `memory_type` 索引查询
+    fn get_memories_by_type(&self, memory_type: MemoryType) -> StoreFuture<'_, Vec<MemoryEntry>> {
+        self.with_conn(move |conn| {
+            query_memories(conn, "SELECT data FROM memories WHERE memory_type = ?1", params![format!("{:?}", memory_type)])
+        })
+    }
+
+    fn search_memories(&self, query: String) -> StoreFuture<'_, Vec<MemoryEntry>> {
+        self.with_conn(move |conn| {
+            let memories = query_memories(conn, "SELECT data FROM memories", [])?;
+            Ok(rank_memories(&memories, &query))
+        })
+    }
+
+    fn upsert_user_profile(&self, profile: UserProfile) -> StoreFuture<'_, ()> {
+        self.with_conn(move |conn| upsert_user_profile(conn, &profile))
+    }
+
+    fn upsert_group_profile(&self, profile: GroupProfile) -> StoreFuture<'_, ()> {
+        self.with_conn(move |conn| upsert_group_profile(conn, &profile))
+    }
+
+    fn load_personality(&self) -> StoreFuture<'_, Option<BotPersonality>> {
+        self.with_conn(|conn| {
+            let json: Option<String> = conn
+                .query_row("SELECT data FROM personality WHERE id = 1", [], |row| row.get(0))
+                .optional()?;
+            Ok(json.map(|json| serde_json::from_str(&json)).transpose()?)
+        })
+    }
+
+    fn save_personality(&self, personality: BotPersonality) -> StoreFuture<'_, ()> {
+        self.with_conn(move |conn| save_personality(conn, &personality))
+    }
+
+    fn files(&self) -> Vec<&Path> {
+        vec![&self.db_file]
+    }
+
+    /// 通过 SQLite 在线备份接口导出数据库
+    ///
+    /// 备份期间持有连接锁，不会与写入交错，得到的是一致的数据库文件
+    fn backup(&self, dir: PathBuf) -> StoreFuture<'_, ()> {
+        let file_name = self.db_file.file_name().map(|name| name.to_os_string());
+        self.with_conn(move |conn| {
+            let file_name = file_name.context("无效的数据库路径")?;
+            conn.backup(MAIN_DB, dir.join(file_name), None)?;
+            Ok(())
+        })
+    }
+
+    /// 通过 SQLite 在线备份接口将快照中的数据库导入当前连接
+    fn restore(&self, dir: PathBuf) -> StoreFuture<'_, ()> {
+        let file_name = self.db_file.file_name().map(|name| name.to_os_string());
+        self.with_conn(move |conn| {
+            let source = dir.join(file_name.context("无效的数据库路径")?);
+            if !source.exists() {
+                return Err(anyhow::anyhow!("快照中没有数据库文件: {}", source.display()));
+            }
+            conn.restore(MAIN_DB, source, None::<fn(Progress)>)?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+
+    fn personality(mood: &str) -> BotPersonality {
+        BotPersonality {
+            current_mood: mood.to_string(),
+            mood_intensity: 5,
+            energy_level: 7,
+            social_confidence: 6,
+            curiosity_level: 8,
+            last_mood_change: Local::now(),
+            personality_traits: Vec::new(),
+            last_reflection: None,
+        }
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn backup_and_restore_round_trip() {
+        let root = std::env::temp_dir().join(format!("kovi-sqlite-backup-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let snapshot = root.join("snapshot");
+        std::fs::create_dir_all(&snapshot).unwrap();
+
+        let store = SqliteStore::open(&root.join("bot_memory.db")).unwrap();
+        store.save_personality(personality("happy")).await.unwrap();
+        store.backup(snapshot.clone()).await.unwrap();
+        assert!(snapshot.join("bot_memory.db").exists());
+
+        store.save_personality(personality("sad")).await.unwrap();
+        store.restore(snapshot).await.unwrap();
+        assert_eq!(store.load_personality().await.unwrap().unwrap().current_mood, "happy");
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
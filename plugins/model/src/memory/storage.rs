@@ -0,0 +1,109 @@
+//! # 记忆存储格式
+//!
+//! 支持以 JSON 或 MessagePack 格式保存记忆文件：
+//! - JSON：便于阅读和手动修改，默认格式
+//! - MessagePack：紧凑的二进制格式，体积更小、解析更快
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 是否已经提示过扩展名与配置的格式不一致，避免每次保存都打印
+static MISMATCH_REPORTED: AtomicBool = AtomicBool::new(false);
+
+/// 记忆文件的存储格式
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageFormat {
+    /// 格式化的 JSON
+    #[default]
+    Json,
+    /// MessagePack 二进制格式
+    #[serde(rename = "msgpack")]
+    MessagePack,
+}
+
+impl StorageFormat {
+    /// 确定文件使用的存储格式
+    ///
+    /// 始终使用配置的格式；文件扩展名暗示的格式与配置不一致时
+    /// （如配置了 `msgpack` 而记忆文件仍为 `bot_memory.json`）打印一次提示
+    pub fn resolve(path: &Path, configured: StorageFormat) -> StorageFormat {
+        if let Some(implied) = Self::from_extension(path)
+            && implied != configured
+            && !MISMATCH_REPORTED.swap(true, Ordering::Relaxed)
+        {
+            eprintln!(
+                "[ERROR] 记忆文件 {} 的扩展名与配置的存储格式 {:?} 不一致，将按配置的格式读写，建议同时修改 memory_file 的扩展名",
+                path.display(),
+                configured
+            );
+        }
+        configured
+    }
+
+    /// 根据文件扩展名推断存储格式
+    ///
+    /// 扩展名为 `.msgpack` 或 `.mpk` 时为 MessagePack，为 `.json` 时为 JSON，其他情况无法推断
+    pub fn from_extension(path: &Path) -> Option<StorageFormat> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("msgpack") | Some("mpk") => Some(StorageFormat::MessagePack),
+            Some("json") => Some(StorageFormat::Json),
+            _ => None,
+        }
+    }
+
+    /// 另一种存储格式，用于读取失败时回退
+    pub fn other(&self) -> StorageFormat {
+        match self {
+            StorageFormat::Json => StorageFormat::MessagePack,
+            StorageFormat::MessagePack => StorageFormat::Json,
+        }
+    }
+
+    /// 序列化数据
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        match self {
+            StorageFormat::Json => Ok(serde_json::to_vec_pretty(value)?),
+            StorageFormat::MessagePack => Ok(rmp_serde::to_vec_named(value)?),
+        }
+    }
+
+    /// 反序列化数据
+    pub fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        match self {
+            StorageFormat::Json => Ok(serde_json::from_slice(bytes)?),
+            StorageFormat::MessagePack => Ok(rmp_serde::from_slice(bytes)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::store::MemoryData;
+    use crate::memory::UserProfile;
+
+    #[test]
+    fn configured_format_wins_over_extension() {
+        let path = Path::new("bot_memory.json");
+        assert_eq!(StorageFormat::resolve(path, StorageFormat::MessagePack), StorageFormat::MessagePack);
+        assert_eq!(StorageFormat::resolve(path, StorageFormat::Json), StorageFormat::Json);
+        assert_eq!(StorageFormat::from_extension(Path::new("bot_memory.mpk")), Some(StorageFormat::MessagePack));
+        assert_eq!(StorageFormat::from_extension(Path::new("bot_memory")), None);
+    }
+
+    #[test]
+    fn message_pack_round_trip() {
+        let mut data = MemoryData::default();
+        data.user_profiles.insert(42, UserProfile::new(42, "小明"));
+
+        let bytes = StorageFormat::MessagePack.encode(&data).unwrap();
+        assert!(StorageFormat::Json.decode::<MemoryData>(&bytes).is_err());
+
+        let decoded: MemoryData = StorageFormat::MessagePack.decode(&bytes).unwrap();
+        assert_eq!(decoded.user_profiles[&42].nickname, "小明");
+    }
+}
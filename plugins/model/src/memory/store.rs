@@ -0,0 +1,450 @@
+//! # 记忆存储后端
+//!
+//! 存储后端负责记忆和档案的增删改查与检索，记忆管理器的所有读写都经过 [`MemoryStore`]，
+//! 管理器只在内存中保留一份缓存用于统计类查询和仅内存模式：
+//! - JSON 文件：默认后端，数据保存在内存中，每次保存重写整个记忆文件
+//! - SQLite：需要启用 `sqlite` 功能，每条记忆和档案单独写入，按类型查询走索引
+//! - 宿主自定义：实现 [`MemoryStore`] 并通过 [`crate::memory::install_store`] 安装，
+//!   可以接入 Postgres、Redis 等外部存储
+
+use crate::memory::snapshot;
+use crate::memory::storage::StorageFormat;
+use crate::memory::{BotPersonality, GroupProfile, MemoryEntry, MemoryType, UserProfile, rank_memories};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+/// 存储后端类型
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// JSON / MessagePack 记忆文件
+    #[default]
+    Json,
+    /// SQLite 数据库，需要启用 `sqlite` 功能
+    Sqlite,
+}
+
+impl StorageBackend {
+    /// 当前构建是否支持该后端
+    pub fn is_available(&self) -> bool {
+        match self {
+            StorageBackend::Json => true,
+            StorageBackend::Sqlite => cfg!(feature = "sqlite"),
+        }
+    }
+}
+
+/// 存储中的全部记忆数据
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MemoryData {
+    pub memories: HashMap<String, MemoryEntry>,
+    pub user_profiles: HashMap<i64, UserProfile>,
+    pub group_profiles: HashMap<i64, GroupProfile>,
+}
+
+/// 自上次保存以来发生变化的条目
+#[derive(Debug, Default, Clone)]
+pub struct ChangeSet {
+    /// 新增或修改的记忆ID
+    pub memories: HashSet<String>,
+    /// 被删除的记忆ID
+    pub removed_memories: HashSet<String>,
+    /// 新增或修改的用户档案
+    pub user_profiles: HashSet<i64>,
+    /// 新增或修改的群组档案
+    pub group_profiles: HashSet<i64>,
+}
+
+impl ChangeSet {
+    /// 是否没有任何变更
+    pub fn is_empty(&self) -> bool {
+        self.memories.is_empty()
+            && self.removed_memories.is_empty()
+            && self.user_profiles.is_empty()
+            && self.group_profiles.is_empty()
+    }
+
+    /// 记录新增或修改的记忆
+    pub fn touch_memory(&mut self, id: &str) {
+        self.removed_memories.remove(id);
+        self.memories.insert(id.to_string());
+    }
+
+    /// 记录被删除的记忆
+    pub fn remove_memory(&mut self, id: &str) {
+        self.memories.remove(id);
+        self.removed_memories.insert(id.to_string());
+    }
+
+    /// 并入一组更早的变更，用于保存失败后保留未写入的变更
+    ///
+    /// 同一条记忆以当前变更集中的记录为准
+    pub fn merge_older(&mut self, older: ChangeSet) {
+        for id in older.memories {
+            if !self.removed_memories.contains(&id) {
+                self.memories.insert(id);
+            }
+        }
+        for id in older.removed_memories {
+            if !self.memories.contains(&id) {
+                self.removed_memories.insert(id);
+            }
+        }
+        self.user_profiles.extend(older.user_profiles);
+        self.group_profiles.extend(older.group_profiles);
+    }
+}
+
+/// 存储操作返回的 Future
+pub type StoreFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// 记忆存储后端
+///
+/// 所有操作都是异步的，内置后端在阻塞线程池中执行磁盘I/O，
+/// 宿主实现可以直接调用 Postgres、Redis 等异步客户端
+pub trait MemoryStore: Send + Sync {
+    /// 加载全部记忆和档案，用于填充记忆管理器的缓存，存储中还没有数据时返回 `None`
+    fn load(&self) -> StoreFuture<'_, Option<MemoryData>>;
+
+    /// 新增或更新一条记忆
+    fn add_memory(&self, memory: MemoryEntry) -> StoreFuture<'_, ()>;
+
+    /// 删除记忆，不存在的ID会被忽略
+    fn remove_memories(&self, ids: Vec<String>) -> StoreFuture<'_, ()>;
+
+    /// 获取指定类型的全部记忆
+    fn get_memories_by_type(&self, memory_type: MemoryType) -> StoreFuture<'_, Vec<MemoryEntry>>;
+
+    /// 搜索记忆，按相关性从高到低排列
+    ///
+    /// 内置后端使用 [`rank_memories`] 的评分规则，宿主实现可以换成全文检索
+    fn search_memories(&self, query: String) -> StoreFuture<'_, Vec<MemoryEntry>>;
+
+    /// 新增或更新用户档案
+    fn upsert_user_profile(&self, profile: UserProfile) -> StoreFuture<'_, ()>;
+
+    /// 新增或更新群组档案
+    fn upsert_group_profile(&self, profile: GroupProfile) -> StoreFuture<'_, ()>;
+
+    /// 加载机器人人格，存储中还没有人格数据时返回 `None`
+    fn load_personality(&self) -> StoreFuture<'_, Option<BotPersonality>>;
+
+    /// 保存机器人人格
+    fn save_personality(&self, personality: BotPersonality) -> StoreFuture<'_, ()>;
+
+    /// 将缓冲的写入持久化，记忆管理器在每批写入之后调用
+    ///
+    /// 逐条写入的后端不需要实现
+    fn flush(&self) -> StoreFuture<'_, ()> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// 存储占用的文件，第一个为主数据文件，用于快照备份和健康检查
+    ///
+    /// 不使用本地文件的存储返回空列表
+    fn files(&self) -> Vec<&Path> {
+        Vec::new()
+    }
+
+    /// 将当前存储的数据备份到快照目录
+    ///
+    /// 默认复制 [`MemoryStore::files`] 中的文件，不使用本地文件的存储需要自行实现
+    fn backup(&self, dir: PathBuf) -> StoreFuture<'_, ()> {
+        copy_files(self.files(), dir, snapshot::copy_into)
+    }
+
+    /// 用快照目录中的数据覆盖当前存储
+    ///
+    /// 默认用快照中的同名文件覆盖 [`MemoryStore::files`]
+    fn restore(&self, dir: PathBuf) -> StoreFuture<'_, ()> {
+        copy_files(self.files(), dir, snapshot::copy_from)
+    }
+}
+
+/// 在阻塞线程池中执行快照复制
+fn copy_files(files: Vec<&Path>, dir: PathBuf, copy: fn(&Path, &[&Path]) -> Result<()>) -> StoreFuture<'static, ()> {
+    let files: Vec<PathBuf> = files.into_iter().map(Path::to_path_buf).collect();
+    Box::pin(async move {
+        if files.is_empty() {
+            return Err(anyhow::anyhow!("当前存储后端不使用本地文件，不支持快照"));
+        }
+        kovi::tokio::task::spawn_blocking(move || {
+            let files: Vec<&Path> = files.iter().map(PathBuf::as_path).collect();
+            copy(&dir, &files)
+        })
+        .await?
+    })
+}
+
+/// JSON 文件存储
+///
+/// 记忆和档案保存在记忆文件中（JSON 或 MessagePack 格式），人格单独保存为 JSON 文件。
+/// 记忆文件在首次访问时读入内存，增删改在内存中完成，[`MemoryStore::flush`] 时整体写回
+pub struct JsonStore {
+    state: Arc<JsonState>,
+}
+
+struct JsonState {
+    memory_file: PathBuf,
+    personality_file: PathBuf,
+    /// 记忆文件中的数据，首次访问时读取
+    data: Mutex<Option<MemoryData>>,
+}
+
+impl JsonStore {
+    pub fn new(memory_file: PathBuf, personality_file: PathBuf) -> Self {
+        Self {
+            state: Arc::new(JsonState { memory_file, personality_file, data: Mutex::new(None) }),
+        }
+    }
+
+    /// 直接读取记忆文件，不经过内存中的数据
+    pub fn read_data(&self) -> Result<Option<MemoryData>> {
+        self.state.read_data()
+    }
+
+    /// 直接读取人格文件
+    pub fn read_personality(&self) -> Result<Option<BotPersonality>> {
+        self.state.read_personality()
+    }
+
+    /// 在阻塞线程池中访问内存中的记忆数据，首次访问时先读取记忆文件
+    fn with_data<T, F>(&self, f: F) -> StoreFuture<'static, T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&JsonState, &mut MemoryData) -> Result<T> + Send + 'static,
+    {
+        let state = Arc::clone(&self.state);
+        Box::pin(async move {
+            kovi::tokio::task::spawn_blocking(move || {
+                let mut data = state.data.lock().unwrap();
+                if data.is_none() {
+                    *data = Some(state.read_data()?.unwrap_or_default());
+                }
+                f(&state, data.as_mut().expect("记忆数据已加载"))
+            })
+            .await?
+        })
+    }
+}
+
+impl JsonState {
+    /// 当前记忆文件使用的存储格式
+    fn storage_format(&self) -> StorageFormat {
+        StorageFormat::resolve(&self.memory_file, crate::config::get().memory().storage_format())
+    }
+
+    /// 读取记忆文件
+    ///
+    /// 按当前存储格式解析失败时尝试另一种格式，便于切换格式后读取旧文件，
+    /// 下次保存时会以新格式写回
+    fn read_data(&self) -> Result<Option<MemoryData>> {
+        if !self.memory_file.exists() {
+            return Ok(None);
+        }
+
+        let bytes = fs::read(&self.memory_file)?;
+        let format = self.storage_format();
+        match format.decode(&bytes) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) => match format.other().decode(&bytes) {
+                Ok(data) => {
+                    println!("[INFO] 记忆文件不是{:?}格式，已按{:?}格式读取", format, format.other());
+                    Ok(Some(data))
+                }
+                Err(_) => Err(e),
+            },
+        }
+    }
+
+    fn read_personality(&self) -> Result<Option<BotPersonality>> {
+        if !self.personality_file.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&fs::read_to_string(&self.personality_file)?)?))
+    }
+}
+
+impl MemoryStore for JsonStore {
+    /// 重新读取记忆文件，并替换内存中的数据
+    fn load(&self) -> StoreFuture<'_, Option<MemoryData>> {
+        let state = Arc::clone(&self.state);
+        Box::pin(async move {
+            kovi::tokio::task::spawn_blocking(move || {
+                let loaded = state.read_data()?;
+                let mut data = state.data.lock().unwrap();
+                *data = Some(loaded.clone().unwrap_or_default());
+                Ok(loaded)
+            })
+            .await?
+        })
+    }
+
+    fn add_memory(&self, memory: MemoryEntry) -> StoreFuture<'_, ()> {
+        self.with_data(move |_, data| {
+            data.memories.insert(memory.id.clone(), memory);
+            Ok(())
+        })
+    }
+
+    fn remove_memories(&self, ids: Vec<String>) -> StoreFuture<'_, ()> {
+        self.with_data(move |_, data| {
+            for id in &ids {
+                data.memories.remove(id);
+            }
+            Ok(())
+        })
+    }
+
+    fn get_memories_by_type(&self, memory_type: MemoryType) -> StoreFuture<'_, Vec<MemoryEntry>> {
+        self.with_data(move |_, data| {
+            Ok(data.memories.values().filter(|m| m.is_type(&memory_type)).cloned().collect())
+        })
+    }
+
+    fn search_memories(&self, query: String) -> StoreFuture<'_, Vec<MemoryEntry>> {
+        self.with_data(move |_, data| Ok(rank_memories(data.memories.values(), &query)))
+    }
+
+    fn upsert_user_profile(&self, profile: UserProfile) -> StoreFuture<'_, ()> {
+        self.with_data(move |_, data| {
+            data.user_profiles.insert(profile.user_id, profile);
+            Ok(())
+        })
+    }
+
+    fn upsert_group_profile(&self, profile: GroupProfile) -> StoreFuture<'_, ()> {
+        self.with_data(move |_, data| {
+            data.group_profiles.insert(profile.group_id, profile);
+            Ok(())
+        })
+    }
+
+    fn load_personality(&self) -> StoreFuture<'_, Option<BotPersonality>> {
+        let state = Arc::clone(&self.state);
+        Box::pin(async move { kovi::tokio::task::spawn_blocking(move || state.read_personality()).await? })
+    }
+
+    fn save_personality(&self, personality: BotPersonality) -> StoreFuture<'_, ()> {
+        let state = Arc::clone(&self.state);
+        Box::pin(async move {
+            kovi::tokio::task::spawn_blocking(move || {
+                fs::write(&state.personality_file, serde_json::to_string_pretty(&personality)?)?;
+                Ok(())
+            })
+            .await?
+        })
+    }
+
+    /// 将内存中的数据整体写回记忆文件
+    fn flush(&self) -> StoreFuture<'_, ()> {
+        self.with_data(|state, data| {
+            fs::write(&state.memory_file, state.storage_format().encode(&*data)?)?;
+            Ok(())
+        })
+    }
+
+    fn files(&self) -> Vec<&Path> {
+        vec![&self.state.memory_file, &self.state.personality_file]
+    }
+
+    /// 恢复文件后丢弃内存中的数据，下次访问时重新读取
+    fn restore(&self, dir: PathBuf) -> StoreFuture<'_, ()> {
+        let state = Arc::clone(&self.state);
+        let restore = copy_files(self.files(), dir, snapshot::copy_from);
+        Box::pin(async move {
+            restore.await?;
+            *state.data.lock().unwrap() = None;
+            Ok(())
+        })
+    }
+}
+
+/// 根据配置打开存储后端
+///
+/// 使用 SQLite 且数据库中还没有数据时，会自动导入现有的 JSON 记忆文件和人格文件
+///
+/// # 参数
+/// * `backend` - 配置的后端类型
+/// * `memory_file` - JSON 记忆文件路径
+/// * `personality_file` - JSON 人格文件路径
+pub fn open(backend: StorageBackend, memory_file: &Path, personality_file: &Path) -> Box<dyn MemoryStore> {
+    let json = JsonStore::new(memory_file.to_path_buf(), personality_file.to_path_buf());
+    match backend {
+        StorageBackend::Json => Box::new(json),
+        #[cfg(feature = "sqlite")]
+        StorageBackend::Sqlite => {
+            let db_file = memory_file.with_extension("db");
+            match crate::memory::sqlite::SqliteStore::open(&db_file).and_then(|store| {
+                if store.import_from(&json)? {
+                    println!("[INFO] 已将记忆文件导入SQLite数据库: {}", db_file.display());
+                }
+                Ok(store)
+            }) {
+                Ok(store) => Box::new(store),
+                Err(e) => {
+                    eprintln!("[ERROR] 打开SQLite数据库失败，改用JSON存储: {}", e);
+                    Box::new(json)
+                }
+            }
+        }
+        #[cfg(not(feature = "sqlite"))]
+        StorageBackend::Sqlite => {
+            eprintln!("[ERROR] 未启用 sqlite 功能，改用JSON存储");
+            Box::new(json)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::mock::entry;
+    use chrono::Local;
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn json_store_round_trips_through_files() {
+        crate::config::use_test_data_dir();
+        let dir = std::env::temp_dir().join(format!("kovi-json-store-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let memory_file = dir.join("bot_memory.json");
+        let personality_file = dir.join("bot_personality.json");
+
+        let store = JsonStore::new(memory_file.clone(), personality_file.clone());
+        store.add_memory(entry("e1", "第一次见面", MemoryType::Event)).await.unwrap();
+        store.upsert_user_profile(UserProfile::new(42, "小明")).await.unwrap();
+        store.upsert_group_profile(GroupProfile::new(7)).await.unwrap();
+        store
+            .save_personality(BotPersonality {
+                current_mood: "happy".to_string(),
+                mood_intensity: 5,
+                energy_level: 7,
+                social_confidence: 6,
+                curiosity_level: 8,
+                last_mood_change: Local::now(),
+                personality_traits: Vec::new(),
+                last_reflection: None,
+            })
+            .await
+            .unwrap();
+        store.flush().await.unwrap();
+
+        // 新实例从文件读取，数据与写入时一致
+        let reopened = JsonStore::new(memory_file, personality_file);
+        let data = reopened.load().await.unwrap().unwrap();
+        assert_eq!(data.memories["e1"].content, "第一次见面");
+        assert_eq!(data.user_profiles[&42].nickname, "小明");
+        assert!(data.group_profiles.contains_key(&7));
+        assert_eq!(reopened.load_personality().await.unwrap().unwrap().current_mood, "happy");
+        assert_eq!(reopened.get_memories_by_type(MemoryType::Event).await.unwrap().len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
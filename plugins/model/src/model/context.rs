@@ -0,0 +1,160 @@
+//! # 上下文注入模块
+//!
+//! 负责将用户档案、机器人状态和相关记忆组装成系统提示中的上下文部分，
+//! 并在超出字符预算时按优先级裁剪：
+//! 置顶记忆 > 近期记忆 > 档案要点 > 机器人状态 > 兴趣
+
+/// 上下文片段所属的分区
+///
+/// 声明顺序即优先级顺序，越靠前越重要，越晚被裁剪
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum InjectionSection {
+    /// 置顶记忆：永不裁剪
+    PinnedMemory,
+    /// 近期相关记忆
+    RecentMemory,
+    /// 用户档案要点（昵称、关系等级、语气等）
+    ProfileEssential,
+    /// 机器人当前状态
+    BotStatus,
+    /// 用户兴趣
+    Interest,
+}
+
+/// 单个上下文片段
+#[derive(Debug, Clone)]
+pub(crate) struct InjectionItem {
+    pub(crate) section: InjectionSection,
+    pub(crate) text: String,
+}
+
+impl InjectionItem {
+    pub(crate) fn new(section: InjectionSection, text: impl Into<String>) -> Self {
+        Self {
+            section,
+            text: text.into(),
+        }
+    }
+}
+
+/// 在预算内组装上下文注入文本
+///
+/// 超出预算时，从优先级最低的分区开始、每次移除该分区最后一个片段，
+/// 直到满足预算或只剩置顶记忆为止
+///
+/// # 参数
+/// * `items` - 所有候选片段，同一分区内按重要性从高到低排列
+/// * `budget` - 最大字符数，0表示不限制
+///
+/// # 返回值
+/// 可直接追加到系统提示后的文本
+pub(crate) fn assemble_injection(mut items: Vec<InjectionItem>, budget: usize) -> String {
+    let mut rendered = render(&items);
+    if budget == 0 {
+        return rendered;
+    }
+
+    while rendered.chars().count() > budget {
+        let victim = items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.section != InjectionSection::PinnedMemory)
+            .max_by_key(|(index, item)| (item.section, *index))
+            .map(|(index, _)| index);
+
+        match victim {
+            Some(index) => {
+                items.remove(index);
+                rendered = render(&items);
+            }
+            None => break,
+        }
+    }
+
+    rendered
+}
+
+fn render(items: &[InjectionItem]) -> String {
+    let texts = |section: InjectionSection| -> Vec<&str> {
+        items
+            .iter()
+            .filter(|item| item.section == section)
+            .map(|item| item.text.as_str())
+            .collect()
+    };
+
+    let mut output = String::new();
+
+    let essentials = texts(InjectionSection::ProfileEssential);
+    let interests = texts(InjectionSection::Interest);
+    if !essentials.is_empty() || !interests.is_empty() {
+        output.push_str("\n\n用户信息：");
+        for line in essentials {
+            output.push_str(&format!("\n- {}", line));
+        }
+        if !interests.is_empty() {
+            output.push_str(&format!("\n- 兴趣：{}", interests.join(", ")));
+        }
+    }
+
+    let status = texts(InjectionSection::BotStatus);
+    if !status.is_empty() {
+        output.push_str("\n\n当前状态：");
+        for line in status {
+            output.push_str(&format!("\n- {}", line));
+        }
+    }
+
+    let mut memories = texts(InjectionSection::PinnedMemory);
+    memories.extend(texts(InjectionSection::RecentMemory));
+    if !memories.is_empty() {
+        output.push_str("\n\n相关记忆：");
+        for line in memories {
+            output.push_str(&format!("\n- {}", line));
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> Vec<InjectionItem> {
+        vec![
+            InjectionItem::new(InjectionSection::PinnedMemory, "生日是五月一日"),
+            InjectionItem::new(InjectionSection::RecentMemory, "昨天聊了期末考试"),
+            InjectionItem::new(InjectionSection::ProfileEssential, "关系等级：5"),
+            InjectionItem::new(InjectionSection::BotStatus, "当前情绪：happy"),
+            InjectionItem::new(InjectionSection::Interest, "游戏"),
+            InjectionItem::new(InjectionSection::Interest, "音乐"),
+        ]
+    }
+
+    #[test]
+    fn within_budget_keeps_everything() {
+        let full = assemble_injection(items(), 0);
+        assert_eq!(assemble_injection(items(), full.chars().count()), full);
+        assert!(full.contains("兴趣：游戏, 音乐"));
+    }
+
+    #[test]
+    fn over_budget_trims_low_priority_first() {
+        let full = assemble_injection(items(), 0);
+        let trimmed = assemble_injection(items(), full.chars().count() - 1);
+
+        assert!(trimmed.contains("游戏"));
+        assert!(!trimmed.contains("音乐"));
+        assert!(trimmed.contains("昨天聊了期末考试"));
+    }
+
+    #[test]
+    fn pinned_memories_survive_any_budget() {
+        let trimmed = assemble_injection(items(), 1);
+
+        assert!(trimmed.contains("生日是五月一日"));
+        assert!(!trimmed.contains("昨天聊了期末考试"));
+        assert!(!trimmed.contains("关系等级"));
+    }
+}
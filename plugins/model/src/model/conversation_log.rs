@@ -0,0 +1,112 @@
+//! # 对话日志模块
+//!
+//! 开启对话日志后，以只追加的 JSON Lines 文件记录每次模型请求和回复，便于排查问题。
+//! 写入前对所有文本按配置的脱敏规则替换敏感内容，API令牌始终被替换；
+//! 脱敏只作用于日志副本，实际发送的回复不受影响
+
+use crate::config;
+use chrono::{DateTime, Local};
+use kovi::serde_json::{self, Value};
+use regex::Regex;
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+/// 脱敏后的替换文本
+const REDACTED: &str = "[已脱敏]";
+
+/// 对话日志条目
+#[derive(Debug, Serialize)]
+struct ConversationLogEntry {
+    /// 记录时间
+    timestamp: DateTime<Local>,
+    /// 发送给模型的请求体
+    request: Value,
+    /// 模型返回的消息
+    response: Value,
+}
+
+/// 记录一次模型请求和回复
+///
+/// 未开启对话日志时直接返回；写入失败只记录错误，不影响对话
+///
+/// # 参数
+/// * `request` - 发送给模型的请求体
+/// * `response` - 模型返回的消息
+pub(crate) fn record(request: &impl Serialize, response: &Value) {
+    let config = config::get();
+    let log_config = config.conversation_log();
+    if !log_config.enabled() {
+        return;
+    }
+
+    let result = (|| -> anyhow::Result<()> {
+        let rules = log_config.redaction_rules()?;
+        let token = config.server_config().api_token().unwrap_or_default();
+        let mut entry = ConversationLogEntry {
+            timestamp: Local::now(),
+            request: serde_json::to_value(request)?,
+            response: response.clone(),
+        };
+        redact(&mut entry.request, &rules, &token);
+        redact(&mut entry.response, &rules, &token);
+
+        let path = config::data_path(log_config.file());
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        eprintln!("[ERROR] 写入对话日志失败: {}", e);
+    }
+}
+
+/// 对JSON中的所有字符串应用脱敏规则
+///
+/// # 参数
+/// * `value` - 需要脱敏的JSON，原地修改
+/// * `rules` - 脱敏规则
+/// * `token` - API令牌，为空时不处理
+fn redact(value: &mut Value, rules: &[Regex], token: &str) {
+    match value {
+        Value::String(text) => {
+            if !token.is_empty() && text.contains(token) {
+                *text = text.replace(token, REDACTED);
+            }
+            for rule in rules {
+                if rule.is_match(text) {
+                    *text = rule.replace_all(text, REDACTED).into_owned();
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| redact(item, rules, token)),
+        Value::Object(map) => map.values_mut().for_each(|item| redact(item, rules, token)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kovi::serde_json::json;
+
+    #[test]
+    fn configured_pattern_is_redacted_only_in_log_copy() {
+        let config: config::ModelConfig =
+            kovi::toml::from_str("[conversation_log]\nredaction_patterns = ['1\\d{10}']").unwrap();
+        let rules = config.conversation_log().redaction_rules().unwrap();
+        let reply = json!({ "role": "assistant", "content": "好的，我记下你的电话 13812345678 了" });
+        let mut logged = json!({
+            "request": { "messages": [{ "role": "user", "content": "我的电话是13812345678，令牌 sk-secret" }] },
+            "response": reply.clone(),
+        });
+
+        redact(&mut logged, &rules, "sk-secret");
+
+        assert_eq!(logged["request"]["messages"][0]["content"], "我的电话是[已脱敏]，令牌 [已脱敏]");
+        assert_eq!(logged["response"]["content"], "好的，我记下你的电话 [已脱敏] 了");
+        // 实际发送的回复不受影响
+        assert_eq!(reply["content"], "好的，我记下你的电话 13812345678 了");
+    }
+}
@@ -0,0 +1,97 @@
+//! # 连续消息合并模块
+//!
+//! 群聊中用户经常把一句话拆成几条连续发送（"在吗" / "问个问题" / "就是..."），
+//! 本模块在配置的时间窗口内收集同一用户的连续消息，合并为一轮对话后再交给模型处理
+
+use kovi::tokio::sync::Mutex;
+use kovi::tokio::time::sleep;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// 等待合并的消息批次
+#[derive(Default)]
+struct PendingBatch {
+    /// 已收集的消息片段
+    fragments: Vec<String>,
+    /// 批次版本号，每收到一条新消息加一
+    generation: u64,
+}
+
+/// 等待合并的消息
+///
+/// Key: (群组ID, 用户ID), Value: 消息批次
+static PENDING: LazyLock<Mutex<HashMap<(i64, i64), PendingBatch>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 收集同一用户的连续消息
+///
+/// 每条消息到达后等待一个合并窗口，窗口内没有新消息时，
+/// 由最后一条消息的调用方取走合并后的内容，其余调用方返回 `None`
+///
+/// # 参数
+/// * `group_id` - 群组ID
+/// * `user_id` - 用户ID
+/// * `message` - 消息内容
+/// * `window` - 合并窗口，为零时直接返回原消息
+///
+/// # 返回值
+/// 需要处理的合并消息，已被后续消息合并时返回 `None`
+pub(crate) async fn collect(group_id: i64, user_id: i64, message: &str, window: Duration) -> Option<String> {
+    if window.is_zero() {
+        return Some(message.to_string());
+    }
+
+    let key = (group_id, user_id);
+    let generation = {
+        let mut pending = PENDING.lock().await;
+        let batch = pending.entry(key).or_default();
+        batch.fragments.push(message.to_string());
+        batch.generation += 1;
+        batch.generation
+    };
+
+    sleep(window).await;
+
+    let mut pending = PENDING.lock().await;
+    match pending.get(&key) {
+        Some(batch) if batch.generation == generation => {
+            pending.remove(&key).map(|batch| batch.fragments.join("\n"))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn zero_window_returns_message_immediately() {
+        assert_eq!(collect(1, 1, "在吗", Duration::ZERO).await.as_deref(), Some("在吗"));
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn merges_fragments_within_window() {
+        let window = Duration::from_millis(100);
+        let first = kovi::tokio::spawn(collect(2, 1, "在吗", window));
+        sleep(Duration::from_millis(20)).await;
+        let second = kovi::tokio::spawn(collect(2, 1, "问个问题", window));
+        sleep(Duration::from_millis(20)).await;
+        let third = collect(2, 1, "明天放假吗", window).await;
+
+        assert_eq!(third.as_deref(), Some("在吗\n问个问题\n明天放假吗"));
+        assert_eq!(first.await.unwrap(), None);
+        assert_eq!(second.await.unwrap(), None);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn keeps_users_separate() {
+        let window = Duration::from_millis(50);
+        let other = kovi::tokio::spawn(collect(3, 2, "你好", window));
+        let own = collect(3, 1, "早上好", window).await;
+
+        assert_eq!(own.as_deref(), Some("早上好"));
+        assert_eq!(other.await.unwrap().as_deref(), Some("你好"));
+    }
+}
@@ -0,0 +1,157 @@
+//! # 调试模式模块
+//!
+//! 管理员可通过 `#调试 on|off` 为单个群临时开启调试模式，
+//! 开启后机器人会在接下来的若干次回复后附上内部决策信息（情绪、注入的记忆、使用的模型），
+//! 达到次数上限或超时后自动关闭。
+//! 
+//! 存在开启中的调试会话时，全局日志级别提升为 [`LogLevel::Debug`]，
+//! 通过 [`log`] 输出的 `[DEBUG]` 日志才会打印；最后一个会话关闭或超时后恢复为 [`LogLevel::Info`]
+
+use kovi::tokio::sync::Mutex;
+use std::collections::HashMap;
+use std::fmt::Arguments;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+/// 调试模式最多覆盖的回复次数
+const DEBUG_TURNS: u32 = 5;
+
+/// 调试模式的自动关闭时间
+const DEBUG_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// 调试会话
+struct DebugSession {
+    /// 自动关闭时间
+    expires_at: Instant,
+    /// 剩余可输出调试信息的回复次数
+    remaining_turns: u32,
+}
+
+/// 日志级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    /// 只输出常规日志
+    Info,
+    /// 额外输出 `[DEBUG]` 调试日志
+    Debug,
+}
+
+/// 调试日志级别的生效截止时间，为 `None` 或已过期时日志级别为 [`LogLevel::Info`]
+/// 
+/// 随调试会话的开启、关闭和超时重新计算，读取时不需要异步锁
+static DEBUG_LOG_UNTIL: std::sync::Mutex<Option<Instant>> = std::sync::Mutex::new(None);
+
+/// 获取当前生效的日志级别
+pub fn log_level() -> LogLevel {
+    let until = *DEBUG_LOG_UNTIL.lock().unwrap_or_else(|e| e.into_inner());
+    effective_level(until, Instant::now())
+}
+
+/// 按日志级别输出调试日志，日志级别为 [`LogLevel::Info`] 时不输出
+pub fn log(message: Arguments<'_>) {
+    if log_level() == LogLevel::Debug {
+        println!("[DEBUG] {}", message);
+    }
+}
+
+/// 根据调试日志的截止时间计算日志级别
+fn effective_level(until: Option<Instant>, now: Instant) -> LogLevel {
+    match until {
+        Some(until) if until > now => LogLevel::Debug,
+        _ => LogLevel::Info,
+    }
+}
+
+/// 按当前的调试会话重新计算调试日志的截止时间
+fn reload_log_level(sessions: &HashMap<i64, DebugSession>) {
+    let until = sessions.values().map(|session| session.expires_at).max();
+    *DEBUG_LOG_UNTIL.lock().unwrap_or_else(|e| e.into_inner()) = until;
+}
+
+/// 开启调试模式的群组
+///
+/// Key: 群组ID, Value: 调试会话
+static DEBUG_SESSIONS: LazyLock<Mutex<HashMap<i64, DebugSession>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 为群组开启调试模式
+pub(crate) async fn enable(group_id: i64) {
+    let mut sessions = DEBUG_SESSIONS.lock().await;
+    sessions.insert(
+        group_id,
+        DebugSession {
+            expires_at: Instant::now() + DEBUG_TIMEOUT,
+            remaining_turns: DEBUG_TURNS,
+        },
+    );
+    reload_log_level(&sessions);
+    println!("[INFO] 调试模式已开启 (群组: {})", group_id);
+}
+
+/// 关闭群组的调试模式
+///
+/// # 返回值
+/// 调试模式原本处于开启状态时返回 `true`
+pub(crate) async fn disable(group_id: i64) -> bool {
+    let mut sessions = DEBUG_SESSIONS.lock().await;
+    let removed = sessions.remove(&group_id).is_some();
+    reload_log_level(&sessions);
+    if removed {
+        println!("[INFO] 调试模式已关闭 (群组: {})", group_id);
+    }
+    removed
+}
+
+/// 消耗一次调试输出机会
+///
+/// 调试模式已超时或次数用尽时自动关闭
+///
+/// # 返回值
+/// 本次回复需要输出调试信息时返回 `true`
+pub(crate) async fn take_turn(group_id: i64) -> bool {
+    let mut sessions = DEBUG_SESSIONS.lock().await;
+    let Some(session) = sessions.get_mut(&group_id) else {
+        return false;
+    };
+
+    if session.expires_at <= Instant::now() || session.remaining_turns == 0 {
+        sessions.remove(&group_id);
+        reload_log_level(&sessions);
+        println!("[INFO] 调试模式已自动关闭 (群组: {})", group_id);
+        return false;
+    }
+
+    session.remaining_turns -= 1;
+    true
+}
+
+/// 调试模式的使用说明
+pub(crate) fn usage() -> String {
+    format!(
+        "用法：#调试 on|off\n开启后接下来 {} 次回复会附带调试信息，{} 分钟后自动关闭",
+        DEBUG_TURNS,
+        DEBUG_TIMEOUT.as_secs() / 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_follows_deadline() {
+        let now = Instant::now();
+        assert_eq!(effective_level(None, now), LogLevel::Info);
+        assert_eq!(effective_level(Some(now + Duration::from_secs(1)), now), LogLevel::Debug);
+        assert_eq!(effective_level(Some(now), now), LogLevel::Info);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn enabling_debug_mode_raises_log_level() {
+        enable(-986).await;
+        assert_eq!(log_level(), LogLevel::Debug);
+
+        assert!(disable(-986).await);
+        assert_eq!(log_level(), LogLevel::Info);
+    }
+}
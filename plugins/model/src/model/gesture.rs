@@ -0,0 +1,50 @@
+//! # 社交举动冷却模块
+//!
+//! 里程碑庆祝、自我介绍等机器人主动附加的社交举动统一在这里限频：
+//! 同一用户在冷却时间内最多收到一次，单次互动中也不会叠加发送多条
+
+use crate::config;
+use kovi::tokio::sync::Mutex;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+/// 各用户最近一次收到社交举动的时间
+static LAST_GESTURES: LazyLock<Mutex<HashMap<i64, Instant>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 尝试占用用户的社交举动机会
+///
+/// 冷却时间内已经发送过社交举动时返回 `false`；返回 `true` 时立即记录本次举动，
+/// 同一次互动中后续的举动会因此被拦下
+///
+/// # 参数
+/// * `user_id` - 用户QQ号
+pub(crate) async fn try_acquire(user_id: i64) -> bool {
+    let cooldown = Duration::from_secs(config::get().chat().social_gesture_cooldown_secs());
+    let now = Instant::now();
+    let mut last_gestures = LAST_GESTURES.lock().await;
+    last_gestures.retain(|_, last| now.duration_since(*last) < cooldown);
+
+    if last_gestures.contains_key(&user_id) {
+        return false;
+    }
+    last_gestures.insert(user_id, now);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn quick_successive_gestures_emit_only_one() {
+        config::use_test_data_dir();
+        let user_id = 1_027_001;
+
+        // 例如同一次互动既跨过里程碑又触发自我介绍
+        assert!(try_acquire(user_id).await);
+        assert!(!try_acquire(user_id).await);
+        // 冷却按用户分别计算
+        assert!(try_acquire(user_id + 1).await);
+    }
+}
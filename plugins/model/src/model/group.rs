@@ -1,30 +1,61 @@
-use crate::model::utils::{send_sys_info, silence};
-use crate::config;
-use crate::memory::{MemoryManager, GroupProfile};
+use crate::model::{debounce, debug, throttle, trigger};
+use crate::model::throttle::{Throttle, THROTTLED_REPLY};
+use crate::model::utils::{
+    config_command, describe_group_analytics, describe_group_memory_window, describe_mood_simulation, describe_user_profile, display_name_for, preview_group_prompt, record_group_message, refresh_group_context, send_sys_info, set_preferred_address,
+    set_temperature_override, set_top_p_override, silence,
+};
+use crate::config::{self, AnnouncementHandling};
+use crate::utils::{self, annotate_segments};
+use crate::memory::{GroupProfile, MemoryManager, MemoryType, MEMORY_MANAGER};
 use crate::proactive_chat::startup;
+use crate::usage::{self, UsageScope};
 use crate::health_check::HealthChecker;
+use crate::health_check::self_test;
 use chrono::Local;
 use kovi::RuntimeBot;
 use kovi::event::GroupMsgEvent;
 use std::sync::Arc;
 use std::time::Duration;
 
-// 全局记忆管理器
-static MEMORY_MANAGER: std::sync::LazyLock<Arc<MemoryManager>> =
-    std::sync::LazyLock::new(|| Arc::new(MemoryManager::new("bot_memory.json")));
-
 pub async fn group_message_event(event: Arc<GroupMsgEvent>, bot: Arc<RuntimeBot>) {
-    // 启动主动聊天管理器（只在第一次启动）
-    if let Some(_proactive_manager) = startup::get_or_create_proactive_manager(Arc::clone(&bot)).await {
-        println!("主动聊天管理器已启动");
+    // 忽略机器人自己发出的消息，避免自问自答
+    if trigger::is_own_message(event.user_id, event.self_id) {
+        return;
     }
+
+    // 启动主动聊天管理器（只在第一次启动）
+    startup::get_or_create_proactive_manager(Arc::clone(&bot));
     
     let group_id = event.group_id;
     let time_now_data = Local::now();
     let time = time_now_data.format("%H:%M:%S").to_string();
     let nickname = event.get_sender_nickname();
-    let sender = format!("[{}] {}", time, nickname);
+    let sender = format!("[{}] {}", time, display_name_for(event.user_id, &nickname).await);
     if let Some(message) = event.borrow_text() {
+        // 记忆中保留图片、@ 等消息段的标记
+        let annotated = annotate_segments(&event.message);
+
+        // 开发模式下只回复管理员，其他人的消息只记录不回复
+        if record_without_reply(&MEMORY_MANAGER, group_id, event.user_id, &sender, message, &annotated).await {
+            return;
+        }
+
+        // @全体成员 的公告类消息不触发普通回复，按配置忽略、只记录或发送确认
+        if trigger::is_announcement(&event) {
+            let config = config::get();
+            match config.chat().announcement_handling() {
+                AnnouncementHandling::Ignore => {}
+                AnnouncementHandling::Record => {
+                    record_group_message(&MEMORY_MANAGER, group_id, &sender, &annotated).await;
+                }
+                AnnouncementHandling::Acknowledge => {
+                    record_group_message(&MEMORY_MANAGER, group_id, &sender, &annotated).await;
+                    utils::notify_group(&bot, group_id, config.chat().announcement_ack_message()).await;
+                }
+            }
+            return;
+        }
+
         match message {
             "#系统信息" => {
                 send_sys_info(Arc::clone(&bot), group_id).await;
@@ -32,41 +63,41 @@ pub async fn group_message_event(event: Arc<GroupMsgEvent>, bot: Arc<RuntimeBot>
             
             "#重载配置文件" => {
                 match config::reload_config_from_file() {
-                    Ok(_) => bot.send_group_msg(group_id, "配置重载成功"),
-                    Err(e) => bot.send_group_msg(group_id, format!("配置重载失败: {}", e)),
+                    Ok(_) => utils::notify_group(&bot, group_id, "配置重载成功").await,
+                    Err(e) => utils::notify_group(&bot, group_id, format!("配置重载失败: {}", e)).await,
                 }
             },
             
             "#重载全部配置" => {
                 match config::reload_config() {
-                    Ok(_) => bot.send_group_msg(group_id, "全部配置文件重载成功"),
-                    Err(e) => bot.send_group_msg(group_id, format!("重载失败： {}", e))
+                    Ok(_) => utils::notify_group(&bot, group_id, "全部配置文件重载成功").await,
+                    Err(e) => utils::notify_group(&bot, group_id, format!("重载失败： {}", e)).await
                 }
             },
 
             "#启用自动重载" => {
                 if config::is_auto_reload_enabled() {
-                    bot.send_group_msg(group_id, "自动重载已经启用");
+                    utils::notify_group(&bot, group_id, "自动重载已经启用").await;
                 } else {
                     config::enable_auto_reload(Duration::from_secs(5));
-                    bot.send_group_msg(group_id, "自动重载已启用，每5秒检查一次");
+                    utils::notify_group(&bot, group_id, "自动重载已启用，每5秒检查一次").await;
                 }
             },
 
             "#禁用自动重载" => {
                 if config::is_auto_reload_enabled() {
                     config::disable_auto_reload();
-                    bot.send_group_msg(group_id, "自动重载已禁用");
+                    utils::notify_group(&bot, group_id, "自动重载已禁用").await;
                 } else {
-                    bot.send_group_msg(group_id, "自动重载未启用");
+                    utils::notify_group(&bot, group_id, "自动重载未启用").await;
                 }
             },
 
             "#检查配置变化" => {
                 match config::check_and_reload() {
-                    Ok(true) => bot.send_group_msg(group_id, "检测到配置变化，已自动重载"),
-                    Ok(false) => bot.send_group_msg(group_id, "配置文件无变化"),
-                    Err(e) => bot.send_group_msg(group_id, format!("检查配置失败: {}", e)),
+                    Ok(true) => utils::notify_group(&bot, group_id, "检测到配置变化，已自动重载").await,
+                    Ok(false) => utils::notify_group(&bot, group_id, "配置文件无变化").await,
+                    Err(e) => utils::notify_group(&bot, group_id, format!("检查配置失败: {}", e)).await,
                 }
             },
 
@@ -76,58 +107,335 @@ pub async fn group_message_event(event: Arc<GroupMsgEvent>, bot: Arc<RuntimeBot>
                 } else {
                     "已禁用"
                 };
-                bot.send_group_msg(group_id, format!("配置自动重载状态: {}", status));
+                utils::notify_group(&bot, group_id, format!("配置自动重载状态: {}", status)).await;
             },
 
             "#健康检查" => {
                 let mut health_checker = HealthChecker::new(Arc::clone(&MEMORY_MANAGER));
                 let health_status = health_checker.check_health().await;
-                
-                let status_msg = if health_status.is_healthy {
-                    format!("✅ 系统健康状态良好\n📊 记忆数量: {}\n👥 用户档案: {}\n🏢 群组档案: {}\n💾 记忆文件大小: {:.2}MB", 
-                        health_status.memory_usage.total_memories,
-                        health_status.memory_usage.user_profiles,
-                        health_status.memory_usage.group_profiles,
-                        health_status.memory_usage.memory_file_size as f64 / 1024.0 / 1024.0
-                    )
-                } else {
-                    format!("❌ 系统健康状态异常\n错误: {}\n警告: {}", 
-                        health_status.errors.join(", "),
-                        health_status.warnings.join(", ")
-                    )
-                };
-                
-                bot.send_group_msg(group_id, &status_msg);
+                utils::notify_group(&bot, group_id, health_status.report()).await;
+            },
+
+            "#人格日志" => {
+                match MEMORY_MANAGER.get_personality_journal(10).await {
+                    Ok(entries) if entries.is_empty() => {
+                        utils::notify_group(&bot, group_id, "暂无人格变化记录").await;
+                    }
+                    Ok(entries) => {
+                        let lines = entries.iter()
+                            .map(|e| format!("[{}] {}: {} → {} ({})",
+                                e.timestamp.format("%m-%d %H:%M:%S"),
+                                e.field,
+                                e.old_value,
+                                e.new_value,
+                                e.trigger
+                            ))
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        utils::notify_group(&bot, group_id, format!("📜 最近的人格变化：\n{}", lines)).await;
+                    }
+                    Err(e) => utils::notify_group(&bot, group_id, format!("读取人格日志失败: {}", e)).await,
+                }
+            },
+            "#自检" => {
+                if !is_group_admin(&event) {
+                    utils::notify_group(&bot, group_id, "只有群主或管理员可以执行自检").await;
+                    return;
+                }
+                let items = self_test::run_self_test(&config::get(), Arc::clone(&MEMORY_MANAGER)).await;
+                utils::notify_group(&bot, group_id, self_test::format_report(&items)).await;
+            },
+            "#用量" => {
+                if !is_group_admin(&event) {
+                    utils::notify_group(&bot, group_id, "只有群主或管理员可以查看用量").await;
+                    return;
+                }
+                utils::notify_group(&bot, group_id, usage::describe_usage(UsageScope::Group(group_id)).await).await;
+            },
+            "#群分析" => {
+                if !config::get().chat().group_analytics_enabled() {
+                    utils::notify_group(&bot, group_id, "群分析功能未启用").await;
+                    return;
+                }
+                if !is_group_admin(&event) {
+                    utils::notify_group(&bot, group_id, "只有群主或管理员可以查看群分析").await;
+                    return;
+                }
+                let member_count = bot.get_group_info(group_id, false).await
+                    .ok()
+                    .and_then(|info| info.data.get("member_count").and_then(|v| v.as_i64()));
+                let profile = MEMORY_MANAGER.get_group_profile(group_id).await;
+                let report = describe_group_analytics(group_id, profile.as_ref(), member_count).await;
+                utils::notify_group(&bot, group_id, report).await;
+            },
+            "#刷新记忆" if config::get().chat().context_refresh_enabled() => {
+                if !is_group_admin(&event) {
+                    utils::notify_group(&bot, group_id, "只有群主或管理员可以刷新记忆").await;
+                    return;
+                }
+                utils::notify_group(&bot, group_id, refresh_group_context(group_id).await).await;
+            },
+            "#还记得吗" if config::get().chat().memory_indicator_enabled() => {
+                utils::notify_group(&bot, group_id, describe_group_memory_window(group_id).await).await;
+            },
+            "#查看群提示词" => {
+                if !is_group_admin(&event) {
+                    utils::notify_group(&bot, group_id, "只有群主或管理员可以查看系统提示").await;
+                    return;
+                }
+                utils::notify_group(&bot, group_id, preview_group_prompt(group_id).await).await;
+            },
+            "#备份" => {
+                // 快照覆盖的是所有群组和用户的记忆，只允许机器人管理员操作
+                if !config::get().chat().is_admin(event.user_id) {
+                    utils::notify_group(&bot, group_id, "只有机器人管理员可以备份记忆").await;
+                    return;
+                }
+                match MEMORY_MANAGER.create_snapshot().await {
+                    Ok(name) => utils::notify_group(&bot, group_id, format!("备份完成: {}", name)).await,
+                    Err(e) => utils::notify_group(&bot, group_id, format!("备份失败: {}", e)).await,
+                }
+            },
+            "#备份列表" => {
+                if !config::get().chat().is_admin(event.user_id) {
+                    utils::notify_group(&bot, group_id, "只有机器人管理员可以查看备份").await;
+                    return;
+                }
+                match MEMORY_MANAGER.list_snapshots().await {
+                    Ok(snapshots) if snapshots.is_empty() => utils::notify_group(&bot, group_id, "暂无备份").await,
+                    Ok(snapshots) => {
+                        let lines = snapshots.iter()
+                            .take(10)
+                            .map(|s| format!("{} ({}, {:.1}KB)",
+                                s.name,
+                                s.created_at.format("%Y-%m-%d %H:%M:%S"),
+                                s.size as f64 / 1024.0
+                            ))
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        utils::notify_group(&bot, group_id, format!("💾 最近的备份：\n{}", lines)).await;
+                    }
+                    Err(e) => utils::notify_group(&bot, group_id, format!("读取备份列表失败: {}", e)).await,
+                }
+            },
+            msg if msg.starts_with("#恢复") => {
+                if !config::get().chat().is_admin(event.user_id) {
+                    utils::notify_group(&bot, group_id, "只有机器人管理员可以恢复备份").await;
+                    return;
+                }
+                // 恢复会覆盖当前记忆，需要在命令末尾加上“确认”
+                match msg["#恢复".len()..].split_whitespace().collect::<Vec<_>>().as_slice() {
+                    [name, "确认"] => match MEMORY_MANAGER.restore_snapshot(name).await {
+                        Ok(backup) => utils::notify_group(&bot, group_id, format!("已恢复备份 {}，恢复前的数据已备份为 {}", name, backup)).await,
+                        Err(e) => utils::notify_group(&bot, group_id, format!("恢复失败: {}", e)).await,
+                    },
+                    [name] => utils::notify_group(&bot, group_id, format!("恢复会覆盖当前记忆，确认请发送：#恢复 {} 确认", name)).await,
+                    _ => utils::notify_group(&bot, group_id, "用法：#恢复 <备份名称>").await,
+                }
+            },
+            msg if msg.starts_with("#忘记") => {
+                // 删除的是所有群组和用户的记忆，只允许机器人管理员操作
+                if !config::get().chat().is_admin(event.user_id) {
+                    utils::notify_group(&bot, group_id, "只有机器人管理员可以删除记忆").await;
+                    return;
+                }
+                utils::notify_group(&bot, group_id, forget_memories(msg["#忘记".len()..].trim()).await).await;
+            },
+            msg if msg.starts_with("#配置") => {
+                if !config::get().chat().is_admin(event.user_id) {
+                    utils::notify_group(&bot, group_id, "只有机器人管理员可以查看和修改配置").await;
+                    return;
+                }
+                utils::notify_group(&bot, group_id, config_command(&msg["#配置".len()..])).await;
+            },
+            msg if msg.starts_with("#调试") => {
+                if !is_group_admin(&event) {
+                    utils::notify_group(&bot, group_id, "只有群主或管理员可以开启调试模式").await;
+                    return;
+                }
+                match msg["#调试".len()..].trim() {
+                    "on" => {
+                        debug::enable(group_id).await;
+                        utils::notify_group(&bot, group_id, "调试模式已开启").await;
+                    }
+                    "off" => {
+                        if debug::disable(group_id).await {
+                            utils::notify_group(&bot, group_id, "调试模式已关闭").await;
+                        } else {
+                            utils::notify_group(&bot, group_id, "调试模式未开启").await;
+                        }
+                    }
+                    _ => utils::notify_group(&bot, group_id, debug::usage()).await,
+                }
+            },
+            msg if msg.starts_with("#测情绪") => {
+                if !is_group_admin(&event) {
+                    utils::notify_group(&bot, group_id, "只有群主或管理员可以测试情绪分析").await;
+                    return;
+                }
+                utils::notify_group(&bot, group_id, describe_mood_simulation(&msg["#测情绪".len()..]).await).await;
+            },
+            msg if msg.starts_with("#用户信息") => {
+                if !is_group_admin(&event) {
+                    utils::notify_group(&bot, group_id, "只有群主或管理员可以查看用户信息").await;
+                    return;
+                }
+                match msg["#用户信息".len()..].trim().parse::<i64>() {
+                    Ok(user_id) => utils::notify_group(&bot, group_id, describe_user_profile(user_id).await).await,
+                    Err(_) => utils::notify_group(&bot, group_id, "用法：#用户信息 <用户QQ号>").await,
+                }
+            },
+            msg if msg.starts_with("#设置温度") => {
+                if !is_group_admin(&event) {
+                    utils::notify_group(&bot, group_id, "只有群主或管理员可以调整模型参数").await;
+                    return;
+                }
+                let reply = set_temperature_override(group_id, &msg["#设置温度".len()..]).await;
+                utils::notify_group(&bot, group_id, reply).await;
+            },
+            msg if msg.starts_with("#设置topp") => {
+                if !is_group_admin(&event) {
+                    utils::notify_group(&bot, group_id, "只有群主或管理员可以调整模型参数").await;
+                    return;
+                }
+                let reply = set_top_p_override(group_id, &msg["#设置topp".len()..]).await;
+                utils::notify_group(&bot, group_id, reply).await;
+            },
+            msg if msg.starts_with("#叫我") => {
+                let reply = set_preferred_address(event.user_id, &nickname, &msg["#叫我".len()..]).await;
+                utils::notify_group(&bot, group_id, reply).await;
             },
             _ => {
-                // 更新群组档案
-                update_group_profile(group_id, message, &nickname).await;
-                silence(group_id, message, bot, sender).await;
+                // 只回复呼叫机器人的消息时，未呼叫的消息只记录不回复
+                if !message.starts_with('#')
+                    && config::get().chat().mention_only()
+                    && !trigger::is_triggered(&bot, &event, message).await
+                {
+                    update_group_profile(&MEMORY_MANAGER, group_id, event.user_id, message).await;
+                    record_group_message(&MEMORY_MANAGER, group_id, &sender, &annotated).await;
+                    return;
+                }
+
+                // 禁言等控制命令不参与合并
+                if message.starts_with('#') {
+                    update_group_profile(&MEMORY_MANAGER, group_id, event.user_id, message).await;
+                    silence(group_id, message, bot, sender, Some(event.message_id)).await;
+                    return;
+                }
+
+                // 普通消息等待合并窗口内的后续消息，合并后的一轮对话只更新一次档案、只占用一次请求配额
+                let window = Duration::from_millis(config::get().chat().message_debounce_ms());
+                let Some(combined) = debounce::collect(group_id, event.user_id, &annotated, window).await else {
+                    return;
+                };
+                update_group_profile(&MEMORY_MANAGER, group_id, event.user_id, &combined).await;
+
+                // 超出请求配额时只记录不回复，只在第一次超限时提醒
+                if let Throttle::Limited { notify } = throttle::check(event.user_id).await {
+                    record_group_message(&MEMORY_MANAGER, group_id, &sender, &combined).await;
+                    if notify {
+                        utils::notify_group(&bot, group_id, THROTTLED_REPLY).await;
+                    }
+                    return;
+                }
+
+                silence(group_id, &combined, bot, sender, Some(event.message_id)).await;
             }
         }
     }
 }
 
-async fn update_group_profile(group_id: i64, message: &str, _nickname: &str) {
-    let mut profile = MEMORY_MANAGER.get_group_profile(group_id).await
-        .unwrap_or_else(|| GroupProfile {
-            group_id,
-            group_name: format!("群组_{}", group_id),
-            active_members: Vec::new(),
-            group_personality: "friendly".to_string(),
-            conversation_topics: Vec::new(),
-            last_activity: Local::now(),
-            activity_level: 1,
-        });
+/// 处理 `#忘记 <记忆ID|记忆类型>` 命令
+/// 
+/// 参数为记忆类型名时清除该类型的全部记忆，否则按记忆ID删除单条记忆
+/// 
+/// # 返回值
+/// 回复给用户的提示文本
+async fn forget_memories(target: &str) -> String {
+    if target.is_empty() {
+        return "用法：#忘记 <记忆ID|记忆类型>\n记忆类型：对话、用户档案、群组信息、事件、偏好、情绪".to_string();
+    }
+
+    if let Some(memory_type) = MemoryType::from_name(target) {
+        return match MEMORY_MANAGER.clear_memories_by_type(&memory_type).await {
+            Ok(count) => format!("已清除 {} 条{:?}类型的记忆", count, memory_type),
+            Err(e) => format!("清除记忆失败: {}", e),
+        };
+    }
+
+    match MEMORY_MANAGER.delete_memory(target).await {
+        Ok(true) => format!("已删除记忆 {}", target),
+        Ok(false) => format!("未找到记忆 {}", target),
+        Err(e) => format!("删除记忆失败: {}", e),
+    }
+}
+
+/// 判断消息发送者是否为群主、群管理员或配置的机器人管理员
+fn is_group_admin(event: &GroupMsgEvent) -> bool {
+    matches!(event.sender.role.as_deref(), Some("owner") | Some("admin"))
+        || config::get().chat().is_admin(event.user_id)
+}
+
+/// 开发模式下不回复非管理员，只更新群组档案并记录消息
+/// 
+/// # 参数
+/// * `memory` - 记忆管理器
+/// * `group_id` - 群组ID
+/// * `user_id` - 发送者ID
+/// * `sender` - 带时间的发送者昵称
+/// * `message` - 消息文本
+/// * `annotated` - 保留消息段标记的消息内容
+/// 
+/// # 返回值
+/// 消息只记录、不需要回复时返回 `true`
+async fn record_without_reply(
+    memory: &MemoryManager,
+    group_id: i64,
+    user_id: i64,
+    sender: &str,
+    message: &str,
+    annotated: &str,
+) -> bool {
+    if config::get().chat().should_reply_to(user_id) {
+        return false;
+    }
+    update_group_profile(memory, group_id, user_id, message).await;
+    record_group_message(memory, group_id, sender, annotated).await;
+    true
+}
+
+async fn update_group_profile(memory: &MemoryManager, group_id: i64, user_id: i64, message: &str) {
+    if config::get().memory().skips_memory(group_id, "group_chat") {
+        return;
+    }
+
+    let mut profile = memory.get_group_profile(group_id).await
+        .unwrap_or_else(|| GroupProfile::new(group_id));
 
     // 更新活动信息
     profile.last_activity = Local::now();
     profile.activity_level = (profile.activity_level + 1).min(10);
+    profile.record_member(user_id);
 
     // 提取话题关键词
     let topics = extract_topics_from_message(message);
-    if topics.is_empty() {
-        return;
+    merge_topics(&mut profile, topics, config::get().chat().record_uncategorized_activity());
+
+    // 更新群组档案
+    if let Err(e) = memory.update_group_profile(group_id, profile).await {
+        eprintln!("Failed to update group profile: {}", e);
+    }
+}
+
+/// 将提取到的话题合并进群组档案，未匹配任何话题时按需记录一次未分类消息
+///
+/// # 参数
+/// * `profile` - 群组档案
+/// * `topics` - 本条消息提取到的话题
+/// * `record_uncategorized` - 是否记录未分类消息
+fn merge_topics(profile: &mut GroupProfile, topics: Vec<String>, record_uncategorized: bool) {
+    if topics.is_empty() && record_uncategorized {
+        profile.uncategorized_messages += 1;
     }
     for topic in topics {
         if !profile.conversation_topics.contains(&topic) {
@@ -139,11 +447,6 @@ async fn update_group_profile(group_id: i64, message: &str, _nickname: &str) {
     if profile.conversation_topics.len() > 20 {
         profile.conversation_topics.drain(0..profile.conversation_topics.len() - 20);
     }
-
-    // 更新群组档案
-    if let Err(e) = MEMORY_MANAGER.update_group_profile(group_id, profile).await {
-        eprintln!("Failed to update group profile: {}", e);
-    }
 }
 
 fn extract_topics_from_message(message: &str) -> Vec<String> {
@@ -174,3 +477,102 @@ fn extract_topics_from_message(message: &str) -> Vec<String> {
 
     topics
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::mock;
+    use crate::model::mock::{completion_response, model_config, MockServer};
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn repeated_messages_build_member_list() {
+        config::use_test_data_dir();
+        let group_id = 968_001;
+
+        for user_id in [1, 2, 1, 3, 2] {
+            update_group_profile(&MEMORY_MANAGER, group_id, user_id, "大家好").await;
+        }
+
+        let profile = MEMORY_MANAGER.get_group_profile(group_id).await.unwrap();
+        assert_eq!(profile.active_members, vec![1, 3, 2]);
+        assert_eq!(profile.recently_active_members(chrono::Duration::minutes(1)).len(), 3);
+        assert_eq!(profile.activity_level, GroupProfile::new(group_id).activity_level + 5);
+    }
+
+    #[test]
+    fn off_topic_message_counts_without_adding_topic() {
+        let mut profile = GroupProfile::new(969_001);
+        let topics = extract_topics_from_message("嗯嗯");
+        assert!(topics.is_empty());
+
+        merge_topics(&mut profile, topics, true);
+        assert_eq!(profile.uncategorized_messages, 1);
+        assert!(profile.conversation_topics.is_empty());
+
+        merge_topics(&mut profile, extract_topics_from_message("今晚一起打游戏"), true);
+        assert_eq!(profile.uncategorized_messages, 1);
+        assert!(profile.conversation_topics.contains(&"游戏".to_string()));
+    }
+
+    #[test]
+    fn uncategorized_activity_is_opt_in() {
+        let mut profile = GroupProfile::new(969_002);
+        merge_topics(&mut profile, extract_topics_from_message("嗯嗯"), false);
+        assert_eq!(profile.uncategorized_messages, 0);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn handlers_share_one_memory_manager() {
+        config::use_test_data_dir();
+        let group_id = 1_001_201;
+        let user_id = 1_001_202;
+        // 主动聊天等模块在启动时以同样的方式取得管理器
+        let shared = Arc::clone(&crate::memory::MEMORY_MANAGER);
+
+        update_group_profile(&MEMORY_MANAGER, group_id, user_id, "今晚一起打游戏").await;
+        set_preferred_address(user_id, "小明", "阿明").await;
+
+        let group = shared.get_group_profile(group_id).await.unwrap();
+        assert!(group.conversation_topics.contains(&"游戏".to_string()));
+        let user = shared.get_user_profile(user_id).await.unwrap();
+        assert_eq!(user.preferred_address.as_deref(), Some("阿明"));
+        assert_eq!(display_name_for(user_id, "小明").await, "阿明");
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn dev_mode_records_non_admin_message_without_reply() {
+        let server = MockServer::start(vec![completion_response("不应发出")]).await;
+        let (manager, state) = mock::manager().await;
+        let group_id = 966_001;
+        let config = model_config(&server.url, "[chat]\ndev_mode = true\nadmin_ids = [1]");
+
+        config::with_test_config(config, async {
+            assert!(record_without_reply(&manager, group_id, 2, "[12:00:00] 路人", "大家好", "大家好").await);
+            // 管理员的消息交给后续流程处理，这里不记录
+            assert!(!record_without_reply(&manager, group_id, 1, "[12:00:00] 管理员", "在吗", "在吗").await);
+        })
+        .await;
+
+        let data = state.data.lock().unwrap();
+        let contents: Vec<_> = data.memories.values().map(|memory| memory.content.as_str()).collect();
+        assert_eq!(contents, ["[12:00:00] 路人: 大家好"]);
+        assert_eq!(data.group_profiles[&group_id].active_members, vec![2]);
+        assert_eq!(server.request_count(), 0);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn no_memory_group_profile_is_not_updated() {
+        let (manager, state) = mock::manager().await;
+        let config = model_config("http://127.0.0.1:1/v1/chat/completions", "[memory]\nno_memory_groups = [971_101]");
+
+        config::with_test_config(config, async {
+            update_group_profile(&manager, 971_101, 1, "今晚一起打游戏").await;
+            update_group_profile(&manager, 971_102, 1, "今晚一起打游戏").await;
+        })
+        .await;
+
+        let data = state.data.lock().unwrap();
+        assert!(!data.group_profiles.contains_key(&971_101));
+        assert!(data.group_profiles.contains_key(&971_102));
+    }
+}
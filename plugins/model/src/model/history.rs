@@ -0,0 +1,229 @@
+//! # 对话历史窗口模块
+//!
+//! 控制每个对话保留在内存中、随请求发送给模型的历史消息，支持三种策略：
+//! - 按条数：保留系统提示和最近若干条消息
+//! - 按token：保留系统提示和不超过token预算的最近消息
+//! - 摘要窗口：保留系统提示、一条滚动摘要和最近K轮原文，滑出窗口的消息逐条并入摘要
+
+use crate::config::{self, HistoryStrategy};
+use crate::model::utils::{BotMemory, Roles};
+
+/// 滚动摘要消息的开头，用于识别摘要消息
+const SUMMARY_HEADER: &str = "之前的对话摘要：";
+
+/// 系统提示中长期记忆部分的标题
+const MEMORY_HEADER: &str = "相关记忆：";
+
+/// 摘要最多保留的条目数，超出时淘汰最早的条目
+const SUMMARY_MAX_LINES: usize = 30;
+
+/// 每条摘要条目的最大字符数
+const SUMMARY_LINE_CHARS: usize = 60;
+
+/// 按配置的策略裁剪对话历史，第一条系统提示始终保留
+/// 
+/// # 参数
+/// * `messages` - 对话历史
+/// * `max_messages` - `count` 策略下该对话保留的最大消息数
+pub(crate) fn trim_history(messages: &mut Vec<BotMemory>, max_messages: usize) {
+    let config = config::get();
+    let chat = config.chat();
+    let before = messages.len();
+    match chat.history_strategy() {
+        HistoryStrategy::Count => trim_by_count(messages, max_messages),
+        HistoryStrategy::Tokens => trim_by_tokens(messages, chat.history_max_tokens()),
+        HistoryStrategy::SummaryWindow => slide_summary_window(messages, chat.history_recent_turns()),
+    }
+
+    if messages.len() < before {
+        println!("[INFO] 对话记忆已清理，当前保留 {} 条记录", messages.len());
+    }
+}
+
+/// 保留系统提示和最近的消息，总数不超过 `max_messages`
+fn trim_by_count(messages: &mut Vec<BotMemory>, max_messages: usize) {
+    if messages.len() <= max_messages.max(1) {
+        return;
+    }
+    let excess = messages.len() - max_messages.max(1);
+    messages.drain(1..1 + excess);
+}
+
+/// 从最早的消息开始移除，直到估算的token数不超过预算，至少保留最后一条消息
+fn trim_by_tokens(messages: &mut Vec<BotMemory>, max_tokens: usize) {
+    let mut total: usize = messages.iter().map(|m| estimate_tokens(&m.content)).sum();
+    let mut remove = 0;
+    while total > max_tokens && messages.len() - remove > 2 {
+        total -= estimate_tokens(&messages[1 + remove].content);
+        remove += 1;
+    }
+    messages.drain(1..1 + remove);
+}
+
+/// 估算文本的token数：非ASCII字符（如汉字）每个计1，ASCII字符每4个计1
+fn estimate_tokens(text: &str) -> usize {
+    let ascii = text.chars().filter(char::is_ascii).count();
+    let other = text.chars().count() - ascii;
+    other + ascii.div_ceil(4)
+}
+
+/// 保留系统提示、滚动摘要和最近 `recent_turns` 轮用户/助手消息
+///
+/// 滑出窗口的用户和助手消息会截断后追加到摘要中，滑出窗口的其他系统消息直接丢弃
+fn slide_summary_window(messages: &mut Vec<BotMemory>, recent_turns: usize) {
+    if messages.len() <= 1 {
+        return;
+    }
+
+    let has_summary = messages.get(1).is_some_and(is_summary);
+    let body_start = if has_summary { 2 } else { 1 };
+    let is_turn = |m: &BotMemory| m.role != Roles::System;
+
+    // 窗口起点：从末尾数第 recent_turns 条用户/助手消息
+    let mut turns = 0;
+    let mut window_start = messages.len();
+    for index in (body_start..messages.len()).rev() {
+        if is_turn(&messages[index]) {
+            if turns == recent_turns {
+                break;
+            }
+            turns += 1;
+        }
+        window_start = index;
+    }
+
+    // 窗口之前没有需要并入摘要的消息
+    if !messages[body_start..window_start].iter().any(is_turn) {
+        return;
+    }
+
+    let mut lines: Vec<String> = if has_summary {
+        messages[1].content.lines().skip(1).map(str::to_string).collect()
+    } else {
+        Vec::new()
+    };
+    lines.extend(
+        messages
+            .drain(body_start..window_start)
+            .filter(is_turn)
+            .map(|m| summarize_turn(&m)),
+    );
+    if lines.len() > SUMMARY_MAX_LINES {
+        lines.drain(..lines.len() - SUMMARY_MAX_LINES);
+    }
+
+    let summary = BotMemory {
+        role: Roles::System,
+        content: format!("{}\n{}", SUMMARY_HEADER, lines.join("\n")),
+    };
+    if has_summary {
+        messages[1] = summary;
+    } else {
+        messages.insert(1, summary);
+    }
+}
+
+/// 描述对话当前记得的内容
+///
+/// 统计上下文中的对话轮数、是否有较早对话的摘要，以及系统提示中注入了多少条长期记忆
+///
+/// # 参数
+/// * `messages` - 当前对话的历史消息，没有进行中的对话时为 `None`
+pub(crate) fn describe_window(messages: Option<&[BotMemory]>) -> String {
+    let Some(messages) = messages.filter(|messages| !messages.is_empty()) else {
+        return "现在没有进行中的对话，下次聊天时我会从长期记忆里找相关的内容～".to_string();
+    };
+
+    let user_turns = messages.iter().filter(|m| m.role == Roles::User).count();
+    let mut lines = vec![format!(
+        "我还记得这次对话的 {} 轮发言（上下文共 {} 条消息）",
+        user_turns,
+        messages.len() - 1
+    )];
+    if messages.get(1).is_some_and(is_summary) {
+        lines.push("更早的内容已经压缩成摘要，只记得大概".to_string());
+    }
+
+    let injected = messages[0]
+        .content
+        .split_once(MEMORY_HEADER)
+        .map_or(0, |(_, rest)| rest.lines().skip(1).take_while(|line| line.starts_with("- ")).count());
+    if injected > 0 {
+        lines.push(format!("本次对话注入了 {} 条长期记忆", injected));
+    } else {
+        lines.push("本次对话没有注入长期记忆".to_string());
+    }
+    lines.join("\n")
+}
+
+fn is_summary(message: &BotMemory) -> bool {
+    message.role == Roles::System && message.content.starts_with(SUMMARY_HEADER)
+}
+
+/// 将一条消息压缩为摘要条目
+fn summarize_turn(message: &BotMemory) -> String {
+    let content = message.content.trim().replace('\n', " ");
+    let content = if content.chars().count() > SUMMARY_LINE_CHARS {
+        format!("{}…", content.chars().take(SUMMARY_LINE_CHARS).collect::<String>())
+    } else {
+        content
+    };
+    match message.role {
+        Roles::Assistant => format!("- 我: {}", content),
+        _ => format!("- {}", content),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: Roles, content: &str) -> BotMemory {
+        BotMemory { role, content: content.to_string() }
+    }
+
+    #[test]
+    fn summary_window_keeps_system_summary_and_recent_turns() {
+        let recent_turns = 4;
+        let mut messages = vec![message(Roles::System, "你是小助手")];
+        for turn in 0..20 {
+            messages.push(message(Roles::User, &format!("问题{}", turn)));
+            messages.push(message(Roles::Assistant, &format!("回答{}", turn)));
+            slide_summary_window(&mut messages, recent_turns);
+        }
+
+        assert_eq!(messages.len(), 2 + recent_turns);
+        assert_eq!(messages[0].content, "你是小助手");
+        assert!(is_summary(&messages[1]));
+        let recent: Vec<_> = messages[2..].iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(recent, ["问题18", "回答18", "问题19", "回答19"]);
+
+        // 滑出窗口的36条消息逐条并入摘要，超出上限时淘汰最早的条目
+        let lines: Vec<_> = messages[1].content.lines().skip(1).collect();
+        assert_eq!(lines.len(), SUMMARY_MAX_LINES);
+        assert_eq!(lines.first(), Some(&"- 问题3"));
+        assert_eq!(lines.last(), Some(&"- 我: 回答17"));
+    }
+
+    #[test]
+    fn custom_limit_keeps_system_prompt_and_latest_messages() {
+        let config: config::ModelConfig =
+            kovi::toml::from_str("[chat]\nhistory_max_messages = 10\n[chat.group_history_max_messages]\n1 = 5").unwrap();
+        let limit = config.chat().group_history_max_messages(1);
+        assert_eq!(limit, 5);
+        assert_eq!(config.chat().group_history_max_messages(2), 10);
+        let aliased: config::ModelConfig = kovi::toml::from_str("[chat]\nmax_context_messages = 8").unwrap();
+        assert_eq!(aliased.chat().group_history_max_messages(2), 8);
+
+        let mut messages = vec![message(Roles::System, "你是小助手")];
+        messages.extend((0..20).map(|i| message(Roles::User, &format!("消息{}", i))));
+        trim_by_count(&mut messages, limit);
+
+        let contents: Vec<_> = messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["你是小助手", "消息16", "消息17", "消息18", "消息19"]);
+
+        // 少于系统提示加一轮对话的上限无效
+        let too_small: config::ModelConfig = kovi::toml::from_str("[chat]\nhistory_max_messages = 1").unwrap();
+        assert!(too_small.validate().is_err());
+    }
+}
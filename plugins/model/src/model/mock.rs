@@ -0,0 +1,106 @@
+//! # 模拟模型服务
+//!
+//! 供测试使用的本地HTTP服务，按顺序返回预设响应并记录收到的每个请求，
+//! 以及指向它的服务器配置和完整配置
+
+use crate::config::{self, ModelConfig};
+use kovi::serde_json::json;
+use kovi::tokio::io::{AsyncReadExt, AsyncWriteExt};
+use kovi::tokio::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+
+/// 按顺序返回预设响应的本地模型服务，记录收到的每个请求
+pub(crate) struct MockServer {
+    pub(crate) url: String,
+    requests: Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+impl MockServer {
+    pub(crate) async fn start(responses: Vec<String>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1/chat/completions", listener.local_addr().unwrap());
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&requests);
+        kovi::tokio::spawn(async move {
+            for response in responses {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let request = read_request(&mut stream).await;
+                recorded.lock().unwrap().push(request);
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        Self { url, requests }
+    }
+
+    pub(crate) fn request_count(&self) -> usize {
+        self.requests.lock().unwrap().len()
+    }
+
+    pub(crate) fn request(&self, index: usize) -> String {
+        self.requests.lock().unwrap()[index].clone()
+    }
+}
+
+/// 读取一个完整的HTTP请求（请求头和按 `Content-Length` 读取的请求体）
+pub(crate) async fn read_request(stream: &mut TcpStream) -> String {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    while let Ok(read) = stream.read(&mut chunk).await {
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        let text = String::from_utf8_lossy(&buffer);
+        if let Some(header_end) = text.find("\r\n\r\n") {
+            let content_length = text[..header_end]
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length").then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if buffer.len() >= header_end + 4 + content_length {
+                break;
+            }
+        }
+    }
+    String::from_utf8_lossy(&buffer).into_owned()
+}
+
+/// 组装一个HTTP响应，响应后关闭连接，避免客户端复用连接
+pub(crate) fn http_response(status: &str, headers: &[(&str, &str)], body: &str) -> String {
+    let mut response = format!("HTTP/1.1 {}\r\nContent-Type: application/json\r\nConnection: close\r\n", status);
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str(&format!("Content-Length: {}\r\n\r\n{}", body.len(), body));
+    response
+}
+
+/// 组装一个成功的补全响应
+pub(crate) fn completion_response(content: &str) -> String {
+    let body = json!({ "choices": [{ "message": { "role": "assistant", "content": content } }] });
+    http_response("200 OK", &[], &body.to_string())
+}
+
+/// 指向本地模型服务的服务器配置
+pub(crate) fn server_config(url: &str, extra: &str) -> config::ServerConfig {
+    config::use_test_data_dir();
+    kovi::toml::from_str(&format!("url = \"{}\"\napi_token = \"test-token\"\n{}", url, extra)).unwrap()
+}
+
+/// 模型服务指向本地模型服务的完整配置
+///
+/// # 参数
+/// * `url` - 本地模型服务地址
+/// * `extra` - 追加的TOML配置段，如 `[chat]\ndev_mode = true`
+pub(crate) fn model_config(url: &str, extra: &str) -> ModelConfig {
+    config::use_test_data_dir();
+    kovi::toml::from_str(&format!(
+        "[server_config]\nurl = \"{}\"\napi_token = \"test-token\"\n{}",
+        url, extra
+    ))
+    .unwrap()
+}
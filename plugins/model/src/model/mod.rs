@@ -1,7 +1,23 @@
+mod context;
+mod conversation_log;
+mod debounce;
+mod debug;
+mod gesture;
 mod group;
+mod history;
+#[cfg(test)]
+pub(crate) mod mock;
+mod notice;
 mod private;
+mod provider_error;
+mod routing;
+mod throttle;
+mod tools;
+mod trigger;
 pub(crate) mod utils;
 
 pub use crate::model::group::group_message_event;
 
+pub use crate::model::notice::notice_event;
+
 pub use crate::model::private::private_message_event;
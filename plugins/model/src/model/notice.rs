@@ -0,0 +1,21 @@
+use crate::model::trigger;
+use kovi::RuntimeBot;
+use kovi::event::NoticeEvent;
+use std::sync::Arc;
+
+/// 处理通知事件
+///
+/// 机器人的群名片变化或重新入群时，使对应群的显示名称缓存失效
+pub async fn notice_event(event: Arc<NoticeEvent>, _bot: Arc<RuntimeBot>) {
+    if !matches!(event.notice_type.as_str(), "group_card" | "group_increase") {
+        return;
+    }
+
+    let user_id = event.get("user_id").and_then(|v| v.as_i64());
+    let group_id = event.get("group_id").and_then(|v| v.as_i64());
+    if let (Some(user_id), Some(group_id)) = (user_id, group_id)
+        && user_id == event.self_id
+    {
+        trigger::invalidate(group_id).await;
+    }
+}
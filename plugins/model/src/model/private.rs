@@ -1,22 +1,116 @@
-use crate::model::utils::private_chat;
+use crate::config;
+use crate::memory::{MemoryManager, MEMORY_MANAGER};
+use crate::model::trigger;
+use crate::utils::{self, annotate_segments};
+use crate::model::utils::{config_command, describe_private_memory_window, display_name_for, preview_private_prompt, private_chat, refresh_private_context, record_private_message, set_preferred_address};
 use crate::proactive_chat::startup;
+use crate::usage::{self, UsageScope};
 use chrono::Local;
 use kovi::RuntimeBot;
 use kovi::event::PrivateMsgEvent;
 use std::sync::Arc;
 
 pub async fn private_message_event(event: Arc<PrivateMsgEvent>, bot: Arc<RuntimeBot>) {
-    // 启动主动聊天管理器（只在第一次启动）
-    if let Some(_proactive_manager) = startup::get_or_create_proactive_manager(Arc::clone(&bot)).await {
-        println!("主动聊天管理器已启动");
+    // 忽略机器人自己发出的消息，避免自问自答
+    if trigger::is_own_message(event.user_id, event.self_id) {
+        return;
     }
+
+    // 启动主动聊天管理器（只在第一次启动）
+    startup::get_or_create_proactive_manager(Arc::clone(&bot));
     
     let user_id = event.user_id;
     let nick_name = event.get_sender_nickname();
     let time_now_data = Local::now();
     let time = time_now_data.format("%H:%M:%S").to_string();
-    let format_nickname = format!("[{}] {}", time, nick_name);
     if let Some(message) = event.borrow_text() {
-        private_chat(user_id, message, format_nickname, bot).await;
+        // 记忆中保留图片、@ 等消息段的标记
+        let annotated = annotate_segments(&event.message);
+
+        // 开发模式下只回复管理员，其他人的消息只记录不回复
+        let format_nickname = format!("[{}] {}", time, display_name_for(user_id, &nick_name).await);
+        if record_without_reply(&MEMORY_MANAGER, user_id, &annotated, &format_nickname).await {
+            return;
+        }
+
+        if message == "#刷新记忆" && config::get().chat().context_refresh_enabled() {
+            utils::notify_private(&bot, user_id, refresh_private_context(user_id).await).await;
+            return;
+        }
+
+        if message == "#还记得吗" && config::get().chat().memory_indicator_enabled() {
+            utils::notify_private(&bot, user_id, describe_private_memory_window(user_id).await).await;
+            return;
+        }
+
+        if let Some(args) = message.strip_prefix("#配置")
+            && config::get().chat().is_admin(user_id)
+        {
+            utils::notify_private(&bot, user_id, config_command(args)).await;
+            return;
+        }
+
+        if message == "#用量" && config::get().chat().is_admin(user_id) {
+            utils::notify_private(&bot, user_id, usage::describe_usage(UsageScope::Private(user_id)).await).await;
+            return;
+        }
+
+        if message == "#查看提示词" {
+            utils::notify_private(&bot, user_id, preview_private_prompt(user_id).await).await;
+            return;
+        }
+
+        if let Some(address) = message.strip_prefix("#叫我") {
+            let reply = set_preferred_address(user_id, &nick_name, address).await;
+            utils::notify_private(&bot, user_id, reply).await;
+            return;
+        }
+
+        private_chat(user_id, &annotated, format_nickname, bot).await;
     };
 }
+
+/// 开发模式下不回复非管理员，只记录消息并更新用户档案
+/// 
+/// # 参数
+/// * `memory` - 记忆管理器
+/// * `user_id` - 用户ID
+/// * `annotated` - 保留消息段标记的消息内容
+/// * `format_nickname` - 带时间的发送者昵称
+/// 
+/// # 返回值
+/// 消息只记录、不需要回复时返回 `true`
+async fn record_without_reply(memory: &MemoryManager, user_id: i64, annotated: &str, format_nickname: &str) -> bool {
+    if config::get().chat().should_reply_to(user_id) {
+        return false;
+    }
+    record_private_message(memory, user_id, annotated, format_nickname).await;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::mock;
+    use crate::model::mock::{completion_response, model_config, MockServer};
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn dev_mode_records_non_admin_message_without_reply() {
+        let server = MockServer::start(vec![completion_response("不应发出")]).await;
+        let (manager, state) = mock::manager().await;
+        let config = model_config(&server.url, "[chat]\ndev_mode = true\nadmin_ids = [966_101]");
+
+        config::with_test_config(config, async {
+            assert!(record_without_reply(&manager, 966_102, "你好", "[12:00:00] 路人").await);
+            assert!(!record_without_reply(&manager, 966_101, "在吗", "[12:00:00] 管理员").await);
+        })
+        .await;
+
+        let data = state.data.lock().unwrap();
+        let contents: Vec<_> = data.memories.values().map(|memory| memory.content.as_str()).collect();
+        assert_eq!(contents, ["[12:00:00] 路人: 你好"]);
+        assert_eq!(data.user_profiles[&966_102].interaction_count, 1);
+        assert!(!data.user_profiles.contains_key(&966_101));
+        assert_eq!(server.request_count(), 0);
+    }
+}
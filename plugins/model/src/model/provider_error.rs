@@ -0,0 +1,146 @@
+//! # 模型服务错误解析模块
+//!
+//! 解析模型服务返回的错误响应体，将常见错误归类为鉴权、额度、限流、请求错误等，
+//! 分别给出面向用户的提示和面向运维的日志
+
+use kovi::serde_json::{self, Value};
+use reqwest::StatusCode;
+
+/// 模型服务错误类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ProviderError {
+    /// 鉴权失败（密钥无效或无权限）
+    Auth(String),
+    /// 额度或余额不足
+    Quota(String),
+    /// 被限流
+    RateLimited(String),
+    /// 请求超时
+    Timeout(String),
+    /// 请求参数有误或内容被拒绝
+    BadRequest(String),
+    /// 网络错误或其他服务端错误
+    Other(String),
+}
+
+impl ProviderError {
+    /// 根据响应状态码和响应体解析错误
+    ///
+    /// 响应体支持 `{"error":{"message":..,"code":..}}` 和 `{"code":..,"message":..}` 两种常见格式，
+    /// 无法解析时使用原始响应体作为错误详情
+    ///
+    /// # 参数
+    /// * `status` - HTTP状态码
+    /// * `body` - 响应体文本
+    pub(crate) fn from_response(status: StatusCode, body: &str) -> Self {
+        let detail = parse_error_detail(body).unwrap_or_else(|| {
+            let body = body.trim();
+            if body.is_empty() { status.to_string() } else { body.chars().take(200).collect() }
+        });
+        let lower = detail.to_lowercase();
+
+        if lower.contains("quota") || lower.contains("balance") || lower.contains("insufficient") || detail.contains("余额") {
+            return ProviderError::Quota(detail);
+        }
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ProviderError::Auth(detail),
+            StatusCode::PAYMENT_REQUIRED => ProviderError::Quota(detail),
+            StatusCode::TOO_MANY_REQUESTS => ProviderError::RateLimited(detail),
+            StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY | StatusCode::NOT_FOUND => {
+                ProviderError::BadRequest(detail)
+            }
+            _ => ProviderError::Other(detail),
+        }
+    }
+
+    /// 回复给用户的提示
+    pub(crate) fn user_message(&self) -> &'static str {
+        match self {
+            ProviderError::Auth(_) => "模型服务鉴权失败，请联系管理员检查密钥",
+            ProviderError::Quota(_) => "模型服务额度不足，请联系管理员充值",
+            ProviderError::RateLimited(_) => "繁忙，稍后再试",
+            ProviderError::Timeout(_) => "我想得有点久，稍后再试",
+            ProviderError::BadRequest(_) => "这句话模型处理不了，换个说法试试吧",
+            ProviderError::Other(_) => "模型服务暂时不可用，稍后再试",
+        }
+    }
+
+    /// 记录错误日志
+    pub(crate) fn log(&self) {
+        match self {
+            ProviderError::Auth(detail) => eprintln!("[ERROR] 模型服务鉴权失败，请检查 API 令牌: {}", detail),
+            ProviderError::Quota(detail) => eprintln!("[ERROR] 模型服务额度不足: {}", detail),
+            ProviderError::RateLimited(detail) => eprintln!("[ERROR] 模型服务限流: {}", detail),
+            ProviderError::Timeout(detail) => eprintln!("[ERROR] 模型请求超时: {}", detail),
+            ProviderError::BadRequest(detail) => eprintln!("[ERROR] 模型请求被拒绝: {}", detail),
+            ProviderError::Other(detail) => eprintln!("[ERROR] 模型服务错误: {}", detail),
+        }
+    }
+}
+
+/// 从错误响应体中提取错误信息
+fn parse_error_detail(body: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(body).ok()?;
+    let error = value.get("error").unwrap_or(&value);
+    if let Some(message) = error.as_str() {
+        return Some(message.to_string());
+    }
+
+    let message = error.get("message").and_then(Value::as_str);
+    let code = error
+        .get("code")
+        .or_else(|| error.get("type"))
+        .filter(|code| !code.is_null())
+        .map(|code| code.as_str().map(str::to_string).unwrap_or_else(|| code.to_string()));
+    match (code, message) {
+        (Some(code), Some(message)) => Some(format!("{} ({})", message, code)),
+        (None, Some(message)) => Some(message.to_string()),
+        (Some(code), None) => Some(code),
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_representative_error_bodies() {
+        let quota = r#"{"error":{"message":"You exceeded your current quota","type":"insufficient_quota"}}"#;
+        assert_eq!(
+            ProviderError::from_response(StatusCode::TOO_MANY_REQUESTS, quota),
+            ProviderError::Quota("You exceeded your current quota (insufficient_quota)".to_string())
+        );
+
+        let auth = r#"{"error":{"message":"Invalid API key","code":"invalid_api_key"}}"#;
+        assert_eq!(
+            ProviderError::from_response(StatusCode::UNAUTHORIZED, auth),
+            ProviderError::Auth("Invalid API key (invalid_api_key)".to_string())
+        );
+
+        let rate_limited = r#"{"message":"Rate limit reached","code":50603}"#;
+        assert_eq!(
+            ProviderError::from_response(StatusCode::TOO_MANY_REQUESTS, rate_limited),
+            ProviderError::RateLimited("Rate limit reached (50603)".to_string())
+        );
+
+        let bad_request = r#"{"error":"model not found"}"#;
+        assert_eq!(
+            ProviderError::from_response(StatusCode::NOT_FOUND, bad_request),
+            ProviderError::BadRequest("model not found".to_string())
+        );
+    }
+
+    #[test]
+    fn unparsable_body_falls_back_to_raw_text_or_status() {
+        assert_eq!(
+            ProviderError::from_response(StatusCode::BAD_GATEWAY, "<html>bad gateway</html>"),
+            ProviderError::Other("<html>bad gateway</html>".to_string())
+        );
+        assert_eq!(
+            ProviderError::from_response(StatusCode::BAD_GATEWAY, ""),
+            ProviderError::Other(StatusCode::BAD_GATEWAY.to_string())
+        );
+        assert_eq!(ProviderError::Quota(String::new()).user_message(), "模型服务额度不足，请联系管理员充值");
+    }
+}
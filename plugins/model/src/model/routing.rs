@@ -0,0 +1,43 @@
+//! # 模型路由模块
+//!
+//! 按消息分类选择模型：打招呼和闲聊交给便宜、快速的模型，
+//! 有实质内容的提问交给主模型。分类只依据消息文本，不额外请求模型
+
+use crate::config::MessageClass;
+
+/// 视为打招呼的最长消息字数
+const GREETING_MAX_CHARS: usize = 12;
+
+/// 超过该字数的消息视为有实质内容的提问
+const QUESTION_MIN_CHARS: usize = 40;
+
+/// 打招呼用语
+const GREETING_WORDS: &[&str] = &[
+    "你好", "您好", "早上好", "早安", "午安", "晚上好", "晚安", "在吗", "在不在", "嗨", "哈喽", "hello", "hi",
+    "早", "晚好",
+];
+
+/// 提问用语
+const QUESTION_WORDS: &[&str] = &[
+    "?", "？", "怎么", "为什么", "为啥", "如何", "什么", "哪", "多少", "能不能", "可不可以", "请问", "帮我",
+    "解释", "区别", "推荐",
+];
+
+/// 判断消息的分类
+///
+/// 短消息中包含打招呼用语时视为打招呼，包含提问用语或篇幅较长时视为提问，其余视为闲聊
+///
+/// # 参数
+/// * `message` - 用户消息文本
+pub(crate) fn classify(message: &str) -> MessageClass {
+    let message = message.trim().to_lowercase();
+    let chars = message.chars().count();
+
+    if chars <= GREETING_MAX_CHARS && GREETING_WORDS.iter().any(|word| message.contains(word)) {
+        MessageClass::Greeting
+    } else if chars >= QUESTION_MIN_CHARS || QUESTION_WORDS.iter().any(|word| message.contains(word)) {
+        MessageClass::Question
+    } else {
+        MessageClass::Smalltalk
+    }
+}
@@ -0,0 +1,127 @@
+//! # 请求限流模块
+//!
+//! 按用户统计最近一分钟和一小时内发往模型的请求数，超出配置的配额时不再调用模型，
+//! 避免单个用户刷屏产生大量接口费用。管理员不受限制
+
+use crate::config;
+use kovi::tokio::sync::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+/// 超出配额时的回复
+pub(crate) const THROTTLED_REPLY: &str = "慢一点呀，让我歇口气再聊～";
+
+const MINUTE: Duration = Duration::from_secs(60);
+const HOUR: Duration = Duration::from_secs(3600);
+
+/// 单个用户的请求记录
+#[derive(Default)]
+struct UserQuota {
+    /// 最近一小时内被放行的请求时间
+    requests: VecDeque<Instant>,
+    /// 本次超限后是否已经提醒过
+    notified: bool,
+}
+
+/// 各用户的请求记录
+static QUOTAS: LazyLock<Mutex<HashMap<i64, UserQuota>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 限流检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Throttle {
+    /// 未超出配额，可以调用模型
+    Allowed,
+    /// 超出配额，`notify` 表示这是本次超限后的第一条消息，需要提醒用户
+    Limited { notify: bool },
+}
+
+/// 检查用户是否还有请求配额，有配额时记录本次请求
+///
+/// # 参数
+/// * `user_id` - 用户QQ号
+pub(crate) async fn check(user_id: i64) -> Throttle {
+    let config = config::get();
+    let chat = config.chat();
+    if chat.is_admin(user_id) {
+        return Throttle::Allowed;
+    }
+
+    let now = Instant::now();
+    let mut quotas = QUOTAS.lock().await;
+    quotas.retain(|_, quota| quota.requests.back().is_some_and(|last| now.duration_since(*last) < HOUR));
+
+    let quota = quotas.entry(user_id).or_default();
+    let result = quota.admit(now, chat.user_quota_per_minute(), chat.user_quota_per_hour());
+    if result != Throttle::Allowed {
+        println!("[INFO] 用户 {} 请求过于频繁，已限流", user_id);
+    }
+    result
+}
+
+impl UserQuota {
+    /// 按每分钟、每小时的配额判断本次请求是否放行，放行时记录请求时间
+    ///
+    /// # 参数
+    /// * `now` - 本次请求的时间
+    /// * `per_minute` - 每分钟配额，0表示不限制
+    /// * `per_hour` - 每小时配额，0表示不限制
+    fn admit(&mut self, now: Instant, per_minute: usize, per_hour: usize) -> Throttle {
+        let in_last = |window: Duration| {
+            self.requests.iter().filter(|time| now.duration_since(**time) < window).count()
+        };
+        let over_minute = per_minute > 0 && in_last(MINUTE) >= per_minute;
+        let over_hour = per_hour > 0 && in_last(HOUR) >= per_hour;
+
+        if over_minute || over_hour {
+            let notify = !self.notified;
+            self.notified = true;
+            return Throttle::Limited { notify };
+        }
+
+        while self.requests.front().is_some_and(|time| now.duration_since(*time) >= HOUR) {
+            self.requests.pop_front();
+        }
+        self.requests.push_back(now);
+        self.notified = false;
+        Throttle::Allowed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_quota_is_unlimited() {
+        let mut quota = UserQuota::default();
+        let now = Instant::now();
+        for _ in 0..500 {
+            assert_eq!(quota.admit(now, 0, 0), Throttle::Allowed);
+        }
+    }
+
+    #[test]
+    fn minute_window_limits_and_notifies_once() {
+        let mut quota = UserQuota::default();
+        let start = Instant::now();
+        assert_eq!(quota.admit(start, 2, 0), Throttle::Allowed);
+        assert_eq!(quota.admit(start, 2, 0), Throttle::Allowed);
+        assert_eq!(quota.admit(start, 2, 0), Throttle::Limited { notify: true });
+        assert_eq!(quota.admit(start, 2, 0), Throttle::Limited { notify: false });
+
+        // 一分钟后窗口滑过，恢复放行并重新允许提醒
+        let later = start + MINUTE;
+        assert_eq!(quota.admit(later, 2, 0), Throttle::Allowed);
+    }
+
+    #[test]
+    fn hour_window_counts_older_requests() {
+        let mut quota = UserQuota::default();
+        let start = Instant::now();
+        assert_eq!(quota.admit(start, 5, 2), Throttle::Allowed);
+        assert_eq!(quota.admit(start + MINUTE, 5, 2), Throttle::Allowed);
+        assert_eq!(quota.admit(start + MINUTE * 2, 5, 2), Throttle::Limited { notify: true });
+        assert_eq!(quota.admit(start + HOUR + MINUTE, 5, 2), Throttle::Allowed);
+    }
+}
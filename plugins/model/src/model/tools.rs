@@ -0,0 +1,281 @@
+//! # 工具调用模块
+//!
+//! 为支持工具调用（function calling）的模型提供内置工具。模型返回工具调用时，
+//! 按名称分发到对应的处理函数，并将结果交回模型生成最终回复，避免模型凭空编造时间、计算结果等
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use kovi::serde_json::{self, Value, json};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// 工具处理函数，参数为模型给出的JSON参数，返回交给模型的结果文本
+pub(crate) type ToolFn = fn(&Value) -> Result<String>;
+
+/// 工具定义
+struct Tool {
+    /// 工具说明，告诉模型什么时候使用
+    description: &'static str,
+    /// 参数的JSON Schema
+    parameters: Value,
+    /// 处理函数
+    handler: ToolFn,
+}
+
+/// 已注册的工具
+///
+/// Key: 工具名称, Value: 工具定义
+static TOOLS: LazyLock<HashMap<&'static str, Tool>> = LazyLock::new(|| {
+    HashMap::from([
+        (
+            "current_time",
+            Tool {
+                description: "获取当前的日期、时间和星期",
+                parameters: json!({ "type": "object", "properties": {} }),
+                handler: current_time,
+            },
+        ),
+        (
+            "calculator",
+            Tool {
+                description: "计算四则运算表达式，支持 + - * / ^ 和括号，例如 (1.5 + 2) * 3",
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "expression": { "type": "string", "description": "需要计算的表达式" }
+                    },
+                    "required": ["expression"]
+                }),
+                handler: calculator,
+            },
+        ),
+    ])
+});
+
+/// 生成请求体中的工具列表（OpenAI 格式）
+pub(crate) fn definitions() -> Vec<Value> {
+    let mut names: Vec<&&str> = TOOLS.keys().collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| {
+            let tool = &TOOLS[*name];
+            json!({
+                "type": "function",
+                "function": {
+                    "name": name,
+                    "description": tool.description,
+                    "parameters": tool.parameters,
+                }
+            })
+        })
+        .collect()
+}
+
+/// 执行一次工具调用
+///
+/// # 参数
+/// * `name` - 工具名称
+/// * `arguments` - 模型给出的JSON参数文本
+///
+/// # 返回值
+/// 工具的执行结果；工具不存在或执行失败时返回错误说明，同样交给模型处理
+pub(crate) fn dispatch(name: &str, arguments: &str) -> String {
+    let Some(tool) = TOOLS.get(name) else {
+        return format!("错误：没有名为 {} 的工具", name);
+    };
+    let arguments = if arguments.trim().is_empty() { "{}" } else { arguments };
+    let result = serde_json::from_str::<Value>(arguments)
+        .context("参数不是有效的JSON")
+        .and_then(|arguments| (tool.handler)(&arguments));
+    match result {
+        Ok(result) => result,
+        Err(e) => format!("错误：{:#}", e),
+    }
+}
+
+/// 获取当前时间
+fn current_time(_arguments: &Value) -> Result<String> {
+    let weekdays = ["一", "二", "三", "四", "五", "六", "日"];
+    let now = Local::now();
+    Ok(format!(
+        "{} 星期{}",
+        now.format("%Y-%m-%d %H:%M:%S"),
+        weekdays[chrono::Datelike::weekday(&now).num_days_from_monday() as usize]
+    ))
+}
+
+/// 表达式的最大长度（字符数），超出时直接拒绝，避免解析超长的模型输出
+const MAX_EXPRESSION_LEN: usize = 256;
+
+/// 括号和正负号的最大嵌套深度，防止恶意表达式递归过深导致栈溢出
+const MAX_DEPTH: usize = 32;
+
+/// 计算表达式
+fn calculator(arguments: &Value) -> Result<String> {
+    let expression = arguments["expression"].as_str().context("缺少 expression 参数")?;
+    if expression.chars().count() > MAX_EXPRESSION_LEN {
+        anyhow::bail!("表达式过长（超过 {} 个字符）", MAX_EXPRESSION_LEN);
+    }
+    let mut parser = Parser { chars: expression.chars().filter(|c| !c.is_whitespace()).collect(), pos: 0, depth: 0 };
+    let value = parser.expression()?;
+    if parser.pos < parser.chars.len() {
+        anyhow::bail!("表达式在第 {} 个字符处无法解析", parser.pos + 1);
+    }
+    if !value.is_finite() {
+        anyhow::bail!("计算结果无效（可能除以了零）");
+    }
+    Ok(format!("{} = {}", expression.trim(), value))
+}
+
+/// 四则运算表达式解析器（递归下降）
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    /// 当前嵌套深度
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    /// 进入一层嵌套，超过最大深度时报错
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= MAX_DEPTH {
+            anyhow::bail!("表达式嵌套过深（超过 {} 层）", MAX_DEPTH);
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// 加减
+    fn expression(&mut self) -> Result<f64> {
+        let mut value = self.term()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.pos += 1;
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Ok(value)
+    }
+
+    /// 乘除
+    fn term(&mut self) -> Result<f64> {
+        let mut value = self.power()?;
+        while let Some(op @ ('*' | '/' | '×' | '÷')) = self.peek() {
+            self.pos += 1;
+            let rhs = self.power()?;
+            value = if matches!(op, '*' | '×') { value * rhs } else { value / rhs };
+        }
+        Ok(value)
+    }
+
+    /// 乘方（右结合）
+    fn power(&mut self) -> Result<f64> {
+        let base = self.unary()?;
+        if self.peek() == Some('^') {
+            self.pos += 1;
+            return Ok(base.powf(self.nested(Self::power)?));
+        }
+        Ok(base)
+    }
+
+    /// 正负号
+    fn unary(&mut self) -> Result<f64> {
+        match self.peek() {
+            Some('-') => {
+                self.pos += 1;
+                Ok(-self.nested(Self::unary)?)
+            }
+            Some('+') => {
+                self.pos += 1;
+                self.nested(Self::unary)
+            }
+            _ => self.atom(),
+        }
+    }
+
+    /// 数字或括号
+    fn atom(&mut self) -> Result<f64> {
+        if matches!(self.peek(), Some('(' | '（')) {
+            self.pos += 1;
+            let value = self.nested(Self::expression)?;
+            if !matches!(self.peek(), Some(')' | '）')) {
+                anyhow::bail!("缺少右括号");
+            }
+            self.pos += 1;
+            return Ok(value);
+        }
+
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            anyhow::bail!("表达式在第 {} 个字符处需要数字", self.pos + 1);
+        }
+        let number: String = self.chars[start..self.pos].iter().collect();
+        number.parse().with_context(|| format!("无效的数字: {}", number))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calc(expression: &str) -> Result<String> {
+        calculator(&json!({ "expression": expression }))
+    }
+
+    #[test]
+    fn calculator_respects_precedence_and_associativity() {
+        assert_eq!(calc("1 + 2 * 3").unwrap(), "1 + 2 * 3 = 7");
+        assert_eq!(calc("(1 + 2) * 3").unwrap(), "(1 + 2) * 3 = 9");
+        assert_eq!(calc("2 ^ 3 ^ 2").unwrap(), "2 ^ 3 ^ 2 = 512");
+        assert_eq!(calc("-2 ^ 2").unwrap(), "-2 ^ 2 = 4");
+        assert_eq!(calc("10 - 4 - 3").unwrap(), "10 - 4 - 3 = 3");
+    }
+
+    #[test]
+    fn calculator_accepts_full_width_operators() {
+        assert_eq!(calc("（1+2）×3").unwrap(), "（1+2）×3 = 9");
+        assert_eq!(calc("9÷2").unwrap(), "9÷2 = 4.5");
+    }
+
+    #[test]
+    fn calculator_rejects_invalid_input() {
+        assert!(calc("1 / 0").unwrap_err().to_string().contains("除以了零"));
+        assert!(calc("1 + 2 abc").unwrap_err().to_string().contains("第 4 个字符"));
+        assert!(calc("(1 + 2").unwrap_err().to_string().contains("缺少右括号"));
+        assert!(calculator(&json!({})).is_err());
+    }
+
+    #[test]
+    fn calculator_limits_depth_and_length() {
+        let deep_parens = format!("{}1{}", "(".repeat(MAX_DEPTH + 1), ")".repeat(MAX_DEPTH + 1));
+        assert!(calc(&deep_parens).unwrap_err().to_string().contains("嵌套过深"));
+        let deep_signs = format!("{}1", "-".repeat(MAX_DEPTH + 1));
+        assert!(calc(&deep_signs).unwrap_err().to_string().contains("嵌套过深"));
+        let deep_powers = format!("{}2", "2^".repeat(MAX_DEPTH + 1));
+        assert!(calc(&deep_powers).unwrap_err().to_string().contains("嵌套过深"));
+
+        let nested = format!("{}1{}", "(".repeat(MAX_DEPTH - 1), ")".repeat(MAX_DEPTH - 1));
+        assert!(calc(&nested).is_ok());
+
+        let huge = "(".repeat(100_000);
+        assert!(calc(&huge).unwrap_err().to_string().contains("过长"));
+    }
+
+    #[test]
+    fn dispatch_reports_errors_as_text() {
+        assert_eq!(dispatch("weather", "{}"), "错误：没有名为 weather 的工具");
+        assert!(dispatch("calculator", "{not json").starts_with("错误：参数不是有效的JSON"));
+        assert!(dispatch("calculator", "  ").starts_with("错误：缺少 expression 参数"));
+        assert!(!dispatch("current_time", "").starts_with("错误"));
+        assert_eq!(dispatch("calculator", r#"{"expression":"12*3"}"#), "12*3 = 36");
+    }
+}
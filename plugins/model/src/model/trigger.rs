@@ -0,0 +1,258 @@
+//! # 触发匹配模块
+//!
+//! 判断群聊消息是否在呼叫机器人：@机器人、回复机器人不久前的消息、包含配置的触发词、或包含机器人在该群的名片/昵称。
+//! 机器人在各群的显示名称会缓存一段时间，名片变动等通知事件到来时主动失效
+
+use crate::config;
+use kovi::event::GroupMsgEvent;
+use kovi::tokio::sync::Mutex;
+use kovi::RuntimeBot;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+/// 显示名称缓存的有效期
+const DISPLAY_NAME_TTL: Duration = Duration::from_secs(600);
+
+/// 机器人在各群的显示名称缓存
+///
+/// Key: 群组ID, Value: (显示名称, 获取时间)
+static DISPLAY_NAMES: LazyLock<Mutex<HashMap<i64, (String, Instant)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 判断消息是否由机器人自己发出
+///
+/// 平台回显的机器人消息（如主动聊天）不能当作用户发言处理，否则机器人会自问自答
+///
+/// # 参数
+/// * `user_id` - 消息发送者QQ号
+/// * `self_id` - 机器人QQ号
+pub(crate) fn is_own_message(user_id: i64, self_id: i64) -> bool {
+    user_id == self_id
+}
+
+/// 判断群聊消息是否在呼叫机器人
+///
+/// # 参数
+/// * `bot` - 机器人实例，用于获取机器人的群名片
+/// * `event` - 群聊消息事件
+/// * `message` - 消息文本
+pub(crate) async fn is_triggered(bot: &RuntimeBot, event: &GroupMsgEvent, message: &str) -> bool {
+    let mentioned = event.message.iter().any(|segment| {
+        let qq = &segment.data["qq"];
+        segment.type_ == "at"
+            && qq.as_str().and_then(|qq| qq.parse().ok()).or_else(|| qq.as_i64()) == Some(event.self_id)
+    });
+    let config = config::get();
+    if mentioned || config.chat().matches_trigger_word(message, config.prompt().bot_name()) {
+        return true;
+    }
+    if replies_to_bot(bot, event).await {
+        return true;
+    }
+
+    display_name(bot, event.group_id, event.self_id)
+        .await
+        .is_some_and(|name| message.contains(name.as_str()))
+}
+
+/// 判断消息是否在回复机器人不久前发出的消息
+///
+/// 通过引用的消息ID查询原消息的发送者和发送时间，查询失败时视为未回复机器人
+async fn replies_to_bot(bot: &RuntimeBot, event: &GroupMsgEvent) -> bool {
+    let config = config::get();
+    if !config.chat().reply_trigger_enabled(event.group_id) {
+        return false;
+    }
+    let Some(message_id) = quoted_message_id(event) else {
+        return false;
+    };
+
+    let quoted = match bot.get_msg(message_id).await {
+        Ok(quoted) => quoted,
+        Err(e) => {
+            eprintln!("[ERROR] 获取被回复的消息失败 (消息: {}): {:?}", message_id, e);
+            return false;
+        }
+    };
+    is_recent_bot_message(
+        &quoted.data,
+        event.self_id,
+        chrono::Local::now().timestamp(),
+        config.chat().reply_trigger_window_secs(),
+    )
+}
+
+/// 获取消息中引用的消息ID
+fn quoted_message_id(event: &GroupMsgEvent) -> Option<i32> {
+    event.message.iter().find(|segment| segment.type_ == "reply").and_then(|segment| {
+        let id = &segment.data["id"];
+        id.as_str().and_then(|id| id.parse().ok()).or_else(|| id.as_i64().and_then(|id| i32::try_from(id).ok()))
+    })
+}
+
+/// 判断被引用的消息是否由机器人在时间窗口内发出
+///
+/// # 参数
+/// * `quoted` - `get_msg` 返回的消息数据
+/// * `self_id` - 机器人QQ号
+/// * `now` - 当前时间戳（秒）
+/// * `window_secs` - 时间窗口（秒）
+fn is_recent_bot_message(quoted: &kovi::serde_json::Value, self_id: i64, now: i64, window_secs: u64) -> bool {
+    let from_bot = quoted["sender"]["user_id"].as_i64() == Some(self_id);
+    let recent = quoted["time"].as_i64().is_some_and(|time| now - time <= window_secs as i64);
+    from_bot && recent
+}
+
+/// 判断群聊消息是否为 @全体成员 的公告类消息
+pub(crate) fn is_announcement(event: &GroupMsgEvent) -> bool {
+    event.message.iter().any(|segment| {
+        segment.type_ == "at" && segment.data["qq"].as_str() == Some("all")
+    })
+}
+
+/// 获取机器人在群内的显示名称（群名片，未设置时为QQ昵称）
+///
+/// 缓存过期或不存在时重新获取，获取失败时返回 `None`
+async fn display_name(bot: &RuntimeBot, group_id: i64, self_id: i64) -> Option<String> {
+    if let Some(name) = cached_display_name(group_id).await {
+        return Some(name);
+    }
+
+    let info = match bot.get_group_member_info(group_id, self_id, true).await {
+        Ok(info) => info,
+        Err(e) => {
+            eprintln!("[ERROR] 获取机器人群名片失败 (群组: {}): {:?}", group_id, e);
+            return None;
+        }
+    };
+    let name = ["card", "nickname"]
+        .iter()
+        .filter_map(|key| info.data.get(key).and_then(|v| v.as_str()))
+        .map(str::trim)
+        .find(|name| !name.is_empty())?
+        .to_string();
+
+    DISPLAY_NAMES.lock().await.insert(group_id, (name.clone(), Instant::now()));
+    Some(name)
+}
+
+/// 获取缓存中未过期的群内显示名称
+async fn cached_display_name(group_id: i64) -> Option<String> {
+    DISPLAY_NAMES
+        .lock()
+        .await
+        .get(&group_id)
+        .filter(|(_, fetched_at)| fetched_at.elapsed() < DISPLAY_NAME_TTL)
+        .map(|(name, _)| name.clone())
+}
+
+/// 使群内显示名称缓存失效，下次匹配时重新获取
+pub(crate) async fn invalidate(group_id: i64) {
+    if DISPLAY_NAMES.lock().await.remove(&group_id).is_some() {
+        println!("[INFO] 机器人群名片缓存已失效 (群组: {})", group_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kovi::Message;
+    use kovi::bot::message::Segment;
+    use kovi::event::{PostType, Sender};
+    use kovi::serde_json::{Value, json};
+
+    const SELF_ID: i64 = 10000;
+
+    fn event(user_id: i64, segments: Vec<Segment>) -> GroupMsgEvent {
+        GroupMsgEvent {
+            time: 0,
+            self_id: SELF_ID,
+            post_type: PostType::Message,
+            message_type: "group".to_string(),
+            sub_type: "normal".to_string(),
+            message: Message::from(segments),
+            message_id: 1,
+            group_id: 1,
+            user_id,
+            anonymous: None,
+            raw_message: String::new(),
+            font: 0,
+            sender: Sender {
+                user_id,
+                nickname: None,
+                card: None,
+                sex: None,
+                age: None,
+                area: None,
+                level: None,
+                role: None,
+                title: None,
+            },
+            text: None,
+            human_text: String::new(),
+            original_json: Value::Null,
+            api_tx: kovi::tokio::sync::mpsc::channel(1).0,
+        }
+    }
+
+    #[test]
+    fn message_from_bot_itself_is_ignored() {
+        let own = event(SELF_ID, vec![Segment::new("text", json!({ "text": "大家好" }))]);
+        let user = event(42, vec![Segment::new("text", json!({ "text": "大家好" }))]);
+
+        assert!(is_own_message(own.user_id, own.self_id));
+        assert!(!is_own_message(user.user_id, user.self_id));
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn cached_group_card_matches_until_invalidated() {
+        let group_id = 994_001;
+        DISPLAY_NAMES.lock().await.insert(group_id, ("小云同学".to_string(), Instant::now()));
+
+        let name = cached_display_name(group_id).await.unwrap();
+        assert!("小云同学在吗".contains(name.as_str()));
+
+        invalidate(group_id).await;
+        assert_eq!(cached_display_name(group_id).await, None);
+
+        // 过期的缓存不再使用
+        let stale = Instant::now().checked_sub(DISPLAY_NAME_TTL).unwrap();
+        DISPLAY_NAMES.lock().await.insert(group_id, ("小云同学".to_string(), stale));
+        assert_eq!(cached_display_name(group_id).await, None);
+    }
+
+    #[test]
+    fn at_all_message_is_announcement_not_mention() {
+        let at_all = event(42, vec![
+            Segment::new("at", json!({ "qq": "all" })),
+            Segment::new("text", json!({ "text": "今晚八点开会" })),
+        ]);
+        let at_bot = event(42, vec![Segment::new("at", json!({ "qq": SELF_ID.to_string() }))]);
+
+        // 公告在进入触发判断前就被拦下，配置为忽略时不会走到模型回复
+        assert!(is_announcement(&at_all));
+        assert!(!is_announcement(&at_bot));
+
+        let config: config::ModelConfig =
+            kovi::toml::from_str("[chat]\nannouncement_handling = \"ignore\"").unwrap();
+        assert_eq!(config.chat().announcement_handling(), config::AnnouncementHandling::Ignore);
+    }
+
+    #[test]
+    fn reply_to_recent_bot_message_triggers_without_mention() {
+        let reply = event(42, vec![
+            Segment::new("reply", json!({ "id": "123" })),
+            Segment::new("text", json!({ "text": "那后来呢" })),
+        ]);
+        assert_eq!(quoted_message_id(&reply), Some(123));
+        assert_eq!(quoted_message_id(&event(42, vec![Segment::new("text", json!({ "text": "那后来呢" }))])), None);
+
+        let now = 1_700_000_000;
+        let quoted = |user_id: i64, time: i64| json!({ "sender": { "user_id": user_id }, "time": time });
+        assert!(is_recent_bot_message(&quoted(SELF_ID, now - 60), SELF_ID, now, 300));
+        // 回复其他人的消息或机器人很久以前的消息不触发
+        assert!(!is_recent_bot_message(&quoted(42, now - 60), SELF_ID, now, 300));
+        assert!(!is_recent_bot_message(&quoted(SELF_ID, now - 600), SELF_ID, now, 300));
+    }
+}
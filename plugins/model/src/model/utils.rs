@@ -9,20 +9,25 @@
 //! - 系统状态监控
 
 use crate::config;
+use crate::model::context::{assemble_injection, InjectionItem, InjectionSection};
 use crate::utils;
-use crate::memory::{MemoryManager, UserProfile};
-use crate::mood_system::MoodSystem;
-use kovi::RuntimeBot;
-use kovi::serde_json::Value;
-use kovi::tokio::sync::{Mutex, MutexGuard};
-use reqwest::Client;
-use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, HeaderMap};
+use crate::memory::{GroupProfile, MemoryEntry, MemoryManager, MemoryType, RelationshipTier, UserProfile, MEMORY_MANAGER};
+use crate::model::{conversation_log, debug, gesture, history, routing, throttle, tools};
+use crate::model::throttle::{Throttle, THROTTLED_REPLY};
+use crate::model::provider_error::ProviderError;
+use crate::mood_system::{self, Mood, MoodSystem};
+use crate::usage::{self, TokenUsage, UsageScope};
+use kovi::{Message, RuntimeBot};
+use kovi::serde_json::{json, Value};
+use kovi::tokio::sync::Mutex;
+use reqwest::StatusCode;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, HeaderMap, RETRY_AFTER};
 use serde::Serialize;
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::future::Future;
 use std::sync::{Arc, LazyLock};
-use std::time::UNIX_EPOCH;
+use std::time::{Instant, UNIX_EPOCH};
 use anyhow::Context;
 use chrono::{Local, TimeZone};
 
@@ -47,11 +52,33 @@ static IS_BANNED: LazyLock<Mutex<HashMap<i64, bool>>> =
 static PRIVATE_MESSAGE_MEMORY: LazyLock<Mutex<HashMap<i64, Vec<BotMemory>>>> =
     LazyLock::new(|| Mutex::new(HashMap::new()));
 
-/// 全局记忆管理器实例
+/// 群聊对话最近活跃时间
 /// 
-/// 负责管理所有类型的记忆数据，包括对话记忆、用户档案、群组信息等
-static MEMORY_MANAGER: LazyLock<Arc<MemoryManager>> =
-    LazyLock::new(|| Arc::new(MemoryManager::new("bot_memory.json")));
+/// 用于群聊对话记忆超出上限时按最久未活跃淘汰
+/// Key: 群组ID, Value: 最近活跃时间
+static MEMORY_ACTIVITY: LazyLock<Mutex<HashMap<i64, Instant>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 私聊对话最近活跃时间
+/// 
+/// 用于私聊对话记忆超出上限时按最久未活跃淘汰
+/// Key: 用户ID, Value: 最近活跃时间
+static PRIVATE_MESSAGE_ACTIVITY: LazyLock<Mutex<HashMap<i64, Instant>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 群聊对话处理锁
+/// 
+/// 同一群的消息按顺序处理，模型请求期间只持有该群的锁
+/// Key: 群组ID, Value: 处理锁
+static GROUP_TURNS: LazyLock<Mutex<HashMap<i64, Arc<Mutex<()>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 私聊对话处理锁
+/// 
+/// 同一用户的消息按顺序处理，模型请求期间只持有该用户的锁
+/// Key: 用户ID, Value: 处理锁
+static PRIVATE_TURNS: LazyLock<Mutex<HashMap<i64, Arc<Mutex<()>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
 
 /// 全局情绪系统实例
 /// 
@@ -59,10 +86,12 @@ static MEMORY_MANAGER: LazyLock<Arc<MemoryManager>> =
 static MOOD_SYSTEM: LazyLock<MoodSystem> =
     LazyLock::new(|| MoodSystem::new(Arc::clone(&MEMORY_MANAGER)));
 
-/// 最大记忆条数限制
+/// 群聊模型参数覆盖
 /// 
-/// 限制单次对话中保留的最大消息数量，防止内存过度使用
-const MAX_MEMORY_SIZE: usize = 25;
+/// 管理员通过命令为单个群设置的温度、top_p等参数，仅保存在内存中
+/// Key: 群组ID, Value: 参数覆盖
+static MODEL_OVERRIDES: LazyLock<Mutex<HashMap<i64, ModelOverrides>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
 
 /// 消息角色枚举
 /// 
@@ -89,6 +118,15 @@ pub struct BotMemory {
     pub(crate) content: String,
 }
 
+/// 模型生成的一条回复
+#[derive(Debug)]
+pub struct ModelReply {
+    /// 回复消息
+    pub(crate) memory: BotMemory,
+    /// 实际生成回复的模型，按消息分类路由或切换到备用模型时与主模型不同
+    pub(crate) model: String,
+}
+
 /// 模型配置结构体
 /// 
 /// 用于向AI模型发送请求时的配置参数
@@ -96,12 +134,86 @@ pub struct BotMemory {
 struct ModelConf<'a> {
     /// 模型名称
     model: &'a str,
-    /// 消息列表
-    messages: &'a Vec<BotMemory>,
+    /// 消息列表，包括本轮工具调用的中间消息
+    messages: Vec<Value>,
     /// 是否流式输出
     stream: bool,
-    /// 温度参数，控制回复的随机性 (0.0-1.0)
+    /// 温度参数，控制回复的随机性 (0.0-2.0)
     temperature: f32,
+    /// 核采样参数 (0.0-1.0)，未设置时使用服务端默认值
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    /// 回复的最大token数，未设置时使用服务端默认值
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    /// 是否返回各token的对数概率，用于计算回复置信度
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    logprobs: bool,
+    /// 可供模型调用的工具，未启用工具调用时不发送
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<Value>>,
+}
+
+/// 模型返回空回复时，每次重试提高的温度
+const EMPTY_REPLY_TEMPERATURE_STEP: f32 = 0.1;
+
+/// 请求失败重试的初始退避时间，每次重试翻倍；限流时优先使用服务端给出的 `Retry-After`
+const RETRY_DEFAULT_BACKOFF: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// 重试等待时间上限，超过时直接提示用户稍后再试
+const RETRY_MAX_WAIT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// 安全模式下需要模型的消息回复的内容
+const SAFE_MODE_REPLY: &str = "离线模式，暂时无法聊天哦";
+
+
+/// 单个对话的模型参数覆盖
+/// 
+/// 未设置的参数使用服务器配置中的值
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ModelOverrides {
+    /// 温度参数覆盖
+    pub temperature: Option<f32>,
+    /// 核采样参数覆盖
+    pub top_p: Option<f32>,
+    /// 本轮消息的分类，用于按配置选择模型，未设置时使用主模型
+    pub message_class: Option<config::MessageClass>,
+    /// 本轮请求的用量计入的对话，未设置时只计入全局用量
+    pub usage_scope: Option<UsageScope>,
+}
+
+/// 记录群聊消息
+/// 
+/// 分析消息情绪并写入对话记忆，不触发回复
+/// 
+/// # 参数
+/// * `memory` - 写入对话记忆的记忆管理器
+/// * `group_id` - 群组ID
+/// * `nickname` - 发送者昵称
+/// * `message` - 消息内容
+/// 
+/// # 返回值
+/// 分析出的情绪，分析失败时返回 `None`
+pub async fn record_group_message(memory: &MemoryManager, group_id: i64, nickname: &str, message: &str) -> Option<Mood> {
+    // 分析情绪并更新
+    let mood = match MOOD_SYSTEM.analyze_and_update_mood(message, "group_chat").await {
+        Ok(mood) => Some(mood),
+        Err(e) => {
+            eprintln!("[ERROR] 群聊情绪分析失败 (群组: {}): {}", group_id, e);
+            None
+        }
+    };
+
+    // 记录对话记忆
+    if let Err(e) = memory.add_conversation_memory(
+        group_id,
+        &format!("{}: {}", nickname, message),
+        "group_chat"
+    ).await {
+        eprintln!("[ERROR] 群聊记忆记录失败 (群组: {}): {}", group_id, e);
+    }
+
+    mood
 }
 
 /// 群聊消息处理主函数
@@ -114,79 +226,95 @@ struct ModelConf<'a> {
 /// - 记忆大小管理
 /// 
 /// # 参数
-/// * `guard` - 群聊记忆的互斥锁守卫
 /// * `group_id` - 群组ID
 /// * `bot` - 机器人实例
 /// * `nickname` - 发送者昵称
 /// * `message` - 消息内容
+/// * `reply_to` - 触发回复的原消息ID，用于引用回复
+/// 
+/// 同一群的消息按顺序处理；等待模型回复期间不持有全局对话锁，其他群不受影响
 pub async fn control_model(
-    guard: &mut MutexGuard<'_, HashMap<i64, Vec<BotMemory>>>,
     group_id: i64,
     bot: Arc<RuntimeBot>,
     nickname: String,
     message: &str,
+    reply_to: Option<i32>,
 ) {
-    // 分析情绪并更新
-    if let Err(e) = MOOD_SYSTEM.analyze_and_update_mood(message, "group_chat").await {
-        eprintln!("[ERROR] 群聊情绪分析失败 (群组: {}): {}", group_id, e);
+    let send = |reply: Message| utils::send_group_message(&bot, group_id, reply);
+    reply_group_turn(group_id, &send, nickname, message, reply_to).await;
+}
+
+/// 处理一轮群聊对话，回复通过 `send` 发送到群组
+/// 
+/// # 参数
+/// * `group_id` - 群组ID
+/// * `send` - 向群组发送一条消息，成功时返回消息ID
+/// * `nickname` - 发送者昵称
+/// * `message` - 消息内容
+/// * `reply_to` - 触发回复的原消息ID，用于引用回复
+async fn reply_group_turn<F>(
+    group_id: i64,
+    send: &impl Fn(Message) -> F,
+    nickname: String,
+    message: &str,
+    reply_to: Option<i32>,
+) where
+    F: Future<Output = anyhow::Result<i32>>,
+{
+    let mood = record_group_message(&MEMORY_MANAGER, group_id, &nickname, message).await;
+    let turn = turn_lock(&GROUP_TURNS, group_id).await;
+    let _turn = turn.lock().await;
+    {
+        let mut guard = get_memory().lock().await;
+        let config = config::get();
+        let idle_secs = config.chat().group_context_idle_secs();
+        expire_idle_conversation(&mut guard, &MEMORY_ACTIVITY, group_id, idle_secs).await;
+        touch_conversation(&mut guard, &MEMORY_ACTIVITY, group_id, config.chat().max_live_conversations()).await;
     }
 
-    // 记录对话记忆
-    if let Err(e) = MEMORY_MANAGER.add_conversation_memory(
-        group_id,
-        &format!("{}: {}", nickname, message),
-        "group_chat"
-    ).await {
-        eprintln!("[ERROR] 群聊记忆记录失败 (群组: {}): {}", group_id, e);
+    // 命中固定回复时直接回复，不调用模型
+    if let Some(reply) = config::get().chat().canned_reply(message) {
+        if send(build_group_reply(reply, reply_to)).await.is_ok() {
+            println!("[INFO] 群聊固定回复已发送 (群组: {}): {}", group_id, reply);
+        }
+        return;
     }
 
     // 获取相关记忆来增强上下文
     let contextual_memories = MEMORY_MANAGER.get_contextual_memories(group_id, "group_chat", 5).await;
     let recent_memories = MEMORY_MANAGER.get_recent_memories(10).await;
 
-    match guard.get_mut(&group_id) {
+    // 按消息分类选择模型，请求成功后改为实际生成回复的模型（可能是备用模型）
+    let message_class = routing::classify(message);
+    let mut model = config::get().server_config().model_for(Some(message_class)).to_string();
+    debug::log(format_args!(
+        "群聊回复决策 (群组: {}): 情绪 {}, 相关记忆 {} 条, 模型 {}",
+        group_id,
+        mood.as_ref().map(|m| m.to_string()).unwrap_or_else(|| "分析失败".to_string()),
+        contextual_memories.len(),
+        model
+    ));
+
+    // 在副本上处理本轮对话，完成后写回；记录本轮用户消息的位置，失败时据此撤回
+    let existing = get_memory().lock().await.get(&group_id).cloned();
+    let turn_start = existing.as_ref().map_or(1, Vec::len);
+    let mut vec = match existing {
         None => {
             // 创建新的对话记录，包含相关记忆
-            let mut system_prompt = config::get().prompt().system_prompt().to_string();
-            
-            // 添加相关记忆到系统提示中
-            if !contextual_memories.is_empty() {
-                system_prompt.push_str("\n\n相关记忆：");
-                for memory in contextual_memories.iter().take(3) {
-                    system_prompt.push_str(&format!("\n- {}", memory.content));
-                }
-            }
-
-            guard.insert(
-                group_id,
-                vec![
-                    BotMemory {
-                        role: Roles::System,
-                        content: system_prompt,
-                    },
-                    BotMemory {
-                        role: Roles::User,
-                        content: format!("{}:{}", nickname, message),
-                    },
-                ],
-            );
-            if let Some(vec) = guard.get_mut(&group_id) {
-                println!("[INFO] 群聊新对话开始 (群组: {}, 用户: {})", group_id, nickname);
-                let model = params_model(vec).await;
-                if !model.content.contains("[sp]") {
-                    bot.send_group_msg(group_id, &model.content);
-                    println!("[INFO] 群聊消息已发送 (群组: {}): {}", group_id, model.content);
-                };
-                vec.push(BotMemory {
-                    role: Roles::Assistant,
-                    content: model.content,
-                });
-
-                // 检查并限制记忆大小
-                limit_memory_size(vec);
-            };
+            let system_prompt = build_group_system_prompt(group_id, &contextual_memories).await;
+            println!("[INFO] 群聊新对话开始 (群组: {}, 用户: {})", group_id, nickname);
+            vec![
+                BotMemory {
+                    role: Roles::System,
+                    content: system_prompt,
+                },
+                BotMemory {
+                    role: Roles::User,
+                    content: format!("{}:{}", nickname, message),
+                },
+            ]
         }
-        Some(vec) => {
+        Some(mut vec) => {
             // 添加新的用户消息
             vec.push(BotMemory {
                 role: Roles::User,
@@ -195,20 +323,348 @@ pub async fn control_model(
 
             // 在生成回复前，检查是否需要添加相关记忆
             if should_add_memory_context(vec.len(), &recent_memories) {
-                add_memory_context_to_messages(vec, &contextual_memories);
+                add_memory_context_to_messages(&mut vec, &contextual_memories);
             }
-
             println!("[INFO] 群聊继续对话 (群组: {}, 用户: {})", group_id, nickname);
-            let resp = params_model(vec).await;
-            if !resp.content.contains("[sp]") {
-                bot.send_group_msg(group_id, &resp.content);
-                println!("[INFO] 群聊消息已发送 (群组: {}): {}", group_id, resp.content);
-            };
-            vec.push(resp);
+            vec
+        }
+    };
+
+    let overrides = ModelOverrides {
+        message_class: Some(message_class),
+        usage_scope: Some(UsageScope::Group(group_id)),
+        ..group_model_overrides(group_id).await
+    };
+    match params_model(&vec, overrides).await {
+        Ok(ModelReply { memory: mut resp, model: answered_by }) => {
+            model = answered_by;
+            apply_reply_length_cap(&mut resp.content, group_id);
+            deliver_group_reply(send, group_id, &mut vec, turn_start, resp, reply_to).await;
+        }
+        Err(e) => report_group_failure(send, &mut vec, turn_start, &e, reply_to).await,
+    }
+
+    // 检查并限制记忆大小
+    history::trim_history(&mut vec, config::get().chat().group_history_max_messages(group_id));
+    get_memory().lock().await.insert(group_id, vec);
+
+    // 调试模式下在回复后附上内部决策信息
+    if debug::take_turn(group_id).await {
+        let note = describe_debug_decisions(group_id, mood.as_ref(), &contextual_memories, &model).await;
+        let _ = send(Message::from(note)).await;
+    }
+}
+
+/// 获取对话的处理锁，保证同一群组或用户的消息按顺序处理
+/// 
+/// # 参数
+/// * `locks` - 群聊或私聊的处理锁存储
+/// * `id` - 群组ID或用户ID
+async fn turn_lock(locks: &Mutex<HashMap<i64, Arc<Mutex<()>>>>, id: i64) -> Arc<Mutex<()>> {
+    let mut locks = locks.lock().await;
+    // 清理没有在使用的锁
+    locks.retain(|key, lock| *key == id || Arc::strong_count(lock) > 1);
+    Arc::clone(locks.entry(id).or_default())
+}
+
+/// 组装群聊新对话的系统提示
+/// 
+/// 包含群聊人设、相关记忆、群内熟人、群内梗、回复风格和回复语言指令
+/// 
+/// # 参数
+/// * `group_id` - 群组ID
+/// * `contextual_memories` - 与群聊相关的记忆
+async fn build_group_system_prompt(group_id: i64, contextual_memories: &[crate::memory::MemoryEntry]) -> String {
+    let mut system_prompt = config::get().prompt().group_persona_prompt();
+    
+    // 添加相关记忆到系统提示中
+    if !contextual_memories.is_empty() {
+        system_prompt.push_str("\n\n相关记忆：");
+        for memory in contextual_memories.iter().take(3) {
+            system_prompt.push_str(&format!("\n- {}", format_memory_line(memory)));
+        }
+    }
+    // 启用人格核心时，带上与群内熟人的关系，让私聊中建立的关系延续到群聊
+    let config = config::get();
+    if config.prompt().core_prompt().is_some() {
+        let familiar = describe_familiar_members(group_id).await;
+        if !familiar.is_empty() {
+            system_prompt.push_str(&format!("\n\n群里你熟悉的人：\n{}", familiar.join("\n")));
+        }
+    }
+    // 添加群内梗，让机器人能接上群里的老梗
+    if config.memory().group_lore_limit() > 0 {
+        let lore = MEMORY_MANAGER.get_group_lore(
+            group_id,
+            chrono::Duration::days(config.memory().group_lore_days() as i64),
+            config.memory().group_lore_limit(),
+        ).await;
+        if !lore.is_empty() {
+            system_prompt.push_str(&format!("\n\n群里常聊的梗和话题：{}", lore.join("、")));
+        }
+    }
+    if let Some(directive) = config::get().chat().reply_style(group_id).directive() {
+        system_prompt.push_str(&format!("\n\n{}", directive));
+    }
+    apply_reply_language_override(&mut system_prompt);
+    system_prompt
+}
+
+/// 预览群聊系统提示
+/// 
+/// 群内已有对话时返回当前对话实际使用的系统提示，否则按新对话组装，不调用模型
+pub async fn preview_group_prompt(group_id: i64) -> String {
+    if let Some(prompt) = MEMORY.lock().await.get(&group_id).and_then(|history| history.first()) {
+        return format!("当前对话的系统提示：\n{}", prompt.content);
+    }
+
+    let contextual_memories = MEMORY_MANAGER.get_contextual_memories(group_id, "group_chat", 5).await;
+    format!("新对话的系统提示：\n{}", build_group_system_prompt(group_id, &contextual_memories).await)
+}
+
+/// 预览私聊系统提示
+/// 
+/// 与该用户已有对话时返回当前对话实际使用的系统提示，否则按新对话组装，不调用模型
+pub async fn preview_private_prompt(user_id: i64) -> String {
+    if let Some(prompt) = get_private_message_memory().lock().await.get(&user_id).and_then(|history| history.first()) {
+        return format!("当前对话的系统提示：\n{}", prompt.content);
+    }
+
+    let user_profile = MEMORY_MANAGER.get_user_profile(user_id).await;
+    let contextual_memories = MEMORY_MANAGER.get_contextual_memories(user_id, "private_chat", 3).await;
+    let personality = MEMORY_MANAGER.get_bot_personality().await;
+    format!(
+        "新对话的系统提示：\n{}",
+        generate_personalized_system_prompt(&user_profile, &personality, &contextual_memories).await
+    )
+}
+
+/// 描述群聊当前对话记得的内容
+/// 
+/// 处理 `#还记得吗` 命令
+pub async fn describe_group_memory_window(group_id: i64) -> String {
+    history::describe_window(MEMORY.lock().await.get(&group_id).map(Vec::as_slice))
+}
+
+/// 描述私聊当前对话记得的内容
+/// 
+/// 处理 `#还记得吗` 命令
+pub async fn describe_private_memory_window(user_id: i64) -> String {
+    history::describe_window(get_private_message_memory().lock().await.get(&user_id).map(Vec::as_slice))
+}
+
+/// 刷新群聊当前对话的系统提示
+/// 
+/// 重新检索相关记忆并重建系统提示，保留已有的对话内容
+/// 
+/// # 返回值
+/// 回复给用户的结果说明
+pub async fn refresh_group_context(group_id: i64) -> String {
+    let contextual_memories = MEMORY_MANAGER.get_contextual_memories(group_id, "group_chat", 5).await;
+    let system_prompt = build_group_system_prompt(group_id, &contextual_memories).await;
+    match MEMORY.lock().await.get_mut(&group_id).and_then(|history| history.first_mut()) {
+        Some(prompt) => {
+            prompt.content = system_prompt;
+            format!("已刷新对话记忆，注入了 {} 条相关记忆", contextual_memories.len().min(3))
+        }
+        None => "当前没有进行中的对话，新对话会自动使用最新记忆".to_string(),
+    }
+}
+
+/// 刷新私聊当前对话的系统提示
+/// 
+/// 重新读取用户档案和相关记忆并重建系统提示，保留已有的对话内容
+/// 
+/// # 返回值
+/// 回复给用户的结果说明
+pub async fn refresh_private_context(user_id: i64) -> String {
+    let user_profile = MEMORY_MANAGER.get_user_profile(user_id).await;
+    let contextual_memories = MEMORY_MANAGER.get_contextual_memories(user_id, "private_chat", 3).await;
+    let personality = MEMORY_MANAGER.get_bot_personality().await;
+    let system_prompt = generate_personalized_system_prompt(&user_profile, &personality, &contextual_memories).await;
+    match get_private_message_memory().lock().await.get_mut(&user_id).and_then(|history| history.first_mut()) {
+        Some(prompt) => {
+            prompt.content = system_prompt;
+            "已刷新对话记忆".to_string()
+        }
+        None => "当前没有进行中的对话，新对话会自动使用最新记忆".to_string(),
+    }
+}
+
+/// 描述群内近期活跃且关系较好的成员
+/// 
+/// 只包含关系阶段为朋友及以上的成员，最多5人
+async fn describe_familiar_members(group_id: i64) -> Vec<String> {
+    let Some(group_profile) = MEMORY_MANAGER.get_group_profile(group_id).await else {
+        return Vec::new();
+    };
+
+    let mut familiar = Vec::new();
+    for user_id in group_profile.recently_active_members(chrono::Duration::days(1)) {
+        let Some(profile) = MEMORY_MANAGER.get_user_profile(user_id).await else {
+            continue;
+        };
+        if profile.relationship_tier() == RelationshipTier::Acquaintance {
+            continue;
+        }
+        familiar.push(format!("- {}（关系等级 {}/10）", profile.display_name(), profile.relationship_level));
+        if familiar.len() >= 5 {
+            break;
+        }
+    }
+    familiar
+}
 
-            // 检查并限制记忆大小
-            limit_memory_size(vec);
+/// 发送群聊回复，并在确认发送成功后写入对话历史
+/// 
+/// 回复为 `[sp]` 时不发送但仍记录，表示机器人选择了沉默；
+/// 发送失败时不记录回复，同时撤回本轮的用户消息，
+/// 避免重试时对话历史中出现重复的用户消息或回复
+/// 
+/// # 参数
+/// * `send` - 向群组发送一条消息
+/// * `group_id` - 群组ID
+/// * `history` - 群组对话历史
+/// * `turn_start` - 本轮用户消息在对话历史中的位置
+/// * `reply` - 模型生成的回复
+/// * `reply_to` - 需要引用的原消息ID
+async fn deliver_group_reply<F>(
+    send: &impl Fn(Message) -> F,
+    group_id: i64,
+    history: &mut Vec<BotMemory>,
+    turn_start: usize,
+    reply: BotMemory,
+    reply_to: Option<i32>,
+) where
+    F: Future<Output = anyhow::Result<i32>>,
+{
+    if !reply.content.contains("[sp]") {
+        wait_before_reply().await;
+        if send(build_group_reply(&reply.content, reply_to)).await.is_err() {
+            rollback_turn(history, turn_start);
+            return;
         }
+        println!("[INFO] 群聊消息已发送 (群组: {}): {}", group_id, reply.content);
+    }
+    history.push(reply);
+}
+
+/// 模型请求失败时向群聊发送提示
+/// 
+/// 提示不写入对话上下文，本轮写入的消息也会撤回，避免失败的轮次影响后续回复
+async fn report_group_failure<F>(
+    send: &impl Fn(Message) -> F,
+    history: &mut Vec<BotMemory>,
+    turn_start: usize,
+    error: &ProviderError,
+    reply_to: Option<i32>,
+) where
+    F: Future<Output = anyhow::Result<i32>>,
+{
+    rollback_turn(history, turn_start);
+    let _ = send(build_group_reply(error.user_message(), reply_to)).await;
+}
+
+/// 发送回复前等待一段带随机抖动的时间，避免回复节奏过于机械
+async fn wait_before_reply() {
+    let config = config::get();
+    let delay = jittered_delay(
+        std::time::Duration::from_millis(config.chat().reply_delay_ms()),
+        config.chat().reply_delay_jitter(),
+    );
+    if !delay.is_zero() {
+        kovi::tokio::time::sleep(delay).await;
+    }
+}
+
+/// 为延迟乘上随机抖动系数
+/// 
+/// # 参数
+/// * `base` - 基础延迟
+/// * `jitter` - 抖动比例，系数在 `[1 - jitter, 1 + jitter]` 内均匀分布
+fn jittered_delay(base: std::time::Duration, jitter: f64) -> std::time::Duration {
+    use std::hash::{BuildHasher, Hasher};
+
+    if base.is_zero() || jitter <= 0.0 {
+        return base;
+    }
+    // 每个 RandomState 使用不同的随机种子，足够用于延迟抖动
+    let sample = std::collections::hash_map::RandomState::new().build_hasher().finish();
+    let unit = sample as f64 / u64::MAX as f64;
+    base.mul_f64(1.0 - jitter + 2.0 * jitter * unit)
+}
+
+/// 撤回本轮对话写入历史的所有消息
+/// 
+/// 按本轮开始前记录的位置截断，即使用户消息之后还有其他消息也能完整撤回，
+/// 用于回复发送失败时保持对话历史与实际发出的消息一致
+/// 
+/// # 参数
+/// * `history` - 对话历史
+/// * `turn_start` - 本轮用户消息在对话历史中的位置
+fn rollback_turn(history: &mut Vec<BotMemory>, turn_start: usize) {
+    history.truncate(turn_start);
+}
+
+/// 生成调试信息
+/// 
+/// 包含本轮分析出的情绪、可注入的相关记忆和使用的模型参数
+/// 
+/// # 参数
+/// * `group_id` - 群组ID
+/// * `mood` - 本轮分析出的情绪
+/// * `memories` - 可注入的相关记忆
+/// * `model` - 本轮实际使用的模型
+async fn describe_debug_decisions(
+    group_id: i64,
+    mood: Option<&Mood>,
+    memories: &[crate::memory::MemoryEntry],
+    model: &str,
+) -> String {
+    let config = config::get();
+    let overrides = group_model_overrides(group_id).await;
+    let mut lines = vec![
+        "🔧 调试信息".to_string(),
+        format!("情绪: {}", mood.map(|m| m.to_string()).unwrap_or_else(|| "分析失败".to_string())),
+        format!(
+            "模型: {} (温度 {}, top_p {})",
+            model,
+            overrides.temperature.unwrap_or(config.server_config().temperature()),
+            overrides.top_p.map(|p| p.to_string()).unwrap_or_else(|| "默认".to_string())
+        ),
+        format!("相关记忆: {} 条", memories.len()),
+    ];
+    for memory in memories.iter().take(3) {
+        lines.push(format!("- [{}] {}", memory.importance, format_memory_line(memory)));
+    }
+    lines.join("\n")
+}
+
+/// 构建群聊回复消息
+/// 
+/// 启用引用回复且原消息ID可用时，回复会引用触发它的原消息，否则发送普通消息
+/// 
+/// # 参数
+/// * `content` - 回复内容
+/// * `reply_to` - 触发回复的原消息ID
+fn build_group_reply(content: &str, reply_to: Option<i32>) -> Message {
+    quoted_message(content, reply_to.filter(|_| config::get().chat().quote_reply()))
+}
+
+/// 构建引用指定消息的回复，没有可引用的消息ID时发送普通消息
+fn quoted_message(content: &str, quote: Option<i32>) -> Message {
+    match quote {
+        Some(message_id) => Message::new().add_reply(message_id).add_text(content),
+        None => Message::from(content),
+    }
+}
+
+/// 按群组回复风格截断过长的回复
+fn apply_reply_length_cap(content: &mut String, group_id: i64) {
+    if let Some(max_chars) = config::get().chat().reply_style(group_id).max_reply_chars()
+        && content.chars().count() > max_chars
+    {
+        *content = format!("{}…", content.chars().take(max_chars).collect::<String>());
     }
 }
 
@@ -234,7 +690,7 @@ fn should_add_memory_context(current_length: usize, recent_memories: &[crate::me
 /// # 参数
 /// * `messages` - 消息列表（可变引用）
 /// * `memories` - 要添加的相关记忆
-fn add_memory_context_to_messages(messages: &mut Vec<BotMemory>, memories: &[crate::memory::MemoryEntry]) {
+fn add_memory_context_to_messages(messages: &mut [BotMemory], memories: &[crate::memory::MemoryEntry]) {
     if memories.is_empty() {
         return;
     }
@@ -244,124 +700,526 @@ fn add_memory_context_to_messages(messages: &mut Vec<BotMemory>, memories: &[cra
         let memory_context = format!("\n\n相关记忆：\n{}", 
             memories.iter()
                 .take(2)
-                .map(|m| format!("- {}", m.content))
+                .map(|m| format!("- {}", format_memory_line(m)))
                 .collect::<Vec<_>>()
                 .join("\n")
         );
         
-        if let Some(system_msg) = messages.first_mut() {
-            if system_msg.role == Roles::System {
-                system_msg.content.push_str(&memory_context);
-            }
+        if let Some(system_msg) = messages.first_mut()
+            && system_msg.role == Roles::System
+        {
+            system_msg.content.push_str(&memory_context);
         }
     }
 }
 
-/// 限制对话记忆大小
+/// 格式化注入上下文的记忆内容
 /// 
-/// 保持最多25条记录（包括system prompt），防止内存过度使用
-/// 优先保留最近的对话内容
-/// 
-/// # 参数
-/// * `messages` - 消息列表（可变引用）
-fn limit_memory_size(messages: &mut Vec<BotMemory>) {
-    if messages.len() <= MAX_MEMORY_SIZE {
-        return;
+/// 机器人自己产生的记忆（如主动发起的话题）会加上标注，避免被模型误认为用户发言
+fn format_memory_line(memory: &crate::memory::MemoryEntry) -> String {
+    if memory.is_bot_authored() {
+        format!("（我自己说过）{}", memory.content)
+    } else {
+        memory.content.clone()
     }
-
-    // 保留system prompt (第一条消息)
-    let system_message = messages[0].clone();
-
-    // 计算需要保留的消息数量（除了system prompt）
-    let keep_count = MAX_MEMORY_SIZE - 1;
-
-    // 保留最近的对话
-    let recent_messages = messages.drain(messages.len() - keep_count..).collect::<Vec<_>>();
-
-    // 重新构建消息列表
-    messages.clear();
-    messages.push(system_message);
-    messages.extend(recent_messages);
-
-    println!("[INFO] 对话记忆已清理，当前保留 {} 条记录", messages.len());
 }
 
 /// 调用AI模型生成回复
 /// 
 /// 向配置的AI模型发送请求，生成智能回复。包括以下功能：
-/// - 添加情绪化思考过程
+/// - 添加情绪化思考过程（只加入本次请求，不写入对话历史）
 /// - 发送HTTP请求到AI模型
 /// - 解析响应并清理格式
 /// 
 /// # 参数
-/// * `messages` - 对话消息列表（可变引用）
+/// * `messages` - 对话消息列表
+/// * `overrides` - 本次对话的模型参数覆盖
 /// 
 /// # 返回值
-/// 生成的机器人回复消息
+/// 生成的机器人回复消息及实际生成回复的模型
 /// 
 /// # 错误处理
-/// 如果API调用失败，返回默认错误消息
-pub async fn params_model(messages: &mut Vec<BotMemory>) -> BotMemory {
+/// 连接失败、被限流或服务端错误时按指数退避重试（限流时优先按 `Retry-After` 等待），
+/// 仍然失败时依次尝试配置的备用模型，全部失败后记录日志并返回错误，由调用方向用户发送提示
+pub async fn params_model(messages: &[BotMemory], overrides: ModelOverrides) -> Result<ModelReply, ProviderError> {
     let config = config::get();
     let server_config = config.server_config();
 
-    // 添加思考过程
-    let thinking_prompt = generate_thinking_prompt(messages).await;
+    // 安全模式下不调用模型接口
+    if config.chat().safe_mode() {
+        return Ok(ModelReply {
+            memory: BotMemory {
+                role: Roles::Assistant,
+                content: SAFE_MODE_REPLY.to_string(),
+            },
+            model: "无（安全模式）".to_string(),
+        });
+    }
+
+    // 添加思考过程，声明了模型类别时只保留情绪指令
+    let thinking_prompt = if server_config.injects_thinking_prompt() {
+        generate_thinking_prompt(messages).await
+    } else {
+        String::new()
+    };
+    let mut instructions = Vec::new();
     if !thinking_prompt.is_empty() {
-        messages.push(BotMemory {
+        instructions.push(format!("思考过程：{}\n请基于以上思考给出回复。", thinking_prompt));
+    }
+
+    // 情绪强烈时要求模型在语气上明显体现
+    let personality = MEMORY_MANAGER.get_bot_personality().await;
+    if let Some(directive) = mood_system::high_intensity_directive(&personality) {
+        instructions.push(directive.to_string());
+    }
+
+    // 指令只附加在本次请求的末尾，不写入对话历史，避免逐轮累积
+    let mut request_messages = messages.to_vec();
+    if !instructions.is_empty() {
+        request_messages.push(BotMemory {
             role: Roles::System,
-            content: format!("思考过程：{}\n请基于以上思考给出回复。", thinking_prompt),
+            content: instructions.join("\n"),
         });
     }
 
-    let bot_conf = ModelConf {
-        model: server_config.model_name(),
-        messages,
-        stream: false,
-        temperature: 0.7,
+    request_reply(&config, &request_messages, overrides).await
+}
+
+/// 请求模型回复，处理工具调用和空回复重试
+/// 
+/// # 参数
+/// * `config` - 当前配置，提供服务器和提示配置
+/// * `request_messages` - 本次请求的完整消息列表
+/// * `overrides` - 本次对话的模型参数覆盖
+async fn request_reply(
+    config: &config::ModelConfig,
+    request_messages: &[BotMemory],
+    overrides: ModelOverrides,
+) -> Result<ModelReply, ProviderError> {
+    let server_config = config.server_config();
+
+    // 先合并额外请求头，鉴权和内容类型始终以内置值为准
+    let mut header = match server_config.extra_header_map() {
+        Ok(extra_headers) => extra_headers,
+        Err(e) => {
+            eprintln!("[ERROR] 额外请求头无效，已忽略: {}", e);
+            HeaderMap::new()
+        }
     };
-    let mut header = HeaderMap::new();
-    let token = std::env::var("BOT_API_TOKEN").expect("BOT_API_TOKEN must be set");
-    header.insert(AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+    let token = server_config.api_token().map_err(|e| ProviderError::Auth(e.to_string())).inspect_err(ProviderError::log)?;
+    let authorization = format!("Bearer {}", token)
+        .parse()
+        .map_err(|_| ProviderError::Auth("API令牌包含请求头中不允许的字符".to_string()))
+        .inspect_err(ProviderError::log)?;
+    header.insert(AUTHORIZATION, authorization);
     header.insert(CONTENT_TYPE, "application/json".parse().unwrap());
-    let client = Client::new();
-    let resp = client
-        .post(server_config.url())
-        .headers(header)
-        .json(&bot_conf)
-        .send()
-        .await
-        .unwrap();
-    let text = resp.json::<Value>().await.unwrap();
-    let bot_content = text
-        .get("choices")
-        .and_then(|c| c.get(0))
-        .and_then(|c| c.get("message"))
-        .and_then(|m| m.get("content"))
-        .and_then(|c| c.as_str())
-        .unwrap_or("余额不足或者文档有更改")
-        .trim()
-        .replace("芸汐：", "")
-        .to_string();
-    BotMemory {
-        role: Roles::Assistant,
-        content: bot_content,
+
+    let model = server_config.model_for(overrides.message_class);
+    if model != server_config.model_name() {
+        println!("[INFO] 消息分类为 {:?}，使用模型 {}", overrides.message_class, model);
+    }
+
+    // 模型返回空回复时略微提高温度重试，仍然为空则保持沉默
+    // 模型请求调用工具时执行工具并交回结果，工具调用的中间消息不写入对话历史
+    let mut overrides = overrides;
+    let mut tool_exchange = Vec::new();
+    let mut tool_rounds = 0;
+    let mut retry = 0;
+    loop {
+        let bot_conf = build_request_payload(request_messages, &tool_exchange, server_config, &overrides);
+        let Completion { message, confidence, usage, model } =
+            request_with_fallbacks(server_config, &header, bot_conf).await.inspect_err(ProviderError::log)?;
+        usage::record(overrides.usage_scope, usage).await;
+
+        if let Some(tool_calls) = message.get("tool_calls").and_then(Value::as_array)
+            && !tool_calls.is_empty()
+            && tool_rounds < server_config.max_tool_rounds()
+        {
+            let results: Vec<Value> = tool_calls.iter().map(run_tool_call).collect();
+            tool_exchange.push(message);
+            tool_exchange.extend(results);
+            tool_rounds += 1;
+            continue;
+        }
+
+        let bot_content = config.prompt().strip_name_prefix(
+            message.get("content").and_then(Value::as_str).unwrap_or_default().trim(),
+        );
+        let bot_content = if server_config.strips_think_tags() {
+            strip_think_tags(&bot_content)
+        } else {
+            bot_content
+        };
+        if !bot_content.is_empty() {
+            return Ok(ModelReply {
+                memory: BotMemory {
+                    role: Roles::Assistant,
+                    content: apply_confidence_threshold(bot_content, confidence, server_config),
+                },
+                model,
+            });
+        }
+
+        if retry >= server_config.empty_reply_retries() {
+            eprintln!("[ERROR] 模型多次返回空回复，本轮保持沉默");
+            return Ok(ModelReply {
+                memory: BotMemory {
+                    role: Roles::Assistant,
+                    content: "[sp]".to_string(),
+                },
+                model,
+            });
+        }
+        retry += 1;
+        let temperature = overrides.temperature.unwrap_or(server_config.temperature());
+        overrides.temperature = Some((temperature + EMPTY_REPLY_TEMPERATURE_STEP).min(2.0));
+        println!("[INFO] 模型返回空回复 (第 {} 次)，提高温度后重试", retry);
     }
 }
 
-/// 生成情绪化思考过程
+/// 按回复置信度处理回复
 /// 
-/// 根据机器人的当前人格状态生成个性化的思考过程，包括：
-/// - 基于当前情绪调整思考风格
-/// - 结合相关记忆增强上下文理解
-/// - 根据能量水平调整思考深度
+/// 置信度低于配置的阈值时附加不确定的说明或改为保持沉默；未检查置信度或服务未返回 logprobs 时原样返回
 /// 
 /// # 参数
-/// * `_messages` - 对话消息列表（当前未使用）
+/// * `content` - 模型回复内容
+/// * `confidence` - 回复的平均token概率
+/// * `server_config` - 服务器配置，提供阈值和处理方式
+fn apply_confidence_threshold(content: String, confidence: Option<f64>, server_config: &config::ServerConfig) -> String {
+    let Some(confidence) = confidence.filter(|_| server_config.checks_reply_confidence()) else {
+        return content;
+    };
+    if confidence >= server_config.min_reply_confidence() {
+        return content;
+    }
+
+    println!(
+        "[INFO] 回复置信度 {:.2} 低于阈值 {:.2}，按 {:?} 处理",
+        confidence,
+        server_config.min_reply_confidence(),
+        server_config.low_confidence_action()
+    );
+    match server_config.low_confidence_action() {
+        config::LowConfidenceAction::Hedge => format!("{}{}", content, server_config.low_confidence_hedge()),
+        config::LowConfidenceAction::Silence => "[sp]".to_string(),
+    }
+}
+
+/// 执行模型请求的一次工具调用
+/// 
+/// # 参数
+/// * `tool_call` - 模型回复中 `tool_calls` 的一项
 /// 
 /// # 返回值
-/// 生成的思考过程文本
-async fn generate_thinking_prompt(_messages: &[BotMemory]) -> String {
+/// 交回模型的 `tool` 角色消息
+fn run_tool_call(tool_call: &Value) -> Value {
+    let name = tool_call["function"]["name"].as_str().unwrap_or_default();
+    let arguments = tool_call["function"]["arguments"].as_str().unwrap_or_default();
+    let result = tools::dispatch(name, arguments);
+    println!("[INFO] 模型调用工具 {}({}) -> {}", name, arguments, result);
+    json!({
+        "role": "tool",
+        "tool_call_id": tool_call["id"],
+        "content": result,
+    })
+}
+
+/// 请求模型补全，主模型失败时依次尝试备用模型
+/// 
+/// 中间失败的错误只记录日志，全部失败时返回最后一个错误
+/// 
+/// # 参数
+/// * `server_config` - 服务器配置，提供主模型和备用模型
+/// * `header` - 主模型的请求头，备用模型配置了令牌时替换其中的鉴权头
+/// * `bot_conf` - 请求体，切换到备用模型时替换其中的模型名称
+async fn request_with_fallbacks<'a>(
+    server_config: &'a config::ServerConfig,
+    header: &HeaderMap,
+    mut bot_conf: ModelConf<'a>,
+) -> Result<Completion, ProviderError> {
+    let mut result = request_completion(server_config, server_config.url(), header, &bot_conf).await;
+    for fallback in server_config.fallback_models() {
+        let Err(error) = &result else {
+            break;
+        };
+        error.log();
+        println!("[INFO] 模型 {} 请求失败，改用备用模型 {}", bot_conf.model, fallback.model_name());
+
+        let mut fallback_header = header.clone();
+        if let Some(token) = fallback.api_token() {
+            match format!("Bearer {}", token).parse() {
+                Ok(value) => {
+                    fallback_header.insert(AUTHORIZATION, value);
+                }
+                Err(_) => {
+                    result = Err(ProviderError::Auth(format!("备用模型 {} 的API令牌无效", fallback.model_name())));
+                    continue;
+                }
+            }
+        }
+        bot_conf.model = fallback.model_name();
+        result = request_completion(server_config, fallback.url(), &fallback_header, &bot_conf).await;
+    }
+
+    if let Ok(completion) = &mut result {
+        conversation_log::record(&bot_conf, &completion.message);
+        completion.model = bot_conf.model.to_string();
+    }
+    result
+}
+
+/// 一次模型补全的结果
+struct Completion {
+    /// 模型回复的消息对象（包含 `content` 或 `tool_calls`）
+    message: Value,
+    /// 回复各token概率的平均值，服务未返回 logprobs 时为 `None`
+    confidence: Option<f64>,
+    /// 本次请求消耗的token数，服务未返回 `usage` 时为 `None`
+    usage: Option<TokenUsage>,
+    /// 生成回复的模型
+    model: String,
+}
+
+/// 计算回复的平均token概率
+/// 
+/// # 参数
+/// * `choice` - 响应中 `choices` 的一项，读取其中的 `logprobs.content[].logprob`
+/// 
+/// # 返回值
+/// 各token概率的平均值 (0.0-1.0)，没有 logprobs 时返回 `None`
+fn average_confidence(choice: &Value) -> Option<f64> {
+    let logprobs: Vec<f64> = choice
+        .get("logprobs")?
+        .get("content")?
+        .as_array()?
+        .iter()
+        .filter_map(|token| token.get("logprob").and_then(Value::as_f64))
+        .collect();
+    if logprobs.is_empty() {
+        return None;
+    }
+    Some(logprobs.iter().map(|logprob| logprob.exp()).sum::<f64>() / logprobs.len() as f64)
+}
+
+/// 请求一次模型补全
+/// 
+/// 连接失败、被限流或服务端错误（5xx）时按指数退避重试，被限流时优先按服务端要求的时间等待；
+/// 超时不再重试，避免用户等待过久；其他错误解析响应体后直接返回
+/// 
+/// # 返回值
+/// 成功时返回模型回复的消息对象（包含 `content` 或 `tool_calls`）及其置信度，失败时返回解析出的服务错误
+async fn request_completion(
+    server_config: &config::ServerConfig,
+    url: &str,
+    header: &HeaderMap,
+    bot_conf: &ModelConf<'_>,
+) -> Result<Completion, ProviderError> {
+    let mut attempt = 0;
+    let resp = loop {
+        let backoff = RETRY_DEFAULT_BACKOFF * 2u32.saturating_pow(attempt);
+        let (error, wait) = match send_model_request(server_config, url, header, bot_conf).await {
+            Ok(resp) if resp.status() == StatusCode::TOO_MANY_REQUESTS => {
+                let wait = parse_retry_after(resp.headers()).unwrap_or(backoff);
+                let body = resp.text().await.unwrap_or_default();
+                let detail = format!("需等待 {} 秒: {}", wait.as_secs(), body.trim());
+                (ProviderError::RateLimited(detail), wait)
+            }
+            Ok(resp) if resp.status().is_server_error() => {
+                let status = resp.status();
+                let body = resp.text().await.unwrap_or_default();
+                (ProviderError::from_response(status, &body), backoff)
+            }
+            Ok(resp) => break resp,
+            Err(e) if e.is_timeout() => return Err(ProviderError::Timeout(e.to_string())),
+            Err(e) => (ProviderError::Other(format!("请求发送失败: {}", e)), backoff),
+        };
+
+        if attempt >= server_config.request_retries() || wait > RETRY_MAX_WAIT {
+            eprintln!("[ERROR] 模型请求已重试 {} 次仍然失败", attempt);
+            return Err(error);
+        }
+        println!("[INFO] 模型请求失败，{} 毫秒后重试: {:?}", wait.as_millis(), error);
+        kovi::tokio::time::sleep(wait).await;
+        attempt += 1;
+    };
+
+    let status = resp.status();
+    let body = resp
+        .text()
+        .await
+        .map_err(|e| {
+            if e.is_timeout() {
+                ProviderError::Timeout(e.to_string())
+            } else {
+                ProviderError::Other(format!("读取响应失败: {}", e))
+            }
+        })?;
+    if !status.is_success() {
+        return Err(ProviderError::from_response(status, &body));
+    }
+
+    let text: Value = kovi::serde_json::from_str(&body)
+        .map_err(|e| ProviderError::Other(format!("响应不是有效的JSON: {}", e)))?;
+    let choice = text.get("choices").and_then(|c| c.get(0));
+    let message = choice
+        .and_then(|c| c.get("message"))
+        .filter(|m| m.get("content").is_some_and(Value::is_string) || m.get("tool_calls").is_some_and(Value::is_array))
+        .ok_or_else(|| ProviderError::from_response(status, &body))?;
+    Ok(Completion {
+        message: message.clone(),
+        confidence: choice.and_then(average_confidence),
+        usage: TokenUsage::from_response(&text),
+        model: bot_conf.model.to_string(),
+    })
+}
+
+/// 去除推理模型回复中的思考内容
+/// 
+/// 移除所有 `<think>...</think>` 块；只有结束标签时（部分服务不返回开始标签），
+/// 保留最后一个结束标签之后的内容
+/// 
+/// # 返回值
+/// 去除思考内容并去除首尾空白后的回复
+fn strip_think_tags(content: &str) -> String {
+    let mut rest = content;
+    if let Some(index) = rest.rfind("</think>")
+        && !rest[..index].contains("<think>")
+    {
+        rest = &rest[index + "</think>".len()..];
+    }
+
+    let mut stripped = String::new();
+    while let Some(start) = rest.find("<think>") {
+        stripped.push_str(&rest[..start]);
+        match rest[start..].find("</think>") {
+            Some(end) => rest = &rest[start + end + "</think>".len()..],
+            // 思考内容未结束（回复被截断），之后的内容全部丢弃
+            None => rest = "",
+        }
+    }
+    stripped.push_str(rest);
+    stripped.trim().to_string()
+}
+
+/// 构建模型请求体
+/// 
+/// 纯函数，不涉及网络和全局状态，便于单独检查组装出的请求内容
+/// 
+/// # 参数
+/// * `messages` - 发送给模型的对话消息
+/// * `tool_exchange` - 本轮工具调用产生的中间消息，追加在对话消息之后
+/// * `server_config` - 服务器配置，提供模型名称和是否启用工具调用
+/// * `overrides` - 模型参数覆盖，未覆盖的参数使用默认值，消息分类决定使用的模型
+/// 
+/// # 返回值
+/// 可直接序列化为JSON的请求体
+fn build_request_payload<'a>(
+    messages: &[BotMemory],
+    tool_exchange: &[Value],
+    server_config: &'a config::ServerConfig,
+    overrides: &ModelOverrides,
+) -> ModelConf<'a> {
+    let messages = messages
+        .iter()
+        .map(|memory| json!({ "role": memory.role, "content": memory.content }))
+        .chain(tool_exchange.iter().cloned())
+        .collect();
+    ModelConf {
+        model: server_config.model_for(overrides.message_class),
+        messages,
+        stream: false,
+        temperature: overrides.temperature.unwrap_or(server_config.temperature()),
+        top_p: overrides.top_p,
+        max_tokens: server_config.max_tokens(),
+        logprobs: server_config.checks_reply_confidence(),
+        tools: server_config.tools_enabled().then(tools::definitions),
+    }
+}
+
+/// 发送模型请求
+/// 
+/// 只负责HTTP发送，请求体和请求头由调用方组装。
+/// 超时时间按每次请求设置，重载配置后立即生效
+async fn send_model_request(
+    server_config: &config::ServerConfig,
+    url: &str,
+    headers: &HeaderMap,
+    payload: &ModelConf<'_>,
+) -> reqwest::Result<reqwest::Response> {
+    utils::http_client()
+        .post(url)
+        .timeout(std::time::Duration::from_secs(server_config.request_timeout_secs()))
+        .headers(headers.clone())
+        .json(payload)
+        .send()
+        .await
+}
+
+/// 解析 `Retry-After` 响应头
+/// 
+/// 支持秒数和HTTP日期两种格式，日期已过去时返回零
+/// 
+/// # 返回值
+/// 需要等待的时长，响应头不存在或无法解析时返回 `None`
+fn parse_retry_after(headers: &HeaderMap) -> Option<std::time::Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(std::time::Duration::from_secs(seconds));
+    }
+
+    let retry_at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = retry_at.signed_duration_since(Local::now());
+    Some(wait.to_std().unwrap_or_default())
+}
+
+/// 预热模型连接
+/// 
+/// 向配置的模型服务发送一次极小的请求，提前建立HTTP连接并验证鉴权，
+/// 降低第一条真实消息的冷启动延迟。响应内容会被丢弃
+/// 
+/// # 返回值
+/// 成功时返回本次预热请求的耗时，安全模式下返回错误
+pub async fn warmup_model() -> anyhow::Result<std::time::Duration> {
+    utils::ensure_online()?;
+    warmup_server(config::get().server_config()).await
+}
+
+/// 向指定的模型服务发送一次预热请求
+pub(crate) async fn warmup_server(server_config: &config::ServerConfig) -> anyhow::Result<std::time::Duration> {
+    let messages = vec![BotMemory {
+        role: Roles::User,
+        content: "你好".to_string(),
+    }];
+    let bot_conf = build_request_payload(&messages, &[], server_config, &ModelOverrides::default());
+
+    let token = server_config.api_token()?;
+    let mut header = server_config.extra_header_map()?;
+    header.insert(AUTHORIZATION, format!("Bearer {}", token).parse()?);
+    header.insert(CONTENT_TYPE, "application/json".parse()?);
+    let start = std::time::Instant::now();
+    let resp = send_model_request(server_config, server_config.url(), &header, &bot_conf)
+        .await
+        .context("预热请求发送失败")?;
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(anyhow::anyhow!("预热请求返回错误状态: {}", status));
+    }
+    Ok(start.elapsed())
+}
+
+/// 生成情绪化思考过程
+/// 
+/// 根据机器人的当前人格状态生成个性化的思考过程，包括：
+/// - 基于当前情绪调整思考风格
+/// - 结合相关记忆增强上下文理解
+/// - 根据能量水平调整思考深度
+/// 
+/// # 参数
+/// * `_messages` - 对话消息列表（当前未使用）
+/// 
+/// # 返回值
+/// 生成的思考过程文本
+async fn generate_thinking_prompt(_messages: &[BotMemory]) -> String {
     let personality = MEMORY_MANAGER.get_bot_personality().await;
     let recent_memories = MEMORY_MANAGER.get_recent_memories(5).await;
     
@@ -405,6 +1263,71 @@ fn instance_is_ban() -> &'static Mutex<HashMap<i64, bool>> {
     &IS_BANNED
 }
 
+/// 移除空闲过久的对话上下文，让长时间冷场后的消息开始新的对话
+/// 
+/// # 参数
+/// * `conversations` - 对话记忆存储
+/// * `activity` - 对应的活跃时间存储
+/// * `id` - 当前活跃的群组ID或用户ID
+/// * `idle_secs` - 空闲多少秒后过期，0表示不过期
+async fn expire_idle_conversation(
+    conversations: &mut HashMap<i64, Vec<BotMemory>>,
+    activity: &Mutex<HashMap<i64, Instant>>,
+    id: i64,
+    idle_secs: u64,
+) {
+    if idle_secs == 0 {
+        return;
+    }
+    let idle = activity.lock().await.get(&id).map(Instant::elapsed);
+    if idle.is_some_and(|idle| idle.as_secs() >= idle_secs) && conversations.remove(&id).is_some() {
+        println!("[INFO] 对话空闲超过 {} 秒，重新开始对话上下文: {}", idle_secs, id);
+    }
+}
+
+/// 记录对话的活跃时间，并在对话数量超出上限时淘汰最久未活跃的对话
+/// 
+/// 淘汰只移除内存中的对话上下文，长期记忆和用户档案不受影响。
+/// 调用时当前对话可能尚未创建，因此会为它预留一个位置
+/// 
+/// # 参数
+/// * `conversations` - 对话记忆存储
+/// * `activity` - 对应的活跃时间存储
+/// * `id` - 当前活跃的群组ID或用户ID
+/// * `max_conversations` - 对话数量上限，0表示不限制
+async fn touch_conversation(
+    conversations: &mut HashMap<i64, Vec<BotMemory>>,
+    activity: &Mutex<HashMap<i64, Instant>>,
+    id: i64,
+    max_conversations: usize,
+) {
+    let mut activity = activity.lock().await;
+    activity.insert(id, Instant::now());
+
+    if max_conversations == 0 {
+        return;
+    }
+
+    let limit = if conversations.contains_key(&id) {
+        max_conversations
+    } else {
+        max_conversations.saturating_sub(1)
+    };
+    while conversations.len() > limit {
+        let Some(oldest) = conversations
+            .keys()
+            .filter(|key| **key != id)
+            .min_by_key(|key| activity.get(key).copied())
+            .copied()
+        else {
+            break;
+        };
+        conversations.remove(&oldest);
+        activity.remove(&oldest);
+        println!("[INFO] 对话数量超出上限，已淘汰最久未活跃的对话上下文: {}", oldest);
+    }
+}
+
 fn get_memory() -> &'static Mutex<HashMap<i64, Vec<BotMemory>>> {
     &MEMORY
 }
@@ -413,13 +1336,13 @@ fn get_private_message_memory() -> &'static Mutex<HashMap<i64, Vec<BotMemory>>>
     &PRIVATE_MESSAGE_MEMORY
 }
 
-pub async fn silence(group_id: i64, message: &str, bot: Arc<RuntimeBot>, sender: String) {
+pub async fn silence(group_id: i64, message: &str, bot: Arc<RuntimeBot>, sender: String, reply_to: Option<i32>) {
     let mut banned_list = instance_is_ban().lock().await;
     match banned_list.get_mut(&group_id) {
         None => {
             if message.eq("#禁言") {
                 banned_list.insert(group_id, true);
-                bot.send_group_msg(group_id, "禁言成功");
+                utils::notify_group(&bot, group_id, "禁言成功").await;
             } else {
                 banned_list.insert(group_id, false);
             }
@@ -428,21 +1351,22 @@ pub async fn silence(group_id: i64, message: &str, bot: Arc<RuntimeBot>, sender:
             if !*is_ban {
                 if message.eq("#禁言") {
                     *is_ban = true;
-                    bot.send_group_msg(group_id, "禁言成功");
+                    utils::notify_group(&bot, group_id, "禁言成功").await;
                 } else {
-                    let mut guard = get_memory().lock().await;
-                    control_model(&mut guard, group_id, bot, sender, message).await;
+                    // 释放禁言状态锁后再处理消息，避免等待模型回复时阻塞其他群
+                    drop(banned_list);
+                    control_model(group_id, bot, sender, message, reply_to).await;
                 }
             } else if message.eq("#结束禁言") {
                 *is_ban = false;
-                bot.send_group_msg(group_id, "结束成功");
+                utils::notify_group(&bot, group_id, "结束成功").await;
             }
         }
     }
 }
 
 pub async fn send_sys_info(bot: Arc<RuntimeBot>, group_id: i64) {
-    match std::env::var("BOT_API_TOKEN") {
+    match config::get().server_config().api_token() {
         Ok(_) => {
             let system_info = utils::system_info_get();
             let option_status = bot.get_status().await;
@@ -452,7 +1376,7 @@ pub async fn send_sys_info(bot: Arc<RuntimeBot>, group_id: i64) {
                     .get("memory")
                     .and_then(|t| t.as_i64())
                     .unwrap_or(0);
-                bot.send_group_msg(
+                utils::notify_group(&bot, 
                     group_id,
                     format!(
                         "{} \n系统运行时间：{} \n{} \nLagrange占用: {}MB,\n当前使用的模型为:{}\n配置文件最后修改时间为:{}",
@@ -463,26 +1387,33 @@ pub async fn send_sys_info(bot: Arc<RuntimeBot>, group_id: i64) {
                         config::get().server_config().model_name(),
                         get_file_modified_time_formatted().unwrap_or(String::from("获取失败")),
                     ),
-                );
+                ).await;
             }
         }
-        Err(_) => bot.send_group_msg(group_id, "未设置token"),
+        Err(_) => utils::notify_group(&bot, group_id, "未设置token").await,
     }
 }
 
-pub async fn private_chat(
-    user_id: i64,
-    message: &str,
-    format_nickname: String,
-    bot: Arc<RuntimeBot>,
-) {
+/// 记录私聊消息
+/// 
+/// 分析消息情绪、写入对话记忆并更新用户档案，不触发回复
+/// 
+/// # 参数
+/// * `memory` - 写入对话记忆和用户档案的记忆管理器
+/// * `user_id` - 用户ID
+/// * `message` - 消息内容
+/// * `format_nickname` - 带时间的发送者昵称
+/// 
+/// # 返回值
+/// 关系等级跨过新的里程碑时返回庆祝消息
+pub async fn record_private_message(memory: &MemoryManager, user_id: i64, message: &str, format_nickname: &str) -> Option<String> {
     // 分析情绪并更新
     if let Err(e) = MOOD_SYSTEM.analyze_and_update_mood(message, "private_chat").await {
         eprintln!("[ERROR] 私聊情绪分析失败 (用户: {}): {}", user_id, e);
     }
 
     // 记录对话记忆
-    if let Err(e) = MEMORY_MANAGER.add_conversation_memory(
+    if let Err(e) = memory.add_conversation_memory(
         user_id,
         &format!("{}: {}", format_nickname, message),
         "private_chat"
@@ -491,41 +1422,207 @@ pub async fn private_chat(
     }
 
     // 更新用户档案
-    update_user_profile_from_message(user_id, message, &format_nickname).await;
+    update_user_profile_from_message(memory, user_id, message, format_nickname).await
+}
+
+pub async fn private_chat(
+    user_id: i64,
+    message: &str,
+    format_nickname: String,
+    bot: Arc<RuntimeBot>,
+) {
+    let send = |reply: Message| utils::send_private_message(&bot, user_id, reply);
+    private_chat_with(user_id, &send, message, format_nickname).await;
+}
+
+/// 处理一条私聊消息，回复通过 `send` 发送给用户
+/// 
+/// # 参数
+/// * `user_id` - 用户ID
+/// * `send` - 向用户发送一条消息，成功时返回消息ID
+/// * `message` - 消息内容
+/// * `format_nickname` - 带时间的发送者昵称
+async fn private_chat_with<F>(
+    user_id: i64,
+    send: &impl Fn(Message) -> F,
+    message: &str,
+    format_nickname: String,
+) where
+    F: Future<Output = anyhow::Result<i32>>,
+{
+    let milestone = record_private_message(&MEMORY_MANAGER, user_id, message, &format_nickname).await;
+
+    // 里程碑庆祝和自我介绍属于社交举动，受统一的冷却限制，一次互动最多发送一条
+    let mut gestured = false;
+
+    // 关系等级达到里程碑时先发送庆祝消息
+    if let Some(celebration) = milestone {
+        if gesture::try_acquire(user_id).await {
+            gestured = true;
+            if send(Message::from(celebration.as_str())).await.is_ok() {
+                println!("[INFO] 关系里程碑庆祝已发送 (用户: {}): {}", user_id, celebration);
+            }
+        } else {
+            println!("[INFO] 社交举动冷却中，跳过里程碑庆祝 (用户: {})", user_id);
+        }
+    }
+
+    // 命中固定回复时直接回复，不调用模型
+    if let Some(reply) = config::get().chat().canned_reply(message) {
+        if send(Message::from(reply)).await.is_ok() {
+            println!("[INFO] 私聊固定回复已发送 (用户: {}): {}", user_id, reply);
+        }
+        return;
+    }
+
+    // 新用户的前几次私聊依次发送自我介绍
+    if !gestured {
+        send_onboarding_line(user_id, send).await;
+    }
+
+    // 超出请求配额时不调用模型，只在第一次超限时提醒
+    if let Throttle::Limited { notify } = throttle::check(user_id).await {
+        if notify {
+            let _ = send(Message::from(THROTTLED_REPLY)).await;
+        }
+        return;
+    }
 
     // 获取用户档案和个性化信息
     let user_profile = MEMORY_MANAGER.get_user_profile(user_id).await;
     let contextual_memories = MEMORY_MANAGER.get_contextual_memories(user_id, "private_chat", 3).await;
     let personality = MEMORY_MANAGER.get_bot_personality().await;
 
-    let mut private = get_private_message_memory().lock().await;
-    let history = private.entry(user_id).or_insert(vec![
-        BotMemory {
+    // 同一用户的消息按顺序处理，等待模型回复期间不持有全局私聊对话锁
+    let turn = turn_lock(&PRIVATE_TURNS, user_id).await;
+    let _turn = turn.lock().await;
+    let existing = {
+        let mut private = get_private_message_memory().lock().await;
+        let max_conversations = config::get().chat().max_live_conversations();
+        touch_conversation(&mut private, &PRIVATE_MESSAGE_ACTIVITY, user_id, max_conversations).await;
+        private.get(&user_id).cloned()
+    };
+    let mut history = match existing {
+        Some(history) => history,
+        None => vec![BotMemory {
             role: Roles::System,
             content: generate_personalized_system_prompt(&user_profile, &personality, &contextual_memories).await,
-        },
-    ]);
+        }],
+    };
+
+    // 根据用户关系等级调整回复风格
+    let relationship_level = user_profile.as_ref().map(|p| p.relationship_level).unwrap_or(1);
+    reply_private_turn(&mut history, user_id, message, &format_nickname, relationship_level, send).await;
+
+    // 限制私聊记忆大小
+    history::trim_history(&mut history, config::get().chat().private_history_max_messages(user_id));
+    get_private_message_memory().lock().await.insert(user_id, history);
+}
 
-    // 添加用户消息
+/// 处理一轮私聊对话：追加用户消息、请求模型并发送回复
+/// 
+/// 请求或发送失败时撤回本轮用户消息，不记录回复
+async fn reply_private_turn<F>(
+    history: &mut Vec<BotMemory>,
+    user_id: i64,
+    message: &str,
+    format_nickname: &str,
+    relationship_level: u8,
+    send: &impl Fn(Message) -> F,
+) where
+    F: Future<Output = anyhow::Result<i32>>,
+{
+    // 添加用户消息，记录其位置，失败时据此撤回
+    let turn_start = history.len();
     history.push(BotMemory {
         role: Roles::User,
         content: format!("{}:{}", format_nickname, message),
     });
-
-    // 根据用户关系等级调整回复风格
-    let relationship_level = user_profile.as_ref().map(|p| p.relationship_level).unwrap_or(1);
     adjust_response_style_for_relationship(history, relationship_level);
 
     println!("[INFO] 私聊对话 (用户: {})", user_id);
-    let bot_content = params_model(history).await;
-    bot.send_private_msg(user_id, &bot_content.content);
-    println!("[INFO] 私聊消息已发送 (用户: {}): {}", user_id, bot_content.content);
+    let overrides = ModelOverrides {
+        message_class: Some(routing::classify(message)),
+        usage_scope: Some(UsageScope::Private(user_id)),
+        ..Default::default()
+    };
+    let bot_content = match params_model(history, overrides).await {
+        Ok(reply) => reply.memory,
+        Err(e) => {
+            // 提示不写入对话上下文，并撤回本轮用户消息
+            rollback_turn(history, turn_start);
+            let _ = send(Message::from(e.user_message())).await;
+            return;
+        }
+    };
+    deliver_private_reply(send, user_id, history, turn_start, bot_content).await;
+}
+
+/// 发送私聊回复，并在确认发送成功后写入对话历史
+/// 
+/// 回复为 `[sp]` 时保持沉默，不发送也不记录；发送失败时不记录回复，同时撤回本轮的用户消息，
+/// 避免重试时对话历史中出现重复的用户消息或回复
+/// 
+/// # 参数
+/// * `send` - 向用户发送一条消息
+/// * `user_id` - 用户ID
+/// * `history` - 私聊对话历史
+/// * `turn_start` - 本轮用户消息在对话历史中的位置
+/// * `reply` - 模型生成的回复
+async fn deliver_private_reply<F>(
+    send: &impl Fn(Message) -> F,
+    user_id: i64,
+    history: &mut Vec<BotMemory>,
+    turn_start: usize,
+    reply: BotMemory,
+) where
+    F: Future<Output = anyhow::Result<i32>>,
+{
+    if reply.content.contains("[sp]") {
+        return;
+    }
+    wait_before_reply().await;
+    if send(Message::from(reply.content.as_str())).await.is_err() {
+        rollback_turn(history, turn_start);
+        return;
+    }
+    println!("[INFO] 私聊消息已发送 (用户: {}): {}", user_id, reply.content);
 
     // 添加机器人回复
-    history.push(bot_content);
+    history.push(reply);
+}
 
-    // 限制私聊记忆大小
-    limit_memory_size(history);
+/// 向新用户发送下一条自我介绍
+/// 
+/// 自我介绍按配置顺序在前几次私聊中逐条发送，进度记录在用户档案中；
+/// 社交举动冷却中时本次不发送，留到之后的互动
+async fn send_onboarding_line<F>(user_id: i64, send: &impl Fn(Message) -> F)
+where
+    F: Future<Output = anyhow::Result<i32>>,
+{
+    let config = config::get();
+    let lines = config.prompt().onboarding_lines();
+    if lines.is_empty() {
+        return;
+    }
+
+    let Some(mut profile) = MEMORY_MANAGER.get_user_profile(user_id).await else {
+        return;
+    };
+    let Some(line) = profile.next_onboarding_line(&lines) else {
+        return;
+    };
+    if !gesture::try_acquire(user_id).await {
+        return;
+    }
+
+    if let Err(e) = MEMORY_MANAGER.update_user_profile(user_id, profile).await {
+        eprintln!("[ERROR] 更新自我介绍进度失败 (用户: {}): {}", user_id, e);
+        return;
+    }
+    if send(Message::from(line)).await.is_ok() {
+        println!("[INFO] 自我介绍已发送 (用户: {}): {}", user_id, line);
+    }
 }
 
 async fn generate_personalized_system_prompt(
@@ -533,76 +1630,168 @@ async fn generate_personalized_system_prompt(
     personality: &crate::memory::BotPersonality,
     contextual_memories: &[crate::memory::MemoryEntry],
 ) -> String {
-    let mut prompt = config::get().prompt().private_prompt().to_string();
-    
+    let config = config::get();
+    let mut prompt = config.prompt().private_persona_prompt();
+    let mut items = Vec::new();
+
     // 添加个性化信息
     if let Some(profile) = user_profile {
-        prompt.push_str(&format!("\n\n用户信息：\n- 昵称：{}\n- 关系等级：{}/10\n- 互动次数：{}\n- 兴趣：{}", 
-            profile.nickname,
-            profile.relationship_level,
-            profile.interaction_count,
-            profile.interests.join(", ")
-        ));
-        
-        // 根据关系等级调整语气
-        match profile.relationship_level {
-            8..=10 => prompt.push_str("\n- 语气：亲密友好，可以开玩笑"),
-            5..=7 => prompt.push_str("\n- 语气：友好但保持一定距离"),
-            1..=4 => prompt.push_str("\n- 语气：礼貌但较为正式"),
-            _ => {}
+        items.push(InjectionItem::new(InjectionSection::ProfileEssential, format!("昵称：{}", profile.nickname)));
+        if let Some(address) = &profile.preferred_address {
+            items.push(InjectionItem::new(InjectionSection::ProfileEssential, format!("称呼用户为{}", address)));
+        }
+        items.push(InjectionItem::new(InjectionSection::ProfileEssential, format!("关系等级：{}/10", profile.relationship_level)));
+
+        // 根据关系阶段调整语气
+        let tone = match profile.relationship_tier() {
+            RelationshipTier::Close => "语气：亲密友好，可以开玩笑",
+            RelationshipTier::Friendly => "语气：友好但保持一定距离",
+            RelationshipTier::Acquaintance => "语气：礼貌但较为正式",
+        };
+        items.push(InjectionItem::new(InjectionSection::ProfileEssential, tone));
+
+        items.push(InjectionItem::new(InjectionSection::ProfileEssential, format!("互动次数：{}", profile.interaction_count)));
+
+        for interest in &profile.interests {
+            items.push(InjectionItem::new(InjectionSection::Interest, interest.clone()));
         }
     }
-    
+
     // 添加机器人当前状态
-    prompt.push_str(&format!("\n\n当前状态：\n- 情绪：{}\n- 能量水平：{}/10\n- 社交信心：{}/10", 
-        personality.current_mood,
-        personality.energy_level,
-        personality.social_confidence
-    ));
-    
-    // 添加相关记忆
-    if !contextual_memories.is_empty() {
-        prompt.push_str("\n\n相关记忆：");
-        for memory in contextual_memories.iter().take(2) {
-            prompt.push_str(&format!("\n- {}", memory.content));
-        }
+    items.push(InjectionItem::new(InjectionSection::BotStatus, format!("情绪：{}", personality.current_mood)));
+    items.push(InjectionItem::new(InjectionSection::BotStatus, format!("能量水平：{}/10", personality.energy_level)));
+    items.push(InjectionItem::new(InjectionSection::BotStatus, format!("社交信心：{}/10", personality.social_confidence)));
+
+    // 添加相关记忆，置顶记忆全部保留，其余取最相关的两条
+    for memory in contextual_memories.iter().filter(|m| m.pinned) {
+        items.push(InjectionItem::new(InjectionSection::PinnedMemory, format_memory_line(memory)));
     }
-    
+    for memory in contextual_memories.iter().filter(|m| !m.pinned).take(2) {
+        items.push(InjectionItem::new(InjectionSection::RecentMemory, format_memory_line(memory)));
+    }
+
+    prompt.push_str(&assemble_injection(items, config.prompt().context_injection_budget()));
+    apply_reply_language_override(&mut prompt);
     prompt
 }
 
-fn adjust_response_style_for_relationship(history: &mut Vec<BotMemory>, relationship_level: u8) {
+/// 识别消息使用的语言
+/// 
+/// 含有假名时视为日语，含有汉字时视为中文，至少包含3个英文字母时视为英语，其余无法判断
+/// 
+/// # 返回值
+/// 语言代码，无法判断时返回 `None`
+fn detect_language(message: &str) -> Option<&'static str> {
+    let mut letters = 0;
+    let mut han = false;
+    for c in message.chars() {
+        match c {
+            '\u{3040}'..='\u{30ff}' => return Some("ja"),
+            '\u{4e00}'..='\u{9fff}' => han = true,
+            c if c.is_ascii_alphabetic() => letters += 1,
+            _ => {}
+        }
+    }
+    if han {
+        Some("zh")
+    } else if letters >= 3 {
+        Some("en")
+    } else {
+        None
+    }
+}
+
+/// 获取语言代码对应的语言名称，用于翻译指令
+fn language_name(code: &str) -> &str {
+    match code {
+        "en" => "英语",
+        "ja" => "日语",
+        "zh" => "中文",
+        other => other,
+    }
+}
+
+/// 将消息翻译成指定语言
+/// 
+/// 中文或安全模式下原样返回，翻译失败时同样返回原文
+/// 
+/// # 参数
+/// * `message` - 原始消息
+/// * `language` - 目标语言代码
+pub(crate) async fn localize_message(message: &str, language: &str) -> String {
+    let config = config::get();
+    translate_with(&config, message, language).await
+}
+
+/// 使用指定配置将消息翻译成指定语言
+/// 
+/// 翻译请求不注入思考过程和情绪指令，避免译文被改写
+async fn translate_with(config: &config::ModelConfig, message: &str, language: &str) -> String {
+    if language == "zh" || config.chat().safe_mode() {
+        return message.to_string();
+    }
+
+    let messages = vec![
+        BotMemory {
+            role: Roles::System,
+            content: format!(
+                "把用户发来的这段话翻译成{}，保持原有的语气、称呼和表情，只输出译文",
+                language_name(language)
+            ),
+        },
+        BotMemory {
+            role: Roles::User,
+            content: message.to_string(),
+        },
+    ];
+    match request_reply(config, &messages, ModelOverrides::default()).await {
+        Ok(reply) if !reply.memory.content.is_empty() && !reply.memory.content.contains("[sp]") => reply.memory.content,
+        _ => message.to_string(),
+    }
+}
+
+/// 追加全局回复语言指令
+/// 
+/// 配置了 `reply_language` 时，将语言指令放在系统提示最后，确保其优先生效
+fn apply_reply_language_override(prompt: &mut String) {
+    if let Some(directive) = config::get().prompt().reply_language_directive() {
+        prompt.push_str(&format!("\n\n{}", directive));
+    }
+}
+
+fn adjust_response_style_for_relationship(history: &mut [BotMemory], relationship_level: u8) {
     if relationship_level >= 8 {
         // 高关系等级，可以更随意
-        if let Some(system_msg) = history.first_mut() {
-            if system_msg.role == Roles::System {
-                system_msg.content.push_str("\n- 可以适当使用表情符号和网络用语");
-                system_msg.content.push_str("\n- 可以开玩笑和调侃");
-            }
+        if let Some(system_msg) = history.first_mut()
+            && system_msg.role == Roles::System
+        {
+            system_msg.content.push_str("\n- 可以适当使用表情符号和网络用语");
+            system_msg.content.push_str("\n- 可以开玩笑和调侃");
         }
     } else if relationship_level <= 3 {
         // 低关系等级，保持礼貌
-        if let Some(system_msg) = history.first_mut() {
-            if system_msg.role == Roles::System {
-                system_msg.content.push_str("\n- 保持礼貌和正式的语气");
-                system_msg.content.push_str("\n- 避免过于随意或开玩笑");
-            }
+        if let Some(system_msg) = history.first_mut()
+            && system_msg.role == Roles::System
+        {
+            system_msg.content.push_str("\n- 保持礼貌和正式的语气");
+            system_msg.content.push_str("\n- 避免过于随意或开玩笑");
         }
     }
 }
 
-async fn update_user_profile_from_message(user_id: i64, message: &str, nickname: &str) {
-    let mut profile = MEMORY_MANAGER.get_user_profile(user_id).await
-        .unwrap_or_else(|| UserProfile {
-            user_id,
-            nickname: nickname.to_string(),
-            personality_traits: Vec::new(),
-            interests: Vec::new(),
-            relationship_level: 1,
-            last_interaction: Local::now(),
-            interaction_count: 0,
-            mood_history: Vec::new(),
-        });
+/// 根据私聊消息更新用户档案
+/// 
+/// # 返回值
+/// 关系等级跨过新的里程碑时返回配置的庆祝消息
+async fn update_user_profile_from_message(memory: &MemoryManager, user_id: i64, message: &str, nickname: &str) -> Option<String> {
+    let config = config::get();
+    if config.memory().skips_memory(user_id, "private_chat") {
+        return None;
+    }
+
+    let mut profile = memory.get_user_profile(user_id).await
+        .unwrap_or_else(|| UserProfile::new(user_id, nickname));
+    let previous_level = profile.relationship_level;
 
     // 更新互动信息
     profile.last_interaction = Local::now();
@@ -613,10 +1802,10 @@ async fn update_user_profile_from_message(user_id: i64, message: &str, nickname:
         profile.relationship_level = (profile.relationship_level + 1).min(10);
     }
 
-    // 提取兴趣关键词
+    // 提取兴趣关键词，未匹配时按配置记录一次未分类互动
     let interests = extract_interests_from_message(message);
-    if interests.is_empty() {
-        return;
+    if interests.is_empty() && config.chat().record_uncategorized_activity() {
+        profile.uncategorized_interactions += 1;
     }
     for interest in interests {
         if !profile.interests.contains(&interest) {
@@ -624,10 +1813,282 @@ async fn update_user_profile_from_message(user_id: i64, message: &str, nickname:
         }
     };
 
+    // 识别用户常用的语言，用于主动私聊时本地化开场白
+    if let Some(language) = detect_language(message) {
+        profile.preferred_language = Some(language.to_string());
+    }
+
+    // 检查关系里程碑
+    let milestone = profile.cross_milestone(previous_level, &config.chat().milestone_levels());
+    let display_name = profile.display_name().to_string();
+
     // 更新用户档案
-    if let Err(e) = MEMORY_MANAGER.update_user_profile(user_id, profile).await {
+    if let Err(e) = memory.update_user_profile(user_id, profile).await {
         eprintln!("Failed to update user profile: {}", e);
+        return None;
+    }
+
+    let level = milestone?;
+    let event = MemoryEntry {
+        id: format!("event_{}_{}", user_id, Local::now().timestamp_millis()),
+        content: format!("与{}的关系等级达到了{}", display_name, level),
+        timestamp: Local::now(),
+        memory_type: MemoryType::Event,
+        importance: 8,
+        tags: vec!["里程碑".to_string()],
+        context: "relationship_milestone".to_string(),
+        pinned: false,
+        last_reinforced: None,
+        decay_steps: 0,
+        embedding: None,
+    };
+    if let Err(e) = memory.add_memory(event).await {
+        eprintln!("[ERROR] 关系里程碑记录失败 (用户: {}): {}", user_id, e);
+    }
+
+    config.chat().milestone_message(level).map(str::to_string)
+}
+
+/// 查看或修改运行中的配置
+/// 
+/// 处理 `#配置` 命令：
+/// - `#配置`：导出全部配置
+/// - `#配置 <配置项>`：导出指定的配置段或配置项
+/// - `#配置 <配置项> <值>`：修改配置项，验证通过后立即生效并写回配置文件
+/// 
+/// # 返回值
+/// 回复给用户的提示文本
+pub fn config_command(args: &str) -> String {
+    let args = args.trim();
+    let (key, value) = match args.split_once(char::is_whitespace) {
+        Some((key, value)) => (key, Some(value.trim())),
+        None => (args, None),
+    };
+
+    match value {
+        None => {
+            let key = (!key.is_empty()).then_some(key);
+            match config::get().to_redacted_toml(key) {
+                Ok(content) => format!("当前生效的配置：\n{}", content.trim_end()),
+                Err(e) => format!("读取配置失败: {}", e),
+            }
+        }
+        Some(value) => match config::ModelConfig::set_value(key, value) {
+            Ok(()) => format!("已将 {} 设置为 {}", key, value),
+            Err(e) => format!("修改配置失败: {:#}", e),
+        },
+    }
+}
+
+/// 获取群聊的模型参数覆盖
+pub async fn group_model_overrides(group_id: i64) -> ModelOverrides {
+    MODEL_OVERRIDES.lock().await.get(&group_id).copied().unwrap_or_default()
+}
+
+/// 设置群聊的温度参数
+/// 
+/// 处理 `#设置温度 <0.0-2.0>` 命令
+/// 
+/// # 返回值
+/// 回复给用户的提示文本
+pub async fn set_temperature_override(group_id: i64, value: &str) -> String {
+    match value.trim().parse::<f32>() {
+        Ok(temperature) if (0.0..=2.0).contains(&temperature) => {
+            MODEL_OVERRIDES.lock().await.entry(group_id).or_default().temperature = Some(temperature);
+            format!("本群温度已设置为 {}", temperature)
+        }
+        _ => "温度必须是 0.0 到 2.0 之间的数字，用法：#设置温度 <0.0-2.0>".to_string(),
+    }
+}
+
+/// 设置群聊的核采样参数
+/// 
+/// 处理 `#设置topp <0.0-1.0>` 命令
+/// 
+/// # 返回值
+/// 回复给用户的提示文本
+pub async fn set_top_p_override(group_id: i64, value: &str) -> String {
+    match value.trim().parse::<f32>() {
+        Ok(top_p) if (0.0..=1.0).contains(&top_p) => {
+            MODEL_OVERRIDES.lock().await.entry(group_id).or_default().top_p = Some(top_p);
+            format!("本群 top_p 已设置为 {}", top_p)
+        }
+        _ => "top_p 必须是 0.0 到 1.0 之间的数字，用法：#设置topp <0.0-1.0>".to_string(),
+    }
+}
+
+/// 模拟情绪分析并生成结果描述
+/// 
+/// 处理 `#测情绪 <文本>` 命令，展示各情绪得分和最终情绪，不会改变机器人人格
+pub async fn describe_mood_simulation(text: &str) -> String {
+    let text = text.trim();
+    if text.is_empty() {
+        return "用法：#测情绪 <文本>".to_string();
+    }
+
+    let (scores, mood) = MOOD_SYSTEM.simulate_mood(text, "group_chat").await;
+    let score_lines = scores
+        .iter()
+        .filter(|(_, score)| *score > 0)
+        .map(|(mood, score)| format!("- {}: {}", mood, score))
+        .collect::<Vec<_>>();
+
+    let mut lines = vec![format!("🧪 情绪分析结果: {}", mood)];
+    if score_lines.is_empty() {
+        lines.push("未匹配到任何情绪关键词".to_string());
+    } else {
+        lines.push("各情绪得分:".to_string());
+        lines.extend(score_lines);
+    }
+    lines.join("\n")
+}
+
+/// 用户自定义称呼的最大长度（字符数）
+const MAX_PREFERRED_ADDRESS_LEN: usize = 20;
+
+/// 设置用户自定义的称呼
+/// 
+/// 处理 `#叫我 <称呼>` 命令，将称呼保存到用户档案中
+/// 
+/// # 参数
+/// * `user_id` - 用户ID
+/// * `nickname` - 用户昵称，档案不存在时用于创建档案
+/// * `address` - 用户希望的称呼
+/// 
+/// # 返回值
+/// 回复给用户的提示文本
+pub async fn set_preferred_address(user_id: i64, nickname: &str, address: &str) -> String {
+    let address = address.trim();
+    if address.is_empty() {
+        return "要我怎么称呼你呀？用法：#叫我 <称呼>".to_string();
+    }
+    if address.chars().count() > MAX_PREFERRED_ADDRESS_LEN {
+        return format!("称呼太长啦，最多{}个字哦", MAX_PREFERRED_ADDRESS_LEN);
+    }
+
+    let mut profile = MEMORY_MANAGER.get_user_profile(user_id).await
+        .unwrap_or_else(|| UserProfile::new(user_id, nickname));
+    profile.preferred_address = Some(address.to_string());
+
+    match MEMORY_MANAGER.update_user_profile(user_id, profile).await {
+        Ok(_) => format!("好的，以后就叫你{}啦~", address),
+        Err(e) => {
+            eprintln!("[ERROR] 保存用户称呼失败 (用户: {}): {}", user_id, e);
+            "保存称呼失败了，稍后再试试吧".to_string()
+        }
+    }
+}
+
+/// 获取对用户的称呼
+/// 
+/// 用户设置过自定义称呼时使用该称呼，否则使用传入的昵称
+pub async fn display_name_for(user_id: i64, nickname: &str) -> String {
+    MEMORY_MANAGER.get_user_profile(user_id).await
+        .and_then(|profile| profile.preferred_address)
+        .unwrap_or_else(|| nickname.to_string())
+}
+
+/// 生成用户档案的文本描述
+/// 
+/// 用于管理员查看指定用户的档案，包含昵称、关系等级、互动次数、兴趣、
+/// 最后互动时间和最近的情绪记录
+/// 
+/// # 参数
+/// * `user_id` - 用户ID
+/// 
+/// # 返回值
+/// 档案描述文本，用户不存在时返回提示信息
+pub async fn describe_user_profile(user_id: i64) -> String {
+    let Some(profile) = MEMORY_MANAGER.get_user_profile(user_id).await else {
+        return format!("未找到用户 {} 的档案", user_id);
+    };
+
+    let mut lines = vec![
+        format!("👤 用户 {} 的档案", profile.user_id),
+        format!("昵称: {}", profile.nickname),
+    ];
+    if let Some(address) = &profile.preferred_address {
+        lines.push(format!("称呼: {}", address));
+    }
+    lines.push(format!("关系等级: {}/10", profile.relationship_level));
+    lines.push(format!("互动次数: {}（未分类 {}）", profile.interaction_count, profile.uncategorized_interactions));
+    lines.push(format!(
+        "兴趣: {}",
+        if profile.interests.is_empty() { "暂无".to_string() } else { profile.interests.join(", ") }
+    ));
+    lines.push(format!("最后互动: {}", profile.last_interaction.format("%Y-%m-%d %H:%M:%S")));
+
+    if profile.mood_history.is_empty() {
+        lines.push("最近情绪: 暂无".to_string());
+    } else {
+        lines.push("最近情绪:".to_string());
+        let skip = profile.mood_history.len().saturating_sub(5);
+        for entry in profile.mood_history.iter().skip(skip) {
+            lines.push(format!(
+                "- [{}] {} ({}/10) {}",
+                entry.timestamp.format("%m-%d %H:%M"),
+                entry.mood,
+                entry.intensity,
+                entry.trigger
+            ));
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// 生成群聊分析报告
+/// 
+/// 用于管理员了解群聊概况，包含活跃度、成员数、热门话题、近期主导情绪和已存储的记忆数量。
+/// 数据不足的项目显示为“暂无”
+/// 
+/// # 参数
+/// * `group_id` - 群组ID
+/// * `profile` - 群组档案
+/// * `member_count` - 群成员总数，获取失败时为 `None`
+/// 
+/// # 返回值
+/// 报告文本
+pub async fn describe_group_analytics(group_id: i64, profile: Option<&GroupProfile>, member_count: Option<i64>) -> String {
+    let memories = MEMORY_MANAGER.get_group_conversation_memories(group_id).await;
+    if profile.is_none() && memories.is_empty() {
+        return "这个群还没有足够的数据，多聊几句再来看看吧".to_string();
+    }
+
+    let mut lines = vec![format!("📊 群 {} 的分析报告", group_id)];
+    match profile {
+        Some(profile) => {
+            lines.push(format!("🔥 活跃度: {}/10", profile.activity_level));
+            lines.push(format!(
+                "👥 成员: {}（近一天发言 {} 人）",
+                member_count.map(|count| format!("{} 人", count)).unwrap_or_else(|| "未知".to_string()),
+                profile.recently_active_members(chrono::Duration::days(1)).len()
+            ));
+            let skip = profile.conversation_topics.len().saturating_sub(5);
+            let topics = &profile.conversation_topics[skip..];
+            lines.push(format!(
+                "💬 近期话题: {}",
+                if topics.is_empty() { "暂无".to_string() } else { topics.join("、") }
+            ));
+        }
+        None => lines.push("🔥 活跃度: 暂无".to_string()),
     }
+
+    // 统计最近50条群聊记忆中出现最多的情绪
+    let mut mood_counts: HashMap<Mood, usize> = HashMap::new();
+    for memory in memories.iter().take(50) {
+        if let Some(mood) = MOOD_SYSTEM.dominant_mood(&memory.content) {
+            *mood_counts.entry(mood).or_insert(0) += 1;
+        }
+    }
+    let dominant = mood_counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+        .map(|(mood, _)| mood.to_string());
+    lines.push(format!("😊 近期主导情绪: {}", dominant.unwrap_or_else(|| "暂无".to_string())));
+    lines.push(format!("🧠 已存储记忆: {} 条", memories.len()));
+
+    lines.join("\n")
 }
 
 fn extract_interests_from_message(message: &str) -> Vec<String> {
@@ -658,12 +2119,12 @@ fn extract_interests_from_message(message: &str) -> Vec<String> {
 }
 
 pub fn get_file_modified_time_formatted() -> anyhow::Result<String> {
-    let config_path = "bot.conf.toml";
-    if !Path::new(config_path).exists() {
+    let config_path = config::config_path();
+    if !config_path.exists() {
         return Ok("文件不存在".to_string());
     }
 
-    let metadata = fs::metadata(config_path)
+    let metadata = fs::metadata(&config_path)
         .with_context(|| anyhow::anyhow!("Failed to get file metadata"))?;
 
     let modified = metadata.modified()
@@ -678,4 +2139,794 @@ pub fn get_file_modified_time_formatted() -> anyhow::Result<String> {
         .ok_or_else(|| anyhow::anyhow!("Invalid timestamp"))?;
 
     Ok(datetime.format("%Y-%m-%d %H:%M:%S").to_string())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::mock;
+    use crate::model::mock::{completion_response, http_response, model_config, read_request, server_config, MockServer};
+    use kovi::tokio::net::TcpListener;
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn warmup_sends_exactly_one_request() {
+        let server = MockServer::start(vec![completion_response("你好"), completion_response("你好")]).await;
+
+        warmup_server(&server_config(&server.url, "")).await.unwrap();
+
+        assert_eq!(server.request_count(), 1);
+        assert!(server.request(0).contains("你好"));
+    }
+
+    fn memory(role: Roles, content: &str) -> BotMemory {
+        BotMemory {
+            role,
+            content: content.to_string(),
+        }
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn extra_headers_are_sent_with_requests() {
+        let server = MockServer::start(vec![completion_response("你好")]).await;
+        let config = server_config(&server.url, "[extra_headers]\nOpenAI-Organization = \"org-test\"");
+
+        warmup_server(&config).await.unwrap();
+
+        let request = server.request(0).to_lowercase();
+        assert!(request.contains("openai-organization: org-test"));
+        assert!(request.contains("authorization: bearer test-token"));
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn preferred_address_is_stored_and_used_in_prompt() {
+        crate::config::use_test_data_dir();
+        let user_id = 960_001;
+
+        let reply = set_preferred_address(user_id, "小明", "明明").await;
+        assert!(reply.contains("明明"));
+
+        let profile = MEMORY_MANAGER.get_user_profile(user_id).await;
+        assert_eq!(profile.as_ref().and_then(|p| p.preferred_address.as_deref()), Some("明明"));
+        assert_eq!(display_name_for(user_id, "小明").await, "明明");
+
+        let personality = MEMORY_MANAGER.get_bot_personality().await;
+        let prompt = generate_personalized_system_prompt(&profile, &personality, &[]).await;
+        assert!(prompt.contains("称呼用户为明明"));
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn prompt_preview_matches_personalized_prompt() {
+        crate::config::use_test_data_dir();
+        let user_id = 1_009_001;
+        let mut profile = UserProfile::new(user_id, "小蓝");
+        profile.interests = vec!["摄影".to_string()];
+        MEMORY_MANAGER.update_user_profile(user_id, profile).await.unwrap();
+
+        let preview = preview_private_prompt(user_id).await;
+        let personality = MEMORY_MANAGER.get_bot_personality().await;
+        let expected = generate_personalized_system_prompt(
+            &MEMORY_MANAGER.get_user_profile(user_id).await,
+            &personality,
+            &MEMORY_MANAGER.get_contextual_memories(user_id, "private_chat", 3).await,
+        ).await;
+        assert_eq!(preview, format!("新对话的系统提示：\n{}", expected));
+        assert!(preview.contains("小蓝"));
+
+        // 已有对话时展示当前对话实际使用的系统提示
+        get_private_message_memory().lock().await.insert(user_id, vec![memory(Roles::System, "当前提示")]);
+        assert_eq!(preview_private_prompt(user_id).await, "当前对话的系统提示：\n当前提示");
+        get_private_message_memory().lock().await.remove(&user_id);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn context_refresh_injects_new_memories() {
+        crate::config::use_test_data_dir();
+        let user_id = 1_012_001;
+        assert_eq!(refresh_private_context(user_id).await, "当前没有进行中的对话，新对话会自动使用最新记忆");
+
+        get_private_message_memory().lock().await.insert(
+            user_id,
+            vec![memory(Roles::System, "旧的系统提示"), memory(Roles::User, "你好")],
+        );
+        let mut remembered = crate::memory::mock::entry("refresh_1012", "用户1012001下周要搬家", MemoryType::UserProfile);
+        remembered.importance = 10;
+        remembered.context = "private_chat".to_string();
+        MEMORY_MANAGER.add_memory(remembered).await.unwrap();
+
+        assert_eq!(refresh_private_context(user_id).await, "已刷新对话记忆");
+        let history = get_private_message_memory().lock().await.remove(&user_id).unwrap();
+        assert!(history[0].content.contains("下周要搬家"));
+        // 已有的对话内容保留
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].content, "你好");
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn memory_window_reflects_live_context() {
+        let user_id = 1_015_001;
+        assert!(describe_private_memory_window(user_id).await.starts_with("现在没有进行中的对话"));
+
+        get_private_message_memory().lock().await.insert(
+            user_id,
+            vec![
+                memory(Roles::System, "你是小助手\n\n相关记忆：\n- 喜欢猫\n- 住在杭州"),
+                memory(Roles::User, "你好"),
+                memory(Roles::Assistant, "你好呀"),
+                memory(Roles::User, "还记得我吗"),
+            ],
+        );
+        let description = describe_private_memory_window(user_id).await;
+        get_private_message_memory().lock().await.remove(&user_id);
+
+        assert!(description.contains("2 轮发言（上下文共 3 条消息）"));
+        assert!(description.contains("注入了 2 条长期记忆"));
+        assert!(!description.contains("摘要"));
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn describes_known_and_unknown_user_profiles() {
+        crate::config::use_test_data_dir();
+        let user_id = 965_001;
+        let mut profile = UserProfile::new(user_id, "小红");
+        profile.relationship_level = 6;
+        profile.interests = vec!["音乐".to_string()];
+        MEMORY_MANAGER.update_user_profile(user_id, profile).await.unwrap();
+
+        let description = describe_user_profile(user_id).await;
+        assert!(description.contains("昵称: 小红"));
+        assert!(description.contains("关系等级: 6/10"));
+        assert!(description.contains("兴趣: 音乐"));
+
+        assert_eq!(describe_user_profile(965_002).await, "未找到用户 965002 的档案");
+    }
+
+    #[test]
+    fn proactive_self_logs_are_marked_in_context() {
+        let mut memory = MemoryEntry {
+            id: "m1".to_string(),
+            content: "今天天气真好".to_string(),
+            timestamp: Local::now(),
+            memory_type: MemoryType::Conversation,
+            importance: 5,
+            tags: Vec::new(),
+            context: "proactive_group_chat".to_string(),
+            pinned: false,
+            last_reinforced: None,
+            decay_steps: 0,
+            embedding: None,
+        };
+        assert_eq!(format_memory_line(&memory), "（我自己说过）今天天气真好");
+
+        memory.context = "group_chat".to_string();
+        assert_eq!(format_memory_line(&memory), "今天天气真好");
+    }
+
+    #[test]
+    fn rollback_turn_removes_user_turn_followed_by_system_message() {
+        let mut history = vec![
+            memory(Roles::System, "系统提示"),
+            memory(Roles::User, "A:你好"),
+            memory(Roles::Assistant, "你好呀"),
+        ];
+        let turn_start = history.len();
+        history.push(memory(Roles::User, "A:在吗"));
+        history.push(memory(Roles::System, "思考过程"));
+
+        rollback_turn(&mut history, turn_start);
+
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.last().unwrap().content, "你好呀");
+    }
+
+    #[test]
+    fn rollback_turn_keeps_system_prompt_of_new_conversation() {
+        let mut history = vec![memory(Roles::System, "系统提示"), memory(Roles::User, "A:你好")];
+
+        rollback_turn(&mut history, 1);
+
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].role, Roles::System);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn failed_send_then_retry_records_turn_once() {
+        let config = model_config("http://127.0.0.1:1/v1/chat/completions", "[chat]\nreply_delay_ms = 0");
+        // 第一次发送失败，之后都成功
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+        let send = |_message: Message| {
+            let attempt = attempts.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            async move {
+                if attempt == 0 {
+                    Err(anyhow::anyhow!("消息发送失败"))
+                } else {
+                    Ok(1)
+                }
+            }
+        };
+        let count = |history: &[BotMemory], role: Roles| history.iter().filter(|m| m.role == role).count();
+
+        config::with_test_config(config, async {
+            let mut group = vec![memory(Roles::System, "系统提示")];
+            for _ in 0..2 {
+                let turn_start = group.len();
+                group.push(memory(Roles::User, "A:你好"));
+                deliver_group_reply(&send, 990_001, &mut group, turn_start, memory(Roles::Assistant, "你好呀"), None).await;
+            }
+            assert_eq!(group.len(), 3);
+            assert_eq!(count(&group, Roles::User), 1);
+            assert_eq!(count(&group, Roles::Assistant), 1);
+
+            attempts.store(0, std::sync::atomic::Ordering::Relaxed);
+            let mut private = vec![memory(Roles::System, "系统提示")];
+            for _ in 0..2 {
+                let turn_start = private.len();
+                private.push(memory(Roles::User, "A:你好"));
+                deliver_private_reply(&send, 990_002, &mut private, turn_start, memory(Roles::Assistant, "你好呀")).await;
+            }
+            assert_eq!(private.len(), 3);
+            assert_eq!(count(&private, Roles::User), 1);
+            assert_eq!(count(&private, Roles::Assistant), 1);
+        })
+        .await;
+
+        assert_eq!(attempts.load(std::sync::atomic::Ordering::Relaxed), 2);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn temperature_override_is_sent_in_payload() {
+        let group_id = 973_001;
+        let config = server_config("http://127.0.0.1:1/v1/chat/completions", "");
+
+        assert!(set_temperature_override(group_id, " 3.5").await.contains("必须"));
+        assert!(set_top_p_override(group_id, " 0.9").await.contains("0.9"));
+        assert!(set_temperature_override(group_id, " 1.5").await.contains("1.5"));
+
+        let overrides = group_model_overrides(group_id).await;
+        let payload = serde_json::to_value(build_request_payload(&[memory(Roles::User, "A:你好")], &[], &config, &overrides)).unwrap();
+        assert_eq!(payload["temperature"], json!(1.5));
+        assert_eq!(payload["top_p"], json!(0.9f32));
+
+        // 未设置覆盖的群使用服务器配置，且不发送 top_p
+        let payload = serde_json::to_value(build_request_payload(&[], &[], &config, &group_model_overrides(973_002).await)).unwrap();
+        assert_eq!(payload["temperature"], json!(config.temperature()));
+        assert!(payload.get("top_p").is_none());
+    }
+
+    #[test]
+    fn quoted_reply_references_original_message() {
+        let message = quoted_message("收到", Some(42));
+        let reply = message.iter().find(|segment| segment.type_ == "reply").unwrap();
+        assert_eq!(reply.data["id"], "42");
+        let text = message.iter().find(|segment| segment.type_ == "text").unwrap();
+        assert_eq!(text.data["text"], "收到");
+
+        let message = quoted_message("收到", None);
+        assert!(message.iter().all(|segment| segment.type_ != "reply"));
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn mood_simulation_reports_scores_without_changing_mood() {
+        crate::config::use_test_data_dir();
+        let before = MEMORY_MANAGER.get_bot_personality().await;
+
+        let report = describe_mood_simulation(" 今天好开心，哈哈").await;
+        assert!(report.starts_with("🧪 情绪分析结果: happy"));
+        assert!(report.contains("- happy: 4"));
+
+        let after = MEMORY_MANAGER.get_bot_personality().await;
+        assert_eq!(after.current_mood, before.current_mood);
+        assert_eq!(after.mood_intensity, before.mood_intensity);
+
+        assert_eq!(describe_mood_simulation("  ").await, "用法：#测情绪 <文本>");
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn rate_limit_waits_for_retry_after() {
+        let server = MockServer::start(vec![
+            http_response("429 Too Many Requests", &[("Retry-After", "2")], "{}"),
+            completion_response("好的"),
+        ])
+        .await;
+        let config = server_config(&server.url, "request_retries = 1");
+        let payload = build_request_payload(&[memory(Roles::User, "A:你好")], &[], &config, &ModelOverrides::default());
+
+        let start = std::time::Instant::now();
+        let completion = request_completion(&config, &server.url, &HeaderMap::new(), &payload).await.unwrap();
+        let elapsed = start.elapsed();
+
+        assert_eq!(completion.message["content"], "好的");
+        assert_eq!(server.request_count(), 2);
+        assert!(elapsed >= std::time::Duration::from_secs(2));
+        assert!(elapsed < std::time::Duration::from_secs(4));
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn server_error_is_retried_then_succeeds() {
+        let server = MockServer::start(vec![
+            http_response("500 Internal Server Error", &[], "{\"error\":{\"message\":\"boom\"}}"),
+            completion_response("好的"),
+        ])
+        .await;
+        let config = server_config(&server.url, "request_retries = 2");
+        let payload = build_request_payload(&[memory(Roles::User, "A:你好")], &[], &config, &ModelOverrides::default());
+
+        let completion = request_completion(&config, &server.url, &HeaderMap::new(), &payload).await.unwrap();
+
+        assert_eq!(completion.message["content"], "好的");
+        assert_eq!(server.request_count(), 2);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn exhausted_retries_return_error_instead_of_panicking() {
+        let server = MockServer::start(vec![
+            http_response("500 Internal Server Error", &[], "{}"),
+            http_response("502 Bad Gateway", &[], "{}"),
+        ])
+        .await;
+        let config = server_config(&server.url, "request_retries = 1");
+        let payload = build_request_payload(&[memory(Roles::User, "A:你好")], &[], &config, &ModelOverrides::default());
+
+        let Err(error) = request_completion(&config, &server.url, &HeaderMap::new(), &payload).await else {
+            panic!("重试耗尽后应返回错误");
+        };
+
+        assert_eq!(server.request_count(), 2);
+        assert!(!error.user_message().is_empty());
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn slow_endpoint_times_out_with_distinct_message() {
+        // 只接收请求、迟迟不响应的服务
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1/chat/completions", listener.local_addr().unwrap());
+        kovi::tokio::spawn(async move {
+            let Ok((mut stream, _)) = listener.accept().await else {
+                return;
+            };
+            read_request(&mut stream).await;
+            kovi::tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+        });
+        let config = server_config(&url, "request_timeout_secs = 1\nrequest_retries = 2");
+        let payload = build_request_payload(&[memory(Roles::User, "A:你好")], &[], &config, &ModelOverrides::default());
+
+        let start = std::time::Instant::now();
+        let Err(error) = request_completion(&config, &url, &HeaderMap::new(), &payload).await else {
+            panic!("慢速接口应当超时");
+        };
+
+        // 超时不重试，直接提示用户稍后再试
+        assert!(matches!(error, ProviderError::Timeout(_)));
+        assert_eq!(error.user_message(), "我想得有点久，稍后再试");
+        assert!(start.elapsed() < std::time::Duration::from_secs(3));
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn failing_primary_falls_back_to_next_model() {
+        crate::config::use_test_data_dir();
+        let primary = MockServer::start(vec![http_response("503 Service Unavailable", &[], "{}")]).await;
+        let fallback = MockServer::start(vec![completion_response("备用模型的回复")]).await;
+        let config = server_config(
+            &primary.url,
+            &format!(
+                "request_retries = 0\n[[fallback_models]]\nurl = \"{}\"\nmodel_name = \"free-model\"\napi_token = \"fallback-token\"",
+                fallback.url
+            ),
+        );
+        let payload = build_request_payload(&[memory(Roles::User, "A:你好")], &[], &config, &ModelOverrides::default());
+
+        let completion = request_with_fallbacks(&config, &HeaderMap::new(), payload).await.unwrap();
+
+        assert_eq!(completion.message["content"], "备用模型的回复");
+        assert_eq!(primary.request_count(), 1);
+        let request = fallback.request(0);
+        assert!(request.contains("\"model\":\"free-model\""));
+        assert!(request.to_lowercase().contains("authorization: bearer fallback-token"));
+    }
+
+    #[test]
+    fn retry_after_accepts_seconds_and_dates() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, "2".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), Some(std::time::Duration::from_secs(2)));
+
+        headers.insert(RETRY_AFTER, "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), Some(std::time::Duration::ZERO));
+    }
+
+    #[test]
+    fn payload_contains_messages_and_defaults() {
+        let config = server_config("http://127.0.0.1:1/v1/chat/completions", "model_name = \"test-model\"");
+        let messages = [memory(Roles::System, "系统提示"), memory(Roles::User, "A:你好")];
+
+        let payload = serde_json::to_value(build_request_payload(&messages, &[], &config, &ModelOverrides::default())).unwrap();
+
+        assert_eq!(payload["model"], "test-model");
+        assert_eq!(payload["stream"], false);
+        assert_eq!(payload["temperature"], json!(config.temperature()));
+        assert_eq!(
+            payload["messages"],
+            json!([{ "role": "system", "content": "系统提示" }, { "role": "user", "content": "A:你好" }])
+        );
+        for key in ["top_p", "max_tokens", "logprobs", "tools"] {
+            assert!(payload.get(key).is_none(), "{} should be omitted", key);
+        }
+    }
+
+    #[test]
+    fn payload_includes_optional_fields_when_configured() {
+        let config = server_config(
+            "http://127.0.0.1:1/v1/chat/completions",
+            "max_tokens = 256\ntools_enabled = true\nmin_reply_confidence = 0.5",
+        );
+        let tool_result = json!({ "role": "tool", "tool_call_id": "call_1", "content": "晴" });
+
+        let payload = serde_json::to_value(build_request_payload(
+            &[memory(Roles::User, "A:天气怎么样")],
+            std::slice::from_ref(&tool_result),
+            &config,
+            &ModelOverrides::default(),
+        ))
+        .unwrap();
+
+        assert_eq!(payload["max_tokens"], 256);
+        assert_eq!(payload["logprobs"], true);
+        assert!(payload["tools"].as_array().is_some_and(|tools| !tools.is_empty()));
+        assert_eq!(payload["messages"][1], tool_result);
+    }
+
+    #[test]
+    fn greeting_routes_to_cheap_model_and_question_to_main() {
+        let config = server_config(
+            "http://127.0.0.1:1/v1/chat/completions",
+            "model_name = \"main-model\"\n[model_routes]\ngreeting = \"cheap-model\"",
+        );
+        let model = |message: &str| {
+            let overrides = ModelOverrides { message_class: Some(routing::classify(message)), ..Default::default() };
+            serde_json::to_value(build_request_payload(&[memory(Roles::User, message)], &[], &config, &overrides))
+                .unwrap()["model"]
+                .clone()
+        };
+
+        assert_eq!(model("你好呀"), "cheap-model");
+        assert_eq!(model("为什么天空是蓝色的？"), "main-model");
+        // 未配置路由的分类使用主模型
+        assert_eq!(model("今天吃了面条"), "main-model");
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn idle_group_context_starts_fresh() {
+        let mut conversations = HashMap::new();
+        let activity = Mutex::new(HashMap::new());
+        let long_ago = Instant::now().checked_sub(std::time::Duration::from_secs(120)).unwrap();
+        for id in [1, 2] {
+            conversations.insert(id, vec![memory(Roles::System, "系统提示"), memory(Roles::User, "旧话题")]);
+        }
+        activity.lock().await.insert(1, long_ago);
+        activity.lock().await.insert(2, Instant::now());
+
+        // 未开启过期时保留旧对话
+        expire_idle_conversation(&mut conversations, &activity, 1, 0).await;
+        assert!(conversations.contains_key(&1));
+
+        expire_idle_conversation(&mut conversations, &activity, 1, 60).await;
+        expire_idle_conversation(&mut conversations, &activity, 2, 60).await;
+        assert!(!conversations.contains_key(&1));
+        assert_eq!(conversations[&2].len(), 2);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn exceeding_cap_evicts_least_recently_active() {
+        let mut conversations = HashMap::new();
+        let activity = Mutex::new(HashMap::new());
+
+        for id in [1, 2, 3] {
+            touch_conversation(&mut conversations, &activity, id, 3).await;
+            conversations.insert(id, vec![memory(Roles::System, "系统提示")]);
+            kovi::tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        }
+        // 再次活跃的对话移到最近
+        touch_conversation(&mut conversations, &activity, 1, 3).await;
+
+        touch_conversation(&mut conversations, &activity, 4, 3).await;
+        conversations.insert(4, vec![memory(Roles::System, "系统提示")]);
+
+        let mut ids: Vec<_> = conversations.keys().copied().collect();
+        ids.sort();
+        assert_eq!(ids, [1, 3, 4]);
+        assert!(!activity.lock().await.contains_key(&2));
+    }
+
+    #[test]
+    fn jittered_delays_stay_within_range() {
+        let base = std::time::Duration::from_millis(1000);
+        let delays: Vec<_> = (0..50).map(|_| jittered_delay(base, 0.3)).collect();
+
+        for delay in &delays {
+            assert!((700..=1300).contains(&delay.as_millis()), "{:?} out of range", delay);
+        }
+        assert!(delays.iter().any(|delay| *delay != delays[0]));
+
+        assert_eq!(jittered_delay(base, 0.0), base);
+        assert_eq!(jittered_delay(std::time::Duration::ZERO, 0.3), std::time::Duration::ZERO);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn recurring_group_phrase_enters_lore_and_prompt() {
+        crate::config::use_test_data_dir();
+        let group_id = 997_001;
+        let messages = ["小明: 摸鱼大王 又来了", "小红: 今天也是 摸鱼大王", "小刚: 摸鱼大王 上线", "小明: 下班了"];
+        for (i, message) in messages.iter().enumerate() {
+            let memory = MemoryEntry {
+                id: format!("conv_{}_{}", group_id, i),
+                content: message.to_string(),
+                timestamp: Local::now(),
+                memory_type: MemoryType::Conversation,
+                importance: 5,
+                tags: Vec::new(),
+                context: "group_chat".to_string(),
+                pinned: false,
+                last_reinforced: None,
+                decay_steps: 0,
+                embedding: None,
+            };
+            MEMORY_MANAGER.add_memory(memory).await.unwrap();
+        }
+
+        let lore = MEMORY_MANAGER.get_group_lore(group_id, chrono::Duration::days(7), 5).await;
+        assert_eq!(lore, ["摸鱼大王"]);
+
+        let prompt = build_group_system_prompt(group_id, &[]).await;
+        assert!(prompt.contains("群里常聊的梗和话题：摸鱼大王"));
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn empty_reply_is_retried_with_higher_temperature() {
+        crate::config::use_test_data_dir();
+        let server = MockServer::start(vec![completion_response("  "), completion_response("你好呀")]).await;
+        let config: config::ModelConfig = kovi::toml::from_str(&format!(
+            "[server_config]\nurl = \"{}\"\napi_token = \"test-token\"\nempty_reply_retries = 1",
+            server.url
+        ))
+        .unwrap();
+
+        let reply = request_reply(&config, &[memory(Roles::User, "A:你好")], ModelOverrides::default()).await.unwrap();
+
+        assert_eq!(reply.memory.content, "你好呀");
+        assert_eq!(server.request_count(), 2);
+        let body = |i: usize| -> Value {
+            let request = server.request(i);
+            serde_json::from_str(request.split_once("\r\n\r\n").unwrap().1).unwrap()
+        };
+        assert!(body(1)["temperature"].as_f64().unwrap() > body(0)["temperature"].as_f64().unwrap());
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn english_speaker_gets_english_opener() {
+        crate::config::use_test_data_dir();
+        let server = MockServer::start(vec![completion_response("Hi, sorry to bother you~ what have you been up to?")]).await;
+        let config: config::ModelConfig = kovi::toml::from_str(&format!(
+            "[server_config]\nurl = \"{}\"\napi_token = \"test-token\"",
+            server.url
+        ))
+        .unwrap();
+        let language = detect_language("hey, what's up").unwrap();
+        assert_eq!(language, "en");
+
+        let opener = translate_with(&config, "你好，打扰一下～最近在忙什么呀？", language).await;
+
+        assert_eq!(opener, "Hi, sorry to bother you~ what have you been up to?");
+        assert!(server.request(0).contains("英语"));
+        // 中文用户不发起翻译请求
+        assert_eq!(translate_with(&config, "在吗", "zh").await, "在吗");
+        assert_eq!(server.request_count(), 1);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn tool_call_result_is_folded_into_final_reply() {
+        crate::config::use_test_data_dir();
+        let tool_call = json!({ "choices": [{ "message": {
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{
+                "id": "call_1",
+                "type": "function",
+                "function": { "name": "calculator", "arguments": "{\"expression\":\"12*3\"}" }
+            }]
+        } }] });
+        let server = MockServer::start(vec![
+            http_response("200 OK", &[], &tool_call.to_string()),
+            completion_response("算好啦，是36"),
+        ])
+        .await;
+        let config: config::ModelConfig = kovi::toml::from_str(&format!(
+            "[server_config]\nurl = \"{}\"\napi_token = \"test-token\"\ntools_enabled = true",
+            server.url
+        ))
+        .unwrap();
+
+        let reply = request_reply(&config, &[memory(Roles::User, "A:12乘3是多少")], ModelOverrides::default()).await.unwrap();
+
+        assert_eq!(reply.memory.content, "算好啦，是36");
+        assert_eq!(server.request_count(), 2);
+        let request = server.request(1);
+        let body: Value = serde_json::from_str(request.split_once("\r\n\r\n").unwrap().1).unwrap();
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages[1]["tool_calls"][0]["id"], "call_1");
+        assert_eq!(messages[2], json!({ "role": "tool", "tool_call_id": "call_1", "content": "12*3 = 36" }));
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn low_confidence_reply_is_hedged_or_silenced() {
+        crate::config::use_test_data_dir();
+        let low_confidence = |content: &str| {
+            let body = json!({ "choices": [{
+                "message": { "role": "assistant", "content": content },
+                "logprobs": { "content": [{ "token": "大", "logprob": -2.0 }, { "token": "概", "logprob": -1.5 }] }
+            }] });
+            http_response("200 OK", &[], &body.to_string())
+        };
+        let server = MockServer::start(vec![low_confidence("应该是周三"), low_confidence("应该是周三")]).await;
+        let config_with = |action: &str| -> config::ModelConfig {
+            kovi::toml::from_str(&format!(
+                "[server_config]\nurl = \"{}\"\napi_token = \"test-token\"\nmin_reply_confidence = 0.5\nlow_confidence_action = \"{}\"",
+                server.url, action
+            ))
+            .unwrap()
+        };
+        let messages = [memory(Roles::User, "A:下次开会是哪天")];
+
+        let hedge = config_with("hedge");
+        let hedged = request_reply(&hedge, &messages, ModelOverrides::default()).await.unwrap();
+        assert_eq!(hedged.memory.content, format!("应该是周三{}", hedge.server_config().low_confidence_hedge()));
+        assert!(server.request(0).contains("\"logprobs\":true"));
+
+        let silenced = request_reply(&config_with("silence"), &messages, ModelOverrides::default()).await.unwrap();
+        assert_eq!(silenced.memory.content, "[sp]");
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn group_analytics_renders_sections() {
+        crate::config::use_test_data_dir();
+        let group_id = 999_001;
+        let mut profile = GroupProfile::new(group_id);
+        profile.activity_level = 7;
+        profile.conversation_topics = vec!["游戏".to_string(), "美食".to_string()];
+        profile.record_member(1);
+
+        let report = describe_group_analytics(group_id, Some(&profile), Some(42)).await;
+        assert!(report.starts_with("📊 群 999001 的分析报告"));
+        assert!(report.contains("🔥 活跃度: 7/10"));
+        assert!(report.contains("👥 成员: 42 人（近一天发言 1 人）"));
+        assert!(report.contains("💬 近期话题: 游戏、美食"));
+        assert!(report.contains("😊 近期主导情绪: 暂无"));
+        assert!(report.contains("🧠 已存储记忆: 0 条"));
+
+        let empty = describe_group_analytics(999_002, None, None).await;
+        assert_eq!(empty, "这个群还没有足够的数据，多聊几句再来看看吧");
+    }
+
+    /// 取出消息中的文本内容
+    fn text_of(message: &Message) -> String {
+        message
+            .iter()
+            .filter(|segment| segment.type_ == "text")
+            .filter_map(|segment| segment.data["text"].as_str())
+            .collect()
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn no_memory_group_is_replied_to_without_storing() {
+        let server = MockServer::start(vec![completion_response("好呀，几点？")]).await;
+        let (manager, state) = mock::manager().await;
+        let group_id = 971_001;
+        let user_id = 971_002;
+        let config = model_config(
+            &server.url,
+            &format!(
+                "[chat]\nreply_delay_ms = 0\n[memory]\nno_memory_groups = [{}]\nno_memory_users = [{}]",
+                group_id, user_id
+            ),
+        );
+        let sent = std::sync::Mutex::new(Vec::new());
+        let send = |message: Message| {
+            sent.lock().unwrap().push(text_of(&message));
+            async { Ok(1) }
+        };
+
+        config::with_test_config(config, async {
+            record_group_message(&manager, group_id, "[12:00:00] 小明", "今晚一起打游戏").await;
+            assert_eq!(record_private_message(&manager, user_id, "谢谢你", "[12:00:00] 小明").await, None);
+            reply_group_turn(group_id, &send, "[12:00:00] 小明".to_string(), "今晚一起打游戏", None).await;
+        })
+        .await;
+
+        // 回复照常生成并发送，但对话记忆和用户档案都没有写入
+        assert_eq!(server.request_count(), 1);
+        assert_eq!(*sent.lock().unwrap(), ["好呀，几点？"]);
+        assert!(MEMORY_MANAGER.get_group_conversation_memories(group_id).await.is_empty());
+        let data = state.data.lock().unwrap();
+        assert!(data.memories.is_empty());
+        assert!(data.user_profiles.is_empty());
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn canned_trigger_replies_without_model_request() {
+        let server = MockServer::start(vec![completion_response("模型的回复")]).await;
+        let config = model_config(
+            &server.url,
+            "[chat]\nreply_delay_ms = 0\n[[chat.canned_responses]]\ntrigger = \"群规是什么\"\nreply = \"请看群公告\"",
+        );
+        let sent = std::sync::Mutex::new(Vec::new());
+        let send = |message: Message| {
+            sent.lock().unwrap().push(text_of(&message));
+            async { Ok(1) }
+        };
+
+        config::with_test_config(config.clone(), async {
+            reply_group_turn(979_001, &send, "[12:00:00] 小明".to_string(), "群规是什么", None).await;
+            private_chat_with(979_002, &send, "群规是什么", "[12:00:00] 小明".to_string()).await;
+        })
+        .await;
+        assert_eq!(*sent.lock().unwrap(), ["请看群公告", "请看群公告"]);
+        assert_eq!(server.request_count(), 0);
+
+        // 未命中固定回复的消息照常请求模型
+        config::with_test_config(config, async {
+            reply_group_turn(979_001, &send, "[12:00:01] 小明".to_string(), "今天吃什么", None).await;
+        })
+        .await;
+        assert_eq!(server.request_count(), 1);
+        assert_eq!(sent.lock().unwrap().last().unwrap(), "模型的回复");
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn debug_note_names_the_model_that_answered() {
+        let primary = MockServer::start(vec![
+            completion_response("你好呀"),
+            http_response("503 Service Unavailable", &[], "{}"),
+        ])
+        .await;
+        let fallback = MockServer::start(vec![completion_response("备用模型的回复")]).await;
+        let config = model_config(
+            &primary.url,
+            &format!(
+                "model_name = \"main-model\"\nrequest_retries = 0\n\
+                 [server_config.model_routes]\ngreeting = \"cheap-model\"\n\
+                 [[server_config.fallback_models]]\nurl = \"{}\"\nmodel_name = \"free-model\"",
+                fallback.url
+            ),
+        );
+        let overrides = ModelOverrides { message_class: Some(routing::classify("你好呀")), ..Default::default() };
+        let messages = [memory(Roles::User, "A:你好呀")];
+
+        // 按分类路由到的模型
+        let reply = request_reply(&config, &messages, overrides).await.unwrap();
+        assert_eq!(reply.model, "cheap-model");
+
+        // 路由的模型失败后由备用模型回复
+        let reply = request_reply(&config, &messages, overrides).await.unwrap();
+        assert_eq!(reply.memory.content, "备用模型的回复");
+        assert_eq!(reply.model, "free-model");
+        let note = describe_debug_decisions(1_014_001, None, &[], &reply.model).await;
+        assert!(note.contains("模型: free-model"));
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn invalid_token_is_an_auth_error_not_a_panic() {
+        let server = MockServer::start(vec![completion_response("你好")]).await;
+        let config: config::ModelConfig = kovi::toml::from_str(&format!(
+            "[server_config]\nurl = \"{}\"\napi_token = \"bad\\ntoken\"",
+            server.url
+        ))
+        .unwrap();
+
+        let Err(error) = request_reply(&config, &[memory(Roles::User, "A:你好")], ModelOverrides::default()).await else {
+            panic!("无效的令牌应当返回错误");
+        };
+
+        assert!(matches!(error, ProviderError::Auth(_)));
+        assert_eq!(server.request_count(), 0);
+    }
+}
@@ -8,18 +8,21 @@
 //! - 情绪缓存和性能优化
 //! - 人格特征动态调整
 
-use crate::memory::{MemoryManager, BotPersonality};
+use crate::config::TraitDelta;
+use crate::memory::{MemoryManager, MemoryType, BotPersonality};
 use chrono::{Duration, Local, Timelike};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::sync::Mutex;
 use anyhow::Result;
 
 /// 情绪状态枚举
 /// 
 /// 定义机器人可能的各种情绪状态，用于人格化和个性化交互
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+/// 声明顺序同时用于得分相同时的确定性裁决，越靠前越优先
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Mood {
     /// 开心：积极正面的情绪状态
     Happy,
@@ -47,9 +50,9 @@ pub enum Mood {
     Neutral,
 }
 
-impl Mood {
-    pub fn to_string(&self) -> String {
-        match self {
+impl std::fmt::Display for Mood {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
             Mood::Happy => "happy",
             Mood::Sad => "sad",
             Mood::Angry => "angry",
@@ -62,9 +65,12 @@ impl Mood {
             Mood::Confident => "confident",
             Mood::Shy => "shy",
             Mood::Neutral => "neutral",
-        }.to_string()
+        };
+        f.write_str(name)
     }
+}
 
+impl Mood {
     pub fn from_string(s: &str) -> Self {
         match s {
             "happy" => Mood::Happy,
@@ -83,6 +89,120 @@ impl Mood {
     }
 }
 
+/// 同一个表情符号在一条消息中最多计分的次数
+const MAX_EMOJI_REPEATS: usize = 3;
+
+/// 上下文情绪额外增加的得分，只在该情绪已有得分时生效，不足以压过强烈的消息信号
+const CONTEXT_MOOD_WEIGHT: i32 = 1;
+
+/// 高强度情绪阈值，情绪强度达到该值时要求模型明显表现出情绪
+pub const HIGH_INTENSITY_THRESHOLD: u8 = 8;
+
+/// 情绪发生切换时的初始强度
+const BASE_MOOD_INTENSITY: u8 = 5;
+
+/// 每日反思至少需要的情绪互动次数，互动太少时不调整人格
+const REFLECTION_MIN_INTERACTIONS: usize = 5;
+
+/// 每日反思能将社交信心调整到的下限和上限
+const REFLECTION_CONFIDENCE_FLOOR: u8 = 3;
+const REFLECTION_CONFIDENCE_CEILING: u8 = 8;
+
+/// 生成高强度情绪的语气指令
+/// 
+/// 情绪强度达到 [`HIGH_INTENSITY_THRESHOLD`] 时返回对应情绪的语气要求，
+/// 平静和中性情绪不需要额外指令
+pub fn high_intensity_directive(personality: &BotPersonality) -> Option<&'static str> {
+    if personality.mood_intensity < HIGH_INTENSITY_THRESHOLD {
+        return None;
+    }
+
+    match Mood::from_string(&personality.current_mood) {
+        Mood::Happy => Some("你现在非常开心，语气要充满活力"),
+        Mood::Sad => Some("你现在非常难过，语气要低落、轻柔一些"),
+        Mood::Angry => Some("你现在非常生气，语气可以带点小脾气，但不要失礼"),
+        Mood::Excited => Some("你现在非常兴奋，可以多用感叹的语气"),
+        Mood::Curious => Some("你现在非常好奇，可以多追问一些细节"),
+        Mood::Playful => Some("你现在非常想调皮，语气要俏皮一些"),
+        Mood::Thoughtful => Some("你现在正沉浸在思考中，语气要沉稳认真"),
+        Mood::Lonely => Some("你现在非常孤单，语气要柔软，流露出想要陪伴的心情"),
+        Mood::Confident => Some("你现在非常自信，语气要干脆利落"),
+        Mood::Shy => Some("你现在非常害羞，语气要温柔腼腆"),
+        Mood::Calm | Mood::Neutral => None,
+    }
+}
+
+/// 情绪缓存最多保存的条目数
+const MOOD_CACHE_CAPACITY: usize = 512;
+
+/// 情绪缓存条目
+struct MoodCacheEntry {
+    /// 分析出的情绪
+    mood: Mood,
+    /// 缓存写入时间，用于过期判断
+    cached_at: chrono::DateTime<Local>,
+    /// 最近一次访问序号，用于LRU淘汰
+    last_used: u64,
+}
+
+/// 情绪缓存
+/// 
+/// 以消息和上下文的哈希值为键，同时按过期时间和容量淘汰
+struct MoodCache {
+    entries: HashMap<u64, MoodCacheEntry>,
+    capacity: usize,
+    /// 单调递增的访问计数
+    tick: u64,
+}
+
+impl MoodCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            capacity,
+            tick: 0,
+        }
+    }
+
+    fn key(message: &str, context: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        message.hash(&mut hasher);
+        context.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// 获取未过期的缓存情绪，并刷新其访问顺序
+    fn get(&mut self, key: u64, now: chrono::DateTime<Local>, ttl: Duration) -> Option<Mood> {
+        self.tick += 1;
+        let tick = self.tick;
+        let entry = self.entries.get_mut(&key)?;
+        if now.signed_duration_since(entry.cached_at) >= ttl {
+            return None;
+        }
+        entry.last_used = tick;
+        Some(entry.mood.clone())
+    }
+
+    /// 写入缓存，清理过期条目，超出容量时淘汰最久未使用的条目
+    fn insert(&mut self, key: u64, mood: Mood, now: chrono::DateTime<Local>, expire_after: Duration) {
+        self.tick += 1;
+        self.entries.insert(key, MoodCacheEntry { mood, cached_at: now, last_used: self.tick });
+
+        self.entries.retain(|_, entry| now.signed_duration_since(entry.cached_at) < expire_after);
+
+        while self.entries.len() > self.capacity {
+            let Some(oldest) = self.entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key)
+            else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+}
+
 /// 情绪系统结构体
 /// 
 /// 负责分析用户消息的情绪并调整机器人的人格状态
@@ -91,7 +211,7 @@ pub struct MoodSystem {
     /// 记忆管理器引用，用于获取和更新机器人人格
     memory_manager: Arc<MemoryManager>,
     /// 情绪分析缓存，避免重复计算相同消息的情绪
-    mood_cache: Arc<Mutex<HashMap<String, (Mood, chrono::DateTime<Local>)>>>,
+    mood_cache: Arc<Mutex<MoodCache>>,
 }
 
 impl MoodSystem {
@@ -105,7 +225,7 @@ impl MoodSystem {
     pub fn new(memory_manager: Arc<MemoryManager>) -> Self {
         Self { 
             memory_manager,
-            mood_cache: Arc::new(Mutex::new(HashMap::new())),
+            mood_cache: Arc::new(Mutex::new(MoodCache::new(MOOD_CACHE_CAPACITY))),
         }
     }
 
@@ -126,16 +246,16 @@ impl MoodSystem {
     /// 成功时返回分析出的情绪状态，失败时返回错误
     pub async fn analyze_and_update_mood(&self, message: &str, context: &str) -> Result<Mood> {
         // 检查缓存
-        let cache_key = format!("{}:{}", message, context);
+        let cache_key = MoodCache::key(message, context);
         let now = Local::now();
         
         {
-            let cache = self.mood_cache.lock().unwrap();
-            if let Some((cached_mood, cache_time)) = cache.get(&cache_key) {
-                // 如果缓存时间在5分钟内，直接返回缓存结果
-                if now.signed_duration_since(*cache_time) < Duration::minutes(5) {
-                    return Ok(cached_mood.clone());
-                }
+            // 如果缓存时间在5分钟内，直接返回缓存结果
+            let mut cache = self.mood_cache.lock().unwrap();
+            let cached = cache.get(cache_key, now, Duration::minutes(5));
+            crate::health_check::record_mood_cache(cached.is_some());
+            if let Some(cached_mood) = cached {
+                return Ok(cached_mood);
             }
         }
 
@@ -145,27 +265,51 @@ impl MoodSystem {
         // 更新缓存
         {
             let mut cache = self.mood_cache.lock().unwrap();
-            cache.insert(cache_key, (new_mood.clone(), now));
-            
-            // 清理过期缓存
-            cache.retain(|_, (_, cache_time)| {
-                now.signed_duration_since(*cache_time) < Duration::hours(1)
-            });
+            cache.insert(cache_key, new_mood.clone(), now, Duration::hours(1));
         }
         
-        // 更新机器人人格
+        // 更新机器人人格，同一情绪持续出现时强度逐步升高，切换情绪时重置
         let mut updated_personality = current_personality;
+        updated_personality.mood_intensity = if updated_personality.current_mood == new_mood.to_string() {
+            (updated_personality.mood_intensity + 1).min(10)
+        } else {
+            BASE_MOOD_INTENSITY
+        };
         updated_personality.current_mood = new_mood.to_string();
         updated_personality.last_mood_change = now;
         
         // 根据情绪调整其他属性
-        self.adjust_personality_traits(&mut updated_personality, &new_mood);
+        Self::adjust_personality_traits(&mut updated_personality, crate::config::get().mood().trait_delta(&new_mood));
         
-        self.memory_manager.update_bot_personality(updated_personality).await?;
+        let trigger = format!("{}: {}", context, message.chars().take(30).collect::<String>());
+        self.memory_manager.update_bot_personality(updated_personality, &trigger).await?;
         
         Ok(new_mood)
     }
 
+    /// 模拟情绪分析
+    /// 
+    /// 对任意文本执行完整的情绪评分和裁决，但不修改机器人人格，也不写入缓存，
+    /// 用于调试情绪关键词
+    /// 
+    /// # 参数
+    /// * `message` - 要分析的文本
+    /// * `context` - 分析使用的上下文
+    /// 
+    /// # 返回值
+    /// 按得分从高到低排列的各情绪得分，以及最终裁决的情绪
+    pub async fn simulate_mood(&self, message: &str, context: &str) -> (Vec<(Mood, i32)>, Mood) {
+        let current_personality = self.memory_manager.get_bot_personality().await;
+        let mut scores: Vec<(Mood, i32)> = self
+            .calculate_mood_scores(&message.to_lowercase())
+            .into_iter()
+            .collect();
+        scores.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let mood = self.analyze_mood_from_message(message, context, &current_personality).await;
+        (scores, mood)
+    }
+
     async fn analyze_mood_from_message(
         &self,
         message: &str,
@@ -181,9 +325,7 @@ impl MoodSystem {
         let context_mood = self.analyze_context_mood(context);
         
         // 结合当前情绪状态
-        let final_mood = self.combine_mood_analysis(mood_scores, context_mood, current_personality);
-
-        final_mood
+        self.combine_mood_analysis(mood_scores, context_mood, current_personality)
     }
 
     /// 计算消息的情绪得分
@@ -193,18 +335,19 @@ impl MoodSystem {
     /// ## 评分规则
     /// - **高权重关键词** (+2分)：开心、难过、生气、兴奋、孤独等强烈情绪
     /// - **中权重关键词** (+1分)：好奇、顽皮、深思、自信、害羞等温和情绪
+    /// - **表情符号**：按情绪配置中的表情表计分，每个表情最多计 3 次
     /// 
     /// ## 关键词分类
-    /// - **开心**：开心、高兴、快乐、哈哈、😊、😄、好棒、太好了、喜欢
-    /// - **难过**：难过、伤心、哭、😢、😭、糟糕、不好、讨厌
-    /// - **生气**：生气、愤怒、讨厌、烦、😠、😡、气死
-    /// - **兴奋**：兴奋、激动、太棒了、哇、！、!!!、😆、😃
+    /// - **开心**：开心、高兴、快乐、哈哈、好棒、太好了、喜欢
+    /// - **难过**：难过、伤心、哭、糟糕、不好、讨厌
+    /// - **生气**：生气、愤怒、讨厌、烦、气死
+    /// - **兴奋**：兴奋、激动、太棒了、哇、！、!!!
     /// - **好奇**：什么、为什么、怎么、？、???、好奇、想知道
-    /// - **顽皮**：调皮、顽皮、哈哈、嘿嘿、😏、😜、开玩笑
+    /// - **顽皮**：调皮、顽皮、哈哈、嘿嘿、开玩笑
     /// - **深思**：思考、想想、觉得、认为、可能、也许
     /// - **孤独**：一个人、孤单、寂寞、没人、只有我
     /// - **自信**：肯定、一定、当然、没问题、我可以、我能
-    /// - **害羞**：害羞、不好意思、脸红、😳、尴尬
+    /// - **害羞**：害羞、不好意思、脸红、尴尬
     /// 
     /// # 参数
     /// * `message` - 要分析的消息内容
@@ -222,7 +365,7 @@ impl MoodSystem {
         }
 
         // 开心关键词
-        let happy_keywords = ["开心", "高兴", "快乐", "哈哈", "😊", "😄", "好棒", "太好了", "喜欢"];
+        let happy_keywords = ["开心", "高兴", "快乐", "哈哈", "好棒", "太好了", "喜欢"];
         for keyword in &happy_keywords {
             if message.contains(keyword) {
                 *scores.get_mut(&Mood::Happy).unwrap() += 2;
@@ -230,7 +373,7 @@ impl MoodSystem {
         }
 
         // 难过关键词
-        let sad_keywords = ["难过", "伤心", "哭", "😢", "😭", "糟糕", "不好", "讨厌"];
+        let sad_keywords = ["难过", "伤心", "哭", "糟糕", "不好", "讨厌"];
         for keyword in &sad_keywords {
             if message.contains(keyword) {
                 *scores.get_mut(&Mood::Sad).unwrap() += 2;
@@ -238,7 +381,7 @@ impl MoodSystem {
         }
 
         // 生气关键词
-        let angry_keywords = ["生气", "愤怒", "讨厌", "烦", "😠", "😡", "气死"];
+        let angry_keywords = ["生气", "愤怒", "讨厌", "烦", "气死"];
         for keyword in &angry_keywords {
             if message.contains(keyword) {
                 *scores.get_mut(&Mood::Angry).unwrap() += 2;
@@ -246,7 +389,7 @@ impl MoodSystem {
         }
 
         // 兴奋关键词
-        let excited_keywords = ["兴奋", "激动", "太棒了", "哇", "！", "!!!", "😆", "😃"];
+        let excited_keywords = ["兴奋", "激动", "太棒了", "哇", "！", "!!!"];
         for keyword in &excited_keywords {
             if message.contains(keyword) {
                 *scores.get_mut(&Mood::Excited).unwrap() += 2;
@@ -262,7 +405,7 @@ impl MoodSystem {
         }
 
         // 顽皮关键词
-        let playful_keywords = ["调皮", "顽皮", "哈哈", "嘿嘿", "😏", "😜", "开玩笑"];
+        let playful_keywords = ["调皮", "顽皮", "哈哈", "嘿嘿", "开玩笑"];
         for keyword in &playful_keywords {
             if message.contains(keyword) {
                 *scores.get_mut(&Mood::Playful).unwrap() += 1;
@@ -294,13 +437,22 @@ impl MoodSystem {
         }
 
         // 害羞关键词
-        let shy_keywords = ["害羞", "不好意思", "脸红", "😳", "尴尬"];
+        let shy_keywords = ["害羞", "不好意思", "脸红", "尴尬"];
         for keyword in &shy_keywords {
             if message.contains(keyword) {
                 *scores.get_mut(&Mood::Shy).unwrap() += 1;
             }
         }
 
+        // 表情符号，与文字关键词分开计分
+        let config = crate::config::get();
+        for (emoji, rule) in config.mood().emoji_moods() {
+            let count = message.matches(emoji.as_str()).count().min(MAX_EMOJI_REPEATS);
+            if count > 0 {
+                *scores.entry(Mood::from_string(&rule.mood)).or_insert(0) += rule.weight * count as i32;
+            }
+        }
+
         scores
     }
 
@@ -318,78 +470,56 @@ impl MoodSystem {
         }
     }
 
+    /// 综合关键词得分、上下文和当前人格确定最终情绪
+    /// 
+    /// ## 裁决规则
+    /// 1. 得分最高的情绪胜出，单一强烈信号总能胜出
+    /// 2. 得分相同时优先保持当前情绪，避免在相近情绪间反复跳动
+    /// 3. 仍无法区分时按情绪声明顺序确定，保证结果可预期
+    /// 4. 上下文情绪有得分时额外加分，只在与其他情绪得分接近时起决定作用
+    /// 5. 所有情绪都没有得分时，精力充沛则保持当前情绪，否则转为中性
     fn combine_mood_analysis(
         &self,
-        mood_scores: std::collections::HashMap<Mood, i32>,
+        mut mood_scores: std::collections::HashMap<Mood, i32>,
         context_mood: Option<Mood>,
         current_personality: &BotPersonality,
     ) -> Mood {
-        // 找到得分最高的情绪
-        let mut best_mood = Mood::Neutral;
-        let mut best_score = 0;
-
-        for (mood, score) in &mood_scores {
-            if *score > best_score {
-                best_score = *score;
-                best_mood = mood.clone();
-            }
-        }
+        let current_mood = Mood::from_string(&current_personality.current_mood);
 
-        // 如果上下文有特殊情绪，给予额外权重
-        if let Some(context_mood) = context_mood {
-            if let Some(context_score) = mood_scores.get(&context_mood) {
-                if *context_score > 0 {
-                    best_mood = context_mood;
-                }
-            }
+        // 上下文情绪作为附加权重参与比较，而不是直接覆盖消息情绪
+        if let Some(context_mood) = context_mood
+            && let Some(context_score) = mood_scores.get_mut(&context_mood)
+            && *context_score > 0
+        {
+            *context_score += CONTEXT_MOOD_WEIGHT;
         }
 
+        // 找到得分最高的情绪，平分时依次按当前情绪和声明顺序裁决
+        let best = mood_scores
+            .iter()
+            .filter(|(_, score)| **score > 0)
+            .max_by_key(|(mood, score)| (**score, **mood == current_mood, std::cmp::Reverse(*mood)))
+            .map(|(mood, _)| mood.clone());
+
         // 如果所有情绪得分都很低，保持当前情绪或转为中性
-        if best_score == 0 {
-            let current_mood = Mood::from_string(&current_personality.current_mood);
+        let Some(best_mood) = best else {
             return if current_personality.energy_level > 5 {
                 current_mood
             } else {
                 Mood::Neutral
-            }
-        }
+            };
+        };
 
         best_mood
     }
 
-    fn adjust_personality_traits(&self, personality: &mut BotPersonality, mood: &Mood) {
-        match mood {
-            Mood::Happy | Mood::Excited => {
-                personality.energy_level = (personality.energy_level + 1).min(10);
-                personality.social_confidence = (personality.social_confidence + 1).min(10);
-            },
-            Mood::Sad | Mood::Lonely => {
-                personality.energy_level = personality.energy_level.saturating_sub(1);
-                personality.social_confidence = personality.social_confidence.saturating_sub(1);
-            },
-            Mood::Angry => {
-                personality.energy_level = (personality.energy_level + 1).min(10);
-                personality.social_confidence = personality.social_confidence.saturating_sub(1);
-            },
-            Mood::Calm | Mood::Thoughtful => {
-                personality.energy_level = personality.energy_level.saturating_sub(1);
-                personality.curiosity_level = (personality.curiosity_level + 1).min(10);
-            },
-            Mood::Curious => {
-                personality.curiosity_level = (personality.curiosity_level + 2).min(10);
-            },
-            Mood::Playful => {
-                personality.energy_level = (personality.energy_level + 1).min(10);
-                personality.social_confidence = (personality.social_confidence + 1).min(10);
-            },
-            Mood::Confident => {
-                personality.social_confidence = (personality.social_confidence + 2).min(10);
-            },
-            Mood::Shy => {
-                personality.social_confidence = personality.social_confidence.saturating_sub(2);
-            },
-            _ => {}
-        }
+    /// 根据情绪调整人格属性
+    /// 
+    /// 每种情绪对能量、社交信心、好奇心的调整幅度由情绪配置决定
+    fn adjust_personality_traits(personality: &mut BotPersonality, delta: TraitDelta) {
+        personality.energy_level = TraitDelta::apply(personality.energy_level, delta.energy);
+        personality.social_confidence = TraitDelta::apply(personality.social_confidence, delta.social_confidence);
+        personality.curiosity_level = TraitDelta::apply(personality.curiosity_level, delta.curiosity);
     }
 
     pub async fn get_mood_based_response_style(&self) -> String {
@@ -421,6 +551,60 @@ impl MoodSystem {
         time_since_last_change > Duration::hours(2)
     }
 
+    /// 每日反思
+    /// 
+    /// 到达配置的反思时间后每天执行一次：回顾当天的对话记忆，
+    /// 积极互动明显多于消极互动时社交信心 +1，反之 -1，
+    /// 调整结果限制在 [`REFLECTION_CONFIDENCE_FLOOR`, `REFLECTION_CONFIDENCE_CEILING`] 之间
+    /// 
+    /// # 返回值
+    /// 本次执行了反思时返回 `true`
+    pub async fn daily_reflection(&self) -> Result<bool> {
+        let config = crate::config::get();
+        let now = Local::now();
+        if !config.memory().daily_reflection_enabled() || now.hour() < config.memory().reflection_hour() {
+            return Ok(false);
+        }
+
+        let mut personality = self.memory_manager.get_bot_personality().await;
+        let today = now.date_naive();
+        if personality.last_reflection == Some(today) {
+            return Ok(false);
+        }
+
+        let (positive, negative) = self
+            .memory_manager
+            .get_memories_by_type(&MemoryType::Conversation)
+            .await
+            .iter()
+            .filter(|memory| memory.timestamp.date_naive() == today && !memory.is_bot_authored())
+            .filter_map(|memory| self.dominant_mood(&memory.content))
+            .fold((0, 0), |(positive, negative), mood| match mood {
+                Mood::Happy | Mood::Excited | Mood::Playful | Mood::Confident => (positive + 1, negative),
+                Mood::Sad | Mood::Angry | Mood::Lonely => (positive, negative + 1),
+                _ => (positive, negative),
+            });
+
+        personality.social_confidence = reflect_social_confidence(personality.social_confidence, positive, negative);
+        personality.last_reflection = Some(today);
+        println!(
+            "[INFO] 每日反思完成: 积极互动 {} 次, 消极互动 {} 次, 社交信心 {}",
+            positive, negative, personality.social_confidence
+        );
+        self.memory_manager.update_bot_personality(personality, "daily_reflection").await?;
+
+        Ok(true)
+    }
+
+    /// 获取文本中得分最高的情绪，没有命中任何情绪关键词时返回 `None`
+    pub fn dominant_mood(&self, content: &str) -> Option<Mood> {
+        self.calculate_mood_scores(&content.to_lowercase())
+            .into_iter()
+            .filter(|(_, score)| *score > 0)
+            .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+            .map(|(mood, _)| mood)
+    }
+
     pub async fn natural_mood_drift(&self) -> Result<()> {
         if !self.should_change_mood_naturally().await {
             return Ok(());
@@ -443,8 +627,178 @@ impl MoodSystem {
         personality.current_mood = new_mood.to_string();
         personality.last_mood_change = Local::now();
         
-        self.memory_manager.update_bot_personality(personality).await?;
+        self.memory_manager.update_bot_personality(personality, "natural_mood_drift").await?;
         
         Ok(())
     }
 }
+
+/// 根据当天的积极和消极互动次数计算反思后的社交信心
+/// 
+/// 每次最多调整1点，且只会在上下限之间移动，不会把原本超出范围的值拉回
+/// 
+/// # 参数
+/// * `current` - 当前社交信心
+/// * `positive` - 积极互动次数
+/// * `negative` - 消极互动次数
+fn reflect_social_confidence(current: u8, positive: usize, negative: usize) -> u8 {
+    if positive + negative < REFLECTION_MIN_INTERACTIONS {
+        return current;
+    }
+
+    if positive >= negative * 2 && current < REFLECTION_CONFIDENCE_CEILING {
+        current + 1
+    } else if negative >= positive * 2 && current > REFLECTION_CONFIDENCE_FLOOR {
+        current - 1
+    } else {
+        current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::mock;
+
+    async fn mood_system() -> (MoodSystem, BotPersonality) {
+        let (manager, _state) = mock::manager().await;
+        let manager = Arc::new(manager);
+        let personality = manager.get_bot_personality().await;
+        (MoodSystem::new(manager), personality)
+    }
+
+    fn scores(entries: &[(Mood, i32)]) -> HashMap<Mood, i32> {
+        entries.iter().cloned().collect()
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn tied_scores_resolve_predictably() {
+        let (system, mut personality) = mood_system().await;
+
+        // 平分时按声明顺序裁决
+        let tied = scores(&[(Mood::Sad, 2), (Mood::Angry, 2)]);
+        assert_eq!(system.combine_mood_analysis(tied.clone(), None, &personality), Mood::Sad);
+
+        // 平分时优先保持当前情绪
+        personality.current_mood = "angry".to_string();
+        assert_eq!(system.combine_mood_analysis(tied, None, &personality), Mood::Angry);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn single_strong_signal_wins() {
+        let (system, mut personality) = mood_system().await;
+        personality.current_mood = "playful".to_string();
+
+        let strong = scores(&[(Mood::Happy, 4), (Mood::Playful, 1)]);
+        assert_eq!(system.combine_mood_analysis(strong, Some(Mood::Playful), &personality), Mood::Happy);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn high_intensity_adds_directive() {
+        let (_system, mut personality) = mood_system().await;
+        personality.current_mood = "happy".to_string();
+
+        personality.mood_intensity = HIGH_INTENSITY_THRESHOLD;
+        assert_eq!(high_intensity_directive(&personality), Some("你现在非常开心，语气要充满活力"));
+
+        personality.mood_intensity = HIGH_INTENSITY_THRESHOLD - 1;
+        assert_eq!(high_intensity_directive(&personality), None);
+
+        // 平静情绪即便强度很高也不需要额外指令
+        personality.current_mood = "calm".to_string();
+        personality.mood_intensity = 10;
+        assert_eq!(high_intensity_directive(&personality), None);
+    }
+
+    #[test]
+    fn mood_cache_never_exceeds_capacity() {
+        let mut cache = MoodCache::new(3);
+        let now = Local::now();
+        let ttl = Duration::hours(1);
+
+        for i in 0..10 {
+            cache.insert(MoodCache::key(&format!("消息{}", i), "group_chat"), Mood::Happy, now, ttl);
+            assert!(cache.entries.len() <= 3);
+        }
+
+        // 最近访问过的条目不会被淘汰
+        let kept = MoodCache::key("消息7", "group_chat");
+        assert_eq!(cache.get(kept, now, ttl), Some(Mood::Happy));
+        cache.insert(MoodCache::key("消息10", "group_chat"), Mood::Sad, now, ttl);
+        assert_eq!(cache.get(kept, now, ttl), Some(Mood::Happy));
+        assert_eq!(cache.get(MoodCache::key("消息8", "group_chat"), now, ttl), None);
+    }
+
+    #[test]
+    fn positive_day_nudges_confidence_within_bounds() {
+        let positive = REFLECTION_MIN_INTERACTIONS;
+        assert_eq!(reflect_social_confidence(6, positive, 0), 7);
+        assert_eq!(reflect_social_confidence(REFLECTION_CONFIDENCE_CEILING, positive, 0), REFLECTION_CONFIDENCE_CEILING);
+
+        assert_eq!(reflect_social_confidence(6, 0, positive), 5);
+        assert_eq!(reflect_social_confidence(REFLECTION_CONFIDENCE_FLOOR, 0, positive), REFLECTION_CONFIDENCE_FLOOR);
+
+        // 互动太少或好坏参半时不调整
+        assert_eq!(reflect_social_confidence(6, REFLECTION_MIN_INTERACTIONS - 1, 0), 6);
+        assert_eq!(reflect_social_confidence(6, positive, positive), 6);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn custom_trait_deltas_change_personality() {
+        let (_system, personality) = mood_system().await;
+        let default_config = crate::config::ModelConfig::default();
+        let custom: crate::config::ModelConfig =
+            kovi::toml::from_str("[mood.trait_deltas]\nhappy = { energy = 3, curiosity = -2 }").unwrap();
+
+        let mut with_default = personality.clone();
+        MoodSystem::adjust_personality_traits(&mut with_default, default_config.mood().trait_delta(&Mood::Happy));
+        let mut with_custom = personality.clone();
+        MoodSystem::adjust_personality_traits(&mut with_custom, custom.mood().trait_delta(&Mood::Happy));
+
+        assert_eq!(with_default.energy_level, personality.energy_level + 1);
+        assert_eq!(with_custom.energy_level, personality.energy_level + 3);
+        assert_eq!(with_custom.social_confidence, personality.social_confidence);
+        assert_eq!(with_custom.curiosity_level, personality.curiosity_level - 2);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn emoji_only_message_reads_as_sad() {
+        crate::config::use_test_data_dir();
+        let (system, _personality) = mood_system().await;
+
+        let (scores, mood) = system.simulate_mood("😭😭", "").await;
+        assert_eq!(mood, Mood::Sad);
+        assert_eq!(scores.first(), Some(&(Mood::Sad, 4)));
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn strongly_sad_message_in_group_stays_sad() {
+        crate::config::use_test_data_dir();
+        let (system, mut personality) = mood_system().await;
+        personality.current_mood = "playful".to_string();
+
+        // 群聊上下文偏向顽皮，但只作为附加权重，不能盖过强烈的悲伤
+        let sad_with_playful = scores(&[(Mood::Sad, 6), (Mood::Playful, 1)]);
+        assert_eq!(system.combine_mood_analysis(sad_with_playful, Some(Mood::Playful), &personality), Mood::Sad);
+
+        let (_, mood) = system.simulate_mood("今天好难过，伤心得想哭", "群聊").await;
+        assert_eq!(mood, Mood::Sad);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn repeated_message_within_ttl_counts_cache_hit() {
+        crate::config::use_test_data_dir();
+        let (system, _personality) = mood_system().await;
+        let before = crate::health_check::mood_cache_metrics();
+
+        let first = system.analyze_and_update_mood("今天真开心", "private_chat").await.unwrap();
+        let after_miss = crate::health_check::mood_cache_metrics();
+        let second = system.analyze_and_update_mood("今天真开心", "private_chat").await.unwrap();
+        let after_hit = crate::health_check::mood_cache_metrics();
+
+        assert_eq!(first, second);
+        // 计数器是全局的，其他测试可能同时累加
+        assert!(after_miss.misses > before.misses);
+        assert!(after_hit.hits > after_miss.hits);
+    }
+}
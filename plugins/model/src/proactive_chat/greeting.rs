@@ -0,0 +1,170 @@
+//! # 定时问候模块
+//!
+//! 在配置的时间向开启问候的群组发送早安、晚安等问候，
+//! 与一般的主动聊天相互独立，每条问候每天最多发送一次
+
+use crate::config::{self, ScheduledGreeting};
+use crate::memory::MemoryManager;
+use crate::mood_system::MoodSystem;
+use crate::utils;
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime};
+use kovi::RuntimeBot;
+use kovi::tokio::time::sleep;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 问候时刻之后仍可补发的时间窗口（分钟），避免在问候时刻很久之后启动时误发
+const GREETING_WINDOW_MINUTES: i64 = 10;
+
+/// 定时问候调度器
+pub struct GreetingScheduler {
+    /// 记忆管理器，用于记录发送过的问候
+    memory_manager: Arc<MemoryManager>,
+    /// 情绪系统，用于根据情绪调整问候语气
+    mood_system: MoodSystem,
+    /// 机器人实例，用于发送消息
+    bot: Arc<RuntimeBot>,
+    /// 每条问候最后发送的日期 (问候序号 -> 日期)
+    last_sent: Mutex<HashMap<usize, NaiveDate>>,
+}
+
+impl GreetingScheduler {
+    pub fn new(memory_manager: Arc<MemoryManager>, bot: Arc<RuntimeBot>) -> Self {
+        let mood_system = MoodSystem::new(Arc::clone(&memory_manager));
+
+        Self {
+            memory_manager,
+            mood_system,
+            bot,
+            last_sent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn start_greeting_loop(&self) {
+        loop {
+            self.send_due_greetings(Local::now()).await;
+
+            // 每30秒检查一次
+            sleep(Duration::from_secs(30)).await;
+        }
+    }
+
+    async fn send_due_greetings(&self, now: DateTime<Local>) {
+        let config = config::get();
+        let greeting_config = config.greeting();
+
+        // 开发模式和安全模式下不在群聊中主动发言
+        if !greeting_config.enabled() || config.chat().dev_mode() || config.chat().safe_mode() {
+            return;
+        }
+
+        // 免打扰时段内不发送问候
+        if config.proactive().is_quiet_time(now) {
+            return;
+        }
+
+        let due = take_due_greetings(&mut self.last_sent.lock().unwrap(), greeting_config.greetings(), now);
+        for index in due {
+            let greeting = &greeting_config.greetings()[index];
+            let message = self.compose_message(greeting, now).await;
+
+            for &group_id in greeting_config.groups() {
+                // 与主动话题共用发送间隔，同一群组短时间内不连续发送主动消息
+                if !utils::reserve_proactive_group(group_id) {
+                    println!("[INFO] 群组 {} 刚收到过主动消息，跳过本次定时问候", group_id);
+                    continue;
+                }
+
+                if utils::send_group_message(&self.bot, group_id, &message).await.is_err() {
+                    continue;
+                }
+
+                println!("[INFO] 定时问候已发送 (群组: {}): {}", group_id, message);
+                if let Err(e) = self.memory_manager.add_conversation_memory(
+                    group_id,
+                    &format!("定时问候: {}", message),
+                    "proactive_group_greeting"
+                ).await {
+                    eprintln!("[ERROR] 定时问候记忆记录失败 (群组: {}): {}", group_id, e);
+                }
+            }
+        }
+    }
+
+    /// 组织问候语：按日期轮换候选问候语，并加上当前情绪的语气
+    async fn compose_message(&self, greeting: &ScheduledGreeting, now: DateTime<Local>) -> String {
+        let candidates: Vec<&String> = greeting
+            .messages
+            .iter()
+            .filter(|message| !message.trim().is_empty())
+            .collect();
+        let content = candidates[now.ordinal() as usize % candidates.len()];
+
+        let mood_prefix = self.mood_system.get_mood_based_response_style().await;
+        if mood_prefix.is_empty() {
+            content.clone()
+        } else {
+            format!("（{}）{}", mood_prefix, content)
+        }
+    }
+}
+
+/// 找出当前到期且今天尚未发送的问候，并标记为已发送
+///
+/// # 参数
+/// * `last_sent` - 每条问候最后发送的日期
+/// * `greetings` - 配置的问候列表
+/// * `now` - 当前时间
+///
+/// # 返回值
+/// 到期问候在列表中的序号
+fn take_due_greetings(
+    last_sent: &mut HashMap<usize, NaiveDate>,
+    greetings: &[ScheduledGreeting],
+    now: DateTime<Local>,
+) -> Vec<usize> {
+    let today = now.date_naive();
+
+    let due: Vec<usize> = greetings
+        .iter()
+        .enumerate()
+        .filter(|(index, greeting)| {
+            let Some(scheduled) = NaiveTime::from_hms_opt(greeting.hour, greeting.minute, 0) else {
+                return false;
+            };
+            let elapsed = now.time().signed_duration_since(scheduled);
+            elapsed >= chrono::Duration::zero()
+                && elapsed < chrono::Duration::minutes(GREETING_WINDOW_MINUTES)
+                && last_sent.get(index) != Some(&today)
+        })
+        .map(|(index, _)| index)
+        .collect();
+
+    for index in &due {
+        last_sent.insert(*index, today);
+    }
+    due
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 10, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn greeting_fires_once_per_day_at_configured_time() {
+        let greetings = vec![ScheduledGreeting { hour: 8, minute: 0, messages: vec!["早安".to_string()] }];
+        let mut last_sent = HashMap::new();
+
+        assert!(take_due_greetings(&mut last_sent, &greetings, at(1, 7, 59)).is_empty());
+        assert_eq!(take_due_greetings(&mut last_sent, &greetings, at(1, 8, 0)), vec![0]);
+        assert!(take_due_greetings(&mut last_sent, &greetings, at(1, 8, 5)).is_empty());
+        assert!(take_due_greetings(&mut last_sent, &greetings, at(1, 8, 30)).is_empty());
+        assert_eq!(take_due_greetings(&mut last_sent, &greetings, at(2, 8, 1)), vec![0]);
+    }
+}
@@ -6,16 +6,23 @@
 //! - 活跃度检测和时机判断
 //! - 话题生成和个性化聊天
 
-use crate::memory::MemoryManager;
+use crate::config;
+use crate::memory::{GroupProfile, MemoryManager, RelationshipTier, UserProfile};
 use crate::topic_generator::TopicGenerator;
 use crate::mood_system::MoodSystem;
+use crate::utils;
 use kovi::RuntimeBot;
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Duration;
 use kovi::tokio::time::sleep;
 use anyhow::Result;
 use chrono::Local;
 
+/// 关闭主动聊天时，情绪变化和每日反思的检查间隔（秒）
+const IDLE_CHECK_INTERVAL_SECS: u64 = 300;
+
+pub mod greeting;
 pub mod startup;
 
 /// 主动聊天管理器
@@ -52,19 +59,34 @@ impl ProactiveChatManager {
                 eprintln!("Failed to update mood naturally: {}", e);
             }
 
+            // 每日反思，根据当天的互动小幅调整基础人格
+            if let Err(e) = self.mood_system.daily_reflection().await {
+                eprintln!("[ERROR] 每日反思失败: {}", e);
+            }
+
+            // 检查间隔为0时关闭主动聊天，情绪变化和每日反思照常进行
+            let interval = config::get().proactive().check_interval_secs();
+
             // 检查是否应该主动发起对话
-            if self.should_initiate_chat().await {
-                if let Err(e) = self.try_initiate_chat().await {
-                    eprintln!("Failed to initiate chat: {}", e);
-                }
+            if interval > 0
+                && self.should_initiate_chat().await
+                && let Err(e) = self.try_initiate_chat().await
+            {
+                eprintln!("Failed to initiate chat: {}", e);
             }
 
             // 等待一段时间再检查
-            sleep(Duration::from_secs(300)).await; // 5分钟检查一次
+            let wait = if interval > 0 { interval } else { IDLE_CHECK_INTERVAL_SECS };
+            sleep(Duration::from_secs(wait)).await;
         }
     }
 
     async fn should_initiate_chat(&self) -> bool {
+        // 免打扰时段内不主动发起话题
+        if config::get().proactive().is_quiet_time(Local::now()) {
+            return false;
+        }
+
         let personality = self.memory_manager.get_bot_personality().await;
         
         // 检查基本条件
@@ -87,9 +109,25 @@ impl ProactiveChatManager {
     }
 
     async fn try_initiate_chat(&self) -> Result<()> {
+        // 安全模式下不主动发言
+        if config::get().chat().safe_mode() {
+            return Ok(());
+        }
+
         // 获取所有群组和用户
-        let groups = self.get_active_groups().await;
-        let users = self.get_active_users().await;
+        let mut groups = self.get_active_groups().await;
+        let mut users = self.get_active_users().await;
+
+        // 开发模式下不在群聊中主动发言，私聊只找管理员
+        let config = config::get();
+        let chat_config = config.chat();
+        if chat_config.dev_mode() {
+            groups.clear();
+            users.retain(|user_id| chat_config.is_admin(*user_id));
+        }
+
+        // 冷却中的目标不参与选择，避免反复打扰同一个群或用户
+        filter_cooled_down(&self.memory_manager, &mut groups, &mut users).await;
 
         // 随机选择一个目标
         let target = self.select_chat_target(groups, users).await;
@@ -110,49 +148,63 @@ impl ProactiveChatManager {
     }
 
     async fn get_active_groups(&self) -> Vec<i64> {
-        // 从群组档案中获取活跃群组
+        // 只在机器人仍在的群组中发言，获取群列表失败时退回到有档案的群组
+        let joined = self.get_joined_groups().await;
         let group_profiles = self.memory_manager.get_all_group_profiles().await;
-        let now = Local::now();
-        let one_day_ago = now - chrono::Duration::days(1);
-        
-        group_profiles
-            .into_iter()
-            .filter(|profile| profile.last_activity > one_day_ago && profile.activity_level > 3)
-            .map(|profile| profile.group_id)
-            .collect()
+        filter_active_groups(&group_profiles, joined.as_ref(), Local::now())
+    }
+
+    /// 获取机器人当前加入的群组
+    ///
+    /// # 返回值
+    /// 获取失败时返回 `None`
+    async fn get_joined_groups(&self) -> Option<HashSet<i64>> {
+        let list = match self.bot.get_group_list().await {
+            Ok(list) => list,
+            Err(e) => {
+                eprintln!("[ERROR] 获取群列表失败，改用群组档案: {:?}", e);
+                return None;
+            }
+        };
+        let groups = list
+            .data
+            .as_array()?
+            .iter()
+            .filter_map(|group| group.get("group_id").and_then(|id| id.as_i64()))
+            .collect();
+        Some(groups)
     }
 
     async fn get_active_users(&self) -> Vec<i64> {
-        // 从用户档案中获取最近活跃的用户
-        let user_profiles = self.memory_manager.get_all_user_profiles().await;
-        let now = Local::now();
-        let three_days_ago = now - chrono::Duration::days(3);
-        
-        user_profiles
-            .into_iter()
-            .filter(|profile| profile.last_interaction > three_days_ago && profile.relationship_level > 2)
-            .map(|profile| profile.user_id)
-            .collect()
+        // 获取最近3天活跃且关系较好的用户，最近互动的用户优先
+        let mut users = Vec::new();
+        for user_id in self.memory_manager.get_recently_active_users(chrono::Duration::days(3)).await {
+            if let Some(profile) = self.memory_manager.get_user_profile(user_id).await
+                && profile.relationship_level > 2
+            {
+                users.push(user_id);
+            }
+        }
+        users
     }
 
     async fn select_chat_target(&self, groups: Vec<i64>, users: Vec<i64>) -> ChatTarget {
         let personality = self.memory_manager.get_bot_personality().await;
-        
-        // 根据社交信心决定是群聊还是私聊
-        if personality.social_confidence >= 7 && !groups.is_empty() {
-            // 高社交信心，选择群聊
-            let group_id = groups[0]; // 简化选择逻辑
-            return ChatTarget::Group(group_id);
-        } else if !users.is_empty() {
-            // 选择私聊
-            let user_id = users[0]; // 简化选择逻辑
-            return ChatTarget::User(user_id);
-        }
-        
-        ChatTarget::None
+        let config = config::get();
+        choose_chat_target(
+            personality.social_confidence,
+            &groups,
+            &users,
+            config.proactive().proactive_group_enabled(),
+            config.proactive().proactive_private_enabled(),
+        )
     }
 
     async fn initiate_group_chat(&self, group_id: i64) -> Result<()> {
+        if !config::get().proactive().proactive_group_enabled() {
+            return Ok(());
+        }
+
         // 检查是否应该在这个群组发起对话
         if !self.topic_generator.should_initiate_conversation(Some(group_id), None).await {
             return Ok(());
@@ -169,8 +221,14 @@ impl ProactiveChatManager {
                 format!("{} {}", mood_prefix, content)
             };
 
+            // 同一群组短时间内已发过主动消息（如定时问候）时不再打扰
+            if !utils::reserve_proactive_group(group_id) {
+                return Ok(());
+            }
+
             // 发送消息
-            self.bot.send_group_msg(group_id, &message);
+            utils::send_group_message(&self.bot, group_id, &message).await?;
+            self.memory_manager.record_proactive_message(Some(group_id), None).await?;
             
             // 记录这次主动对话
             self.memory_manager.add_conversation_memory(
@@ -184,6 +242,10 @@ impl ProactiveChatManager {
     }
 
     async fn initiate_private_chat(&self, user_id: i64) -> Result<()> {
+        if !config::get().proactive().proactive_private_enabled() {
+            return Ok(());
+        }
+
         // 检查是否应该向这个用户发起对话
         if !self.topic_generator.should_initiate_conversation(None, Some(user_id)).await {
             return Ok(());
@@ -191,17 +253,22 @@ impl ProactiveChatManager {
 
         // 生成个性化话题
         if let Some(topic) = self.topic_generator.generate_personalized_topic(user_id).await? {
-            // 添加情绪前缀
+            // 根据情绪和关系阶段组织开场白
             let mood_prefix = self.mood_system.get_mood_based_response_style().await;
             let content = topic.content.clone();
-            let message = if mood_prefix.is_empty() {
-                content.clone()
-            } else {
-                format!("{} {}", mood_prefix, content)
-            };
+            let profile = self.memory_manager.get_user_profile(user_id).await;
+            let mut message = frame_private_opener(profile.as_ref(), &mood_prefix, &content);
+
+            // 用户常用其他语言时将开场白翻译成对应语言
+            if config::get().proactive().localize_private_openers()
+                && let Some(language) = profile.as_ref().and_then(|p| p.preferred_language.as_deref())
+            {
+                message = crate::model::utils::localize_message(&message, language).await;
+            }
 
             // 发送消息
-            self.bot.send_private_msg(user_id, &message);
+            utils::send_private_message(&self.bot, user_id, &message).await?;
+            self.memory_manager.record_proactive_message(None, Some(user_id)).await?;
             
             // 记录这次主动对话
             self.memory_manager.add_conversation_memory(
@@ -234,16 +301,7 @@ impl ProactiveChatManager {
 
     async fn update_user_profile(&self, user_id: i64, message: &str, _is_group: bool) -> Result<()> {
         let mut profile = self.memory_manager.get_user_profile(user_id).await
-            .unwrap_or_else(|| crate::memory::UserProfile {
-                user_id,
-                nickname: format!("User_{}", user_id),
-                personality_traits: Vec::new(),
-                interests: Vec::new(),
-                relationship_level: 1,
-                last_interaction: Local::now(),
-                interaction_count: 0,
-                mood_history: Vec::new(),
-            });
+            .unwrap_or_else(|| crate::memory::UserProfile::new(user_id, &format!("User_{}", user_id)));
 
         // 更新互动信息
         profile.last_interaction = Local::now();
@@ -256,6 +314,9 @@ impl ProactiveChatManager {
 
         // 提取兴趣关键词
         let interests = self.extract_interests_from_message(message);
+        if interests.is_empty() && config::get().chat().record_uncategorized_activity() {
+            profile.uncategorized_interactions += 1;
+        }
         for interest in interests {
             if !profile.interests.contains(&interest) {
                 profile.interests.push(interest);
@@ -296,9 +357,198 @@ impl ProactiveChatManager {
     }
 }
 
-#[derive(Debug)]
+/// 从群组档案中筛选活跃群组
+///
+/// 最近活跃时间和活跃度等级都需要满足配置的阈值，最近活跃的群组排在前面
+///
+/// # 参数
+/// * `profiles` - 群组档案
+/// * `joined` - 机器人当前加入的群组，为 `None` 时不按群列表过滤
+/// * `now` - 当前时间
+fn filter_active_groups(
+    profiles: &[GroupProfile],
+    joined: Option<&HashSet<i64>>,
+    now: chrono::DateTime<Local>,
+) -> Vec<i64> {
+    let config = config::get();
+    let proactive = config.proactive();
+    let since = now - chrono::Duration::hours(proactive.group_active_within_hours() as i64);
+
+    let mut active: Vec<&GroupProfile> = profiles
+        .iter()
+        .filter(|profile| joined.is_none_or(|joined| joined.contains(&profile.group_id)))
+        .filter(|profile| {
+            profile.last_activity > since && profile.activity_level >= proactive.group_min_activity_level()
+        })
+        .collect();
+    active.sort_by(|a, b| b.last_activity.cmp(&a.last_activity).then_with(|| a.group_id.cmp(&b.group_id)));
+    active.into_iter().map(|profile| profile.group_id).collect()
+}
+
+/// 移除仍在主动聊天冷却期内的群组和用户
+///
+/// # 参数
+/// * `memory_manager` - 记忆管理器，提供上次主动聊天的时间
+/// * `groups` - 候选群组
+/// * `users` - 候选用户
+async fn filter_cooled_down(memory_manager: &MemoryManager, groups: &mut Vec<i64>, users: &mut Vec<i64>) {
+    let config = config::get();
+    let now = Local::now();
+    let group_since = now - chrono::Duration::hours(config.proactive().group_cooldown_hours() as i64);
+    let user_since = now - chrono::Duration::hours(config.proactive().user_cooldown_hours() as i64);
+
+    let mut ready_groups = Vec::new();
+    for &group_id in groups.iter() {
+        let last = memory_manager.get_group_profile(group_id).await.and_then(|p| p.last_proactive_message);
+        if last.is_none_or(|last| last <= group_since) {
+            ready_groups.push(group_id);
+        }
+    }
+    let mut ready_users = Vec::new();
+    for &user_id in users.iter() {
+        let last = memory_manager.get_user_profile(user_id).await.and_then(|p| p.last_proactive_message);
+        if last.is_none_or(|last| last <= user_since) {
+            ready_users.push(user_id);
+        }
+    }
+    *groups = ready_groups;
+    *users = ready_users;
+}
+
+#[derive(Debug, PartialEq)]
 enum ChatTarget {
     Group(i64),
     User(i64),
     None,
 }
+
+/// 从候选群组和用户中选择主动聊天的对象
+/// 
+/// 社交信心高时优先选择群聊，否则选择私聊；按配置关闭的主动聊天类型不参与选择
+/// 
+/// # 参数
+/// * `social_confidence` - 机器人当前的社交信心
+/// * `groups` - 候选群组
+/// * `users` - 候选用户
+/// * `group_enabled` - 是否允许主动群聊
+/// * `private_enabled` - 是否允许主动私聊
+fn choose_chat_target(
+    social_confidence: u8,
+    groups: &[i64],
+    users: &[i64],
+    group_enabled: bool,
+    private_enabled: bool,
+) -> ChatTarget {
+    let group = groups.first().filter(|_| group_enabled);
+    let user = users.first().filter(|_| private_enabled);
+
+    // 根据社交信心决定是群聊还是私聊
+    match (group, user) {
+        (Some(&group_id), _) if social_confidence >= 7 => ChatTarget::Group(group_id),
+        (_, Some(&user_id)) => ChatTarget::User(user_id),
+        _ => ChatTarget::None,
+    }
+}
+
+/// 根据关系阶段组织私聊开场白
+/// 
+/// - 亲密好友：带上称呼和情绪，语气随意亲昵
+/// - 朋友：带上称呼和情绪，语气友好
+/// - 初识或无档案：不带情绪前缀，先礼貌打招呼
+/// 
+/// # 参数
+/// * `profile` - 目标用户档案
+/// * `mood_prefix` - 当前情绪对应的语气前缀
+/// * `content` - 话题内容
+fn frame_private_opener(profile: Option<&UserProfile>, mood_prefix: &str, content: &str) -> String {
+    let with_mood = if mood_prefix.is_empty() {
+        content.to_string()
+    } else {
+        format!("{} {}", mood_prefix, content)
+    };
+    let address = profile.and_then(|p| p.preferred_address.as_deref());
+    let tier = profile
+        .map(|p| p.relationship_tier())
+        .unwrap_or(RelationshipTier::Acquaintance);
+
+    match (tier, address) {
+        (RelationshipTier::Close, Some(address)) => format!("{}～{} 嘿嘿", address, with_mood),
+        (RelationshipTier::Close, None) => format!("嘿嘿，又来找你玩啦～{}", with_mood),
+        (RelationshipTier::Friendly, Some(address)) => format!("{}，{}", address, with_mood),
+        (RelationshipTier::Friendly, None) => with_mood,
+        (RelationshipTier::Acquaintance, Some(address)) => format!("{}你好，打扰一下～{}", address, content),
+        (RelationshipTier::Acquaintance, None) => format!("你好，打扰一下～{}", content),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opener_tone_follows_relationship_tier() {
+        let mut close = UserProfile::new(1, "小明");
+        close.relationship_level = 10;
+        let mut stranger = UserProfile::new(2, "小红");
+        stranger.relationship_level = 2;
+
+        let close_opener = frame_private_opener(Some(&close), "开心地", "最近在忙什么呀？");
+        let stranger_opener = frame_private_opener(Some(&stranger), "开心地", "最近在忙什么呀？");
+
+        assert_ne!(close_opener, stranger_opener);
+        assert!(close_opener.contains("开心地"));
+        // 初识用户先礼貌打招呼，不带情绪前缀
+        assert_eq!(stranger_opener, "你好，打扰一下～最近在忙什么呀？");
+        assert_eq!(frame_private_opener(None, "开心地", "在吗"), "你好，打扰一下～在吗");
+    }
+
+    #[test]
+    fn disabled_private_proactive_never_selects_user() {
+        for confidence in 0..=10 {
+            assert!(!matches!(choose_chat_target(confidence, &[1], &[2], true, false), ChatTarget::User(_)));
+            assert!(!matches!(choose_chat_target(confidence, &[], &[2], true, false), ChatTarget::User(_)));
+        }
+        assert_eq!(choose_chat_target(3, &[1], &[2], true, true), ChatTarget::User(2));
+        assert_eq!(choose_chat_target(8, &[1], &[2], true, true), ChatTarget::Group(1));
+        assert_eq!(choose_chat_target(8, &[1], &[2], false, true), ChatTarget::User(2));
+    }
+
+    #[test]
+    fn active_groups_meet_activity_and_recency_thresholds() {
+        crate::config::use_test_data_dir();
+        let now = Local::now();
+        let profile = |group_id, hours_ago, activity_level| {
+            let mut profile = GroupProfile::new(group_id);
+            profile.last_activity = now - chrono::Duration::hours(hours_ago);
+            profile.activity_level = activity_level;
+            profile
+        };
+        // 默认阈值：24小时内活跃且活跃度不低于4
+        let profiles = [profile(1, 2, 6), profile(2, 1, 5), profile(3, 48, 9), profile(4, 1, 2)];
+
+        assert_eq!(filter_active_groups(&profiles, None, now), [2, 1]);
+
+        // 机器人已退出的群组不再选择
+        let joined = HashSet::from([1, 3, 4]);
+        assert_eq!(filter_active_groups(&profiles, Some(&joined), now), [1]);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn recently_chosen_targets_are_skipped() {
+        crate::config::use_test_data_dir();
+        let (manager, _state) = crate::memory::mock::manager().await;
+
+        let (mut groups, mut users) = (vec![1, 2], vec![3, 4]);
+        filter_cooled_down(&manager, &mut groups, &mut users).await;
+        assert_eq!(groups, [1, 2]);
+        assert_eq!(users, [3, 4]);
+
+        // 刚刚主动聊过的群组和用户在冷却期内不再被选中
+        manager.record_proactive_message(Some(1), None).await.unwrap();
+        manager.record_proactive_message(None, Some(3)).await.unwrap();
+        let (mut groups, mut users) = (vec![1, 2], vec![3, 4]);
+        filter_cooled_down(&manager, &mut groups, &mut users).await;
+        assert_eq!(groups, [2]);
+        assert_eq!(users, [4]);
+    }
+}
@@ -1,50 +1,59 @@
 use crate::proactive_chat::ProactiveChatManager;
+use crate::proactive_chat::greeting::GreetingScheduler;
 use kovi::RuntimeBot;
-use std::sync::Arc;
-use std::collections::HashMap;
-use std::sync::Mutex;
-use std::sync::LazyLock;
-use std::sync::atomic::{AtomicBool, Ordering};
-
-// 全局主动聊天管理器
-static PROACTIVE_MANAGERS: LazyLock<Mutex<HashMap<String, Arc<ProactiveChatManager>>>> =
-    LazyLock::new(|| Mutex::new(HashMap::new()));
-
-// 启动状态标记
-static IS_STARTED: AtomicBool = AtomicBool::new(false);
-
-pub async fn get_or_create_proactive_manager(bot: Arc<RuntimeBot>) -> Option<Arc<ProactiveChatManager>> {
-    // 检查是否已经启动过
-    if IS_STARTED.load(Ordering::Relaxed) {
-        return None;
-    }
-    
-    let bot_id = format!("bot_{}", std::ptr::addr_of!(bot) as usize);
-    
-    {
-        let managers = PROACTIVE_MANAGERS.lock().unwrap();
-        if let Some(manager) = managers.get(&bot_id) {
-            return Some(Arc::clone(manager));
-        }
-    }
-    
-    // 创建新的管理器
-    let memory_manager = Arc::clone(&crate::memory::MEMORY_MANAGER);
-    let manager = Arc::new(ProactiveChatManager::new(memory_manager, bot));
-    
-    {
-        let mut managers = PROACTIVE_MANAGERS.lock().unwrap();
-        managers.insert(bot_id, Arc::clone(&manager));
+use std::sync::{Arc, OnceLock};
+
+// 全局主动聊天管理器，整个进程只有一个机器人实例，第一次收到消息时创建
+static PROACTIVE_MANAGER: OnceLock<Arc<ProactiveChatManager>> = OnceLock::new();
+
+/// 获取主动聊天管理器，第一次调用时创建并启动主动聊天和定时问候循环
+///
+/// # 参数
+/// * `bot` - 机器人实例，只在第一次调用时使用
+pub fn get_or_create_proactive_manager(bot: Arc<RuntimeBot>) -> Arc<ProactiveChatManager> {
+    shared(&PROACTIVE_MANAGER, || {
+        let memory_manager = Arc::clone(&crate::memory::MEMORY_MANAGER);
+        let manager = Arc::new(ProactiveChatManager::new(memory_manager, Arc::clone(&bot)));
+
+        // 启动主动聊天循环
+        let manager_clone = Arc::clone(&manager);
+        kovi::tokio::spawn(async move {
+            manager_clone.start_proactive_chat_loop().await;
+        });
+
+        // 启动定时问候循环
+        let greeting_scheduler = GreetingScheduler::new(Arc::clone(&crate::memory::MEMORY_MANAGER), bot);
+        kovi::tokio::spawn(async move {
+            greeting_scheduler.start_greeting_loop().await;
+        });
+
+        println!("[INFO] 主动聊天管理器已启动");
+        manager
+    })
+}
+
+/// 获取进程内共享的实例，只有第一次调用时执行 `init`
+fn shared<T>(cell: &OnceLock<Arc<T>>, init: impl FnOnce() -> Arc<T>) -> Arc<T> {
+    Arc::clone(cell.get_or_init(init))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn repeated_calls_return_same_instance() {
+        let cell = OnceLock::new();
+        let inits = AtomicUsize::new(0);
+        let create = || {
+            inits.fetch_add(1, Ordering::Relaxed);
+            Arc::new("manager")
+        };
+
+        let first = shared(&cell, create);
+        let second = shared(&cell, create);
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(inits.load(Ordering::Relaxed), 1);
     }
-    
-    // 标记为已启动
-    IS_STARTED.store(true, Ordering::Relaxed);
-    
-    // 启动主动聊天循环
-    let manager_clone = Arc::clone(&manager);
-    kovi::tokio::spawn(async move {
-        manager_clone.start_proactive_chat_loop().await;
-    });
-    
-    Some(manager)
 }
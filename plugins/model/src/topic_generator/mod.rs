@@ -10,6 +10,7 @@ use crate::memory::MemoryManager;
 use chrono::Local;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::collections::HashMap;
 use anyhow::Result;
 
 /// 话题结构体
@@ -32,7 +33,7 @@ pub struct Topic {
 /// 话题分类枚举
 /// 
 /// 定义不同类型的话题，用于分类和筛选
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum TopicCategory {
     /// 日常闲聊：轻松随意的话题
     Casual,
@@ -158,10 +159,10 @@ impl TopicGenerator {
             .iter()
             .filter(|template| {
                 // 检查情绪要求
-                if let Some(required_mood) = &template.mood_requirement {
-                    if bot_personality.current_mood != *required_mood {
-                        return false;
-                    }
+                if let Some(required_mood) = &template.mood_requirement
+                    && bot_personality.current_mood != *required_mood
+                {
+                    return false;
                 }
                 
                 // 检查能量水平要求
@@ -173,6 +174,12 @@ impl TopicGenerator {
             return Ok(None);
         }
 
+        // 根据当前情绪偏好的话题分类调整候选顺序
+        let suitable_templates = Self::apply_mood_preference(
+            suitable_templates,
+            crate::config::get().proactive().preferred_categories(&bot_personality.current_mood),
+        );
+
         // 根据群组或用户的历史记录调整话题选择
         let selected_template = self.select_best_template(suitable_templates, group_id, user_id).await?;
         
@@ -187,6 +194,26 @@ impl TopicGenerator {
         Ok(Some(topic))
     }
 
+    /// 根据情绪偏好调整候选话题
+    /// 
+    /// 如果存在符合偏好分类的话题，则只保留这些话题；否则保持原候选不变
+    fn apply_mood_preference<'a>(
+        templates: Vec<&'a TopicTemplate>,
+        preferred: &[TopicCategory],
+    ) -> Vec<&'a TopicTemplate> {
+        let preferred_templates: Vec<&TopicTemplate> = templates
+            .iter()
+            .copied()
+            .filter(|template| preferred.contains(&template.category))
+            .collect();
+
+        if preferred_templates.is_empty() {
+            templates
+        } else {
+            preferred_templates
+        }
+    }
+
     async fn select_best_template(
         &self,
         templates: Vec<&TopicTemplate>,
@@ -194,28 +221,38 @@ impl TopicGenerator {
         user_id: Option<i64>,
     ) -> Result<TopicTemplate> {
         // 如果有群组或用户信息，尝试选择更相关的话题
-        if let Some(gid) = group_id {
-            if let Some(group_profile) = self.memory_manager.get_group_profile(gid).await {
-                // 根据群组话题偏好选择
-                for template in &templates {
-                    if group_profile.conversation_topics.iter().any(|topic| 
-                        template.tags.iter().any(|tag| tag.contains(topic))
-                    ) {
-                        return Ok((*template).clone());
-                    }
+        if let Some(gid) = group_id
+            && let Some(group_profile) = self.memory_manager.get_group_profile(gid).await
+        {
+            // 根据群组话题偏好选择
+            for template in &templates {
+                if group_profile.conversation_topics.iter().any(|topic| 
+                    template.tags.iter().any(|tag| tag.contains(topic))
+                ) {
+                    return Ok((*template).clone());
+                }
+            }
+
+            // 根据近期活跃成员的共同兴趣选择
+            let member_interests = self.aggregate_member_interests(&group_profile).await;
+            for interest in &member_interests {
+                if let Some(template) = templates.iter().find(|template|
+                    template.tags.iter().any(|tag| tag.contains(interest.as_str()))
+                ) {
+                    return Ok((*template).clone());
                 }
             }
         }
 
-        if let Some(uid) = user_id {
-            if let Some(user_profile) = self.memory_manager.get_user_profile(uid).await {
-                // 根据用户兴趣选择
-                for template in &templates {
-                    if user_profile.interests.iter().any(|interest| 
-                        template.tags.iter().any(|tag| tag.contains(interest))
-                    ) {
-                        return Ok((*template).clone());
-                    }
+        if let Some(uid) = user_id
+            && let Some(user_profile) = self.memory_manager.get_user_profile(uid).await
+        {
+            // 根据用户兴趣选择
+            for template in &templates {
+                if user_profile.interests.iter().any(|interest| 
+                    template.tags.iter().any(|tag| tag.contains(interest))
+                ) {
+                    return Ok((*template).clone());
                 }
             }
         }
@@ -228,6 +265,24 @@ impl TopicGenerator {
         Ok(templates[index].clone())
     }
 
+    /// 汇总群组近期活跃成员的兴趣
+    /// 
+    /// 只统计最近一天内发言过的成员，按兴趣出现的人数从多到少排序
+    async fn aggregate_member_interests(&self, group_profile: &crate::memory::GroupProfile) -> Vec<String> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for member_id in group_profile.recently_active_members(chrono::Duration::days(1)) {
+            if let Some(profile) = self.memory_manager.get_user_profile(member_id).await {
+                for interest in profile.interests {
+                    *counts.entry(interest).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut interests: Vec<(String, usize)> = counts.into_iter().collect();
+        interests.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        interests.into_iter().map(|(interest, _)| interest).collect()
+    }
+
     pub async fn generate_personalized_topic(&self, user_id: i64) -> Result<Option<Topic>> {
         // 获取用户档案
         if let Some(user_profile) = self.memory_manager.get_user_profile(user_id).await {
@@ -257,8 +312,13 @@ impl TopicGenerator {
 
         for (interest, topic) in interest_topics {
             if interests.iter().any(|i| i.contains(interest)) {
+                // 用户以前聊过这个话题时，先提一句上次说过的话
+                let content = match self.memory_manager.get_memories_about(user_profile.user_id, interest, 1).await.first() {
+                    Some(memory) => format!("上次你说{}，{}", recall_snippet(&memory.content), topic),
+                    None => topic.to_string(),
+                };
                 return Ok(Some(Topic {
-                    content: topic.to_string(),
+                    content,
                     category: TopicCategory::Personal,
                     mood_requirement: None,
                     energy_level_required: 4,
@@ -293,24 +353,24 @@ impl TopicGenerator {
         }
 
         // 检查特定群组或用户的活跃度
-        if let Some(gid) = group_id {
-            if let Some(group_profile) = self.memory_manager.get_group_profile(gid).await {
-                // 如果群组不活跃，增加主动聊天的概率
-                if group_profile.activity_level < 3 {
-                    return bot_personality.curiosity_level > 5;
-                }
+        if let Some(gid) = group_id
+            && let Some(group_profile) = self.memory_manager.get_group_profile(gid).await
+        {
+            // 如果群组不活跃，增加主动聊天的概率
+            if group_profile.activity_level < 3 {
+                return bot_personality.curiosity_level > 5;
             }
         }
 
-        if let Some(uid) = user_id {
-            if let Some(user_profile) = self.memory_manager.get_user_profile(uid).await {
-                // 根据关系等级调整主动聊天的概率
-                match user_profile.relationship_level {
-                    8..=10 => return bot_personality.curiosity_level > 4, // 高关系等级更容易主动聊天
-                    5..=7 => return bot_personality.curiosity_level > 6,
-                    1..=4 => return bot_personality.curiosity_level > 8, // 低关系等级需要更高好奇心
-                    _ => return false,
-                }
+        if let Some(uid) = user_id
+            && let Some(user_profile) = self.memory_manager.get_user_profile(uid).await
+        {
+            // 根据关系等级调整主动聊天的概率
+            match user_profile.relationship_level {
+                8..=10 => return bot_personality.curiosity_level > 4, // 高关系等级更容易主动聊天
+                5..=7 => return bot_personality.curiosity_level > 6,
+                1..=4 => return bot_personality.curiosity_level > 8, // 低关系等级需要更高好奇心
+                _ => return false,
             }
         }
 
@@ -323,3 +383,40 @@ impl TopicGenerator {
         }
     }
 }
+
+/// 回忆引用的最大字符数
+const RECALL_SNIPPET_CHARS: usize = 30;
+
+/// 从对话记忆中截取用户原话，用于“上次你说……”式的回忆引用
+///
+/// 去掉记忆开头的 `[时间] 昵称: ` 前缀，过长时截断
+fn recall_snippet(content: &str) -> String {
+    let message = content.split_once(": ").map_or(content, |(_, message)| message).trim();
+    if message.chars().count() > RECALL_SNIPPET_CHARS {
+        format!("{}…", message.chars().take(RECALL_SNIPPET_CHARS).collect::<String>())
+    } else {
+        message.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ModelConfig;
+
+    #[test]
+    fn playful_mood_prefers_fun_topics() {
+        let templates = TopicGenerator::init_topic_templates();
+        let candidates: Vec<&TopicTemplate> = templates.iter().collect();
+        let config: ModelConfig = kovi::toml::from_str("").unwrap();
+        let config = config.proactive();
+
+        let preferred = TopicGenerator::apply_mood_preference(candidates.clone(), config.preferred_categories("playful"));
+        assert!(!preferred.is_empty());
+        assert!(preferred.iter().all(|template| template.category == TopicCategory::Fun));
+
+        // 没有偏好配置的情绪保持原有候选
+        let unchanged = TopicGenerator::apply_mood_preference(candidates.clone(), config.preferred_categories("unknown"));
+        assert_eq!(unchanged.len(), candidates.len());
+    }
+}
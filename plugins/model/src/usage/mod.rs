@@ -0,0 +1,308 @@
+//! # 用量统计模块
+//!
+//! 记录模型接口的调用次数和token消耗（来自响应中的 `usage` 字段），
+//! 按全局、群组和私聊用户分别累计当天和历史总量，并保存到数据目录下，
+//! 便于使用付费接口时掌握开销。响应不包含 `usage` 字段时只计调用次数
+
+use crate::config;
+use anyhow::Result;
+use chrono::{Local, NaiveDate};
+use kovi::serde_json::Value;
+use kovi::tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// 用量计入的对话
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageScope {
+    /// 群聊
+    Group(i64),
+    /// 私聊
+    Private(i64),
+}
+
+/// 一次请求消耗的token数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    /// 输入token数
+    pub prompt_tokens: u64,
+    /// 输出token数
+    pub completion_tokens: u64,
+}
+
+impl TokenUsage {
+    /// 解析响应中的 `usage` 字段
+    ///
+    /// # 参数
+    /// * `response` - 模型接口返回的完整响应
+    ///
+    /// # 返回值
+    /// 响应不包含 `usage` 字段时返回 `None`，缺少的计数按0处理
+    pub fn from_response(response: &Value) -> Option<Self> {
+        let usage = response.get("usage")?.as_object()?;
+        let count = |key: &str| usage.get(key).and_then(Value::as_u64).unwrap_or(0);
+        Some(Self {
+            prompt_tokens: count("prompt_tokens"),
+            completion_tokens: count("completion_tokens"),
+        })
+    }
+}
+
+/// 累计用量
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct UsageTotals {
+    /// 请求次数
+    pub requests: u64,
+    /// 输入token数
+    pub prompt_tokens: u64,
+    /// 输出token数
+    pub completion_tokens: u64,
+}
+
+impl UsageTotals {
+    /// 总token数
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    fn add(&mut self, usage: Option<TokenUsage>) {
+        self.requests += 1;
+        if let Some(usage) = usage {
+            self.prompt_tokens += usage.prompt_tokens;
+            self.completion_tokens += usage.completion_tokens;
+        }
+    }
+}
+
+/// 当天和历史的累计用量
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct UsageRecord {
+    /// 当天用量
+    pub today: UsageTotals,
+    /// 历史总用量
+    pub all_time: UsageTotals,
+}
+
+impl UsageRecord {
+    fn add(&mut self, usage: Option<TokenUsage>) {
+        self.today.add(usage);
+        self.all_time.add(usage);
+    }
+}
+
+/// 用量账本
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct UsageLedger {
+    /// `today` 用量对应的日期
+    pub day: Option<NaiveDate>,
+    /// 全局用量
+    pub global: UsageRecord,
+    /// 群号 -> 群聊用量
+    pub groups: HashMap<i64, UsageRecord>,
+    /// QQ号 -> 私聊用量
+    pub users: HashMap<i64, UsageRecord>,
+}
+
+impl UsageLedger {
+    /// 日期变化时清零所有当天用量
+    ///
+    /// # 参数
+    /// * `today` - 当前日期
+    pub fn roll_over(&mut self, today: NaiveDate) {
+        if self.day == Some(today) {
+            return;
+        }
+        self.day = Some(today);
+        for record in std::iter::once(&mut self.global)
+            .chain(self.groups.values_mut())
+            .chain(self.users.values_mut())
+        {
+            record.today = UsageTotals::default();
+        }
+    }
+
+    /// 累计一次请求的用量
+    ///
+    /// # 参数
+    /// * `scope` - 用量计入的对话，为 `None` 时只计入全局
+    /// * `usage` - 本次消耗的token数，响应未提供时只计请求次数
+    /// * `today` - 当前日期，跨天时先清零当天用量
+    pub fn record(&mut self, scope: Option<UsageScope>, usage: Option<TokenUsage>, today: NaiveDate) {
+        self.roll_over(today);
+        self.global.add(usage);
+        match scope {
+            Some(UsageScope::Group(group_id)) => self.groups.entry(group_id).or_default().add(usage),
+            Some(UsageScope::Private(user_id)) => self.users.entry(user_id).or_default().add(usage),
+            None => {}
+        }
+    }
+}
+
+/// 全局用量账本，首次使用时从文件加载
+static LEDGER: LazyLock<Mutex<Option<UsageLedger>>> = LazyLock::new(|| Mutex::new(None));
+
+/// 保存锁，保证账本按修改顺序写入文件
+static SAVE_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+
+fn usage_path() -> std::path::PathBuf {
+    config::data_path(config::get().memory().usage_file())
+}
+
+/// 锁定用量账本，首次使用时在阻塞线程中从文件加载
+async fn lock_ledger() -> MappedMutexGuard<'static, UsageLedger> {
+    let mut ledger = LEDGER.lock().await;
+    if ledger.is_none() {
+        let path = usage_path();
+        let loaded = kovi::tokio::task::spawn_blocking(move || load(&path))
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("[ERROR] 加载用量统计失败，重新开始统计: {}", e);
+                UsageLedger::default()
+            });
+        *ledger = Some(loaded);
+    }
+    MutexGuard::map(ledger, |ledger| ledger.get_or_insert_with(UsageLedger::default))
+}
+
+/// 从文件加载用量账本，文件不存在或无法解析时从零开始
+fn load(path: &Path) -> UsageLedger {
+    let Ok(content) = fs::read_to_string(path) else {
+        return UsageLedger::default();
+    };
+    kovi::serde_json::from_str(&content).unwrap_or_else(|e| {
+        eprintln!("[ERROR] 用量文件解析失败，重新开始统计: {}", e);
+        UsageLedger::default()
+    })
+}
+
+fn save(path: &Path, ledger: &UsageLedger) -> Result<()> {
+    fs::write(path, kovi::serde_json::to_string_pretty(ledger)?)?;
+    Ok(())
+}
+
+/// 在阻塞线程中保存账本的最新副本，不占用账本锁
+async fn persist() -> Result<()> {
+    let _save = SAVE_LOCK.lock().await;
+    let ledger = lock_ledger().await.clone();
+    let path = usage_path();
+    kovi::tokio::task::spawn_blocking(move || save(&path, &ledger)).await?
+}
+
+/// 记录一次模型请求的用量并保存
+///
+/// # 参数
+/// * `scope` - 用量计入的对话，为 `None` 时只计入全局
+/// * `usage` - 本次消耗的token数
+pub async fn record(scope: Option<UsageScope>, usage: Option<TokenUsage>) {
+    lock_ledger().await.record(scope, usage, Local::now().date_naive());
+    if let Err(e) = persist().await {
+        eprintln!("[ERROR] 保存用量统计失败: {}", e);
+    }
+}
+
+/// 获取当前的用量账本
+pub async fn snapshot() -> UsageLedger {
+    let mut ledger = lock_ledger().await;
+    ledger.roll_over(Local::now().date_naive());
+    ledger.clone()
+}
+
+/// 生成用量报告
+///
+/// 处理 `#用量` 命令，包含全局用量和指定对话的用量
+///
+/// # 参数
+/// * `scope` - 当前所在的对话
+pub async fn describe_usage(scope: UsageScope) -> String {
+    let ledger = snapshot().await;
+    let record = match scope {
+        UsageScope::Group(group_id) => ledger.groups.get(&group_id).copied().unwrap_or_default(),
+        UsageScope::Private(user_id) => ledger.users.get(&user_id).copied().unwrap_or_default(),
+    };
+    let label = match scope {
+        UsageScope::Group(_) => "本群",
+        UsageScope::Private(_) => "我们的私聊",
+    };
+    let line = |name: &str, totals: &UsageTotals| {
+        format!(
+            "{}: {} 次请求，{} tokens（输入 {} / 输出 {}）",
+            name,
+            totals.requests,
+            totals.total_tokens(),
+            totals.prompt_tokens,
+            totals.completion_tokens
+        )
+    };
+
+    [
+        "📈 模型用量".to_string(),
+        line("今日全部", &ledger.global.today),
+        line("累计全部", &ledger.global.all_time),
+        line(&format!("今日{}", label), &record.today),
+        line(&format!("累计{}", label), &record.all_time),
+    ]
+    .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+    }
+
+    fn tokens(prompt_tokens: u64, completion_tokens: u64) -> Option<TokenUsage> {
+        Some(TokenUsage { prompt_tokens, completion_tokens })
+    }
+
+    #[test]
+    fn record_accumulates_global_and_scoped_usage() {
+        let mut ledger = UsageLedger::default();
+        ledger.record(Some(UsageScope::Group(1)), tokens(10, 5), day(1));
+        ledger.record(Some(UsageScope::Private(2)), None, day(1));
+        ledger.record(None, tokens(1, 1), day(1));
+
+        assert_eq!(ledger.global.today.requests, 3);
+        assert_eq!(ledger.global.today.total_tokens(), 17);
+        assert_eq!(ledger.groups[&1].all_time.total_tokens(), 15);
+        assert_eq!(ledger.users[&2].today, UsageTotals { requests: 1, ..Default::default() });
+    }
+
+    #[test]
+    fn roll_over_clears_today_but_keeps_all_time() {
+        let mut ledger = UsageLedger::default();
+        ledger.record(Some(UsageScope::Group(1)), tokens(10, 5), day(1));
+
+        ledger.roll_over(day(1));
+        assert_eq!(ledger.groups[&1].today.requests, 1);
+
+        ledger.roll_over(day(2));
+        assert_eq!(ledger.day, Some(day(2)));
+        assert_eq!(ledger.global.today, UsageTotals::default());
+        assert_eq!(ledger.groups[&1].today, UsageTotals::default());
+        assert_eq!(ledger.groups[&1].all_time.total_tokens(), 15);
+    }
+
+    #[test]
+    fn parses_usage_from_response() {
+        let response = kovi::serde_json::json!({ "usage": { "prompt_tokens": 12 } });
+        assert_eq!(TokenUsage::from_response(&response), tokens(12, 0));
+        assert_eq!(TokenUsage::from_response(&kovi::serde_json::json!({})), None);
+    }
+
+    #[kovi::tokio::test(crate = "kovi::tokio")]
+    async fn record_saves_ledger_to_file() {
+        config::use_test_data_dir();
+        record(Some(UsageScope::Group(42)), tokens(3, 4)).await;
+
+        let saved = load(&usage_path());
+        assert!(saved.groups[&42].all_time.total_tokens() >= 7);
+    }
+}
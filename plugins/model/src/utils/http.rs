@@ -0,0 +1,70 @@
+//! # HTTP客户端模块
+//!
+//! 模型请求和向量化请求共用同一个客户端，以复用连接池，避免每次请求重新解析DNS、建立TLS连接
+
+use crate::config::{self, ModelConfig};
+use reqwest::Client;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// 客户端的创建次数，用于测试确认只创建一次
+#[cfg(test)]
+static CLIENT_BUILDS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// 全局HTTP客户端，首次使用时按服务器配置的超时时间创建
+static HTTP_CLIENT: LazyLock<Client> = LazyLock::new(|| {
+    #[cfg(test)]
+    CLIENT_BUILDS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let timeout = config::get().server_config().request_timeout_secs();
+    Client::builder()
+        .timeout(Duration::from_secs(timeout))
+        .build()
+        .unwrap_or_else(|e| {
+            eprintln!("[ERROR] 创建HTTP客户端失败，改用默认设置: {}", e);
+            Client::new()
+        })
+});
+
+/// 获取全局HTTP客户端
+///
+/// 默认超时时间在首次使用时读取，模型请求会按当前配置单独设置超时
+pub fn http_client() -> &'static Client {
+    &HTTP_CLIENT
+}
+
+/// 检查当前是否允许访问外部接口
+///
+/// 安全模式下所有模型和向量化请求都会在发送前被拒绝，保证不会发出任何HTTP请求
+pub fn ensure_online() -> anyhow::Result<()> {
+    check_online(&config::get())
+}
+
+fn check_online(config: &ModelConfig) -> anyhow::Result<()> {
+    if config.chat().safe_mode() {
+        return Err(anyhow::anyhow!("安全模式下不访问外部接口"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kovi::toml;
+
+    #[test]
+    fn safe_mode_blocks_external_requests() {
+        let config: ModelConfig = toml::from_str("[chat]\nsafe_mode = true").unwrap();
+        assert!(check_online(&config).is_err());
+
+        assert!(check_online(&ModelConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn client_is_built_once_and_reused() {
+        config::use_test_data_dir();
+        let clients: Vec<*const Client> = (0..3).map(|_| std::ptr::from_ref(http_client())).collect();
+
+        assert!(clients.windows(2).all(|pair| pair[0] == pair[1]));
+        assert_eq!(CLIENT_BUILDS.load(std::sync::atomic::Ordering::Relaxed), 1);
+    }
+}
@@ -1,5 +1,13 @@
+mod http;
+mod segments;
+mod sender;
 mod system_info;
 
+pub use crate::utils::http::{ensure_online, http_client};
+pub use crate::utils::segments::annotate_segments;
+pub use crate::utils::sender::{
+    notify_group, notify_private, reserve_proactive_group, send_group_message, send_private_message,
+};
 pub use crate::utils::system_info::system_info_get;
 
 #[macro_export]
@@ -0,0 +1,60 @@
+//! # 消息段标注模块
+//!
+//! 将 OneBot 多段消息转换为带标记的文本，
+//! 让记忆中保留图片、@ 等非文本内容的痕迹，而不是只剩下纯文本
+
+use kovi::Message;
+
+/// 将消息的各个消息段转换为带标记的文本
+///
+/// - 文本段保留原文
+/// - 图片、表情、语音等替换为 `[图片]`、`[表情]`、`[语音]` 等标记
+/// - @ 替换为 `@QQ号`，@全体成员 替换为 `@全体成员`
+///
+/// # 参数
+/// * `message` - 原始消息
+///
+/// # 返回值
+/// 带标记的消息文本
+pub fn annotate_segments(message: &Message) -> String {
+    message
+        .iter()
+        .map(|segment| match segment.type_.as_str() {
+            "text" => segment.data["text"].as_str().unwrap_or_default().to_string(),
+            "at" => match &segment.data["qq"] {
+                kovi::serde_json::Value::String(qq) if qq == "all" => "@全体成员 ".to_string(),
+                kovi::serde_json::Value::String(qq) => format!("@{} ", qq),
+                other => format!("@{} ", other),
+            },
+            "image" => "[图片]".to_string(),
+            "face" => "[表情]".to_string(),
+            "record" => "[语音]".to_string(),
+            "video" => "[视频]".to_string(),
+            "reply" => "[回复]".to_string(),
+            "forward" => "[合并转发]".to_string(),
+            other => format!("[{}]", other),
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kovi::bot::message::Segment;
+    use kovi::serde_json::json;
+
+    #[test]
+    fn image_segment_is_recorded_as_marker() {
+        let message = Message::from(vec![
+            Segment::new("at", json!({ "qq": "12345" })),
+            Segment::new("text", json!({ "text": "看这个" })),
+            Segment::new("image", json!({ "file": "abc.jpg" })),
+        ]);
+        assert_eq!(annotate_segments(&message), "@12345 看这个[图片]");
+
+        let message = Message::from(vec![Segment::new("at", json!({ "qq": "all" }))]);
+        assert_eq!(annotate_segments(&message), "@全体成员");
+    }
+}
@@ -0,0 +1,147 @@
+//! # 消息发送模块
+//!
+//! 对 `RuntimeBot` 的消息发送接口进行统一封装：
+//! - 返回 `Result`，让调用方能够感知投递失败
+//! - 统一记录失败日志
+//! - 将投递结果计入健康检查指标
+
+use crate::health_check;
+use kovi::{Message, RuntimeBot};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// 同一群组两条主动消息（主动话题、定时问候）之间的最小间隔，避免短时间内连续刷屏
+const PROACTIVE_GROUP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// 各群组最近一次放行主动消息的时间
+static LAST_PROACTIVE: LazyLock<Mutex<HashMap<i64, Instant>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 发送群聊消息
+///
+/// # 参数
+/// * `bot` - 机器人实例
+/// * `group_id` - 目标群组ID
+/// * `msg` - 消息内容
+///
+/// # 返回值
+/// 成功时返回消息ID，失败时返回错误
+pub async fn send_group_message<T>(bot: &RuntimeBot, group_id: i64, msg: T) -> anyhow::Result<i32>
+where
+    Message: From<T>,
+    T: Serialize,
+{
+    let result = bot.send_group_msg_return(group_id, msg).await;
+    record_result(result, &format!("群组: {}", group_id))
+}
+
+/// 发送私聊消息
+///
+/// # 参数
+/// * `bot` - 机器人实例
+/// * `user_id` - 目标用户ID
+/// * `msg` - 消息内容
+///
+/// # 返回值
+/// 成功时返回消息ID，失败时返回错误
+pub async fn send_private_message<T>(bot: &RuntimeBot, user_id: i64, msg: T) -> anyhow::Result<i32>
+where
+    Message: From<T>,
+    T: Serialize,
+{
+    let result = bot.send_private_msg_return(user_id, msg).await;
+    record_result(result, &format!("用户: {}", user_id))
+}
+
+/// 发送群聊通知类消息（命令回复、状态提示等），不需要处理投递结果
+///
+/// 失败已由 [`send_group_message`] 记录日志并计入健康检查指标
+pub async fn notify_group<T>(bot: &RuntimeBot, group_id: i64, msg: T)
+where
+    Message: From<T>,
+    T: Serialize,
+{
+    let _ = send_group_message(bot, group_id, msg).await;
+}
+
+/// 发送私聊通知类消息（命令回复、状态提示等），不需要处理投递结果
+///
+/// 失败已由 [`send_private_message`] 记录日志并计入健康检查指标
+pub async fn notify_private<T>(bot: &RuntimeBot, user_id: i64, msg: T)
+where
+    Message: From<T>,
+    T: Serialize,
+{
+    let _ = send_private_message(bot, user_id, msg).await;
+}
+
+/// 为群组的主动消息申请发送额度
+///
+/// 主动消息不是对用户消息的回复，同一群组在最小间隔内只放行一条
+///
+/// # 参数
+/// * `group_id` - 目标群组ID
+///
+/// # 返回值
+/// 可以发送时返回 `true` 并记录本次放行时间
+pub fn reserve_proactive_group(group_id: i64) -> bool {
+    let mut last_sent = LAST_PROACTIVE.lock().unwrap();
+    admit(&mut last_sent, group_id, Instant::now())
+}
+
+fn admit(last_sent: &mut HashMap<i64, Instant>, group_id: i64, now: Instant) -> bool {
+    if last_sent.get(&group_id).is_some_and(|last| now.duration_since(*last) < PROACTIVE_GROUP_INTERVAL) {
+        return false;
+    }
+    last_sent.insert(group_id, now);
+    true
+}
+
+fn record_result(result: Result<i32, kovi::ApiReturn>, target: &str) -> anyhow::Result<i32> {
+    match result {
+        Ok(message_id) => {
+            health_check::record_delivery(true);
+            Ok(message_id)
+        }
+        Err(e) => {
+            health_check::record_delivery(false);
+            eprintln!("[ERROR] 消息发送失败 ({}): {}", target, e);
+            Err(anyhow::anyhow!("消息发送失败: {}", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_send_is_recorded_in_metrics() {
+        let before = health_check::delivery_metrics();
+        let failure = kovi::ApiReturn {
+            status: "failed".to_string(),
+            retcode: 1400,
+            data: serde_json::Value::Null,
+            echo: String::new(),
+        };
+
+        assert!(record_result(Err(failure), "群组: 1").is_err());
+        assert_eq!(record_result(Ok(7), "群组: 1").unwrap(), 7);
+
+        let after = health_check::delivery_metrics();
+        assert!(after.failed > before.failed);
+        assert!(after.sent > before.sent);
+    }
+
+    #[test]
+    fn proactive_messages_are_spaced_per_group() {
+        let mut last_sent = HashMap::new();
+        let start = Instant::now();
+
+        assert!(admit(&mut last_sent, 1, start));
+        assert!(!admit(&mut last_sent, 1, start + Duration::from_secs(60)));
+        assert!(admit(&mut last_sent, 2, start + Duration::from_secs(60)));
+        assert!(admit(&mut last_sent, 1, start + PROACTIVE_GROUP_INTERVAL));
+    }
+}